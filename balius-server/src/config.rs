@@ -19,6 +19,9 @@ pub struct AppConfig {
     pub port: u16,
     pub data_dir: PathBuf,
     pub utxorpc: UtxorpcConfig,
+    /// How long an HTTP request made by a worker, such as posting an execution to the
+    /// relay, may take before it fails, in milliseconds
+    pub submit_timeout_ms: u64,
 }
 
 #[derive(Deserialize, Clone)]
@@ -29,13 +29,14 @@ impl WorkerService {
         keys: KeyService,
         predefined_workers: HashMap<String, Vec<u8>>,
     ) -> Result<Self> {
+        let client = reqwest::ClientBuilder::new()
+            .timeout(Duration::from_millis(config.submit_timeout_ms))
+            .build()?;
         Ok(Self {
             next_id: 1,
             config,
             keys,
-            client: reqwest::ClientBuilder::new()
-                .timeout(Duration::from_secs(10))
-                .build()?,
+            client,
             predefined_workers,
         })
     }
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+
+use balius_sdk::{_internal::Handler, Error, Json, http::HttpResponse, wit};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    kv::{self, NamespaceKey},
+    types::{Order, StrategyExecution},
+};
+
+const KV_EXECUTION_HISTORY: &str = "execution_history";
+
+/// How many executions the history keeps; the oldest are dropped first.
+pub(crate) const MAX_EXECUTION_HISTORY: usize = 100;
+
+/// One execution the worker submitted, as returned by `get-execution-history`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ExecutionRecord {
+    /// The latest slot the worker had observed when submitting
+    slot: u64,
+    /// The strategy order being executed, as `tx_hash#index`
+    order_ref: String,
+    details: Order,
+    /// The relay's HTTP status, or null if the execution never got an answer
+    relay_status: Option<u16>,
+    /// Why the submission failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Whether the execution was only logged, because the worker is configured as a dry run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
+}
+
+impl ExecutionRecord {
+    pub(crate) fn new(
+        execution: &StrategyExecution,
+        response: &Result<HttpResponse, Error>,
+        dry_run: bool,
+    ) -> Self {
+        let (relay_status, error) = match response {
+            Ok(response) => (Some(response.status), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        ExecutionRecord {
+            slot: kv::current_slot(),
+            order_ref: execution.tx_ref.namespace_key(),
+            details: execution.details.clone(),
+            relay_status,
+            error,
+            dry_run,
+        }
+    }
+}
+
+/// Append `record` to the execution history.
+///
+/// Failing to write the history never fails the execution; it is only logged.
+pub(crate) fn record_execution(record: ExecutionRecord) {
+    let result = kv::update(KV_EXECUTION_HISTORY, |history| {
+        let mut history: VecDeque<ExecutionRecord> = history.unwrap_or_default();
+        history.push_back(record);
+        while history.len() > MAX_EXECUTION_HISTORY {
+            history.pop_front();
+        }
+        history
+    });
+    if let Err(err) = result {
+        warn!("failed to record execution history: {err}");
+    }
+}
+
+fn execution_history() -> Result<VecDeque<ExecutionRecord>, Error> {
+    Ok(kv::get(KV_EXECUTION_HISTORY)?.unwrap_or_default())
+}
+
+/// Handler for `get-execution-history` requests, returning the most recent executions the
+/// worker submitted, oldest first.
+#[derive(Clone)]
+pub(crate) struct ExecutionHistoryHandler;
+
+impl Handler for ExecutionHistoryHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        _event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        Ok(Json(execution_history()?).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ManagedStrategy, Strategy,
+        sim::Simulator,
+        types::{AssetId, Interval},
+    };
+
+    fn execution(amount: u64) -> StrategyExecution {
+        let ada = AssetId::from((vec![], vec![]));
+        StrategyExecution {
+            tx_ref: ManagedStrategy::mock(&[]).output,
+            validity_range: Interval::inclusive_range(0, 1),
+            details: Order::swap((&ada, amount), (&ada, 1)),
+            extensions: vec![],
+        }
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_executions() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        for amount in 0..MAX_EXECUTION_HISTORY as u64 + 5 {
+            let response = Err(Error::Internal("relay unreachable".into()));
+            record_execution(ExecutionRecord::new(&execution(amount), &response, false));
+        }
+
+        let history = execution_history().unwrap();
+        assert_eq!(history.len(), MAX_EXECUTION_HISTORY);
+        assert!(matches!(
+            history[0].details,
+            Order::Swap {
+                offer: (_, _, 5),
+                ..
+            }
+        ));
+        assert_eq!(history[0].relay_status, None);
+        assert!(history[0].error.is_some());
+    }
+}
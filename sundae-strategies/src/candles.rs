@@ -0,0 +1,267 @@
+//! OHLC candles of the prices of observed pools, for charting and candle-based strategies.
+//!
+//! With `candle_interval_secs` set in the worker config, each state the worker observes of
+//! a pool that some managed order could trade against is folded into a candle for its
+//! interval, timed by the slot of the pool output. When an observation lands in a later
+//! interval, the current candle closes and a new one opens; intervals without any
+//! observation get a flat candle at the last close, so the series has no holes. An
+//! observation older than the current candle, e.g. replayed after a rollback, is dropped. Up to [`MAX_CANDLES`] candles are kept per pool, readable with [`candles`] or
+//! over HTTP with the `get-candles` request.
+
+use std::collections::VecDeque;
+
+use balius_sdk::{_internal::Handler, Json, WorkerResult, wit};
+use serde::{Deserialize, Serialize};
+
+use crate::{Network, PoolState, kv};
+
+/// How many candles are kept per pool; the oldest are dropped first.
+pub const MAX_CANDLES: usize = 500;
+
+const KV_CANDLE_POOLS: &str = "candle_pools";
+
+/// How a worker records candles: its network, to time observations, and interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CandleOptions {
+    pub network: Network,
+    pub interval_secs: u64,
+}
+
+/// The open, high, low and close pool price over one interval, in raw asset_a per raw
+/// asset_b, as returned by `get-candles`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    /// When the interval starts, in UNIX milliseconds
+    pub start_ms: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// How many pool observations fell in the interval; 0 for a gap carrying the last close
+    pub observations: u64,
+}
+
+impl Candle {
+    fn new(start_ms: u64, price: f64) -> Self {
+        Candle {
+            start_ms,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            observations: 1,
+        }
+    }
+
+    /// A candle for an interval without observations: flat at the previous close.
+    fn gap(start_ms: u64, close: f64) -> Self {
+        Candle {
+            observations: 0,
+            ..Candle::new(start_ms, close)
+        }
+    }
+
+    fn update(&mut self, price: f64) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.observations += 1;
+    }
+}
+
+/// The candles of one pool, oldest first and contiguous: every interval since the first
+/// observation has a candle, up to [`MAX_CANDLES`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct CandleSeries {
+    candles: VecDeque<Candle>,
+}
+
+impl CandleSeries {
+    /// Fold a price observed at `time_ms` into the candle for its interval, first rolling
+    /// over through any intervals since the last one.
+    fn observe(&mut self, interval_ms: u64, time_ms: u64, price: f64) {
+        let start_ms = time_ms - time_ms % interval_ms;
+        let Some(last) = self.candles.back_mut() else {
+            self.candles.push_back(Candle::new(start_ms, price));
+            return;
+        };
+        if start_ms < last.start_ms {
+            // A closed candle is never reopened, e.g. by an observation replayed after a rollback
+            return;
+        }
+        if start_ms == last.start_ms {
+            last.update(price);
+            return;
+        }
+
+        let close = last.close;
+        let last_start = last.start_ms;
+        // Only the gaps that would survive the cap are worth creating; saturating in case the
+        // interval was reconfigured and the last candle isn't aligned to it
+        let missed = ((start_ms - last_start) / interval_ms)
+            .saturating_sub(1)
+            .min(MAX_CANDLES as u64);
+        for gap in (1..=missed).rev() {
+            self.push(Candle::gap(start_ms - gap * interval_ms, close));
+        }
+        self.push(Candle::new(start_ms, price));
+    }
+
+    fn push(&mut self, candle: Candle) {
+        self.candles.push_back(candle);
+        while self.candles.len() > MAX_CANDLES {
+            self.candles.pop_front();
+        }
+    }
+}
+
+fn candle_series() -> kv::Namespace<CandleSeries> {
+    kv::Namespace::new("candles")
+}
+
+/// Fold an observed pool state into its pool's candles, if candles are on.
+pub(crate) fn record(options: Option<CandleOptions>, pool: &PoolState) -> WorkerResult<()> {
+    let Some(CandleOptions {
+        network,
+        interval_secs,
+    }) = options
+    else {
+        return Ok(());
+    };
+    let price = pool.pool_datum.raw_price(&pool.utxo).value();
+    if !(price.is_finite() && price > 0.0) {
+        return Ok(());
+    }
+    let pool_id = hex::encode(&pool.pool_datum.identifier);
+    let series = candle_series();
+    let mut candles = match series.get(pool_id.as_str())? {
+        Some(candles) => candles,
+        None => {
+            kv::update(KV_CANDLE_POOLS, |pools: Option<Vec<String>>| {
+                let mut pools = pools.unwrap_or_default();
+                pools.push(pool_id.clone());
+                pools
+            })?;
+            CandleSeries::default()
+        }
+    };
+    candles.observe(interval_secs * 1000, network.to_unix_time(pool.slot), price);
+    series.set(pool_id.as_str(), &candles)
+}
+
+/// The recorded candles of the pool with `pool_ident`, oldest first.
+///
+/// Always empty unless the worker config sets `candle_interval_secs`.
+pub fn candles(pool_ident: &[u8]) -> WorkerResult<Vec<Candle>> {
+    let series = candle_series().get(hex::encode(pool_ident).as_str())?;
+    Ok(series.unwrap_or_default().candles.into())
+}
+
+/// One pool's entry in the `get-candles` response.
+#[derive(Serialize)]
+struct PoolCandles {
+    /// Hex of the pool identifier
+    pool: String,
+    candles: Vec<Candle>,
+}
+
+/// Handler for `get-candles` requests, returning the recorded candles of every observed
+/// pool, oldest first.
+#[derive(Clone)]
+pub(crate) struct CandlesHandler;
+
+impl Handler for CandlesHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        _event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let pools: Vec<String> = kv::get(KV_CANDLE_POOLS)?.unwrap_or_default();
+        let series = candle_series();
+        let mut response = vec![];
+        for pool in pools {
+            let candles = series.get(pool.as_str())?.unwrap_or_default();
+            response.push(PoolCandles {
+                pool,
+                candles: candles.candles.into(),
+            });
+        }
+        Ok(Json(response).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000;
+
+    #[test]
+    fn observations_update_the_current_candle() {
+        let mut series = CandleSeries::default();
+        for (time_ms, price) in [(0, 10.0), (10_000, 12.0), (20_000, 9.0), (59_999, 11.0)] {
+            series.observe(MINUTE, time_ms, price);
+        }
+        assert_eq!(
+            series.candles,
+            [Candle {
+                start_ms: 0,
+                open: 10.0,
+                high: 12.0,
+                low: 9.0,
+                close: 11.0,
+                observations: 4,
+            }]
+        );
+    }
+
+    #[test]
+    fn gaps_carry_the_last_close() {
+        let mut series = CandleSeries::default();
+        series.observe(MINUTE, 30_000, 10.0);
+        series.observe(MINUTE, 70_000, 11.0);
+        series.observe(MINUTE, 4 * MINUTE + 5_000, 13.0);
+
+        let closes: Vec<(u64, f64, u64)> = series
+            .candles
+            .iter()
+            .map(|candle| (candle.start_ms, candle.close, candle.observations))
+            .collect();
+        assert_eq!(
+            closes,
+            [
+                (0, 10.0, 1),
+                (MINUTE, 11.0, 1),
+                (2 * MINUTE, 11.0, 0),
+                (3 * MINUTE, 11.0, 0),
+                (4 * MINUTE, 13.0, 1),
+            ]
+        );
+        assert_eq!(series.candles[2].high, 11.0);
+        assert_eq!(series.candles[4].open, 13.0);
+    }
+
+    #[test]
+    fn observations_before_the_current_candle_are_dropped() {
+        let mut series = CandleSeries::default();
+        series.observe(MINUTE, 0, 10.0);
+        series.observe(MINUTE, MINUTE, 11.0);
+        series.observe(MINUTE, 30_000, 50.0);
+
+        assert_eq!(series.candles.len(), 2);
+        assert_eq!(series.candles[0].high, 10.0);
+        let last = series.candles[1];
+        assert_eq!((last.high, last.close, last.observations), (11.0, 11.0, 1));
+    }
+
+    #[test]
+    fn series_is_bounded() {
+        let mut series = CandleSeries::default();
+        series.observe(MINUTE, 0, 1.0);
+        series.observe(MINUTE, 10_000 * MINUTE, 2.0);
+        assert_eq!(series.candles.len(), MAX_CANDLES);
+        let last = series.candles.back().unwrap();
+        assert_eq!((last.start_ms, last.close), (10_000 * MINUTE, 2.0));
+        assert_eq!(series.candles[0].close, 1.0);
+    }
+}
@@ -0,0 +1,137 @@
+//! A slim stored form of the outputs holding managed orders.
+//!
+//! The full order list is rewritten whenever one of our orders is created or spent, so it
+//! keeps only what strategies read from an order's output: its ADA and asset amounts. The
+//! datum is already parsed into [`crate::ManagedStrategy::order`], and the address and
+//! scripts aren't needed; [`crate::ManagedStrategy::fetch_utxo`] reads the full output
+//! from the ledger if they are.
+//!
+//! Order lists written before this form existed hold full outputs, which still load.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utxorpc_spec::utxorpc::v1alpha::cardano::{Asset, Multiasset, TxOutput};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct CompactOutput {
+    coin: u64,
+    assets: Vec<CompactAsset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct CompactAsset {
+    policy_id: String,
+    name: String,
+    amount: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredOutput {
+    Compact(CompactOutput),
+    Full(TxOutput),
+}
+
+pub fn serialize<S: Serializer>(output: &TxOutput, serializer: S) -> Result<S::Ok, S::Error> {
+    let assets = output
+        .assets
+        .iter()
+        .flat_map(|multiasset| {
+            multiasset.assets.iter().map(|asset| CompactAsset {
+                policy_id: hex::encode(&multiasset.policy_id),
+                name: hex::encode(&asset.name),
+                amount: asset.output_coin,
+            })
+        })
+        .collect();
+    CompactOutput {
+        coin: output.coin,
+        assets,
+    }
+    .serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TxOutput, D::Error> {
+    match StoredOutput::deserialize(deserializer)? {
+        StoredOutput::Full(output) => Ok(output),
+        StoredOutput::Compact(compact) => {
+            let mut output = TxOutput {
+                coin: compact.coin,
+                ..Default::default()
+            };
+            for asset in compact.assets {
+                let policy_id = hex::decode(&asset.policy_id).map_err(serde::de::Error::custom)?;
+                let token = Asset {
+                    name: hex::decode(&asset.name)
+                        .map_err(serde::de::Error::custom)?
+                        .into(),
+                    output_coin: asset.amount,
+                    ..Default::default()
+                };
+                match output
+                    .assets
+                    .iter_mut()
+                    .find(|multiasset| multiasset.policy_id.as_ref() == policy_id.as_slice())
+                {
+                    Some(multiasset) => multiasset.assets.push(token),
+                    None => output.assets.push(Multiasset {
+                        policy_id: policy_id.into(),
+                        assets: vec![token],
+                        ..Default::default()
+                    }),
+                }
+            }
+            Ok(output)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use utxorpc_spec::utxorpc::v1alpha::cardano::Datum;
+
+    use crate::{
+        ManagedStrategy,
+        types::{AssetId, asset_amount},
+    };
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    #[test]
+    fn stores_only_amounts() {
+        let ada = AssetId::from((vec![], vec![]));
+        let mut strategy = ManagedStrategy::mock(&[(&ada, 5_000_000), (&sberry(), 42)]);
+        strategy.utxo.address = vec![0x01; 57].into();
+        strategy.utxo.datum = Some(Datum {
+            original_cbor: vec![0xd8; 500].into(),
+            ..Default::default()
+        });
+
+        let json = serde_json::to_value(&strategy).unwrap();
+        assert_eq!(
+            json["utxo"],
+            serde_json::json!({
+                "coin": 5_000_000,
+                "assets": [{ "policy_id": hex::encode([0x99; 28]), "name": "534245525259", "amount": 42 }],
+            })
+        );
+
+        let loaded: ManagedStrategy = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.utxo.coin, 5_000_000);
+        assert_eq!(asset_amount(&loaded.utxo, &sberry()), 42);
+        assert!(loaded.utxo.datum.is_none());
+    }
+
+    #[test]
+    fn loads_full_outputs_stored_before_compaction() {
+        let strategy = ManagedStrategy::mock(&[(&sberry(), 42)]);
+        let mut json = serde_json::to_value(&strategy).unwrap();
+        json["utxo"] = serde_json::to_value(&strategy.utxo).unwrap();
+
+        let loaded: ManagedStrategy = serde_json::from_value(json).unwrap();
+        assert_eq!(asset_amount(&loaded.utxo, &sberry()), 42);
+    }
+}
@@ -0,0 +1,97 @@
+//! A minimum time between submissions for one order, as a backstop against rapid-fire.
+//!
+//! Strategies guard their own triggers, but a bug or an unexpected sequence of events
+//! could still have one submit against the same order over and over. With
+//! `min_submit_interval_secs` set in the worker config, [`crate::submit_execution`]
+//! remembers when it last submitted against each order output, and refuses another
+//! submission against that output until the interval has passed. The default of 0 leaves
+//! submissions unrestricted.
+
+use balius_sdk::WorkerResult;
+use tracing::{info, warn};
+
+use crate::{ManagedStrategy, Network, kv, types::OutputReference};
+
+/// When each order output was last submitted against, in UNIX milliseconds
+fn last_submits() -> kv::Namespace<u64> {
+    kv::Namespace::new("last_submit")
+}
+
+/// Why a submission against `utxo` within `interval_secs` of the last one should be
+/// skipped, or None if it's been long enough.
+pub(crate) fn check(
+    network: &Network,
+    utxo: &OutputReference,
+    interval_secs: u64,
+) -> WorkerResult<Option<String>> {
+    if interval_secs == 0 {
+        return Ok(None);
+    }
+    let (Some(last_ms), Some(now_ms)) = (last_submits().get(utxo)?, network.now_ms()?) else {
+        return Ok(None);
+    };
+    let elapsed_ms = now_ms.saturating_sub(last_ms);
+    if elapsed_ms < interval_secs * 1000 {
+        info!(
+            "skipping submission for {utxo:?}: the last was {elapsed_ms}ms ago, within min_submit_interval_secs of {interval_secs}"
+        );
+        return Ok(Some(format!(
+            "{utxo:?} was last submitted {elapsed_ms}ms ago, within min_submit_interval_secs of {interval_secs}"
+        )));
+    }
+    Ok(None)
+}
+
+/// Note a submission against `utxo`, if a cooldown of `interval_secs` is configured.
+///
+/// This is best-effort: a KV failure is logged rather than failing the submission.
+pub(crate) fn record(network: &Network, utxo: &OutputReference, interval_secs: u64) {
+    if interval_secs == 0 {
+        return;
+    }
+    if let Err(err) = record_now(network, utxo) {
+        warn!("failed to record the submission time for {utxo:?}: {err}");
+    }
+}
+
+fn record_now(network: &Network, utxo: &OutputReference) -> WorkerResult<()> {
+    match network.now_ms()? {
+        Some(now_ms) => last_submits().set(utxo, &now_ms),
+        None => Ok(()),
+    }
+}
+
+/// Drop the submission times of spent orders, since their outputs can't be submitted
+/// against again.
+pub(crate) fn forget(spent: &[ManagedStrategy]) -> WorkerResult<()> {
+    for order in spent {
+        last_submits().delete(&order.output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Strategy, sim::Simulator};
+
+    #[test]
+    fn refuses_submissions_within_the_interval() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let network = Network::Preview;
+        let order = ManagedStrategy::mock(&[]);
+        kv::observe_slot(100);
+        record_now(&network, &order.output).unwrap();
+
+        kv::observe_slot(120);
+        assert!(check(&network, &order.output, 30).unwrap().is_some());
+        assert!(check(&network, &order.output, 0).unwrap().is_none());
+        kv::observe_slot(130);
+        assert!(check(&network, &order.output, 30).unwrap().is_none());
+
+        forget(&[order.clone()]).unwrap();
+        kv::observe_slot(131);
+        assert!(check(&network, &order.output, 30).unwrap().is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use balius_sdk::{
+    _internal::Handler,
+    Json, Params,
+    txbuilder::{codec::minicbor, plutus::PlutusData},
+    wit,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{self, OrderDatum, PoolDatum, PoolDatumError, PoolDatumV1};
+
+/// The outcome of one attempt to read a datum.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Attempt<T> {
+    Parsed(T),
+    Error(String),
+}
+
+impl<T> Attempt<T> {
+    fn from_result<E: std::fmt::Debug>(result: Result<T, E>) -> Self {
+        match result {
+            Ok(value) => Attempt::Parsed(value),
+            Err(err) => Attempt::Error(format!("{err:?}")),
+        }
+    }
+}
+
+/// What a datum decodes to, as returned by `decode-datum`.
+#[derive(Serialize, Debug)]
+struct DatumDiagnosis {
+    /// The generic Plutus data, in Rust debug notation; if this fails, nothing else can parse
+    plutus_data: Attempt<String>,
+    pool_v3: Attempt<PoolDatum>,
+    /// Normalized to the v3 layout, like pools the worker tracks
+    pool_v1: Attempt<PoolDatum>,
+    order: Attempt<OrderDatum>,
+    /// Why the datum, though laid out like a v3 pool, isn't one the worker can read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unsupported_pool: Option<String>,
+}
+
+fn diagnose(bytes: &[u8]) -> DatumDiagnosis {
+    let plutus_data = minicbor::decode::<PlutusData>(bytes).map(|data| format!("{data:?}"));
+    DatumDiagnosis {
+        plutus_data: Attempt::from_result(plutus_data),
+        pool_v3: Attempt::from_result(types::parse::<PoolDatum>(bytes)),
+        pool_v1: Attempt::from_result(types::parse::<PoolDatumV1>(bytes).map(PoolDatum::from)),
+        order: Attempt::from_result(types::parse::<OrderDatum>(bytes)),
+        unsupported_pool: match types::parse_pool_datum(bytes) {
+            Err(err @ PoolDatumError::UnsupportedPool { .. }) => Some(err.to_string()),
+            _ => None,
+        },
+    }
+}
+
+/// Request parameters for decode-datum
+#[derive(Deserialize)]
+struct DecodeDatumParams {
+    /// The datum's CBOR, hex-encoded
+    datum: String,
+}
+
+/// Handler for `decode-datum` requests, reporting how a datum parses as each kind the worker
+/// recognizes, and why it doesn't when it doesn't. Useful when a pool or order the worker
+/// should be tracking isn't.
+#[derive(Clone)]
+pub(crate) struct DecodeDatumHandler;
+
+impl Handler for DecodeDatumHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let params: Params<DecodeDatumParams> = event.try_into().map_err(|_| wit::HandleError {
+            message: "invalid request parameters".to_string(),
+            code: 400,
+        })?;
+        let bytes = hex::decode(params.datum.trim()).map_err(|_| wit::HandleError {
+            message: "invalid datum hex encoding".to_string(),
+            code: 400,
+        })?;
+        Ok(Json(diagnose(&bytes)).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PoolState, types::AssetId};
+
+    #[test]
+    fn diagnoses_a_pool_datum() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let datum = PoolState::mock(2_000, 1_000, (&ada, &sberry)).pool_datum;
+        let diagnosis = diagnose(&types::serialize(datum.clone()));
+
+        assert!(matches!(diagnosis.plutus_data, Attempt::Parsed(_)));
+        let Attempt::Parsed(pool) = diagnosis.pool_v3 else {
+            panic!("expected a v3 pool datum");
+        };
+        assert_eq!(pool.identifier, datum.identifier);
+        assert!(matches!(diagnosis.pool_v1, Attempt::Error(_)));
+        assert!(matches!(diagnosis.order, Attempt::Error(_)));
+        assert_eq!(diagnosis.unsupported_pool, None);
+    }
+
+    #[test]
+    fn reports_bytes_that_arent_cbor() {
+        let diagnosis = diagnose(&[0xff, 0x00]);
+        assert!(matches!(diagnosis.plutus_data, Attempt::Error(_)));
+        assert!(matches!(diagnosis.pool_v3, Attempt::Error(ref err) if err.contains("Decode")));
+    }
+}
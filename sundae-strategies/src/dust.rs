@@ -0,0 +1,109 @@
+//! A floor on execution size, so strategies don't spend fees on trades too small to matter.
+//!
+//! With `min_order_value` set in the worker config, [`crate::submit_execution`] refuses
+//! executions that offer or ask for less than that many raw units of the worker's
+//! `base_token`, or of lovelace when its config has no `base_token`. Only the sides of an
+//! execution in that asset are measured: a swap between two other assets, a withdrawal, or
+//! a strategy order is never dust. Refused executions are logged and counted under
+//! `sundae_strategy_dust_skipped_total`.
+
+use crate::{
+    options,
+    types::{AssetId, Order, SingletonValue},
+};
+
+/// A worker's `min_order_value`, in raw units of `asset`.
+pub(crate) struct MinOrderValue {
+    pub asset: AssetId,
+    pub amount: u64,
+}
+
+impl MinOrderValue {
+    /// The amount of the measured asset in `value`, if it is that asset.
+    fn measure(&self, value: &SingletonValue) -> Option<u64> {
+        (value.0 == self.asset.policy_id && value.1 == self.asset.asset_name).then_some(value.2)
+    }
+
+    /// The smallest measured side of `details` below the minimum, if any.
+    fn shortfall(&self, details: &Order) -> Option<u64> {
+        let sides = match details {
+            Order::Swap {
+                offer,
+                min_received,
+            } => vec![offer, min_received],
+            Order::Deposit { assets } => vec![&assets.0, &assets.1],
+            Order::Withdraw { .. } | Order::Strategy { .. } => vec![],
+        };
+        sides
+            .into_iter()
+            .filter_map(|side| self.measure(side))
+            .filter(|amount| *amount < self.amount)
+            .min()
+    }
+}
+
+/// Why `details` is too small to submit under the worker's `min_order_value`, or None if it
+/// isn't.
+pub fn check(details: &Order) -> Option<String> {
+    check_against(options::current().min_order_value().as_ref(), details)
+}
+
+/// Why `details` is too small to submit under `min`, or None if it isn't or there's no
+/// minimum.
+pub(crate) fn check_against(min: Option<&MinOrderValue>, details: &Order) -> Option<String> {
+    let min = min?;
+    let amount = min.shortfall(details)?;
+    Some(format!(
+        "{amount} {} is below the min_order_value of {}",
+        min.asset.name_to_string(),
+        min.amount
+    ))
+}
+
+/// Whether `details` is too small to submit under the worker's `min_order_value`.
+///
+/// Strategies that track what they've traded can use this to leave dust untraded instead
+/// of having [`crate::submit_execution`] refuse it.
+pub fn is_dust(details: &Order) -> bool {
+    check(details).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    #[test]
+    fn measures_only_the_base_token_sides() {
+        let ada = AssetId::from((vec![], vec![]));
+        let min = MinOrderValue {
+            asset: AssetId::from((vec![], vec![])),
+            amount: 2_000_000,
+        };
+
+        assert_eq!(
+            min.shortfall(&Order::swap((&ada, 1_000_000), (&sberry(), 10))),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            min.shortfall(&Order::swap((&sberry(), 10), (&ada, 1_500_000))),
+            Some(1_500_000)
+        );
+        assert_eq!(
+            min.shortfall(&Order::swap((&ada, 2_000_000), (&sberry(), 10))),
+            None
+        );
+        // Nothing to measure a swap between other assets against
+        assert_eq!(
+            min.shortfall(&Order::swap((&sberry(), 1), (&sberry(), 1))),
+            None
+        );
+        assert_eq!(
+            min.shortfall(&Order::deposit((&ada, 10), (&sberry(), 10))),
+            Some(10)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Orders that stop being acted on at a configured time.
+//!
+//! A worker with an `expires_at` config option, in UNIX seconds, checks each event against
+//! it with [`is_expired`], and builds the validity range of its executions with
+//! [`validity_range`]. An execution stays valid for a while after the slot it was submitted
+//! at, so one submitted just before expiry could otherwise fill after it; the range is cut
+//! off at `expires_at` instead.
+
+use crate::types::Interval;
+
+/// Whether `now_ms` is past `expires_at`. Never, if there's no expiry.
+pub fn is_expired(expires_at: Option<u64>, now_ms: u64) -> bool {
+    expires_at.is_some_and(|expires_at| now_ms > expires_at.saturating_mul(1000))
+}
+
+/// `seconds` either side of `now_ms`, but ending no later than `expires_at`.
+pub fn validity_range(expires_at: Option<u64>, now_ms: u64, seconds: u64) -> Interval {
+    let delta_ms = seconds.saturating_mul(1000);
+    let end = now_ms.saturating_add(delta_ms);
+    let end = expires_at.map_or(end, |expires_at| end.min(expires_at.saturating_mul(1000)));
+    Interval::inclusive_range(now_ms.saturating_sub(delta_ms), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IntervalBoundType;
+
+    fn bounds(range: Interval) -> (u64, u64) {
+        match (range.lower_bound.bound_type, range.upper_bound.bound_type) {
+            (IntervalBoundType::Finite(start), IntervalBoundType::Finite(end)) => (start, end),
+            _ => panic!("expected finite bounds"),
+        }
+    }
+
+    #[test]
+    fn orders_without_an_expiry_never_expire() {
+        assert!(!is_expired(None, u64::MAX));
+        assert_eq!(
+            bounds(validity_range(None, 900_000, 20)),
+            (880_000, 920_000)
+        );
+    }
+
+    #[test]
+    fn expiry_cuts_off_the_validity_window() {
+        assert!(!is_expired(Some(1_000), 1_000_000));
+        assert!(is_expired(Some(1_000), 1_000_001));
+
+        assert_eq!(
+            bounds(validity_range(Some(1_000), 900_000, 20)),
+            (880_000, 920_000)
+        );
+        assert_eq!(
+            bounds(validity_range(Some(1_000), 990_000, 20)),
+            (970_000, 1_000_000)
+        );
+    }
+}
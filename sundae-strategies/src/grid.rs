@@ -0,0 +1,58 @@
+//! Price lines, and detecting which of them a price move crossed.
+//!
+//! A set of lines is a slice of prices in ascending order. Where the price sits among them
+//! is its index: how many lines lie strictly below it. A price exactly on a line counts as
+//! not above it, so a falling price crosses a line on reaching it, while a rising price only
+//! crosses it once strictly above.
+
+/// How many of the ascending `lines` lie strictly below `price`.
+pub fn line_index(lines: &[f64], price: f64) -> usize {
+    lines.iter().take_while(|line| **line < price).count()
+}
+
+/// The index of `price` among the ascending `lines`, and the lines crossed moving there from
+/// `previous_index`, nearest first. A `previous_index` past the last line is clamped onto it.
+pub fn crossed_lines(lines: &[f64], previous_index: usize, price: f64) -> (usize, Vec<f64>) {
+    let previous_index = previous_index.min(lines.len());
+    let new_index = line_index(lines, price);
+    let crossed = if new_index > previous_index {
+        // Lines crossed going up, nearest first
+        lines[previous_index..new_index].to_vec()
+    } else {
+        // Lines crossed going down, nearest first
+        lines[new_index..previous_index]
+            .iter()
+            .rev()
+            .copied()
+            .collect()
+    };
+    (new_index, crossed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+
+    #[test]
+    fn crossings_are_listed_nearest_first() {
+        assert_eq!(crossed_lines(&LINES, 1, 3.5), (3, vec![2.0, 3.0]));
+        assert_eq!(crossed_lines(&LINES, 3, 1.5), (1, vec![3.0, 2.0]));
+        assert_eq!(crossed_lines(&LINES, 2, 2.5), (2, vec![]));
+    }
+
+    #[test]
+    fn a_line_is_crossed_downward_on_reaching_it() {
+        assert_eq!(crossed_lines(&LINES, 2, 2.0), (1, vec![2.0]));
+        assert_eq!(crossed_lines(&LINES, 1, 2.0), (1, vec![]));
+    }
+
+    #[test]
+    fn an_index_past_the_lines_is_clamped() {
+        assert_eq!(
+            crossed_lines(&LINES, 10, 0.5),
+            (0, vec![4.0, 3.0, 2.0, 1.0])
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::VecDeque;
+
+use balius_sdk::WorkerResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{PoolState, kv};
+
+/// A bounded history of observed prices, oldest first, for indicator-based strategies.
+///
+/// Once `capacity` samples are held, each [`push`](PriceHistory::push) evicts the oldest.
+/// It serializes to JSON, so workers can keep one per strategy in a [`crate::kv::Namespace`]:
+///
+/// ```ignore
+/// let histories = kv::Namespace::<PriceHistory>::new("price_history");
+/// let mut history = histories.get_or_init(auth, || PriceHistory::new(100))?;
+/// history.push(pool.slot, price);
+/// histories.set(auth, &history)?;
+/// let trend = history.sma(20);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PriceHistory {
+    capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+/// A single price observation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub slot: u64,
+    pub price: f64,
+}
+
+impl PriceHistory {
+    /// An empty history keeping at most `capacity` samples (at least one).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Record the price observed at `slot`, evicting the oldest sample if full.
+    pub fn push(&mut self, slot: u64, price: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample { slot, price });
+    }
+
+    /// The most recent sample, if any.
+    pub fn latest(&self) -> Option<Sample> {
+        self.samples.back().copied()
+    }
+
+    /// Every held sample, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    /// The prices of the last `window` samples, oldest first, or None if fewer are held.
+    fn window(&self, window: usize) -> Option<impl Iterator<Item = f64> + '_> {
+        if window == 0 || window > self.samples.len() {
+            return None;
+        }
+        let skip = self.samples.len() - window;
+        Some(self.samples.iter().skip(skip).map(|sample| sample.price))
+    }
+
+    /// The simple moving average of the last `window` prices.
+    ///
+    /// Returns None if fewer than `window` samples are held.
+    pub fn sma(&self, window: usize) -> Option<f64> {
+        Some(self.window(window)?.sum::<f64>() / window as f64)
+    }
+
+    /// The exponential moving average over `window` observations, with smoothing factor
+    /// `2 / (window + 1)`, seeded with the simple average of the oldest `window` samples and
+    /// carried through every later sample.
+    ///
+    /// Returns None if fewer than `window` samples are held.
+    pub fn ema(&self, window: usize) -> Option<f64> {
+        if window == 0 || window > self.samples.len() {
+            return None;
+        }
+        let alpha = 2.0 / (window as f64 + 1.0);
+        let mut prices = self.samples.iter().map(|sample| sample.price);
+        let seed = prices.by_ref().take(window).sum::<f64>() / window as f64;
+        Some(prices.fold(seed, |ema, price| alpha * price + (1.0 - alpha) * ema))
+    }
+
+    /// The population standard deviation of the last `window` prices.
+    ///
+    /// Returns None if fewer than `window` samples are held.
+    pub fn stddev(&self, window: usize) -> Option<f64> {
+        let mean = self.sma(window)?;
+        let variance = self
+            .window(window)?
+            .map(|price| (price - mean).powi(2))
+            .sum::<f64>()
+            / window as f64;
+        Some(variance.sqrt())
+    }
+
+    /// The population standard deviation of the log returns between the last `window + 1`
+    /// prices, i.e. over `window` returns.
+    ///
+    /// The result is per observation, not annualized: pool observations arrive whenever the
+    /// pool trades rather than at a fixed interval, so there's no sound way to scale it to a
+    /// calendar period. Compare it against other values from the same history, or use it
+    /// as a fractional move, e.g. `0.02` is a typical move of about 2% between observations.
+    ///
+    /// Returns None if fewer than `window + 1` samples are held, or any price in the window
+    /// isn't positive.
+    pub fn realized_volatility(&self, window: usize) -> Option<f64> {
+        let prices: Vec<f64> = self.window(window.checked_add(1)?)?.collect();
+        if window == 0 || !prices.iter().all(|price| *price > 0.0) {
+            return None;
+        }
+        let returns: Vec<f64> = prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+        let mean = returns.iter().sum::<f64>() / window as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / window as f64;
+        Some(variance.sqrt())
+    }
+
+    /// The Relative Strength Index over the last `period` price changes, from 0 to 100.
+    ///
+    /// Gains and losses are averaged simply over the window, so this is Cutler's RSI rather
+    /// than Wilder's smoothed one: each value depends only on the window, not on how long
+    /// the history has been running. A window with no losses is 100, and a flat one is 50.
+    ///
+    /// Returns None if fewer than `period + 1` samples are held.
+    pub fn rsi(&self, period: usize) -> Option<f64> {
+        let prices: Vec<f64> = self.window(period.checked_add(1)?)?.collect();
+        if period == 0 {
+            return None;
+        }
+        let (mut gains, mut losses) = (0.0, 0.0);
+        for change in prices.windows(2).map(|w| w[1] - w[0]) {
+            if change > 0.0 {
+                gains += change;
+            } else {
+                losses -= change;
+            }
+        }
+        if losses == 0.0 {
+            return Some(if gains == 0.0 { 50.0 } else { 100.0 });
+        }
+        // The averages share a divisor, so their ratio is the ratio of the totals
+        Some(100.0 - 100.0 / (1.0 + gains / losses))
+    }
+}
+
+/// Which price a strategy acts on for each pool observation, configured as `"spot"` or
+/// `{"twap": {"window": 5}}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    /// The pool's price as observed.
+    #[default]
+    Spot,
+    /// The average of the last `window` prices observed for the pool, so a single
+    /// manipulated or fleeting price can't trigger the strategy on its own. Until `window`
+    /// prices have been observed, the average of those seen so far.
+    Twap { window: usize },
+}
+
+fn source_histories() -> kv::Namespace<PriceHistory> {
+    kv::Namespace::new("price_source_history")
+}
+
+impl PriceSource {
+    /// Checks the configured window, for use from a worker's config validation.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            PriceSource::Twap { window: 0 } => {
+                Err("price_source twap window must be > 0, got 0".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The price to act on, given the `price` just observed for `pool`.
+    ///
+    /// Under `twap`, the observation is recorded in a history kept per pool, one price per
+    /// slot, so it can be called for each order in a pool without skewing the average. A
+    /// later observation in the same slot replaces the earlier one, leaving the slot's last
+    /// price. The history doesn't know how `price` is scaled or oriented, so a worker must
+    /// pass prices for the same pool the same way every time, and should only pass prices
+    /// of pools its orders trade in, since each pool's history is kept.
+    pub fn price(&self, pool: &PoolState, price: f64) -> WorkerResult<f64> {
+        let PriceSource::Twap { window } = *self else {
+            return Ok(price);
+        };
+        let id = hex::encode(&pool.pool_datum.identifier);
+        let histories = source_histories();
+        let mut history = histories.get_or_init(id.as_str(), || PriceHistory::new(window))?;
+        if history.capacity() != window {
+            // The window was reconfigured; start over rather than average the wrong span
+            history = PriceHistory::new(window);
+        }
+        match history.samples.back_mut() {
+            Some(latest) if latest.slot == pool.slot && latest.price == price => {}
+            Some(latest) if latest.slot == pool.slot => {
+                latest.price = price;
+                histories.set(id.as_str(), &history)?;
+            }
+            _ => {
+                history.push(pool.slot, price);
+                histories.set(id.as_str(), &history)?;
+            }
+        }
+        Ok(history.sma(history.len()).unwrap_or(price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Strategy, sim::Simulator, types::AssetId};
+
+    fn history(prices: &[f64]) -> PriceHistory {
+        let mut history = PriceHistory::new(prices.len());
+        for (slot, price) in prices.iter().enumerate() {
+            history.push(slot as u64, *price);
+        }
+        history
+    }
+
+    #[test]
+    fn oldest_samples_are_evicted() {
+        let mut history = PriceHistory::new(3);
+        for (slot, price) in [1.0, 2.0, 3.0, 4.0].into_iter().enumerate() {
+            history.push(slot as u64, price);
+        }
+        assert_eq!(history.len(), 3);
+        let prices: Vec<f64> = history.samples().map(|sample| sample.price).collect();
+        assert_eq!(prices, vec![2.0, 3.0, 4.0]);
+        assert_eq!(
+            history.latest(),
+            Some(Sample {
+                slot: 3,
+                price: 4.0
+            })
+        );
+    }
+
+    #[test]
+    fn averages_cover_the_latest_window() {
+        let history = history(&[10.0, 2.0, 4.0, 6.0]);
+        assert_eq!(history.sma(3), Some(4.0));
+        assert_eq!(history.sma(4), Some(5.5));
+        assert_eq!(history.sma(5), None);
+        assert_eq!(history.sma(0), None);
+    }
+
+    #[test]
+    fn ema_is_seeded_with_the_sma() {
+        let history = history(&[2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(history.ema(4), history.sma(4));
+        // Seed (2 + 4 + 6) / 3 = 4, then 0.5 * 8 + 0.5 * 4
+        assert_eq!(history.ema(3), Some(6.0));
+        assert_eq!(history.ema(5), None);
+    }
+
+    #[test]
+    fn stddev_of_the_latest_window() {
+        let history = history(&[100.0, 2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(history.stddev(8), Some(2.0));
+        assert_eq!(history.stddev(2), Some(1.0));
+        assert_eq!(history.stddev(10), None);
+    }
+
+    #[test]
+    fn realized_volatility_of_log_returns() {
+        // Alternating 10% moves have log returns of ln(1.1) and -ln(1.1)
+        let choppy = history(&[100.0, 110.0, 100.0, 110.0, 100.0]);
+        let volatility = choppy.realized_volatility(4).unwrap();
+        assert!((volatility - 1.1f64.ln()).abs() < 1e-12);
+
+        let flat = history(&[2.0; 4]);
+        assert_eq!(flat.realized_volatility(3), Some(0.0));
+    }
+
+    #[test]
+    fn realized_volatility_needs_enough_positive_history() {
+        let short = history(&[100.0, 110.0, 100.0]);
+        assert!(short.realized_volatility(2).is_some());
+        assert_eq!(short.realized_volatility(3), None);
+        assert_eq!(short.realized_volatility(0), None);
+        assert_eq!(history(&[0.0, 110.0, 100.0]).realized_volatility(2), None);
+    }
+
+    #[test]
+    fn rsi_compares_average_gains_and_losses() {
+        // Gains of 4 + 4, losses of 1 + 1: RS = 4, RSI = 100 - 100 / 5
+        let history = history(&[50.0, 10.0, 14.0, 13.0, 17.0, 16.0]);
+        assert_eq!(history.rsi(4), Some(80.0));
+        // Only the latest window counts, however far the price fell before it
+        assert_eq!(history.rsi(1), Some(0.0));
+        assert_eq!(history.rsi(6), None);
+        assert_eq!(history.rsi(0), None);
+    }
+
+    #[test]
+    fn rsi_of_one_sided_windows() {
+        assert_eq!(history(&[1.0, 2.0, 3.0]).rsi(2), Some(100.0));
+        assert_eq!(history(&[3.0, 2.0, 1.0]).rsi(2), Some(0.0));
+        assert_eq!(history(&[2.0; 3]).rsi(2), Some(50.0));
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let history = history(&[1.0, 2.5]);
+        let json = serde_json::to_string(&history).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PriceHistory>(&json).unwrap(),
+            history
+        );
+    }
+
+    fn pool() -> PoolState {
+        let ada = AssetId::from((vec![], vec![]));
+        PoolState::mock(
+            100,
+            100,
+            (&ada, &AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))),
+        )
+    }
+
+    #[test]
+    fn spot_is_the_observed_price() {
+        assert_eq!(PriceSource::default(), PriceSource::Spot);
+        let source: PriceSource = serde_json::from_value(serde_json::json!("spot")).unwrap();
+        assert_eq!(source, PriceSource::Spot);
+        let pool = pool();
+        assert_eq!(source.price(&pool, 3.0).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn twap_averages_one_price_per_slot() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let source: PriceSource =
+            serde_json::from_value(serde_json::json!({ "twap": { "window": 3 } })).unwrap();
+        assert_eq!(source, PriceSource::Twap { window: 3 });
+
+        let mut pool = pool();
+        let mut observe = |slot, price| {
+            pool.slot = slot;
+            source.price(&pool, price).unwrap()
+        };
+        // Averages what's been seen until the window fills
+        assert_eq!(observe(1, 4.0), 4.0);
+        assert_eq!(observe(2, 8.0), 6.0);
+        // A second order in the same pool update doesn't count the price twice
+        assert_eq!(observe(2, 8.0), 6.0);
+        // A later update in the same slot replaces its price
+        assert_eq!(observe(2, 6.0), 5.0);
+        assert_eq!(observe(3, 2.0), 4.0);
+        assert_eq!(observe(4, 1.0), 3.0);
+    }
+
+    #[test]
+    fn twap_window_must_be_positive() {
+        assert!(PriceSource::Spot.validate().is_ok());
+        assert!(PriceSource::Twap { window: 1 }.validate().is_ok());
+        assert!(PriceSource::Twap { window: 0 }.validate().is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Optional jitter on when executions become valid, to make them harder to sandwich.
+//!
+//! An execution submitted the moment a trigger is crossed, valid from that moment, is easy
+//! to anticipate. With `execution_jitter_secs` set in the worker config, each execution
+//! instead only becomes valid a pseudo-random delay of up to that many seconds after it is
+//! submitted, deferring when a scooper can apply it. This trades a little timing precision
+//! for MEV resistance: a stop may fill a few seconds later, at a slightly different price.
+//!
+//! The jitter must be shorter than the worker's validity window, which is the one it
+//! declared with [`crate::Strategy::with_validity_window`], else its `validity_window_secs`
+//! option, else [`crate::DEFAULT_VALIDITY_WINDOW_SECS`]; longer jitter fails the config.
+//!
+//! The delay is derived from the order's output reference and the current slot rather than
+//! a random source, so the same execution is always jittered the same way, and simulations
+//! are reproducible.
+
+use tracing::warn;
+
+use crate::{
+    Network,
+    types::{Interval, IntervalBoundType, OutputReference},
+};
+
+/// A delay of up to `max_secs` seconds, in milliseconds, that is the same for every call
+/// with the same `utxo` and `slot`.
+fn delay_ms(utxo: &OutputReference, slot: u64, max_secs: u64) -> u64 {
+    // FNV-1a: not cryptographic, but enough to spread delays evenly across the window
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let bytes = utxo
+        .transaction_id
+        .0
+        .iter()
+        .copied()
+        .chain(utxo.output_index.to_le_bytes())
+        .chain(slot.to_le_bytes());
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash % (max_secs.saturating_mul(1000) + 1)
+}
+
+/// Move the start of `validity_range` to no earlier than `earliest_ms`. Ranges without
+/// finite bounds are left alone, and so is a range the delay would collapse, since an
+/// execution valid for an instant or less is as good as never valid.
+fn delay_start(mut validity_range: Interval, earliest_ms: u64) -> Interval {
+    let (IntervalBoundType::Finite(start), IntervalBoundType::Finite(end)) = (
+        &validity_range.lower_bound.bound_type,
+        &validity_range.upper_bound.bound_type,
+    ) else {
+        return validity_range;
+    };
+    if earliest_ms >= *end {
+        warn!(
+            "not applying execution jitter: it would leave no time before the range ends at {end}"
+        );
+        return validity_range;
+    }
+    let start = (*start).max(earliest_ms);
+    validity_range.lower_bound.bound_type = IntervalBoundType::Finite(start);
+    validity_range
+}
+
+/// Apply up to `max_secs` of jitter to the `validity_range` of an execution for `utxo`, so
+/// it only becomes valid after a delay. With no jitter, or before the worker has observed a
+/// slot, the range is returned unchanged.
+pub(crate) fn apply(
+    network: &Network,
+    utxo: &OutputReference,
+    validity_range: Interval,
+    max_secs: u64,
+) -> Interval {
+    if max_secs == 0 {
+        return validity_range;
+    }
+    let slot = crate::kv::current_slot();
+    if slot == 0 {
+        return validity_range;
+    }
+    let earliest_ms = network
+        .to_unix_time(slot)
+        .saturating_add(delay_ms(utxo, slot, max_secs));
+    delay_start(validity_range, earliest_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TransactionId;
+
+    fn output(index: u64) -> OutputReference {
+        OutputReference {
+            transaction_id: TransactionId(vec![0xab; 32]),
+            output_index: index,
+        }
+    }
+
+    fn bounds(range: &Interval) -> (u64, u64) {
+        match (&range.lower_bound.bound_type, &range.upper_bound.bound_type) {
+            (IntervalBoundType::Finite(start), IntervalBoundType::Finite(end)) => (*start, *end),
+            _ => panic!("expected a finite range"),
+        }
+    }
+
+    #[test]
+    fn delays_are_reproducible_and_bounded() {
+        let delay = delay_ms(&output(0), 1_000, 30);
+        assert_eq!(delay, delay_ms(&output(0), 1_000, 30));
+        assert!(delay <= 30_000);
+
+        // Different orders and slots spread across the window
+        let delays: Vec<u64> = (0..8).map(|i| delay_ms(&output(i), 1_000, 30)).collect();
+        assert!(delays.iter().any(|d| *d != delays[0]));
+        assert_ne!(delay, delay_ms(&output(0), 1_001, 30));
+    }
+
+    #[test]
+    fn the_start_moves_later_but_never_collapses_the_range() {
+        let range = Interval::inclusive_range(1_000, 5_000);
+        assert_eq!(bounds(&delay_start(range.clone(), 3_000)), (3_000, 5_000));
+        assert_eq!(bounds(&delay_start(range.clone(), 500)), (1_000, 5_000));
+        assert_eq!(bounds(&delay_start(range.clone(), 5_000)), (1_000, 5_000));
+        assert_eq!(bounds(&delay_start(range, 9_000)), (1_000, 5_000));
+    }
+}
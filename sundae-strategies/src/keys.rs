@@ -2,15 +2,31 @@ use balius_sdk::{Config, Error, Json, Params, WorkerResult};
 use serde::Serialize;
 use std::collections::HashMap;
 
+use crate::STRATEGY_KEY;
+
+/// The public key of the worker's default signer, which signs every strategy execution.
+pub fn public_key() -> WorkerResult<Vec<u8>> {
+    balius_sdk::get_public_keys()
+        .remove(STRATEGY_KEY)
+        .ok_or_else(|| Error::Internal("key not found".into()))
+}
+
+/// The public key of the worker's default signer, hex-encoded.
+///
+/// This is the `signer` to put in a strategy order's `StrategyAuthorization::Signature`
+/// so the worker takes custody of it: the raw ed25519 public key, not a hash of it. It's
+/// the same value the `get-signer-key` request returns as `signer`, and `baliusd show-keys
+/// default` prints, so a worker can log or serve it without a round-trip.
+pub fn public_key_hex() -> WorkerResult<String> {
+    Ok(hex::encode(public_key()?))
+}
+
 pub fn get_signer_key<T>(
     _: Config<T>,
     _: Params<HashMap<String, String>>,
 ) -> WorkerResult<Json<SignerKey>> {
-    let Some(key) = balius_sdk::get_public_keys().remove("default") else {
-        return Err(Error::Internal("key not found".into()));
-    };
     Ok(Json(SignerKey {
-        signer: hex::encode(key),
+        signer: public_key_hex()?,
     }))
 }
 
@@ -1,17 +1,490 @@
-use balius_sdk::{WorkerResult, wit::balius::app::kv};
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use balius_sdk::{Error, WorkerResult, wit::balius::app::kv};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    KV_LAST_PROCESSED_SLOT, ManagedStrategy,
+    types::{OutputReference, StrategyAuthorization},
+};
+
+/// The most recent slot observed by the strategy handlers; used as the clock for expiring entries.
+static CURRENT_SLOT: AtomicU64 = AtomicU64::new(0);
+
+/// A value stored with [`set_with_ttl`].
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expiring<T> {
+    expires_at_slot: u64,
+    value: T,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Stored<T> {
+    Expiring(Expiring<T>),
+    Plain(T),
+}
+
 /// Retrieve a value from the KV store. Returns None if the value does not already exist.
+///
+/// Entries written with [`set_with_ttl`] are returned as None (and deleted) once they have expired.
 pub fn get<D: for<'a> Deserialize<'a>>(key: &str) -> WorkerResult<Option<D>> {
-    match kv::get_value(key) {
-        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
-        Err(kv::KvError::NotFound(_)) => Ok(None),
-        Err(err) => Err(err.into()),
+    let bytes = match backend::get_value(key) {
+        Ok(bytes) => bytes,
+        Err(kv::KvError::NotFound(_)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    match serde_json::from_slice(&bytes)? {
+        Stored::Plain(value) => Ok(Some(value)),
+        Stored::Expiring(entry) if is_expired(entry.expires_at_slot, clock()?.unwrap_or(0)) => {
+            delete(key)?;
+            Ok(None)
+        }
+        Stored::Expiring(entry) => Ok(Some(entry.value)),
     }
 }
 
 /// Store a value in the KV store.
 pub fn set<S: Serialize>(key: &str, value: &S) -> WorkerResult<()> {
-    kv::set_value(key, &serde_json::to_vec(value)?)?;
+    backend::set_value(key, &serde_json::to_vec(value)?)?;
     Ok(())
 }
+
+/// Store a value in the KV store which [`get`] treats as absent after `ttl_secs` seconds.
+///
+/// Time is measured against the latest slot the strategy has observed (slots are one second
+/// long on every supported network), so an entry only expires once chain events move past it.
+/// Request handlers, and a worker that has just restarted, haven't observed a slot; they
+/// fall back to the last slot the worker recorded. Before it has ever recorded one there is
+/// no clock to expire the entry by, so the write is refused.
+pub fn set_with_ttl<S: Serialize>(key: &str, value: &S, ttl_secs: u64) -> WorkerResult<()> {
+    let Some(now) = clock()? else {
+        return Err(Error::Internal(format!(
+            "can't store {key} with a TTL before the worker has observed a slot"
+        )));
+    };
+    let entry = Expiring {
+        expires_at_slot: now.saturating_add(ttl_secs),
+        value,
+    };
+    set(key, &entry)
+}
+
+/// Remove a value from the KV store.
+///
+/// The KV interface has no removal call, so the key is overwritten with an empty payload,
+/// which [`get`] and [`list_keys`] treat as absent.
+pub fn delete(key: &str) -> WorkerResult<()> {
+    backend::set_value(key, &[])?;
+    Ok(())
+}
+
+/// List every stored key that starts with `prefix`, e.g. `"peak_price:"`.
+///
+/// The scan is performed by the runtime's KV backend; deleted keys are filtered out.
+pub fn list_keys(prefix: &str) -> WorkerResult<Vec<String>> {
+    let mut keys = vec![];
+    for key in backend::list_values(prefix)? {
+        match backend::get_value(&key) {
+            Ok(bytes) if !bytes.is_empty() => keys.push(key),
+            Ok(_) | Err(kv::KvError::NotFound(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(keys)
+}
+
+/// Retrieve a value from the KV store, storing and returning `init()` if it does not already exist.
+pub fn get_or_init<T, F>(key: &str, init: F) -> WorkerResult<T>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+    F: FnOnce() -> T,
+{
+    match get(key)? {
+        Some(value) => Ok(value),
+        None => {
+            let value = init();
+            set(key, &value)?;
+            Ok(value)
+        }
+    }
+}
+
+/// Read a value, transform it, and store the result, returning the new value.
+///
+/// Balius delivers events to a worker one at a time and commits the KV writes made
+/// while handling an event together, so a read-modify-write performed inside a single
+/// `update` call can't interleave with another event touching the same key.
+pub fn update<T, F>(key: &str, f: F) -> WorkerResult<T>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+    F: FnOnce(Option<T>) -> T,
+{
+    let value = f(get(key)?);
+    set(key, &value)?;
+    Ok(value)
+}
+
+/// The latest slot observed by the strategy, or 0 if nothing has been observed since startup.
+pub fn current_slot() -> u64 {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(slot) = crate::sim::current_slot() {
+        return slot;
+    }
+    CURRENT_SLOT.load(Ordering::Relaxed)
+}
+
+/// The slot to measure time by: the latest observed, or else the last the worker recorded
+/// while handling an event, or None if it has never recorded one.
+pub(crate) fn clock() -> WorkerResult<Option<u64>> {
+    match current_slot() {
+        0 => get::<u64>(KV_LAST_PROCESSED_SLOT),
+        slot => Ok(Some(slot)),
+    }
+}
+
+pub(crate) fn observe_slot(slot: u64) {
+    #[cfg(any(test, feature = "testing"))]
+    if crate::sim::observe_slot(slot) {
+        return;
+    }
+    CURRENT_SLOT.fetch_max(slot, Ordering::Relaxed);
+}
+
+/// The store behind this module: the runtime's KV, or the in-memory store of a
+/// [`crate::sim::Simulator`] while one is running on this thread.
+mod backend {
+    use balius_sdk::wit::balius::app::kv::{self, KvError};
+
+    pub fn get_value(key: &str) -> Result<Vec<u8>, KvError> {
+        #[cfg(any(test, feature = "testing"))]
+        if let Some(result) = crate::sim::kv_get(key) {
+            return result;
+        }
+        kv::get_value(key)
+    }
+
+    pub fn set_value(key: &str, value: &[u8]) -> Result<(), KvError> {
+        #[cfg(any(test, feature = "testing"))]
+        if crate::sim::kv_set(key, value) {
+            return Ok(());
+        }
+        kv::set_value(key, value)
+    }
+
+    pub fn list_values(prefix: &str) -> Result<Vec<String>, KvError> {
+        #[cfg(any(test, feature = "testing"))]
+        if let Some(keys) = crate::sim::kv_list(prefix) {
+            return Ok(keys);
+        }
+        kv::list_values(prefix)
+    }
+}
+
+/// Identifies an entry within a [`Namespace`].
+pub trait NamespaceKey {
+    fn namespace_key(&self) -> String;
+}
+
+impl NamespaceKey for OutputReference {
+    fn namespace_key(&self) -> String {
+        format!(
+            "{}#{}",
+            hex::encode(&self.transaction_id.0),
+            self.output_index
+        )
+    }
+}
+
+impl NamespaceKey for StrategyAuthorization {
+    fn namespace_key(&self) -> String {
+        match self {
+            StrategyAuthorization::Signature { signer } => hex::encode(signer),
+        }
+    }
+}
+
+impl NamespaceKey for str {
+    fn namespace_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// A typed view over every KV entry stored under `{prefix}:{id}`.
+///
+/// # Examples
+/// ```ignore
+/// let peak_prices = kv::Namespace::<f64>::new("peak_price");
+/// peak_prices.set(&strategy.output, &pool_price)?;
+/// let peak = peak_prices.get(&strategy.output)?;
+/// ```
+pub struct Namespace<T> {
+    prefix: String,
+    _value: PhantomData<T>,
+}
+
+impl<T: Serialize + for<'a> Deserialize<'a>> Namespace<T> {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            _value: PhantomData,
+        }
+    }
+
+    /// The full KV key used for `id`.
+    pub fn key<K: NamespaceKey + ?Sized>(&self, id: &K) -> String {
+        format!("{}:{}", self.prefix, id.namespace_key())
+    }
+
+    pub fn get<K: NamespaceKey + ?Sized>(&self, id: &K) -> WorkerResult<Option<T>> {
+        get(&self.key(id))
+    }
+
+    pub fn set<K: NamespaceKey + ?Sized>(&self, id: &K, value: &T) -> WorkerResult<()> {
+        set(&self.key(id), value)
+    }
+
+    pub fn set_with_ttl<K: NamespaceKey + ?Sized>(
+        &self,
+        id: &K,
+        value: &T,
+        ttl_secs: u64,
+    ) -> WorkerResult<()> {
+        set_with_ttl(&self.key(id), value, ttl_secs)
+    }
+
+    pub fn get_or_init<K, F>(&self, id: &K, init: F) -> WorkerResult<T>
+    where
+        K: NamespaceKey + ?Sized,
+        F: FnOnce() -> T,
+    {
+        get_or_init(&self.key(id), init)
+    }
+
+    pub fn delete<K: NamespaceKey + ?Sized>(&self, id: &K) -> WorkerResult<()> {
+        delete(&self.key(id))
+    }
+
+    /// The id of every entry in the namespace, without reading the values.
+    pub fn ids(&self) -> WorkerResult<Vec<String>> {
+        let prefix = format!("{}:", self.prefix);
+        Ok(list_keys(&prefix)?
+            .into_iter()
+            .map(|key| key[prefix.len()..].to_string())
+            .collect())
+    }
+
+    /// Every entry in the namespace, as `(id, value)` pairs.
+    pub fn iter(&self) -> WorkerResult<impl Iterator<Item = (String, T)>> {
+        let prefix = format!("{}:", self.prefix);
+        let mut entries = vec![];
+        for key in list_keys(&prefix)? {
+            if let Some(value) = get(&key)? {
+                entries.push((key[prefix.len()..].to_string(), value));
+            }
+        }
+        Ok(entries.into_iter())
+    }
+}
+
+/// What a [`StrategyState`] is keyed by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StateScope {
+    /// The order's strategy authorization, so the state carries over when an execution
+    /// replaces the order UTxO with a new one for the same strategy.
+    Authorization,
+    /// The order UTxO itself, so the state belongs to that one output.
+    Output,
+}
+
+/// Typed state for each strategy order, stored in a [`Namespace`] under a key derived
+/// from the order according to its [`StateScope`].
+///
+/// # Examples
+/// ```ignore
+/// let peaks = kv::StrategyState::<f64>::per_output("peak_price");
+/// peaks.store(&strategy, &pool_price)?;
+/// let peak = peaks.load(&strategy)?;
+/// ```
+pub struct StrategyState<T> {
+    namespace: Namespace<T>,
+    scope: StateScope,
+}
+
+impl<T: Serialize + for<'a> Deserialize<'a>> StrategyState<T> {
+    pub fn new(prefix: impl Into<String>, scope: StateScope) -> Self {
+        Self {
+            namespace: Namespace::new(prefix),
+            scope,
+        }
+    }
+
+    pub fn per_authorization(prefix: impl Into<String>) -> Self {
+        Self::new(prefix, StateScope::Authorization)
+    }
+
+    pub fn per_output(prefix: impl Into<String>) -> Self {
+        Self::new(prefix, StateScope::Output)
+    }
+
+    /// The namespace the state is stored in, e.g. to list or look up entries by id.
+    pub fn namespace(&self) -> &Namespace<T> {
+        &self.namespace
+    }
+
+    /// The id `strategy`'s state is stored under. An order without a strategy
+    /// authorization, which the library never manages, falls back to its output.
+    pub fn id(&self, strategy: &ManagedStrategy) -> String {
+        match (self.scope, strategy.authorization()) {
+            (StateScope::Authorization, Some(auth)) => auth.namespace_key(),
+            _ => strategy.output.namespace_key(),
+        }
+    }
+
+    pub fn load(&self, strategy: &ManagedStrategy) -> WorkerResult<Option<T>> {
+        self.namespace.get(self.id(strategy).as_str())
+    }
+
+    pub fn load_or_init<F>(&self, strategy: &ManagedStrategy, init: F) -> WorkerResult<T>
+    where
+        F: FnOnce() -> T,
+    {
+        self.namespace.get_or_init(self.id(strategy).as_str(), init)
+    }
+
+    pub fn store(&self, strategy: &ManagedStrategy, value: &T) -> WorkerResult<()> {
+        self.namespace.set(self.id(strategy).as_str(), value)
+    }
+
+    pub fn clear(&self, strategy: &ManagedStrategy) -> WorkerResult<()> {
+        self.namespace.delete(self.id(strategy).as_str())
+    }
+}
+
+fn is_expired(expires_at_slot: u64, now: u64) -> bool {
+    // Before the first observation we have no clock, so nothing can be considered expired
+    now != 0 && now >= expires_at_slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TransactionId;
+
+    #[test]
+    fn namespace_keys_are_prefixed_by_id() {
+        let output = OutputReference {
+            transaction_id: TransactionId(vec![0xab, 0xcd]),
+            output_index: 3,
+        };
+        let auth = StrategyAuthorization::Signature {
+            signer: vec![0x01, 0x02],
+        };
+        let namespace = Namespace::<f64>::new("peak_price");
+
+        assert_eq!(namespace.key(&output), "peak_price:abcd#3");
+        assert_eq!(namespace.key(&auth), "peak_price:0102");
+        assert_eq!(namespace.key("raw"), "peak_price:raw");
+    }
+
+    #[test]
+    fn strategy_state_is_keyed_by_its_scope() {
+        let mut strategy = ManagedStrategy::mock(&[]);
+        strategy.order.details = crate::types::Order::Strategy {
+            auth: StrategyAuthorization::Signature {
+                signer: vec![0x01, 0x02],
+            },
+        };
+
+        let per_auth = StrategyState::<f64>::per_authorization("iceberg_state");
+        assert_eq!(per_auth.id(&strategy), "0102");
+        let per_output = StrategyState::<f64>::per_output("peak_price");
+        assert_eq!(per_output.id(&strategy), strategy.output.namespace_key());
+
+        // Without an authorization, state falls back to the output
+        strategy.order.details = crate::types::Order::Withdraw {
+            lp: (vec![], vec![], 1),
+        };
+        assert_eq!(per_auth.id(&strategy), strategy.output.namespace_key());
+    }
+
+    #[test]
+    fn strategy_state_round_trips() {
+        let _sim = crate::sim::Simulator::new(
+            crate::Strategy::<serde_json::Value>::new(),
+            &serde_json::json!({}),
+        )
+        .unwrap();
+        let strategy = ManagedStrategy::mock(&[]);
+        let state = StrategyState::<u64>::per_output("counter");
+
+        assert_eq!(state.load(&strategy).unwrap(), None);
+        state.store(&strategy, &3).unwrap();
+        assert_eq!(state.load(&strategy).unwrap(), Some(3));
+        state.clear(&strategy).unwrap();
+        assert_eq!(state.load(&strategy).unwrap(), None);
+    }
+
+    #[test]
+    fn entries_expire_once_the_clock_reaches_them() {
+        assert!(!is_expired(100, 99));
+        assert!(is_expired(100, 100));
+        assert!(is_expired(100, 101));
+    }
+
+    #[test]
+    fn nothing_expires_before_the_first_observation() {
+        assert!(!is_expired(0, 0));
+        assert!(!is_expired(100, 0));
+    }
+
+    #[test]
+    fn ttls_fall_back_to_the_recorded_slot() {
+        let _sim = crate::sim::Simulator::new(
+            crate::Strategy::<serde_json::Value>::new(),
+            &serde_json::json!({}),
+        )
+        .unwrap();
+        let pending = Namespace::<bool>::new("pending");
+
+        // No clock at all: the entry could never expire, so it isn't written
+        assert!(pending.set_with_ttl("a", &true, 60).is_err());
+        assert_eq!(pending.get("a").unwrap(), None);
+
+        // As after a restart: nothing observed yet, but a slot was recorded before it
+        set(KV_LAST_PROCESSED_SLOT, &1_000u64).unwrap();
+        pending.set_with_ttl("a", &true, 60).unwrap();
+        assert_eq!(pending.get("a").unwrap(), Some(true));
+        set(KV_LAST_PROCESSED_SLOT, &1_060u64).unwrap();
+        assert_eq!(pending.get("a").unwrap(), None);
+    }
+
+    #[test]
+    fn expiring_entries_are_distinguished_from_plain_values() {
+        let bytes = serde_json::to_vec(&Expiring {
+            expires_at_slot: 10,
+            value: 1.5f64,
+        })
+        .unwrap();
+        assert!(matches!(
+            serde_json::from_slice::<Stored<f64>>(&bytes).unwrap(),
+            Stored::Expiring(Expiring {
+                expires_at_slot: 10,
+                ..
+            })
+        ));
+
+        let bytes = serde_json::to_vec(&1.5f64).unwrap();
+        assert!(matches!(
+            serde_json::from_slice::<Stored<f64>>(&bytes).unwrap(),
+            Stored::Plain(_)
+        ));
+    }
+}
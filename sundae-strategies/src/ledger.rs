@@ -0,0 +1,128 @@
+use balius_sdk::{
+    Error, WorkerResult,
+    wit::balius::app::ledger::{self, AssetPattern, TxoRef, UtxoPattern},
+};
+use pallas_primitives::conway::PseudoDatumOption;
+use pallas_traverse::{Era, MultiEraOutput};
+use utxorpc_spec::utxorpc::v1alpha::cardano::{Asset, Datum, Multiasset, TxOutput};
+
+use crate::{
+    Network,
+    types::{OutputReference, TransactionId},
+};
+
+/// The CIP-68 (222) label prefixed to a pool identifier to name the NFT held by the pool.
+const POOL_NFT_LABEL: [u8; 4] = [0x00, 0x0d, 0xe1, 0x40];
+
+/// The CIP-68 (333) label prefixed to a pool identifier to name the pool's LP token.
+const POOL_LP_LABEL: [u8; 4] = [0x00, 0x14, 0xdf, 0x10];
+
+pub(crate) fn pool_nft_name(identifier: &[u8]) -> Vec<u8> {
+    [POOL_NFT_LABEL.as_slice(), identifier].concat()
+}
+
+pub(crate) fn pool_lp_name(identifier: &[u8]) -> Vec<u8> {
+    [POOL_LP_LABEL.as_slice(), identifier].concat()
+}
+
+/// Look up the UTXO currently holding the NFT of the v3 pool with `identifier`.
+pub(crate) fn find_pool_utxo(
+    network: &Network,
+    identifier: &[u8],
+) -> WorkerResult<Option<(OutputReference, TxOutput)>> {
+    let pattern = UtxoPattern {
+        address: None,
+        asset: Some(AssetPattern {
+            policy: network.pool_script_hash(),
+            name: Some(pool_nft_name(identifier)),
+        }),
+    };
+    let page = ledger::search_utxos(&pattern, None, 1)
+        .map_err(|err| Error::Internal(format!("failed to query pool utxo: {err:?}")))?;
+    let Some(utxo) = page.utxos.into_iter().next() else {
+        return Ok(None);
+    };
+    let output = decode_output(&utxo.body)?;
+    let output_ref = OutputReference {
+        transaction_id: TransactionId(utxo.ref_.tx_hash),
+        output_index: utxo.ref_.tx_index as u64,
+    };
+    Ok(Some((output_ref, output)))
+}
+
+/// Read the unspent output at `output` from the ledger.
+pub(crate) fn read_output(output: &OutputReference) -> WorkerResult<Option<TxOutput>> {
+    let txo_ref = TxoRef {
+        tx_hash: output.transaction_id.0.clone(),
+        tx_index: output.output_index as u32,
+    };
+    let utxos = ledger::read_utxos(&[txo_ref])
+        .map_err(|err| Error::Internal(format!("failed to read {output:?}: {err:?}")))?;
+    utxos
+        .into_iter()
+        .next()
+        .map(|utxo| decode_output(&utxo.body))
+        .transpose()
+}
+
+/// Convert the CBOR of a ledger output into the utxorpc form delivered with UTXO events,
+/// keeping the parts the library reads: value, address, and inline datum.
+fn decode_output(cbor: &[u8]) -> WorkerResult<TxOutput> {
+    let output = MultiEraOutput::decode(Era::Conway, cbor)
+        .map_err(|err| Error::Internal(format!("failed to decode pool utxo: {err}")))?;
+    let value = output.value();
+    let assets = value
+        .assets()
+        .iter()
+        .map(|policy| Multiasset {
+            policy_id: policy.policy().to_vec().into(),
+            assets: policy
+                .assets()
+                .iter()
+                .map(|asset| Asset {
+                    name: asset.name().to_vec().into(),
+                    output_coin: asset.output_coin().unwrap_or_default(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        })
+        .collect();
+    let datum = match output.datum() {
+        Some(PseudoDatumOption::Data(data)) => Some(Datum {
+            original_cbor: data.0.raw_cbor().to_vec().into(),
+            ..Default::default()
+        }),
+        Some(PseudoDatumOption::Hash(_)) | None => None,
+    };
+    Ok(TxOutput {
+        address: output
+            .address()
+            .map(|address| address.to_vec())
+            .unwrap_or_default()
+            .into(),
+        coin: value.coin(),
+        assets,
+        datum,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_nft_is_labelled_identifier() {
+        let identifier =
+            hex::decode("ba228444515fbefd2c8725338e49589f206c7f18a33e002b157aac3c").unwrap();
+        assert_eq!(
+            hex::encode(pool_nft_name(&identifier)),
+            "000de140ba228444515fbefd2c8725338e49589f206c7f18a33e002b157aac3c"
+        );
+        assert_eq!(
+            hex::encode(pool_lp_name(&identifier)),
+            "0014df10ba228444515fbefd2c8725338e49589f206c7f18a33e002b157aac3c"
+        );
+    }
+}
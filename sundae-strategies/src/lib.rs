@@ -1,28 +1,58 @@
+mod audit;
+pub mod candles;
+mod compact;
+mod cooldown;
+mod decode;
+pub mod dust;
+pub mod expiry;
+pub mod grid;
+pub mod history;
+mod jitter;
 pub mod keys;
 pub mod kv;
+mod ledger;
+pub mod logging;
+mod manual;
+pub mod metrics;
+mod options;
+mod pause;
+pub mod price;
+#[cfg(any(test, feature = "testing"))]
+pub mod sim;
+pub mod sink;
+pub mod state;
+mod status;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod types;
+mod unspent;
+pub mod vwap;
+mod webhook;
+
+use std::{cmp::Ordering, collections::BTreeMap, fmt, ops::RangeInclusive, str::FromStr};
 
 use balius_sdk::{
-    _internal::Handler,
-    Ack, Config, Error, Tx, Utxo, UtxoMatcher, Worker, WorkerResult,
-    http::{HttpRequest, HttpResponse},
-    wit,
+    _internal::Handler, Ack, Config, Error, Tx, Utxo, UtxoMatcher, Worker, WorkerResult,
+    http::HttpResponse, wit,
 };
 use serde::{Deserialize, Serialize};
-use tracing::{info, trace};
+use tracing::{info, trace, warn};
 use url::Url;
 use utxorpc_spec::utxorpc::v1alpha::cardano::TxOutput;
 
 use crate::{
     keys::get_signer_key,
+    price::{DecimalPrice, PriceChange},
+    sink::{ExecutionSink, HttpSink},
     types::{
-        AssetId, Interval, Order, OrderDatum, OutputReference, PoolDatum, SignedStrategyExecution,
-        StrategyAuthorization, StrategyExecution, SubmitSSE, TransactionId, serialize,
+        AssetId, DatumVersion, Interval, Order, OrderDatum, OutputReference, PoolDatum,
+        PoolDatumError, SignedStrategyExecution, StrategyAuthorization, StrategyExecution,
+        SubmitSSE, TransactionId, asset_amount, serialize,
     },
 };
 
 /// Which network is this strategy running against?
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Network {
     Preview,
@@ -39,6 +69,21 @@ impl Network {
         (slot + offset) * 1000
     }
 
+    /// A best-effort UNIX time (ms) for code that isn't handling a chain event, such as
+    /// request handlers: the latest slot this worker has observed, falling back to the last
+    /// slot recorded in KV. None if no slot has been processed yet.
+    pub fn now_ms(&self) -> WorkerResult<Option<u64>> {
+        Ok(kv::clock()?.map(|slot| self.to_unix_time(slot)))
+    }
+
+    /// The name used for the network in worker configs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Preview => "preview",
+            Self::Mainnet => "mainnet",
+        }
+    }
+
     pub(crate) fn relay_url(&self) -> Url {
         let url = match self {
             Self::Preview => "http://sse-relay.preview.sundae.fi/publish",
@@ -46,6 +91,82 @@ impl Network {
         };
         Url::parse(url).unwrap()
     }
+
+    /// The v3 pool validator, which also mints each pool's NFT and LP tokens.
+    pub(crate) fn pool_script_hash(&self) -> Vec<u8> {
+        let hash = match self {
+            Self::Preview => "44a1eb2d9f58add4eb1932bd0048e6a1947e85e3fe4f32956a110414",
+            Self::Mainnet => "e0302560ced2fdcbfcb2602697df970cd0d6a38f94b32703f51c312b",
+        };
+        hex::decode(hash).unwrap()
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    /// Parse the lowercase names used in worker configs, e.g. `"preview"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preview" => Ok(Self::Preview),
+            "mainnet" => Ok(Self::Mainnet),
+            other => Err(format!(
+                "unknown network {other:?}, expected \"preview\" or \"mainnet\""
+            )),
+        }
+    }
+}
+
+/// How long, in seconds either side of now, a worker's executions are valid for by default.
+pub const DEFAULT_VALIDITY_WINDOW_SECS: u64 = 20 * 60;
+
+/// The validity windows a worker config may ask for, in seconds: long enough for an execution
+/// to propagate and be scooped, short enough that a stale one can't fill long after its trigger.
+pub const VALIDITY_WINDOW_SECS: RangeInclusive<u64> = 60..=3600;
+
+/// Check a configured `validity_window_secs` against [`VALIDITY_WINDOW_SECS`].
+pub fn validate_validity_window(secs: u64) -> Result<u64, String> {
+    if VALIDITY_WINDOW_SECS.contains(&secs) {
+        Ok(secs)
+    } else {
+        Err(format!(
+            "validity_window_secs must be within {}..={}, got {secs}",
+            VALIDITY_WINDOW_SECS.start(),
+            VALIDITY_WINDOW_SECS.end()
+        ))
+    }
+}
+
+/// Check that a configured float, such as a price or a percentage, is neither NaN nor
+/// infinite, either of which would silently poison every computation it feeds into.
+pub fn validate_finite(name: &str, value: f64) -> Result<f64, String> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{name} must be finite, got {value}"))
+    }
+}
+
+/// The slippage tolerance a worker's executions accept by default (3%).
+pub const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+/// Check that a configured fraction, such as a slippage tolerance or a trail, is a finite
+/// value in range (0.0, 1.0).
+pub fn validate_fraction(name: &str, value: f64) -> Result<f64, String> {
+    let value = validate_finite(name, value)?;
+    if value <= 0.0 {
+        return Err(format!("{name} must be > 0.0, got {value}"));
+    }
+    if value >= 1.0 {
+        return Err(format!("{name} must be < 1.0, got {value}"));
+    }
+    Ok(value)
 }
 
 /// Information about a strategy order getting managed by this library.
@@ -55,25 +176,238 @@ pub struct ManagedStrategy {
     pub slot: u64,
     /// A reference to the UTXO which is holding the order.
     pub output: OutputReference,
-    /// Contents of the UTXO which is holding the order.
+    /// Contents of the UTXO which is holding the order. Only its ADA and asset amounts are
+    /// kept once stored; see [`ManagedStrategy::fetch_utxo`] for the rest.
+    #[serde(with = "compact")]
     pub utxo: TxOutput,
     /// The parsed order.
     pub order: OrderDatum,
+    /// The contract version the order datum was parsed as. Strategy orders were introduced
+    /// in v3, so this is always [`DatumVersion::V3`] today.
+    #[serde(default)]
+    pub version: DatumVersion,
 }
 
 impl ManagedStrategy {
+    /// Read the full UTXO holding the order from the ledger, including the address and datum
+    /// that aren't kept in `utxo`. Returns None if it has already been spent.
+    pub fn fetch_utxo(&self) -> WorkerResult<Option<TxOutput>> {
+        ledger::read_output(&self.output)
+    }
+
+    /// The strategy authorization of the order. Every order the library manages has one.
+    pub fn authorization(&self) -> Option<&StrategyAuthorization> {
+        match &self.order.details {
+            Order::Strategy { auth } => Some(auth),
+            _ => None,
+        }
+    }
+
+    /// How much of `asset` the order's UTxO holds.
+    pub fn balance(&self, asset: &AssetId) -> u64 {
+        asset_amount(&self.utxo, asset)
+    }
+
+    /// How much of each of `a` and `b` the order's UTxO holds, e.g. a strategy's two
+    /// configured tokens.
+    pub fn balances(&self, a: &AssetId, b: &AssetId) -> (u64, u64) {
+        (self.balance(a), self.balance(b))
+    }
+
+    /// Whether the order's UTxO holds none of any of `tokens`, e.g. once it has been fully
+    /// drained, so there is nothing to offer.
+    pub fn is_empty(&self, tokens: &[&AssetId]) -> bool {
+        tokens.iter().all(|token| self.balance(token) == 0)
+    }
+
+    /// Whether `key` is the public key authorized to execute this order, e.g. to re-check an
+    /// order reference received by a request handler. See [`OrderDatum::is_owned_by`].
+    pub fn is_owned_by(&self, key: &[u8]) -> bool {
+        self.order.is_owned_by(key)
+    }
+
+    /// Whether the order's UTxO holds at least `amount` of `asset`.
+    pub fn has_balance(&self, asset: &AssetId, amount: u64) -> bool {
+        self.balance(asset) >= amount
+    }
+
+    /// Submit a strategy execution for this order, failing without posting anything if the
+    /// order doesn't hold everything `details` offers, which the relay would reject anyway.
+    /// Use the free [`submit_execution`], or set `"check_balance": false` in the worker
+    /// config, to skip the check.
+    ///
+    /// The execution is skipped if the protocol's fee for scooping the order exceeds its
+    /// `max_protocol_fee`. That only happens with `protocol_fee_per_order` set in the worker
+    /// config; see [`PoolState::fee_within`].
     pub fn submit_execution(
         &self,
         network: &Network,
         validity_range: Interval,
         details: Order,
-    ) -> Result<HttpResponse, balius_sdk::Error> {
+    ) -> Result<Submission, balius_sdk::Error> {
+        if !protocol_fee_within(&self.order) {
+            let reason = format!(
+                "protocol fee per order {:?} exceeds max_protocol_fee {:?}",
+                options::current().protocol_fee_per_order,
+                self.order.max_protocol_fee
+            );
+            info!("skipping execution for {:?}: {reason}", self.output);
+            return Ok(Submission::Skipped(reason));
+        }
+        if options::current().check_balance {
+            self.check_balance(&details)?;
+        }
         submit_execution(network, &self.output, validity_range, details)
     }
+
+    fn check_balance(&self, details: &Order) -> Result<(), balius_sdk::Error> {
+        let offered = match details {
+            Order::Swap { offer, .. } => vec![offer],
+            Order::Deposit { assets: (a, b) } => vec![a, b],
+            Order::Withdraw { lp } => vec![lp],
+            Order::Strategy { .. } => vec![],
+        };
+        for (policy_id, asset_name, amount) in offered {
+            let asset = AssetId::from((policy_id.clone(), asset_name.clone()));
+            let held = self.balance(&asset);
+            if held < *amount {
+                return Err(Error::Internal(format!(
+                    "order {:?} holds {held} {}, {} short of the {amount} offered",
+                    self.output,
+                    asset.name_to_string(),
+                    amount - held
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// How much of `offer` a swap offering `fraction` of this order's balance would give,
+    /// rounded down. Returns None if `fraction` isn't in `(0, 1]` or the amount rounds to 0.
+    ///
+    /// For ADA the balance is the whole coin of the order, which includes its deposit and
+    /// scooper fee, so a fraction close to 1 may leave too little for the order to execute.
+    pub fn partial_offer(&self, offer: &AssetId, fraction: f64) -> Option<u64> {
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return None;
+        }
+        let balance = self.balance(offer);
+        let amount = ((balance as f64 * fraction) as u64).min(balance);
+        (amount > 0).then_some(amount)
+    }
+
+    /// Submit a swap of `offer_fraction` of this order's `offer` balance, asking for at least
+    /// `min_received(amount)` of `receive` in return, where `amount` is what's being offered.
+    ///
+    /// Strategy orders pay out to [`Destination::Self_`](types::Destination::Self_): the
+    /// scooper returns the swap's proceeds, together with everything that wasn't offered,
+    /// to the order's own address under the same datum. A partial fill therefore replaces
+    /// the order with a new UTxO still under custody of the same strategy authorization,
+    /// which is reported through `on_strategy_spent` for the old output and
+    /// `on_new_strategy` for the new one, ready for the next fill.
+    pub fn submit_partial_execution(
+        &self,
+        network: &Network,
+        validity_range: Interval,
+        offer: &AssetId,
+        offer_fraction: f64,
+        receive: &AssetId,
+        min_received: impl FnOnce(u64) -> u64,
+    ) -> Result<Submission, balius_sdk::Error> {
+        let Some(amount) = self.partial_offer(offer, offer_fraction) else {
+            return Err(Error::Internal(format!(
+                "cannot offer {offer_fraction} of the {} {} held by order {:?}",
+                self.balance(offer),
+                offer.name_to_string(),
+                self.output
+            )));
+        };
+        let swap = Order::swap((offer, amount), (receive, min_received(amount)));
+        self.submit_execution(network, validity_range, swap)
+    }
+
+    /// The order UTxO that `tx` paid this one back to under the same datum, or None if `tx`
+    /// didn't spend it or paid nothing back, e.g. because the owner cancelled the order.
+    ///
+    /// A scoop executing several orders with identical datums pays them back in the order
+    /// it spends them, so they're paired with the outputs carrying that datum in input order.
+    pub fn successor(&self, tx: &Tx) -> Option<ManagedStrategy> {
+        let datum = serialize(self.order.clone());
+        let carries_datum = |output: &TxOutput| {
+            output
+                .datum
+                .as_ref()
+                .and_then(|datum| types::try_parse::<OrderDatum>(&datum.original_cbor))
+                .is_some_and(|order| serialize(order) == datum)
+        };
+        let position = tx.tx.inputs.iter().position(|input| {
+            input.tx_hash.as_ref() == self.output.transaction_id.0.as_slice()
+                && input.output_index as u64 == self.output.output_index
+        })?;
+        let rank = tx.tx.inputs[..position]
+            .iter()
+            .filter(|input| input.as_output.as_ref().is_some_and(carries_datum))
+            .count();
+        let (index, output) = tx
+            .tx
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| carries_datum(output))
+            .nth(rank)?;
+        Some(ManagedStrategy {
+            slot: tx.block_slot,
+            output: OutputReference {
+                transaction_id: TransactionId(tx.hash.to_vec()),
+                output_index: index as u64,
+            },
+            utxo: output.clone(),
+            order: self.order.clone(),
+            version: self.version,
+        })
+    }
+
+    /// The swap of `offer` for `receive` that `tx`, which spent this order, executed, read
+    /// from what it took from the order and paid back to its [successor](Self::successor).
+    /// None if it didn't deliver one, e.g. because the owner cancelled the order.
+    pub fn swap_fill(&self, tx: &Tx, offer: &AssetId, receive: &AssetId) -> Option<Fill> {
+        let successor = self.successor(tx)?;
+        let sold = self
+            .balance(offer)
+            .checked_sub(successor.balance(offer))
+            .filter(|sold| *sold > 0)?;
+        let received = successor
+            .balance(receive)
+            .checked_sub(self.balance(receive))
+            .filter(|received| *received > 0)?;
+        Some(Fill {
+            sold,
+            received,
+            successor,
+        })
+    }
 }
 
-/// Information about a Sundae pool
+/// A swap executed against a strategy order, as realized by the transaction that spent it.
 #[derive(Debug, Clone)]
+pub struct Fill {
+    /// How much of the offered asset left the order, including any fees taken in it
+    pub sold: u64,
+    /// How much of the received asset was paid back to the order
+    pub received: u64,
+    /// The order UTxO the proceeds were paid to, which replaces the spent one
+    pub successor: ManagedStrategy,
+}
+
+impl Fill {
+    /// The price the swap filled at, in raw sold units per raw received unit.
+    pub fn price(&self) -> f64 {
+        self.sold as f64 / self.received as f64
+    }
+}
+
+/// Information about a Sundae pool
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PoolState {
     /// The slot in which we first saw the pool.
     pub slot: u64,
@@ -81,11 +415,50 @@ pub struct PoolState {
     pub output: OutputReference,
     /// Contents of the UTXO which is holding the pool.
     pub utxo: TxOutput,
-    /// The parsed pool datum.
+    /// The parsed pool datum, normalized to the v3 layout.
     pub pool_datum: PoolDatum,
+    /// The contract version the pool datum was parsed as.
+    pub version: DatumVersion,
+    /// How the pool's raw price changed since the worker last observed this pool. Pool
+    /// states not delivered by an observation, e.g. from [`PoolState::query`], have no
+    /// previous price.
+    #[serde(default)]
+    pub price_change: PriceChange,
 }
 
 impl PoolState {
+    /// Fetch the current state of the v3 pool with `identifier` from the ledger, rather than
+    /// waiting for its next update to be observed. Useful from request handlers, where a
+    /// price from the last observed pool state may be stale.
+    ///
+    /// Returns None if no UTXO holds the pool's NFT, or its datum isn't a Sundae pool datum,
+    /// and an error if it's a pool of a shape the library can't read, such as one of three
+    /// assets. The ledger doesn't report when the UTXO was created, so `slot` is the latest
+    /// slot the worker has observed.
+    pub fn query(network: &Network, identifier: &[u8]) -> WorkerResult<Option<PoolState>> {
+        let Some((output, utxo)) = ledger::find_pool_utxo(network, identifier)? else {
+            return Ok(None);
+        };
+        let Some(datum) = &utxo.datum else {
+            return Ok(None);
+        };
+        let (pool_datum, version) = match types::parse_pool_datum(&datum.original_cbor) {
+            Ok(parsed) => parsed,
+            Err(err @ PoolDatumError::UnsupportedPool { .. }) => {
+                return Err(Error::Internal(err.to_string()));
+            }
+            Err(PoolDatumError::NotAPool) => return Ok(None),
+        };
+        Ok(Some(PoolState {
+            slot: kv::current_slot(),
+            price_change: PriceChange::new(None, pool_datum.raw_price(&utxo).value()),
+            output,
+            utxo,
+            pool_datum,
+            version,
+        }))
+    }
+
     /// Returns true if the pool is relevant to the provided order
     pub fn is_correct_pool(
         &self,
@@ -101,14 +474,161 @@ impl PoolState {
         }
     }
 
-    pub fn price(&self, token_a_decimals: u8, token_b_decimals: u8) -> f64 {
-        let raw = self.pool_datum.raw_price(&self.utxo);
-        raw * 10f64.powi(token_a_decimals as i32 - token_b_decimals as i32)
+    /// Returns true if `self` is a better pool than `other` to execute `for_order` against.
+    ///
+    /// A pool the order can't use (it's pinned to another pool, or the protocol fee exceeds
+    /// its `max_protocol_fee`) never wins. Between usable pools the deeper one wins, measured
+    /// by the product of its reserves, with the lower swap fee breaking ties.
+    /// Both pools are assumed to trade the same pair.
+    pub fn better_than(&self, other: &PoolState, for_order: &OrderDatum) -> bool {
+        match (self.usable_for(for_order), other.usable_for(for_order)) {
+            (true, false) => return true,
+            (false, _) => return false,
+            (true, true) => {}
+        }
+        match self.depth().cmp(&other.depth()) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => self.swap_fee() < other.swap_fee(),
+        }
+    }
+
+    fn usable_for(&self, order: &OrderDatum) -> bool {
+        let pinned_elsewhere = order
+            .pool_ident
+            .as_ref()
+            .is_some_and(|ident| self.pool_datum.identifier != *ident);
+        !pinned_elsewhere && self.fee_within(order)
+    }
+
+    fn depth(&self) -> u128 {
+        let (reserves_a, reserves_b) = self.pool_datum.reserves(&self.utxo);
+        reserves_a as u128 * reserves_b as u128
+    }
+
+    /// The higher of the bid and ask fees, per 10 thousand.
+    fn swap_fee(&self) -> u64 {
+        let bid = types::to_u64(&self.pool_datum.bid_fees_per_10_thousand);
+        let ask = types::to_u64(&self.pool_datum.ask_fees_per_10_thousand);
+        bid.zip(ask).map_or(u64::MAX, |(bid, ask)| bid.max(ask))
+    }
+
+    /// Returns true if the protocol fee for scooping `order` through this pool is within the
+    /// order's `max_protocol_fee`.
+    ///
+    /// That fee is set by the protocol settings rather than the pool, whose `protocol_fees`
+    /// is the treasury it has accrued, so it's taken from the worker config's
+    /// `protocol_fee_per_order`, in lovelace. The check needs that option: without it, every
+    /// pool is assumed to be within, and [`ManagedStrategy::submit_execution`] never skips
+    /// an execution for its fee.
+    pub fn fee_within(&self, order: &OrderDatum) -> bool {
+        protocol_fee_within(order)
+    }
+
+    /// How much of the other pool asset a swap of `offer_amount` of `offer` would receive,
+    /// after the pool's swap fee. The bid fee applies when offering asset A, and the ask
+    /// fee when offering asset B.
+    ///
+    /// Returns None if `offer` isn't one of the pool's assets, or the pool is empty.
+    pub fn expected_output(&self, offer: &AssetId, offer_amount: u64) -> Option<u64> {
+        let (asset_a, asset_b) = &self.pool_datum.assets;
+        let (reserves_a, reserves_b) = self.pool_datum.reserves(&self.utxo);
+        let (reserve_in, reserve_out, fee) = if offer == asset_a {
+            (
+                reserves_a,
+                reserves_b,
+                &self.pool_datum.bid_fees_per_10_thousand,
+            )
+        } else if offer == asset_b {
+            (
+                reserves_b,
+                reserves_a,
+                &self.pool_datum.ask_fees_per_10_thousand,
+            )
+        } else {
+            return None;
+        };
+        if reserve_in == 0 || reserve_out == 0 {
+            return None;
+        }
+        let fee = types::to_u64(fee).unwrap_or(0);
+        Some(types::constant_product_output(
+            reserve_in,
+            reserve_out,
+            offer_amount,
+            fee,
+        ))
+    }
+
+    /// The least a swap of `offer_amount` of `offer` should accept, given what the pool would
+    /// actually deliver along its curve after its swap fee, less `slippage` (e.g. 0.03 for 3%).
+    /// Rounded down, and never below 1, like [`types::min_received`].
+    ///
+    /// A minimum derived from the spot price alone ignores the fee and the swap's own price
+    /// impact, so in a high-fee pool it can ask for more than the pool will ever deliver.
+    /// Workers cap such a minimum at this one to keep their orders fillable.
+    ///
+    /// Returns None if `offer` isn't one of the pool's assets, or the pool is empty.
+    pub fn min_received_after_fees(
+        &self,
+        offer: &AssetId,
+        offer_amount: u64,
+        slippage: f64,
+    ) -> Option<u64> {
+        let output = self.expected_output(offer, offer_amount)?;
+        Some(types::min_received(output, 1.0, slippage))
+    }
+
+    /// The LP token minted by this v3 pool on `network`.
+    pub fn lp_asset(&self, network: &Network) -> AssetId {
+        AssetId::from((
+            network.pool_script_hash(),
+            ledger::pool_lp_name(&self.pool_datum.identifier),
+        ))
+    }
+
+    /// The (asset_a, asset_b) reserves `lp_amount` LP tokens are a claim on, rounded down.
+    ///
+    /// Returns None if the pool has no LP in circulation.
+    pub fn lp_share(&self, lp_amount: u64) -> Option<(u64, u64)> {
+        let circulating = types::to_u64(&self.pool_datum.circulating_lp).filter(|lp| *lp > 0)?;
+        let (reserves_a, reserves_b) = self.pool_datum.reserves(&self.utxo);
+        let share =
+            |reserves: u64| (reserves as u128 * lp_amount as u128 / circulating as u128) as u64;
+        Some((share(reserves_a), share(reserves_b)))
+    }
+
+    /// The fraction (0.0 to 1.0) by which swapping `offer_amount` of `offer` would move the
+    /// execution price away from the current spot price, ignoring the swap fee.
+    ///
+    /// Returns None if `offer` isn't one of the pool's assets, or the pool is empty.
+    pub fn price_impact(&self, offer: &AssetId, offer_amount: u64) -> Option<f64> {
+        let (asset_a, asset_b) = &self.pool_datum.assets;
+        let (reserves_a, reserves_b) = self.pool_datum.reserves(&self.utxo);
+        let (reserve_in, reserve_out) = if offer == asset_a {
+            (reserves_a, reserves_b)
+        } else if offer == asset_b {
+            (reserves_b, reserves_a)
+        } else {
+            return None;
+        };
+        if reserve_in == 0 || reserve_out == 0 {
+            return None;
+        }
+        // For a constant product pool, out/in = spot * reserve_in / (reserve_in + offer_amount)
+        Some(offer_amount as f64 / (reserve_in as f64 + offer_amount as f64))
+    }
+
+    /// The price of asset_b in whole units of asset_a, e.g. ADA per SUNDAE rather than lovelace per sprinkle.
+    pub fn price(&self, token_a_decimals: u8, token_b_decimals: u8) -> DecimalPrice {
+        self.pool_datum
+            .raw_price(&self.utxo)
+            .to_decimal(token_a_decimals, token_b_decimals)
     }
 
     // Returns the validity range in milliseconds relative to the current slot
     pub fn get_validity_range(&self, network: &Network, seconds: u64) -> Interval {
-        let now_ms = network.to_unix_time(self.slot);
+        let now_ms = self.now_ms(network);
         let delta_ms = seconds.saturating_mul(1000);
 
         let start = now_ms.saturating_sub(delta_ms);
@@ -117,6 +637,35 @@ impl PoolState {
     }
 }
 
+/// A chain event that happened at a known slot, and so has a UNIX time.
+pub trait EventTime {
+    /// The slot the event was observed in.
+    fn slot(&self) -> u64;
+
+    /// The UNIX time (ms) of the event, i.e. "now" while handling it.
+    fn now_ms(&self, network: &Network) -> u64 {
+        network.to_unix_time(self.slot())
+    }
+}
+
+impl EventTime for PoolState {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+impl EventTime for Tx {
+    fn slot(&self) -> u64 {
+        self.block_slot
+    }
+}
+
+impl<D> EventTime for Utxo<D> {
+    fn slot(&self) -> u64 {
+        self.block_slot
+    }
+}
+
 pub type NewStrategyCallback<T> = fn(&Config<T>, &ManagedStrategy) -> WorkerResult<Ack>;
 struct NewStrategyHandler<T>(Option<NewStrategyCallback<T>>);
 impl<T> Clone for NewStrategyHandler<T> {
@@ -125,6 +674,13 @@ impl<T> Clone for NewStrategyHandler<T> {
     }
 }
 
+/// Receives the observed pool, and the managed orders that could execute against it: those
+/// pinned to this pool by `pool_ident`, and those not pinned to any pool. The pool's
+/// `price_change` compares its raw price with the worker's previous observation of it.
+///
+/// Orders are passed sorted by output reference (transaction hash, then output index), so a
+/// callback that submits for each order in turn submits in a deterministic order. The
+/// transaction callbacks receive orders in the same order.
 pub type NewPoolStateCallback<T> =
     fn(&Config<T>, &PoolState, &Vec<ManagedStrategy>) -> WorkerResult<Ack>;
 struct NewPoolStateHandler<T>(Option<NewPoolStateCallback<T>>);
@@ -142,11 +698,26 @@ impl<T> Clone for EachTxHandler<T> {
     }
 }
 
+pub type StrategySpentCallback<T> = fn(&Config<T>, &Tx, &ManagedStrategy) -> WorkerResult<Ack>;
+struct StrategySpentHandler<T>(Option<StrategySpentCallback<T>>);
+impl<T> Clone for StrategySpentHandler<T> {
+    fn clone(&self) -> Self {
+        Self(self.0)
+    }
+}
+
 /// The entry point to a Sundae strategy worker. Use this to register handlers for interesting events.
 pub struct Strategy<T> {
     new_strategy_callback: NewStrategyHandler<T>,
     new_pool_state_callback: NewPoolStateHandler<T>,
     each_tx_callback: EachTxHandler<T>,
+    strategy_spent_callback: StrategySpentHandler<T>,
+    metrics: bool,
+    execution_webhook: Option<Url>,
+    cache_pools: bool,
+    pool_versions: Vec<DatumVersion>,
+    state_handler: Option<state::StateFn>,
+    validity_window_secs: Option<u64>,
 }
 impl<T> Clone for Strategy<T> {
     fn clone(&self) -> Self {
@@ -154,6 +725,13 @@ impl<T> Clone for Strategy<T> {
             each_tx_callback: self.each_tx_callback.clone(),
             new_pool_state_callback: self.new_pool_state_callback.clone(),
             new_strategy_callback: self.new_strategy_callback.clone(),
+            strategy_spent_callback: self.strategy_spent_callback.clone(),
+            metrics: self.metrics,
+            execution_webhook: self.execution_webhook.clone(),
+            cache_pools: self.cache_pools,
+            pool_versions: self.pool_versions.clone(),
+            state_handler: self.state_handler,
+            validity_window_secs: self.validity_window_secs,
         }
     }
 }
@@ -167,6 +745,13 @@ where
             new_strategy_callback: NewStrategyHandler(None),
             new_pool_state_callback: NewPoolStateHandler(None),
             each_tx_callback: EachTxHandler(None),
+            strategy_spent_callback: StrategySpentHandler(None),
+            metrics: true,
+            execution_webhook: None,
+            cache_pools: false,
+            pool_versions: vec![DatumVersion::V3],
+            state_handler: None,
+            validity_window_secs: None,
         }
     }
 
@@ -186,6 +771,62 @@ where
         self
     }
 
+    /// Register a callback to run when one of our strategy orders is spent,
+    /// whether by an execution or a cancellation.
+    pub fn on_strategy_spent(mut self, f: StrategySpentCallback<T>) -> Self {
+        self.strategy_spent_callback = StrategySpentHandler(Some(f));
+        self
+    }
+
+    /// Don't register the built-in `metrics` request handler.
+    pub fn without_metrics(mut self) -> Self {
+        self.metrics = false;
+        self
+    }
+
+    /// POST a JSON summary of every execution successfully submitted to the relay to `url`,
+    /// e.g. to drive chat alerts. Failing to reach the webhook doesn't fail the execution.
+    /// Alerts raised with [`notify_alert`] are posted there too.
+    pub fn with_execution_webhook(mut self, url: Url) -> Self {
+        self.execution_webhook = Some(url);
+        self
+    }
+
+    /// Remember the latest state of every observed pool, so [`recent_pools`] can compare
+    /// the pools trading a pair when choosing where to execute. Pools not observed for
+    /// [`RECENT_POOL_TTL_SECS`] are forgotten.
+    pub fn cache_recent_pools(mut self) -> Self {
+        self.cache_pools = true;
+        self
+    }
+
+    /// Observe pools of each of `versions`, rather than only v3 pools.
+    ///
+    /// Strategy orders can only execute against v3 pools, so v1 pools are ignored by default;
+    /// opt in for strategies that only read prices, where a v1 pool may be the deepest.
+    pub fn with_pool_versions(mut self, versions: &[DatumVersion]) -> Self {
+        self.pool_versions = versions.to_vec();
+        self
+    }
+
+    /// Declare that the worker's executions are always valid for `secs` either side of
+    /// their trigger, for a worker without a `validity_window_secs` config option, so
+    /// configs asking for more `execution_jitter_secs` than fits are rejected.
+    pub fn with_validity_window(mut self, secs: u64) -> Self {
+        self.validity_window_secs = Some(secs);
+        self
+    }
+
+    /// Register the `get-state` request handler, which reports the strategy's snapshot of
+    /// one of its orders; see [`state::StateSnapshot`].
+    pub fn with_state(mut self) -> Self
+    where
+        T: state::StateSnapshot,
+    {
+        self.state_handler = Some(state::handle::<T>);
+        self
+    }
+
     /// Finish building this strategy handler and construct a Balius worker.
     pub fn worker(self) -> Worker {
         self.worker_with(|w| w)
@@ -208,9 +849,34 @@ where
     {
         let worker = Worker::new()
             .with_request_handler("get-signer-key", self.clone())
+            .with_request_handler("status", status::StatusHandler)
+            .with_request_handler("get-execution-history", audit::ExecutionHistoryHandler)
+            .with_request_handler("get-vwap", vwap::VwapHandler)
+            .with_request_handler("get-candles", candles::CandlesHandler)
+            .with_request_handler("decode-datum", decode::DecodeDatumHandler)
+            .with_request_handler(
+                "build-execution",
+                manual::BuildExecutionHandler {
+                    validity_window_secs: self.validity_window_secs,
+                },
+            )
+            .with_request_handler("pause", pause::PauseHandler { paused: true })
+            .with_request_handler("resume", pause::PauseHandler { paused: false })
             .with_utxo_handler(UtxoMatcher::all(), self.clone())
             .with_tx_handler(UtxoMatcher::all(), self.clone())
             .with_signer(STRATEGY_KEY, "ed25519");
+        if let Some(url) = self.execution_webhook.clone() {
+            webhook::set_execution_webhook(url);
+        }
+        let worker = if self.metrics {
+            worker.with_request_handler("metrics", metrics::MetricsHandler)
+        } else {
+            worker
+        };
+        let worker = match self.state_handler {
+            Some(handle) => worker.with_request_handler("get-state", state::StateHandler(handle)),
+            None => worker,
+        };
         customize(worker)
     }
 }
@@ -233,10 +899,12 @@ where
         config: wit::Config,
         event: wit::Event,
     ) -> Result<wit::Response, wit::HandleError> {
+        options::observe_config(&config, self.validity_window_secs)
+            .map_err(|message| wit::HandleError { message, code: 400 })?;
         let config: Config<T> = config.try_into()?;
 
         let result = if let Ok(tx) = event.clone().try_into() {
-            self.handle_tx(config, tx)
+            self.handle_tx(&config, tx)
         } else if let Ok(utxo) = event.clone().try_into() {
             self.handle_utxo(config, utxo)
         } else if let Ok(params) = event.clone().try_into() {
@@ -257,7 +925,7 @@ impl<T: Send + Sync + 'static> Strategy<T> {
             .utxo
             .datum
             .clone()
-            .and_then(|d| types::try_parse::<OrderDatum>(&d.original_cbor))
+            .and_then(|d| types::parse_logged::<OrderDatum>(&d.original_cbor))
         else {
             return Ok(Ack);
         };
@@ -268,31 +936,27 @@ impl<T: Send + Sync + 'static> Strategy<T> {
             "transaction output is a sundae v3 order",
         );
 
-        let Some(key) = balius_sdk::get_public_keys().remove(STRATEGY_KEY) else {
-            return Err(Error::Internal("key not found".into()));
-        };
+        let key = keys::public_key()?;
 
         // Check if it's *our* order
-        use StrategyAuthorization::Signature;
-        match &datum.details {
-            Order::Strategy {
-                auth: Signature { signer },
-            } => {
-                if signer == &key {
-                    info!(
-                        "transaction output is a strategy order owned by us ({})",
-                        hex::encode(signer)
-                    );
-                } else {
-                    info!(
-                        "transaction output is a strategy order not owned by ({}), not us ({})",
-                        hex::encode(signer),
-                        hex::encode(&key)
-                    );
-                    return Ok(Ack);
-                }
-            }
-            _ => return Ok(Ack),
+        let Order::Strategy {
+            auth: StrategyAuthorization::Signature { signer },
+        } = &datum.details
+        else {
+            return Ok(Ack);
+        };
+        if datum.is_owned_by(&key) {
+            info!(
+                "transaction output is a strategy order owned by us ({})",
+                hex::encode(signer)
+            );
+        } else {
+            info!(
+                "transaction output is a strategy order not owned by ({}), not us ({})",
+                hex::encode(signer),
+                hex::encode(&key)
+            );
+            return Ok(Ack);
         }
 
         info!(
@@ -310,13 +974,24 @@ impl<T: Send + Sync + 'static> Strategy<T> {
             },
             utxo: utxo.utxo.clone(),
             order: datum,
+            version: DatumVersion::V3,
         };
+        self.track_order(config, seen)
+    }
 
+    /// Take custody of an order we own, and run the new strategy callback.
+    fn track_order(&self, config: &Config<T>, seen: ManagedStrategy) -> WorkerResult<Ack> {
         // This is an order "under our custody", so we hold onto it
-        let mut all_seen: Vec<ManagedStrategy> = kv::get(KV_MANAGED_ORDERS)?.unwrap_or_default();
+        let all_seen = kv::update(
+            KV_MANAGED_ORDERS,
+            |all_seen: Option<Vec<ManagedStrategy>>| {
+                let mut all_seen = all_seen.unwrap_or_default();
+                all_seen.push(seen.clone());
+                all_seen
+            },
+        )?;
 
-        all_seen.push(seen.clone());
-        kv::set(KV_MANAGED_ORDERS, &all_seen)?;
+        store_order_indexes(&all_seen)?;
 
         info!("now tracking {} orders", all_seen.len());
 
@@ -327,20 +1002,31 @@ impl<T: Send + Sync + 'static> Strategy<T> {
         }
     }
     fn handle_pool_state(&self, config: &Config<T>, utxo: &Utxo<()>) -> WorkerResult<Ack> {
-        // Check if it's a sundae v3 order datum?
-        let Some(datum) = utxo
-            .utxo
-            .datum
-            .clone()
-            .and_then(|d| types::try_parse::<PoolDatum>(&d.original_cbor))
-        else {
+        // Check if it's a sundae pool datum, of any version
+        let Some(datum) = &utxo.utxo.datum else {
             return Ok(Ack);
         };
+        let (datum, version) = match types::parse_pool_datum(&datum.original_cbor) {
+            Ok(parsed) => parsed,
+            Err(PoolDatumError::UnsupportedPool { identifier, reason }) => {
+                // Only a real pool holds its NFT; other datums can look like odd pools
+                if holds_pool_nft(&utxo.utxo, &identifier) {
+                    warn!(
+                        tx_ref = format!("{}#{}", hex::encode(&utxo.tx_hash), utxo.index),
+                        "skipping unsupported pool type for pool {}: {reason}",
+                        hex::encode(&identifier)
+                    );
+                }
+                return Ok(Ack);
+            }
+            Err(PoolDatumError::NotAPool) => return Ok(Ack),
+        };
 
         trace!(
             slot = utxo.block_slot,
             tx_ref = format!("{}#{}", hex::encode(&utxo.tx_hash), utxo.index),
-            "transaction output is a new sundae v3 pool state",
+            version = ?version,
+            "transaction output is a new sundae pool state",
         );
 
         let pool_state = PoolState {
@@ -351,9 +1037,40 @@ impl<T: Send + Sync + 'static> Strategy<T> {
             },
             utxo: utxo.utxo.clone(),
             pool_datum: datum,
+            version,
+            price_change: PriceChange::default(),
         };
+        self.observe_pool_state(config, pool_state)
+    }
+
+    /// Record a parsed pool state, and run the new pool state callback.
+    fn observe_pool_state(
+        &self,
+        config: &Config<T>,
+        mut pool_state: PoolState,
+    ) -> WorkerResult<Ack> {
+        if !self.pool_versions.contains(&pool_state.version) {
+            trace!(version = ?pool_state.version, "ignoring pool of an unobserved version");
+            return Ok(Ack);
+        }
+        metrics::increment(metrics::Counter::PoolObservations);
+        let relevant = managed_strategies_for_pool(&pool_state.pool_datum.identifier)?;
+        // Pools no order can trade against are observed far more often than the rest, so
+        // nothing is written about them but what's been opted in to
+        let tracked = !relevant.is_empty();
+        pool_state.price_change = observe_pool_price(&pool_state, tracked)?;
+        if self.cache_pools {
+            cache_pool(&pool_state)?;
+        }
+        if tracked {
+            candles::record(options::current().candles(), &pool_state)?;
+        }
 
-        let all_seen: Vec<ManagedStrategy> = kv::get(KV_MANAGED_ORDERS)?.unwrap_or_default();
+        let Some(mut all_seen) = pause::active(relevant)? else {
+            trace!("all strategies are paused");
+            return Ok(Ack);
+        };
+        sort_by_output(&mut all_seen);
 
         if let NewPoolStateHandler(Some(callback)) = self.new_pool_state_callback {
             callback(config, &pool_state, &all_seen)
@@ -363,6 +1080,7 @@ impl<T: Send + Sync + 'static> Strategy<T> {
     }
 
     fn handle_utxo(&self, config: Config<T>, utxo: Utxo<()>) -> WorkerResult<Ack> {
+        record_slot(utxo.block_slot)?;
         trace!(
             slot = utxo.block_slot,
             tx_ref = format!("{}#{}", hex::encode(&utxo.tx_hash), utxo.index),
@@ -374,7 +1092,8 @@ impl<T: Send + Sync + 'static> Strategy<T> {
         Ok(Ack)
     }
 
-    fn handle_tx(&self, config: Config<T>, tx: Tx) -> WorkerResult<Ack> {
+    fn handle_tx(&self, config: &Config<T>, tx: Tx) -> WorkerResult<Ack> {
+        record_slot(tx.block_slot)?;
         trace!(
             slot = tx.block_slot,
             tx_hash = hex::encode(&tx.hash),
@@ -387,34 +1106,309 @@ impl<T: Send + Sync + 'static> Strategy<T> {
             .map(|input| (input.tx_hash.to_vec(), input.output_index as u64))
             .collect::<Vec<_>>();
 
-        trace!("Marking orders as spent, if any...");
-        let mut seen_orders: Vec<ManagedStrategy> = kv::get(KV_MANAGED_ORDERS)?.unwrap_or_default();
-
-        seen_orders.retain(|spent| {
-            let (spent_hash, spent_index) =
-                (&spent.output.transaction_id.0, &spent.output.output_index);
-            !spent_inputs
-                .iter()
-                .any(|(hash, index)| spent_hash == hash && spent_index == index)
-        });
+        // Most transactions don't touch our orders; check the lightweight index of
+        // output references before paying to deserialize the full order list.
+        let touches_ours = match kv::get::<Vec<OutputReference>>(KV_MANAGED_ORDER_REFS)? {
+            Some(refs) => refs.iter().any(|output| is_spent(&spent_inputs, output)),
+            None => true,
+        };
 
-        kv::set(KV_MANAGED_ORDERS, &seen_orders)?;
+        let seen_orders = if touches_ours {
+            trace!("Marking orders as spent, if any...");
+            let mut spent_orders = vec![];
+            let seen_orders =
+                kv::update(KV_MANAGED_ORDERS, |seen: Option<Vec<ManagedStrategy>>| {
+                    let (spent, unspent) = seen
+                        .unwrap_or_default()
+                        .into_iter()
+                        .partition(|order| is_spent(&spent_inputs, &order.output));
+                    spent_orders = spent;
+                    unspent
+                })?;
+            store_order_indexes(&seen_orders)?;
+            if !spent_orders.is_empty() {
+                pause::carry_over(&tx, &spent_orders)?;
+                cooldown::forget(&spent_orders)?;
+            }
+            if let StrategySpentHandler(Some(callback)) = self.strategy_spent_callback {
+                sort_by_output(&mut spent_orders);
+                for order in &spent_orders {
+                    info!(
+                        slot = tx.block_slot,
+                        tx_ref = ?order.output,
+                        "owned strategy order spent",
+                    );
+                    callback(config, &tx, order)?;
+                }
+            }
+            seen_orders
+        } else if self.each_tx_callback.0.is_some() {
+            kv::get(KV_MANAGED_ORDERS)?.unwrap_or_default()
+        } else {
+            return Ok(Ack);
+        };
 
         trace!("remaining orders: {:?}", seen_orders);
 
         if let EachTxHandler(Some(callback)) = self.each_tx_callback {
-            callback(&config, &tx, &seen_orders)
+            let Some(mut seen_orders) = pause::active(seen_orders)? else {
+                trace!("all strategies are paused");
+                return Ok(Ack);
+            };
+            sort_by_output(&mut seen_orders);
+            callback(config, &tx, &seen_orders)
         } else {
             Ok(Ack)
         }
     }
 }
 
+/// Order `orders` by output reference, transaction hash then index, so callbacks see them,
+/// and submit executions for them, in the same order however they were tracked or indexed.
+fn sort_by_output(orders: &mut [ManagedStrategy]) {
+    orders.sort_by(|a, b| {
+        (&a.output.transaction_id.0, a.output.output_index)
+            .cmp(&(&b.output.transaction_id.0, b.output.output_index))
+    });
+}
+
+fn is_spent(spent_inputs: &[(Vec<u8>, u64)], output: &OutputReference) -> bool {
+    spent_inputs
+        .iter()
+        .any(|(hash, index)| output.transaction_id.0 == *hash && output.output_index == *index)
+}
+
+/// Whether `output` holds the NFT of the pool with `identifier`, under any policy.
+fn holds_pool_nft(output: &TxOutput, identifier: &[u8]) -> bool {
+    let nft_name = ledger::pool_nft_name(identifier);
+    output
+        .assets
+        .iter()
+        .flat_map(|multiasset| multiasset.assets.iter())
+        .any(|asset| asset.name.as_ref() == nft_name.as_slice() && asset.output_coin == 1)
+}
+
+/// Advance the clock and remember the slot for the `status` handler, writing it only when
+/// it moves on: a block's outputs and transactions all share its slot.
+fn record_slot(slot: u64) -> WorkerResult<()> {
+    let previous = kv::current_slot();
+    kv::observe_slot(slot);
+    let current = kv::current_slot();
+    if current == previous {
+        return Ok(());
+    }
+    metrics::flush();
+    kv::set(KV_LAST_PROCESSED_SLOT, &current)
+}
+
+/// The strategy orders currently under this worker's custody.
+///
+/// Useful from request handlers, which don't receive the orders the way event callbacks do.
+pub fn managed_strategies() -> WorkerResult<Vec<ManagedStrategy>> {
+    Ok(kv::get(KV_MANAGED_ORDERS)?.unwrap_or_default())
+}
+
+/// How long [`recent_pools`] remembers a pool after last observing it.
+pub const RECENT_POOL_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// The latest observed state of each pool trading `token_a` and `token_b`, in either order,
+/// leaving out pools not observed within [`RECENT_POOL_TTL_SECS`].
+///
+/// Always empty unless the strategy was built with [`Strategy::cache_recent_pools`].
+pub fn recent_pools(token_a: &AssetId, token_b: &AssetId) -> WorkerResult<Vec<PoolState>> {
+    let key = pair_key(
+        (&token_a.policy_id, &token_a.asset_name),
+        (&token_b.policy_id, &token_b.asset_name),
+    );
+    let mut pools = recent_pool_cache().get(key.as_str())?.unwrap_or_default();
+    if let Some(now) = kv::clock()? {
+        pools.retain(|pool| !is_stale(pool, now));
+    }
+    Ok(pools)
+}
+
+/// The best of `pools` to execute `order` against, per [`PoolState::better_than`], or None
+/// if the order can't use any of them.
+pub fn best_pool<'a>(pools: &'a [PoolState], order: &OrderDatum) -> Option<&'a PoolState> {
+    pools
+        .iter()
+        .filter(|pool| pool.usable_for(order))
+        .reduce(|best, pool| {
+            if pool.better_than(best, order) {
+                pool
+            } else {
+                best
+            }
+        })
+}
+
+/// The last observed raw price of each pool, keyed by pool identifier
+fn last_pool_prices() -> kv::Namespace<f64> {
+    kv::Namespace::new("last_pool_price")
+}
+
+/// How `pool`'s price changed since its previous observation. Only `tracked` pools, those
+/// with orders that could trade against them, have their price remembered for the next.
+fn observe_pool_price(pool: &PoolState, tracked: bool) -> WorkerResult<PriceChange> {
+    let current = pool.pool_datum.raw_price(&pool.utxo).value();
+    if !tracked {
+        return Ok(PriceChange::new(None, current));
+    }
+    let id = hex::encode(&pool.pool_datum.identifier);
+    let previous = last_pool_prices().get(id.as_str())?;
+    if previous != Some(current) {
+        last_pool_prices().set(id.as_str(), &current)?;
+    }
+    Ok(PriceChange::new(previous, current))
+}
+
+fn recent_pool_cache() -> kv::Namespace<Vec<PoolState>> {
+    kv::Namespace::new("recent_pools")
+}
+
+fn is_stale(pool: &PoolState, now: u64) -> bool {
+    pool.slot.saturating_add(RECENT_POOL_TTL_SECS) <= now
+}
+
+/// Cache `pool` under its pair, evicting pools of the pair that have gone stale. The pair
+/// itself expires once none of its pools have been observed for the TTL.
+fn cache_pool(pool: &PoolState) -> WorkerResult<()> {
+    let (asset_a, asset_b) = &pool.pool_datum.assets;
+    let key = pair_key((&asset_a.0, &asset_a.1), (&asset_b.0, &asset_b.1));
+    let cache = recent_pool_cache();
+    let mut pools = cache.get(key.as_str())?.unwrap_or_default();
+    pools.retain(|cached| {
+        cached.pool_datum.identifier != pool.pool_datum.identifier && !is_stale(cached, pool.slot)
+    });
+    pools.push(pool.clone());
+    cache.set_with_ttl(key.as_str(), &pools, RECENT_POOL_TTL_SECS)
+}
+
+/// Identifies a pair of assets regardless of which one is listed first.
+fn pair_key(a: (&[u8], &[u8]), b: (&[u8], &[u8])) -> String {
+    let mut ids =
+        [a, b].map(|(policy, name)| format!("{}.{}", hex::encode(policy), hex::encode(name)));
+    ids.sort();
+    ids.join("-")
+}
+
+/// The managed orders that could execute against the pool with `pool_ident`: those pinned
+/// to it, and those not pinned to any pool.
+///
+/// The output references of the relevant orders are looked up first, so the order list is
+/// only read when there is some order to act on the pool.
+pub fn managed_strategies_for_pool(pool_ident: &[u8]) -> WorkerResult<Vec<ManagedStrategy>> {
+    let index = orders_by_pool();
+    let Some(mut refs) = index.get(UNPINNED_ORDERS)? else {
+        // The index hasn't been written since the worker was upgraded; scan everything
+        return Ok(managed_strategies()?
+            .into_iter()
+            .filter(|order| {
+                order
+                    .order
+                    .pool_ident
+                    .as_ref()
+                    .is_none_or(|ident| ident == pool_ident)
+            })
+            .collect());
+    };
+    refs.extend(
+        index
+            .get(hex::encode(pool_ident).as_str())?
+            .unwrap_or_default(),
+    );
+    if refs.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(managed_strategies()?
+        .into_iter()
+        .filter(|order| {
+            refs.iter().any(|output| {
+                output.transaction_id.0 == order.output.transaction_id.0
+                    && output.output_index == order.output.output_index
+            })
+        })
+        .collect())
+}
+
+/// The output references of managed orders, grouped by the pool they're pinned to, or
+/// [`UNPINNED_ORDERS`]. The orders themselves are only stored under [`KV_MANAGED_ORDERS`].
+fn orders_by_pool() -> kv::Namespace<Vec<OutputReference>> {
+    kv::Namespace::new("orders_by_pool")
+}
+
+const UNPINNED_ORDERS: &str = "any";
+
+fn pool_index_key(order: &ManagedStrategy) -> String {
+    match &order.order.pool_ident {
+        Some(ident) => hex::encode(ident),
+        None => UNPINNED_ORDERS.to_string(),
+    }
+}
+
+/// Group the output references of `orders` by [`pool_index_key`]. The unpinned group is
+/// always present, so its absence from KV means the index has never been written.
+fn group_by_pool(orders: &[ManagedStrategy]) -> BTreeMap<String, Vec<OutputReference>> {
+    let mut groups = BTreeMap::from([(UNPINNED_ORDERS.to_string(), vec![])]);
+    for order in orders {
+        groups
+            .entry(pool_index_key(order))
+            .or_insert_with(Vec::new)
+            .push(order.output.clone());
+    }
+    groups
+}
+
+/// Keep the lookups derived from the full order list in sync with it: the output
+/// references checked on every transaction, and the per-pool groups read on every
+/// pool observation.
+fn store_order_indexes(orders: &[ManagedStrategy]) -> WorkerResult<()> {
+    let refs: Vec<&OutputReference> = orders.iter().map(|order| &order.output).collect();
+    kv::set(KV_MANAGED_ORDER_REFS, &refs)?;
+
+    let groups = group_by_pool(orders);
+    let index = orders_by_pool();
+    for id in index.ids()? {
+        if !groups.contains_key(&id) {
+            index.delete(id.as_str())?;
+        }
+    }
+    for (id, group) in &groups {
+        index.set(id.as_str(), group)?;
+    }
+    Ok(())
+}
+
 pub(crate) const KV_MANAGED_ORDERS: &str = "managed_orders";
+pub(crate) const KV_MANAGED_ORDER_REFS: &str = "managed_order_refs";
+pub(crate) const KV_LAST_PROCESSED_SLOT: &str = "last_processed_slot";
 pub(crate) const STRATEGY_KEY: &str = "default";
 
 /// Submit a strategy execution.
 ///
+/// If the worker's config sets `"dry_run": true`, the signed execution is logged instead
+/// of being posted, and a synthetic success is returned. Either way, the execution and the
+/// relay's answer are appended to the history served by the `get-execution-history` handler.
+///
+/// If the worker's config sets `"execution_jitter_secs"`, the start of `validity_range` is
+/// moved to a pseudo-random delay of up to that many seconds after the current slot, so
+/// the execution can't be applied the instant it's submitted. The delay is derived from
+/// `utxo` and the slot, so it is reproducible.
+///
+/// If the worker's config sets `"min_order_value"`, executions offering or asking for less
+/// than that much of its `base_token` (or lovelace) are skipped instead of being signed;
+/// see [`dust`].
+///
+/// If the worker's config sets `"min_submit_interval_secs"`, a submission against `utxo`
+/// within that many seconds of the last one is skipped.
+///
+/// If the worker's config sets `"check_unspent": true`, `utxo` is read from the ledger
+/// before the execution is signed, and a submission against an order that has already been
+/// spent is skipped. This costs a ledger query per submission.
+///
+/// A skipped execution is returned as [`Submission::Skipped`] rather than an error, so
+/// one refused order doesn't stop a strategy from acting on the rest.
+///
 /// # Examples
 /// ```
 /// # use std::time::Duration;
@@ -436,18 +1430,10 @@ pub(crate) const STRATEGY_KEY: &str = "default";
 ///         now + valid_for.as_millis() as u64,
 ///     );
 ///
-///     let swap = Order::Swap {
-///         offer: (
-///             config.offer_token.policy_id.clone(),
-///             config.offer_token.asset_name.clone(),
-///             config.offer_amount,
-///         ),
-///         min_received: (
-///             config.receive_token.policy_id.clone(),
-///             config.receive_token.asset_name.clone(),
-///             config.receive_amount_min,
-///         ),
-///     };
+///     let swap = Order::swap(
+///         (&config.offer_token, config.offer_amount),
+///         (&config.receive_token, config.receive_amount_min),
+///     );
 ///
 ///     sundae_strategies::submit_execution(&config.network, &order.output, validity_range, swap)?;
 ///
@@ -459,39 +1445,612 @@ pub fn submit_execution(
     utxo: &OutputReference,
     validity_range: Interval,
     details: Order,
-) -> Result<HttpResponse, Error> {
-    let execution = StrategyExecution {
+) -> Result<Submission, Error> {
+    submit_execution_to(&HttpSink, network, utxo, validity_range, details)
+}
+
+/// What became of an execution passed to [`submit_execution`].
+pub enum Submission {
+    /// The relay accepted the execution, or it was logged instead in a dry run.
+    Submitted(HttpResponse),
+    /// The execution was refused before it was signed, for a reason that only concerns it:
+    /// it was dust, its order was submitted against too recently, its order has already
+    /// been spent, or (through [`ManagedStrategy::submit_execution`]) the protocol fee is
+    /// above its order's `max_protocol_fee`. Nothing was posted, so a strategy shouldn't count it as in flight, but
+    /// should carry on with its other orders.
+    Skipped(String),
+}
+
+impl Submission {
+    /// Whether the execution was refused before it was signed.
+    pub fn is_skipped(&self) -> bool {
+        matches!(self, Submission::Skipped(_))
+    }
+}
+
+/// Submit a strategy execution through `sink` rather than posting it to the relay.
+pub fn submit_execution_to(
+    sink: &impl ExecutionSink,
+    network: &Network,
+    utxo: &OutputReference,
+    validity_range: Interval,
+    details: Order,
+) -> Result<Submission, Error> {
+    let options = options::current();
+    let cooldown_secs = options.min_submit_interval_secs;
+    if let Some(reason) = skip_reason(&options, network, utxo, &details)? {
+        return Ok(Submission::Skipped(reason));
+    }
+    let validity_range =
+        jitter::apply(network, utxo, validity_range, options.execution_jitter_secs);
+    let execution = new_execution(utxo, validity_range, details);
+
+    #[cfg(any(test, feature = "testing"))]
+    if sim::capture(&execution) {
+        cooldown::record(network, utxo, cooldown_secs);
+        return Ok(Submission::Submitted(sink::accepted_response()));
+    }
+
+    if options.check_unspent
+        && let Some(reason) = unspent::check(utxo)?
+    {
+        return Ok(Submission::Skipped(reason));
+    }
+    let submit_sse = sign_execution(&execution)?;
+    if options.dry_run {
+        info!(
+            "dry run, not submitting {}#{}: {}",
+            submit_sse.tx_hash, submit_sse.tx_index, submit_sse.data
+        );
+        let response = Ok(sink::accepted_response());
+        cooldown::record(network, utxo, cooldown_secs);
+        audit::record_execution(audit::ExecutionRecord::new(&execution, &response, true));
+        return response.map(Submission::Submitted);
+    }
+    info!(
+        "submitting {}#{}: {}",
+        submit_sse.tx_hash, submit_sse.tx_index, submit_sse.data
+    );
+
+    let response = sink.submit(network, &submit_sse).map_err(Error::from);
+    metrics::increment(match response {
+        Ok(_) => metrics::Counter::ExecutionsSubmitted,
+        Err(_) => metrics::Counter::RelayErrors,
+    });
+    audit::record_execution(audit::ExecutionRecord::new(&execution, &response, false));
+    let response = response?;
+    cooldown::record(network, utxo, cooldown_secs);
+    webhook::notify_execution(&execution);
+    Ok(Submission::Submitted(response))
+}
+
+/// Whether the worker config's `protocol_fee_per_order`, if set, is within the order's
+/// `max_protocol_fee`.
+fn protocol_fee_within(order: &OrderDatum) -> bool {
+    let Some(fee) = options::current().protocol_fee_per_order else {
+        return true;
+    };
+    // A max not representable as a u64 (e.g. a very large bigint) can't be exceeded
+    types::to_u64(&order.max_protocol_fee).is_none_or(|max_fee| fee <= max_fee)
+}
+
+/// Why the worker's shared options refuse an execution of `details` against `utxo` before
+/// it is signed, or None if they let it through. The ledger is only read afterwards, for
+/// `check_unspent`, so simulations never query it.
+fn skip_reason(
+    options: &options::RuntimeOptions,
+    network: &Network,
+    utxo: &OutputReference,
+    details: &Order,
+) -> WorkerResult<Option<String>> {
+    if let Some(reason) = dust::check_against(options.min_order_value().as_ref(), details) {
+        info!("skipping dust execution for {utxo:?}: {reason}");
+        metrics::increment(metrics::Counter::DustSkipped);
+        return Ok(Some(format!("dust execution: {reason}")));
+    }
+    cooldown::check(network, utxo, options.min_submit_interval_secs)
+}
+
+/// Raise an alert about the strategy order at `utxo` that an operator should act on, such
+/// as running out of inventory, posting it to the execution webhook if one is configured.
+///
+/// `alert` names the kind of alert, e.g. `grid_side_depleted`, and `message` describes it.
+/// Failing to reach the webhook is only logged.
+pub fn notify_alert(utxo: &OutputReference, alert: &str, message: &str) {
+    webhook::notify_alert(utxo, alert, message);
+}
+
+/// Build and sign a strategy execution with the worker's key, ready to hand to the relay.
+pub fn build_signed_execution(
+    utxo: &OutputReference,
+    validity_range: Interval,
+    details: Order,
+) -> Result<SubmitSSE, Error> {
+    sign_execution(&new_execution(utxo, validity_range, details))
+}
+
+fn new_execution(
+    utxo: &OutputReference,
+    validity_range: Interval,
+    details: Order,
+) -> StrategyExecution {
+    StrategyExecution {
         tx_ref: utxo.clone(),
         validity_range,
         details,
         extensions: vec![],
-    };
+    }
+}
 
+fn sign_execution(execution: &StrategyExecution) -> Result<SubmitSSE, Error> {
     let bytes = serialize(execution.clone());
-
-    let signature = balius_sdk::wit::balius::app::sign::sign_payload("default", &bytes)?;
+    let signature = balius_sdk::wit::balius::app::sign::sign_payload(STRATEGY_KEY, &bytes)?;
 
     let sse = SignedStrategyExecution {
-        execution,
+        execution: execution.clone(),
         signature: Some(signature),
     };
     let sse_bytes = serialize(sse);
 
-    let submit_sse = SubmitSSE {
-        tx_hash: hex::encode(&utxo.transaction_id.0),
-        tx_index: utxo.output_index,
+    Ok(SubmitSSE {
+        tx_hash: hex::encode(&execution.tx_ref.transaction_id.0),
+        tx_index: execution.tx_ref.output_index,
         data: hex::encode(&sse_bytes),
-    };
+    })
+}
 
-    info!(
-        "posting to {}: {} / {} / {}",
-        network.relay_url(),
-        hex::encode(&utxo.transaction_id.0),
-        utxo.output_index,
-        hex::encode(&sse_bytes)
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::from_u64;
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    fn pool(identifier: u8, lovelace: u64, sberry_reserves: u64, fee: u64) -> PoolState {
+        let ada = AssetId::from((vec![], vec![]));
+        let mut pool = PoolState::mock(lovelace, sberry_reserves, (&ada, &sberry()));
+        pool.pool_datum.identifier = vec![identifier];
+        pool.pool_datum.bid_fees_per_10_thousand = from_u64(fee);
+        pool.pool_datum.ask_fees_per_10_thousand = from_u64(fee);
+        pool
+    }
+
+    fn order(pool_ident: Option<Vec<u8>>) -> OrderDatum {
+        let mut order = ManagedStrategy::mock(&[]).order;
+        order.pool_ident = pool_ident;
+        order
+    }
+
+    #[test]
+    fn non_finite_config_values_are_rejected() {
+        assert_eq!(validate_finite("spacing_percent", 0.05), Ok(0.05));
+        assert!(validate_finite("spacing_percent", f64::NAN).is_err());
+        assert!(validate_finite("execution_price", f64::INFINITY).is_err());
+        assert!(validate_finite("execution_price", f64::NEG_INFINITY).is_err());
+    }
+
+    #[test]
+    fn fractions_must_be_strictly_between_zero_and_one() {
+        assert_eq!(validate_fraction("slippage_tolerance", 0.03), Ok(0.03));
+        for value in [0.0, -0.1, 1.0, 1.5, f64::NAN] {
+            assert!(validate_fraction("slippage_tolerance", value).is_err());
+        }
+    }
+
+    #[test]
+    fn pool_price_changes_are_relative_to_the_last_observation() {
+        let _sim =
+            sim::Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({}))
+                .unwrap();
+        let first = observe_pool_price(&pool(1, 2_000, 1_000, 0), true).unwrap();
+        assert_eq!(first.previous, None);
+        assert_eq!(first.current, 2.0);
+
+        let rise = observe_pool_price(&pool(1, 3_000, 1_000, 0), true).unwrap();
+        assert_eq!(rise.previous, Some(2.0));
+        assert_eq!(rise.pct_change, Some(0.5));
+        assert_eq!(rise.direction, price::Direction::Up);
+
+        // Each pool is compared with its own last price
+        let other = observe_pool_price(&pool(2, 1_000, 1_000, 0), true).unwrap();
+        assert_eq!(other.previous, None);
+
+        // Untracked pools aren't remembered
+        observe_pool_price(&pool(3, 1_000, 1_000, 0), false).unwrap();
+        assert!(last_pool_prices().get("03").unwrap().is_none());
+        let again = observe_pool_price(&pool(3, 2_000, 1_000, 0), true).unwrap();
+        assert_eq!(again.previous, None);
+    }
+
+    #[test]
+    fn is_empty_checks_every_token() {
+        let ada = AssetId::from((vec![], vec![]));
+        let order = ManagedStrategy::mock(&[(&sberry(), 10)]);
+        assert!(!order.is_empty(&[&sberry()]));
+        assert!(!order.is_empty(&[&sberry(), &ada]));
+        assert!(order.is_empty(&[&ada]));
+        assert!(ManagedStrategy::mock(&[]).is_empty(&[&sberry(), &ada]));
+    }
 
-    Ok(HttpRequest::post(network.relay_url())
-        .json(&submit_sse)?
-        .send()?)
+    #[test]
+    fn min_received_after_fees_is_fillable() {
+        // 1 lovelace per sprinkle, with a 1% fee
+        let pool = pool(1, 1_000_000, 1_000_000, 100);
+        let naive = types::min_received(1_000, 1.0, 0.0);
+        let after_fees = pool.min_received_after_fees(&sberry(), 1_000, 0.0).unwrap();
+
+        // The pool can't deliver the spot-price minimum
+        assert_eq!(pool.expected_output(&sberry(), 1_000), Some(989));
+        assert!(naive > 989);
+        assert_eq!(after_fees, 989);
+
+        assert_eq!(
+            pool.min_received_after_fees(&sberry(), 1_000, 0.1),
+            Some(890)
+        );
+        let other = AssetId::from((vec![0x11; 28], vec![]));
+        assert_eq!(pool.min_received_after_fees(&other, 1_000, 0.0), None);
+    }
+
+    #[test]
+    fn deeper_pool_is_better() {
+        let deep = pool(1, 1_000_000_000, 2_000_000_000, 100);
+        let thin = pool(2, 1_000_000, 2_000_000, 30);
+        assert!(deep.better_than(&thin, &order(None)));
+        assert!(!thin.better_than(&deep, &order(None)));
+    }
+
+    #[test]
+    fn lower_fee_breaks_ties() {
+        let cheap = pool(1, 1_000_000, 2_000_000, 30);
+        let expensive = pool(2, 1_000_000, 2_000_000, 100);
+        assert!(cheap.better_than(&expensive, &order(None)));
+        assert!(!expensive.better_than(&cheap, &order(None)));
+    }
+
+    #[test]
+    fn pinned_pool_always_wins() {
+        let deep = pool(1, 1_000_000_000, 2_000_000_000, 30);
+        let pinned = pool(2, 1_000_000, 2_000_000, 100);
+        let order = order(Some(vec![2]));
+        assert!(pinned.better_than(&deep, &order));
+        assert!(!deep.better_than(&pinned, &order));
+
+        let pools = [deep, pinned];
+        assert_eq!(
+            best_pool(&pools, &order).map(|pool| pool.pool_datum.identifier.clone()),
+            Some(vec![2])
+        );
+    }
+
+    #[test]
+    fn the_fee_cap_applies_to_the_fee_per_order_not_the_treasury() {
+        let simulate = |fee_per_order: u64| {
+            sim::Simulator::new(
+                Strategy::<serde_json::Value>::new(),
+                &serde_json::json!({ "protocol_fee_per_order": fee_per_order }),
+            )
+            .unwrap()
+        };
+        let mut rich = pool(1, 1_000_000_000, 2_000_000_000, 30);
+        rich.pool_datum.protocol_fees = from_u64(50_000_000_000);
+        let thin = pool(2, 1_000_000, 2_000_000, 30);
+        let mut order = order(None);
+        order.max_protocol_fee = from_u64(1_000_000);
+
+        // The treasury is far above the cap, but a scoop of the order costs less than it
+        let sim = simulate(500_000);
+        assert!(rich.fee_within(&order));
+        assert!(rich.better_than(&thin, &order));
+        let pools = [thin.clone(), rich.clone()];
+        assert_eq!(
+            best_pool(&pools, &order).map(|pool| pool.pool_datum.identifier.clone()),
+            Some(vec![1])
+        );
+        drop(sim);
+
+        let _sim = simulate(2_000_000);
+        assert!(!rich.fee_within(&order));
+        assert!(!rich.better_than(&thin, &order));
+        assert!(best_pool(&pools, &order).is_none());
+    }
+
+    #[test]
+    fn stale_pools_are_evicted_from_the_cache() {
+        let _sim =
+            sim::Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({}))
+                .unwrap();
+        let ada = AssetId::from((vec![], vec![]));
+        let at = |identifier, slot| {
+            let mut pool = pool(identifier, 1_000_000, 2_000_000, 30);
+            pool.slot = slot;
+            kv::observe_slot(slot);
+            cache_pool(&pool).unwrap();
+        };
+        let cached = || {
+            recent_pools(&ada, &sberry())
+                .unwrap()
+                .iter()
+                .map(|pool| pool.pool_datum.identifier.clone())
+                .collect::<Vec<_>>()
+        };
+
+        at(1, 100);
+        at(2, 200);
+        assert_eq!(cached(), [vec![1], vec![2]]);
+
+        // Pool 1 goes stale first, whether or not the pair is observed again
+        kv::observe_slot(100 + RECENT_POOL_TTL_SECS);
+        assert_eq!(cached(), [vec![2]]);
+        at(3, 150 + RECENT_POOL_TTL_SECS);
+        assert_eq!(cached(), [vec![2], vec![3]]);
+
+        // The whole pair expires once nothing has been observed for the TTL
+        kv::observe_slot(150 + 2 * RECENT_POOL_TTL_SECS);
+        assert!(cached().is_empty());
+        let sberry = sberry();
+        let key = pair_key(
+            (&ada.policy_id, &ada.asset_name),
+            (&sberry.policy_id, &sberry.asset_name),
+        );
+        assert!(recent_pool_cache().get(key.as_str()).unwrap().is_none());
+    }
+
+    #[test]
+    fn orders_are_grouped_by_pinned_pool() {
+        let mut pinned = ManagedStrategy::mock(&[]);
+        pinned.order.pool_ident = Some(vec![0xab]);
+        let unpinned = ManagedStrategy::mock(&[]);
+
+        let groups = group_by_pool(&[pinned.clone(), unpinned, pinned]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["ab"].len(), 2);
+        assert_eq!(groups[UNPINNED_ORDERS].len(), 1);
+
+        // The unpinned group marks the index as written, even when empty
+        assert!(group_by_pool(&[]).contains_key(UNPINNED_ORDERS));
+    }
+
+    #[test]
+    fn pool_lookups_read_only_relevant_orders() {
+        let _sim =
+            sim::Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({}))
+                .unwrap();
+        let order = |index: u64, pool_ident: Option<Vec<u8>>| {
+            let mut order = ManagedStrategy::mock(&[]);
+            order.output.output_index = index;
+            order.order.pool_ident = pool_ident;
+            order
+        };
+        let orders = [
+            order(0, Some(vec![1])),
+            order(1, Some(vec![2])),
+            order(2, None),
+        ];
+
+        // Before the index is written, fall back to filtering the full list
+        kv::set(KV_MANAGED_ORDERS, &orders).unwrap();
+        assert_eq!(managed_strategies_for_pool(&[1]).unwrap().len(), 2);
+
+        store_order_indexes(&orders).unwrap();
+        let relevant = managed_strategies_for_pool(&[1]).unwrap();
+        assert_eq!(relevant.len(), 2);
+        assert!(
+            relevant
+                .iter()
+                .all(|order| order.order.pool_ident != Some(vec![2]))
+        );
+
+        // Pools nothing is pinned to don't read the order list at all
+        kv::set(KV_MANAGED_ORDERS, &orders[..2]).unwrap();
+        store_order_indexes(&orders[..2]).unwrap();
+        assert!(managed_strategies_for_pool(&[3]).unwrap().is_empty());
+        assert_eq!(
+            orders_by_pool()
+                .get("01")
+                .unwrap()
+                .unwrap()
+                .iter()
+                .map(|output| format!("{output:?}"))
+                .collect::<Vec<_>>(),
+            [format!("{:?}", orders[0].output)]
+        );
+
+        // Groups that empty out are removed
+        store_order_indexes(&orders[2..]).unwrap();
+        assert_eq!(orders_by_pool().ids().unwrap(), [UNPINNED_ORDERS]);
+    }
+
+    #[test]
+    fn partial_offers_round_down_within_the_balance() {
+        let order = ManagedStrategy::mock(&[(&sberry(), 1_001)]);
+        assert_eq!(order.partial_offer(&sberry(), 0.5), Some(500));
+        assert_eq!(order.partial_offer(&sberry(), 1.0), Some(1_001));
+        assert_eq!(order.partial_offer(&sberry(), 0.0001), None);
+        assert_eq!(order.partial_offer(&sberry(), 0.0), None);
+        assert_eq!(order.partial_offer(&sberry(), 1.5), None);
+        assert_eq!(order.partial_offer(&sberry(), f64::NAN), None);
+    }
+
+    #[test]
+    fn partial_executions_offer_a_fraction_of_the_balance() {
+        let mut sim =
+            sim::Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({}))
+                .unwrap();
+        let ada = AssetId::from((vec![], vec![]));
+        let order = ManagedStrategy::mock(&[(&sberry(), 1_000)]);
+        order
+            .submit_partial_execution(
+                &Network::Preview,
+                Interval::inclusive_range(0, 1),
+                &sberry(),
+                0.25,
+                &ada,
+                |amount| amount * 2,
+            )
+            .unwrap();
+
+        let executions = sim.run(vec![]).unwrap();
+        assert_eq!(executions.len(), 1);
+        assert!(matches!(
+            &executions[0].details,
+            Order::Swap {
+                offer: (_, _, 250),
+                min_received: (_, _, 500),
+            }
+        ));
+        assert!(
+            order
+                .submit_partial_execution(
+                    &Network::Preview,
+                    Interval::inclusive_range(0, 1),
+                    &sberry(),
+                    0.0,
+                    &ada,
+                    |amount| amount,
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn executions_offering_more_than_the_order_holds_are_rejected() {
+        let _sim =
+            sim::Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({}))
+                .unwrap();
+        let ada = AssetId::from((vec![], vec![]));
+        let order = ManagedStrategy::mock(&[(&sberry(), 100)]);
+        assert!(order.has_balance(&sberry(), 100));
+        assert!(!order.has_balance(&sberry(), 101));
+
+        let submit = |amount| {
+            order.submit_execution(
+                &Network::Preview,
+                Interval::inclusive_range(0, 1),
+                Order::swap((&sberry(), amount), (&ada, 1)),
+            )
+        };
+        assert!(submit(100).is_ok());
+        let Err(Error::Internal(message)) = submit(150) else {
+            panic!("expected the offer to be rejected");
+        };
+        assert!(message.contains("holds 100 SBERRY, 50 short of the 150 offered"));
+    }
+
+    #[test]
+    fn executions_are_skipped_if_the_protocol_fee_exceeds_the_orders_max() {
+        let ada = AssetId::from((vec![], vec![]));
+        let mut order = ManagedStrategy::mock(&[(&sberry(), 100)]);
+        order.order.max_protocol_fee = from_u64(1_000_000);
+        let submit = |fee_per_order: Option<u64>| {
+            let _sim = sim::Simulator::new(
+                Strategy::<serde_json::Value>::new(),
+                &serde_json::json!({ "protocol_fee_per_order": fee_per_order }),
+            )
+            .unwrap();
+            order
+                .submit_execution(
+                    &Network::Preview,
+                    Interval::inclusive_range(0, 1),
+                    Order::swap((&sberry(), 100), (&ada, 1)),
+                )
+                .unwrap()
+        };
+
+        // Without the option, the fee isn't known, so nothing is skipped for it
+        assert!(!submit(None).is_skipped());
+        assert!(!submit(Some(1_000_000)).is_skipped());
+        let Submission::Skipped(reason) = submit(Some(2_000_000)) else {
+            panic!("expected the execution to be skipped");
+        };
+        assert!(reason.contains("exceeds max_protocol_fee"));
+    }
+
+    #[test]
+    fn the_balance_check_can_be_turned_off() {
+        let _sim = sim::Simulator::new(
+            Strategy::<serde_json::Value>::new(),
+            &serde_json::json!({ "check_balance": false }),
+        )
+        .unwrap();
+        let ada = AssetId::from((vec![], vec![]));
+        let order = ManagedStrategy::mock(&[(&sberry(), 100)]);
+        assert!(
+            order
+                .submit_execution(
+                    &Network::Preview,
+                    Interval::inclusive_range(0, 1),
+                    Order::swap((&sberry(), 150), (&ada, 1)),
+                )
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn ownership_is_checked_against_the_strategy_signer() {
+        let mut order = ManagedStrategy::mock(&[]);
+        order.order.details = Order::Strategy {
+            auth: StrategyAuthorization::Signature {
+                signer: vec![0x01; 32],
+            },
+        };
+        assert!(order.is_owned_by(&[0x01; 32]));
+        assert!(!order.is_owned_by(&[0x02; 32]));
+
+        order.order.details = Order::swap((&sberry(), 1), (&sberry(), 1));
+        assert!(!order.is_owned_by(&[0x01; 32]));
+    }
+
+    #[test]
+    fn now_falls_back_to_the_last_processed_slot() {
+        let _sim =
+            sim::Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({}))
+                .unwrap();
+        let network = Network::Preview;
+        assert_eq!(network.now_ms().unwrap(), None);
+
+        kv::set(KV_LAST_PROCESSED_SLOT, &10u64).unwrap();
+        assert_eq!(network.now_ms().unwrap(), Some(network.to_unix_time(10)));
+
+        kv::observe_slot(20);
+        assert_eq!(network.now_ms().unwrap(), Some(network.to_unix_time(20)));
+    }
+
+    #[test]
+    fn network_names_round_trip() {
+        for network in [Network::Preview, Network::Mainnet] {
+            let name = network.to_string();
+            assert_eq!(name, serde_json::to_value(&network).unwrap());
+            assert_eq!(name.parse::<Network>().unwrap().as_str(), name);
+        }
+        assert!("Preview".parse::<Network>().is_err());
+        assert!("testnet".parse::<Network>().is_err());
+    }
+
+    #[test]
+    fn lp_share_is_pro_rata() {
+        let ada = AssetId::from((vec![], vec![]));
+        let pool = PoolState::mock(4_000, 1_000, (&ada, &sberry()));
+        // 2,000 LP in circulation
+        assert_eq!(pool.lp_share(500), Some((1_000, 250)));
+        assert_eq!(pool.lp_share(2_000), Some((4_000, 1_000)));
+        assert!(
+            PoolState::mock(0, 0, (&ada, &sberry()))
+                .lp_share(1)
+                .is_none()
+        );
+
+        let lp = pool.lp_asset(&Network::Preview);
+        assert_eq!(lp.policy_id, Network::Preview.pool_script_hash());
+        assert_eq!(lp.asset_name[..4], [0x00, 0x14, 0xdf, 0x10]);
+    }
+
+    #[test]
+    fn pair_key_ignores_asset_order() {
+        let ada: (&[u8], &[u8]) = (&[], &[]);
+        let sberry: (&[u8], &[u8]) = (&[0x99; 28], b"SBERRY");
+        assert_eq!(pair_key(ada, sberry), pair_key(sberry, ada));
+    }
 }
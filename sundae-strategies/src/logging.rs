@@ -0,0 +1,115 @@
+use std::fmt;
+
+use balius_sdk::wit::balius::app::logging::{self, Level};
+use serde_json::{Map, Value};
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{Layer, layer::Context, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Install a logger which emits each event as a single line of JSON, for ingestion into a
+/// log pipeline. Use this in place of `balius_sdk::logging::init()` in a worker's `main`.
+///
+/// Every field attached to an event (such as `slot` or `tx_ref`) becomes a key of the
+/// object, alongside `level`, `target`, and `message`.
+///
+/// # Examples
+/// ```ignore
+/// #[balius_sdk::main]
+/// fn main() -> Worker {
+///     sundae_strategies::logging::init_json_logging();
+///     Strategy::<Config>::new().on_new_pool_state(on_new_pool_state).worker()
+/// }
+/// ```
+pub fn init_json_logging() {
+    let _ = tracing_subscriber::registry().with(JsonLayer).try_init();
+}
+
+struct JsonLayer;
+
+impl<S: Subscriber> Layer<S> for JsonLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = JsonFields(Map::new());
+        event.record(&mut fields);
+        let line = json_line(metadata.level(), metadata.target(), fields.0);
+        logging::log(to_level(metadata.level()), metadata.target(), &line);
+    }
+}
+
+fn to_level(level: &tracing::Level) -> Level {
+    match *level {
+        tracing::Level::TRACE => Level::Trace,
+        tracing::Level::DEBUG => Level::Debug,
+        tracing::Level::INFO => Level::Info,
+        tracing::Level::WARN => Level::Warn,
+        tracing::Level::ERROR => Level::Error,
+    }
+}
+
+fn json_line(level: &tracing::Level, target: &str, fields: Map<String, Value>) -> String {
+    let mut object = Map::new();
+    object.insert("level".into(), level.as_str().into());
+    object.insert("target".into(), target.into());
+    object.extend(fields);
+    Value::Object(object).to_string()
+}
+
+/// Collects an event's fields, keeping numbers and booleans typed.
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fields_are_merged_into_one_object() {
+        let mut fields = Map::new();
+        fields.insert("message".into(), "owned strategy order observed".into());
+        fields.insert("slot".into(), 1234.into());
+        fields.insert("tx_ref".into(), "abcd#0".into());
+
+        let line = json_line(&tracing::Level::INFO, "stop_loss", fields);
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            parsed,
+            json!({
+                "level": "INFO",
+                "target": "stop_loss",
+                "message": "owned strategy order observed",
+                "slot": 1234,
+                "tx_ref": "abcd#0",
+            })
+        );
+        assert!(!line.contains('\n'));
+    }
+}
@@ -0,0 +1,181 @@
+use balius_sdk::{_internal::Handler, Json, Params, wit};
+use serde::Deserialize;
+
+use crate::{
+    Network, build_signed_execution,
+    options::{self, RuntimeOptions},
+    skip_reason,
+    types::{Interval, Order, OutputReference},
+    unspent,
+};
+
+/// Request parameters for build-execution
+#[derive(Deserialize)]
+struct BuildExecutionParams {
+    /// The strategy order to execute, as `txHashHex#index`
+    order_ref: String,
+    /// The start of the validity window, in UNIX milliseconds
+    valid_from: u64,
+    /// The end of the validity window, in UNIX milliseconds
+    valid_until: u64,
+    /// What to execute, in the form `get-execution-history` reports it
+    details: Order,
+}
+
+fn bad_request(message: String) -> wit::HandleError {
+    wit::HandleError { message, code: 400 }
+}
+
+/// The network to sign for, if the worker config allows manual executions.
+fn enabled_network(options: &RuntimeOptions) -> Result<Network, wit::HandleError> {
+    if !options.allow_manual_executions {
+        return Err(wit::HandleError {
+            message: "build-execution is disabled; set allow_manual_executions to enable it"
+                .to_string(),
+            code: 403,
+        });
+    }
+    options
+        .network
+        .ok_or_else(|| bad_request("the worker config has no network".to_string()))
+}
+
+/// Check the request, returning what to sign.
+fn parse_request(
+    params: &BuildExecutionParams,
+) -> Result<(OutputReference, Interval, Order), String> {
+    let order_ref: OutputReference = params.order_ref.parse()?;
+    if params.valid_from > params.valid_until {
+        return Err(format!(
+            "valid_from ({}) must not be after valid_until ({})",
+            params.valid_from, params.valid_until
+        ));
+    }
+    if matches!(params.details, Order::Strategy { .. }) {
+        return Err("details must be an order to execute, not a strategy".to_string());
+    }
+    let validity_range = Interval::inclusive_range(params.valid_from, params.valid_until);
+    Ok((order_ref, validity_range, params.details.clone()))
+}
+
+/// Handler for `build-execution` requests, which sign an execution of a managed strategy
+/// order with the worker's key and return it, hex-encoded, without posting it anywhere.
+///
+/// This is for submitting by hand when the relay can't be reached: copy an execution's
+/// `order_ref` and `details` from `get-execution-history`, choose a validity window, and
+/// hand the returned `data` to another submission channel. No jitter is applied, and the
+/// execution isn't recorded in the execution history. Nor does it start the order's
+/// `min_submit_interval_secs` cooldown, since it may never be submitted.
+///
+/// Since it signs whatever it's asked to, it's refused unless the worker config sets
+/// `"allow_manual_executions": true`, and holds executions to the same checks as
+/// [`crate::submit_execution`]: the order's balance, `min_order_value`,
+/// `min_submit_interval_secs` and `check_unspent`.
+#[derive(Clone)]
+pub(crate) struct BuildExecutionHandler {
+    /// The validity window the worker fixes for its executions, if it declared one
+    pub validity_window_secs: Option<u64>,
+}
+
+impl Handler for BuildExecutionHandler {
+    fn handle(
+        &self,
+        config: wit::Config,
+        event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        options::observe_config(&config, self.validity_window_secs).map_err(bad_request)?;
+        let options = options::current();
+        let network = enabled_network(&options)?;
+        let params: Params<BuildExecutionParams> = event
+            .try_into()
+            .map_err(|_| bad_request("invalid request parameters".to_string()))?;
+        let (order_ref, validity_range, details) = parse_request(&params).map_err(bad_request)?;
+
+        // Only sign for orders in custody; anything else would just be rejected on chain
+        let managed = crate::managed_strategies()?.into_iter().find(|strategy| {
+            strategy.output.transaction_id.0 == order_ref.transaction_id.0
+                && strategy.output.output_index == order_ref.output_index
+        });
+        let Some(managed) = managed else {
+            return Err(wit::HandleError {
+                message: format!("{order_ref:?} is not a strategy order in this worker's custody"),
+                code: 404,
+            });
+        };
+
+        if options.check_balance {
+            managed
+                .check_balance(&details)
+                .map_err(|err| bad_request(err.to_string()))?;
+        }
+        if let Some(reason) = skip_reason(&options, &network, &order_ref, &details)? {
+            return Err(bad_request(format!("execution refused: {reason}")));
+        }
+        if options.check_unspent
+            && let Some(reason) = unspent::check(&order_ref)?
+        {
+            return Err(bad_request(format!("execution refused: {reason}")));
+        }
+
+        let submit_sse = build_signed_execution(&order_ref, validity_range, details)?;
+        Ok(Json(submit_sse).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AssetId;
+
+    fn params(valid_from: u64, valid_until: u64, details: &Order) -> BuildExecutionParams {
+        serde_json::from_value(serde_json::json!({
+            "order_ref": "00ff#1",
+            "valid_from": valid_from,
+            "valid_until": valid_until,
+            "details": details,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn refused_unless_enabled() {
+        let options = |config: serde_json::Value| {
+            options::observe_config(&serde_json::to_vec(&config).unwrap(), None).unwrap();
+            options::current()
+        };
+        let refused = enabled_network(&options(serde_json::json!({ "network": "preview" })));
+        assert_eq!(refused.unwrap_err().code, 403);
+
+        let enabled = options(serde_json::json!({
+            "network": "preview",
+            "allow_manual_executions": true,
+        }));
+        assert_eq!(enabled_network(&enabled).unwrap(), Network::Preview);
+        options::reset();
+    }
+
+    #[test]
+    fn accepts_details_as_the_history_reports_them() {
+        let ada = AssetId::from((vec![], vec![]));
+        let swap = Order::swap((&ada, 1_000), (&ada, 1));
+        let (order_ref, _, details) = parse_request(&params(10, 20, &swap)).unwrap();
+        assert_eq!(format!("{order_ref:?}"), "00ff#1");
+        assert!(matches!(
+            details,
+            Order::Swap {
+                offer: (_, _, 1_000),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_backwards_windows_and_strategies() {
+        let ada = AssetId::from((vec![], vec![]));
+        let swap = Order::swap((&ada, 1_000), (&ada, 1));
+        assert!(parse_request(&params(20, 10, &swap)).is_err());
+
+        let strategy = crate::ManagedStrategy::mock(&[]).order.details;
+        assert!(parse_request(&params(10, 20, &strategy)).is_err());
+    }
+}
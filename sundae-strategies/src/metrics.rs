@@ -0,0 +1,179 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write};
+
+use balius_sdk::{_internal::Handler, Json, WorkerResult, wit};
+use tracing::warn;
+
+use crate::kv;
+
+/// An operational counter, persisted in KV so it survives worker restarts.
+///
+/// Increments are batched in memory and written once per slot, since some counters move on
+/// every event; a restart loses at most the increments of the slot in progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Counter {
+    /// Strategy executions accepted by the relay
+    ExecutionsSubmitted,
+    /// Strategy executions that failed to be posted to the relay
+    RelayErrors,
+    /// Sundae pool states observed
+    PoolObservations,
+    /// Strategy executions refused for being below `min_order_value`
+    DustSkipped,
+    /// Times a strategy ran out of inventory to trade on one side
+    SidesDepleted,
+}
+
+impl Counter {
+    const ALL: [Counter; 5] = [
+        Counter::ExecutionsSubmitted,
+        Counter::RelayErrors,
+        Counter::PoolObservations,
+        Counter::DustSkipped,
+        Counter::SidesDepleted,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Counter::ExecutionsSubmitted => "sundae_strategy_executions_submitted_total",
+            Counter::RelayErrors => "sundae_strategy_relay_errors_total",
+            Counter::PoolObservations => "sundae_strategy_pool_observations_total",
+            Counter::DustSkipped => "sundae_strategy_dust_skipped_total",
+            Counter::SidesDepleted => "sundae_strategy_sides_depleted_total",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            Counter::ExecutionsSubmitted => "Strategy executions accepted by the relay",
+            Counter::RelayErrors => "Strategy executions that failed to reach the relay",
+            Counter::PoolObservations => "Sundae pool states observed",
+            Counter::DustSkipped => "Strategy executions skipped for being below min_order_value",
+            Counter::SidesDepleted => "Times a strategy ran out of inventory to trade on one side",
+        }
+    }
+}
+
+fn counters() -> kv::Namespace<u64> {
+    kv::Namespace::new("metrics")
+}
+
+thread_local! {
+    /// Increments not yet written to KV, by counter name.
+    static PENDING: RefCell<BTreeMap<&'static str, u64>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+fn pending(counter: Counter) -> u64 {
+    PENDING.with(|pending| {
+        pending
+            .borrow()
+            .get(counter.name())
+            .copied()
+            .unwrap_or_default()
+    })
+}
+
+/// Add one to `counter`.
+pub fn increment(counter: Counter) {
+    PENDING.with(|pending| *pending.borrow_mut().entry(counter.name()).or_default() += 1);
+}
+
+/// Write the pending increments to KV.
+///
+/// Metrics are best-effort: a KV failure is logged, and the increments dropped, rather than
+/// failing the caller.
+pub(crate) fn flush() {
+    let pending = PENDING.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+    for (name, increments) in pending {
+        let result = kv::update(&counters().key(name), |count: Option<u64>| {
+            count.unwrap_or_default() + increments
+        });
+        if let Err(err) = result {
+            warn!("failed to increment {name}: {err}");
+        }
+    }
+}
+
+/// The current value of `counter`, or 0 if it has never been incremented.
+pub fn get(counter: Counter) -> WorkerResult<u64> {
+    let stored: u64 = counters().get(counter.name())?.unwrap_or_default();
+    Ok(stored + pending(counter))
+}
+
+/// Render counter values and the managed order count in the Prometheus text format.
+fn render(counts: &[(Counter, u64)], managed_orders: usize) -> String {
+    let mut out = String::new();
+    for (counter, count) in counts {
+        let name = counter.name();
+        let _ = writeln!(out, "# HELP {name} {}", counter.help());
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {count}");
+    }
+    let name = "sundae_strategy_managed_orders";
+    let _ = writeln!(out, "# HELP {name} Strategy orders currently under custody");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{name} {managed_orders}");
+    out
+}
+
+/// Handler for `metrics` requests, returning the Prometheus text exposition as a JSON string.
+#[derive(Clone)]
+pub(crate) struct MetricsHandler;
+
+impl Handler for MetricsHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        _event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let counts = Counter::ALL
+            .iter()
+            .map(|counter| Ok((*counter, get(*counter)?)))
+            .collect::<WorkerResult<Vec<_>>>()?;
+        let managed_orders = crate::managed_strategies()?.len();
+        Ok(Json(render(&counts, managed_orders)).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increments_are_written_once_flushed() {
+        let _sim = crate::sim::Simulator::new(
+            crate::Strategy::<serde_json::Value>::new(),
+            &serde_json::json!({}),
+        )
+        .unwrap();
+        increment(Counter::PoolObservations);
+        increment(Counter::PoolObservations);
+        assert_eq!(get(Counter::PoolObservations).unwrap(), 2);
+        assert!(
+            counters()
+                .get(Counter::PoolObservations.name())
+                .unwrap()
+                .is_none()
+        );
+
+        flush();
+        increment(Counter::PoolObservations);
+        assert_eq!(
+            counters().get(Counter::PoolObservations.name()).unwrap(),
+            Some(2)
+        );
+        assert_eq!(get(Counter::PoolObservations).unwrap(), 3);
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let text = render(
+            &[(Counter::ExecutionsSubmitted, 3), (Counter::RelayErrors, 1)],
+            2,
+        );
+        assert!(text.contains("# TYPE sundae_strategy_executions_submitted_total counter\n"));
+        assert!(text.contains("\nsundae_strategy_executions_submitted_total 3\n"));
+        assert!(text.contains("\nsundae_strategy_relay_errors_total 1\n"));
+        assert!(text.contains("# TYPE sundae_strategy_managed_orders gauge\n"));
+        assert!(text.ends_with("sundae_strategy_managed_orders 2\n"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Options shared by every strategy worker, read from the worker config alongside the
+//! worker's own fields:
+//!
+//! - `dry_run`: log signed executions instead of posting them
+//! - `execution_jitter_secs`: delay when executions become valid; see [`crate::jitter`]
+//! - `min_order_value` and `base_token`: refuse dust executions; see [`crate::dust`]
+//! - `min_submit_interval_secs`: a cooldown between submissions against one order
+//! - `candle_interval_secs`: record candles of observed pools; see [`crate::candles`]
+//! - `check_unspent`: check the ledger before submitting against an order
+//! - `check_balance`: refuse executions offering more than their order holds; on unless
+//!   set to false, see [`crate::ManagedStrategy::submit_execution`]
+//! - `protocol_fee_per_order`: the protocol's fee for scooping one strategy order, checked
+//!   against each order's `max_protocol_fee` when picking a pool or submitting; unset, no
+//!   fee is checked. See [`crate::PoolState::fee_within`]
+//! - `allow_manual_executions`: serve `build-execution` requests, which sign executions on
+//!   demand; see [`crate::manual`]
+//!
+//! They're parsed and validated once per distinct config, not per event, and handed to
+//! the parts of the library that use them from there. An invalid option fails every event
+//! with the reason, the same as an invalid worker config.
+
+use std::{cell::RefCell, rc::Rc};
+
+use serde::Deserialize;
+
+use crate::{
+    DEFAULT_VALIDITY_WINDOW_SECS, Network, candles::CandleOptions, dust::MinOrderValue,
+    types::AssetId,
+};
+
+/// The shared options of a worker config. Every option but `check_balance` is off by
+/// default.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct RuntimeOptions {
+    pub dry_run: bool,
+    pub execution_jitter_secs: u64,
+    pub min_order_value: Option<u64>,
+    pub base_token: Option<AssetId>,
+    pub min_submit_interval_secs: u64,
+    pub network: Option<Network>,
+    pub candle_interval_secs: Option<u64>,
+    pub check_unspent: bool,
+    pub check_balance: bool,
+    pub protocol_fee_per_order: Option<u64>,
+    pub allow_manual_executions: bool,
+    /// The worker's own validity window, if it has one, which jitter must fit inside: the
+    /// one it declared with [`crate::Strategy::with_validity_window`], or else the one
+    /// read from its config
+    pub validity_window_secs: Option<u64>,
+}
+
+impl Default for RuntimeOptions {
+    fn default() -> Self {
+        RuntimeOptions {
+            dry_run: false,
+            execution_jitter_secs: 0,
+            min_order_value: None,
+            base_token: None,
+            min_submit_interval_secs: 0,
+            network: None,
+            candle_interval_secs: None,
+            check_unspent: false,
+            check_balance: true,
+            protocol_fee_per_order: None,
+            allow_manual_executions: false,
+            validity_window_secs: None,
+        }
+    }
+}
+
+impl RuntimeOptions {
+    fn parse(config: &[u8], fixed_window_secs: Option<u64>) -> Result<Self, String> {
+        let mut options: RuntimeOptions = serde_json::from_slice(config)
+            .map_err(|err| format!("invalid shared worker option: {err}"))?;
+        if fixed_window_secs.is_some() {
+            options.validity_window_secs = fixed_window_secs;
+        }
+        options.validate()?;
+        Ok(options)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let window_secs = self
+            .validity_window_secs
+            .unwrap_or(DEFAULT_VALIDITY_WINDOW_SECS);
+        if self.execution_jitter_secs > 0 && self.execution_jitter_secs >= window_secs {
+            return Err(format!(
+                "execution_jitter_secs must be less than the validity window of {window_secs}s, got {}",
+                self.execution_jitter_secs
+            ));
+        }
+        if self.candle_interval_secs.is_some_and(|secs| secs > 0) && self.network.is_none() {
+            return Err("candle_interval_secs requires a network".to_string());
+        }
+        Ok(())
+    }
+
+    /// The minimum order value to hold executions to, if one is set.
+    pub fn min_order_value(&self) -> Option<MinOrderValue> {
+        let amount = self.min_order_value.filter(|amount| *amount > 0)?;
+        let asset = match &self.base_token {
+            Some(token) => AssetId::from((token.policy_id.clone(), token.asset_name.clone())),
+            None => AssetId::from((vec![], vec![])),
+        };
+        Some(MinOrderValue { asset, amount })
+    }
+
+    /// How to record candles, if they're on.
+    pub fn candles(&self) -> Option<CandleOptions> {
+        let interval_secs = self.candle_interval_secs.filter(|secs| *secs > 0)?;
+        Some(CandleOptions {
+            network: self.network?,
+            interval_secs,
+        })
+    }
+}
+
+thread_local! {
+    /// The raw config and fixed validity window the options were last parsed from, and the
+    /// result.
+    static OPTIONS: RefCell<Option<ParsedOptions>> = const { RefCell::new(None) };
+}
+
+type ParsedOptions = (Vec<u8>, Option<u64>, Rc<RuntimeOptions>);
+
+/// Read the shared options from the raw worker config delivered with an event, reparsing
+/// only if the config has changed since the last event. `fixed_window_secs` is the validity
+/// window the worker fixes for its executions, if it doesn't read one from its config.
+pub(crate) fn observe_config(config: &[u8], fixed_window_secs: Option<u64>) -> Result<(), String> {
+    OPTIONS.with(|options| {
+        let mut options = options.borrow_mut();
+        if options
+            .as_ref()
+            .is_some_and(|(raw, window, _)| raw == config && *window == fixed_window_secs)
+        {
+            return Ok(());
+        }
+        let parsed = RuntimeOptions::parse(config, fixed_window_secs)?;
+        *options = Some((config.to_vec(), fixed_window_secs, Rc::new(parsed)));
+        Ok(())
+    })
+}
+
+/// The options of the config observed last, or the defaults before any has been.
+pub(crate) fn current() -> Rc<RuntimeOptions> {
+    OPTIONS.with(|options| {
+        options
+            .borrow()
+            .as_ref()
+            .map(|(_, _, options)| options.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Forget the observed options, e.g. when a simulation ends.
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn reset() {
+    OPTIONS.with(|options| options.borrow_mut().take());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(config: serde_json::Value) -> Result<RuntimeOptions, String> {
+        RuntimeOptions::parse(&serde_json::to_vec(&config).unwrap(), None)
+    }
+
+    #[test]
+    fn options_are_off_unless_set() {
+        let options = parse(serde_json::json!({ "network": "preview" })).unwrap();
+        assert!(!options.dry_run);
+        assert_eq!(options.execution_jitter_secs, 0);
+        assert!(options.min_order_value().is_none());
+        assert_eq!(options.min_submit_interval_secs, 0);
+        assert!(options.candles().is_none());
+        assert!(!options.check_unspent);
+        assert!(options.check_balance);
+        assert!(current().check_balance);
+        assert!(!options.allow_manual_executions);
+    }
+
+    #[test]
+    fn options_are_read_alongside_the_worker_fields() {
+        let options = parse(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "dry_run": true,
+            "execution_jitter_secs": 30,
+            "min_order_value": 5,
+            "base_token": "99999999999999999999999999999999999999999999999999999999.534245525259",
+            "min_submit_interval_secs": 60,
+            "candle_interval_secs": 300,
+            "check_unspent": true,
+            "check_balance": false,
+        }))
+        .unwrap();
+        assert!(options.dry_run);
+        assert_eq!(options.execution_jitter_secs, 30);
+        let min = options.min_order_value().unwrap();
+        assert_eq!(min.amount, 5);
+        assert!(!min.asset.is_ada());
+        assert_eq!(options.min_submit_interval_secs, 60);
+        assert_eq!(options.candles().unwrap().interval_secs, 300);
+        assert!(options.check_unspent);
+        assert!(!options.check_balance);
+
+        // Without a base_token, the minimum is in lovelace; a minimum of 0 is off
+        let options = parse(serde_json::json!({ "min_order_value": 5 })).unwrap();
+        assert!(options.min_order_value().unwrap().asset.is_ada());
+        let options = parse(serde_json::json!({ "min_order_value": 0 })).unwrap();
+        assert!(options.min_order_value().is_none());
+    }
+
+    #[test]
+    fn jitter_must_fit_in_the_validity_window() {
+        assert!(parse(serde_json::json!({ "execution_jitter_secs": 1199 })).is_ok());
+        assert!(parse(serde_json::json!({ "execution_jitter_secs": 1200 })).is_err());
+        assert!(
+            parse(serde_json::json!({
+                "execution_jitter_secs": 60,
+                "validity_window_secs": 60,
+            }))
+            .is_err()
+        );
+
+        // A worker with a fixed window holds jitter to it, whatever the config says
+        let config = br#"{"execution_jitter_secs": 30, "validity_window_secs": 600}"#;
+        assert!(RuntimeOptions::parse(config, None).is_ok());
+        assert!(RuntimeOptions::parse(config, Some(20)).is_err());
+    }
+
+    #[test]
+    fn malformed_options_are_rejected() {
+        assert!(parse(serde_json::json!({ "dry_run": "yes" })).is_err());
+        assert!(parse(serde_json::json!({ "candle_interval_secs": 60 })).is_err());
+        assert!(RuntimeOptions::parse(b"not json").is_err());
+    }
+
+    #[test]
+    fn unchanged_configs_are_not_reparsed() {
+        observe_config(br#"{"dry_run": true}"#, None).unwrap();
+        let first = current();
+        observe_config(br#"{"dry_run": true}"#, None).unwrap();
+        assert!(Rc::ptr_eq(&first, &current()));
+        assert!(observe_config(br#"{"dry_run": 1}"#, None).is_err());
+        reset();
+        assert!(!current().dry_run);
+    }
+}
@@ -0,0 +1,215 @@
+//! Pausing strategies, so an operator can stop them acting without tearing down the worker.
+//!
+//! The `pause` and `resume` requests take an optional `order_ref` (`txHashHex#index`) naming
+//! one managed order; with `{}` they pause or resume every order. While an order is paused
+//! it is left out of the orders passed to the `on_new_pool_state` and `on_each_tx`
+//! callbacks, and while everything is paused those callbacks don't run at all. Orders are
+//! still tracked, and `on_new_strategy` and `on_strategy_spent` still run, so a strategy's
+//! bookkeeping stays current and it picks up where it left off once resumed.
+//!
+//! A paused order stays paused when a transaction spends it into a successor carrying the
+//! same datum (see [`ManagedStrategy::successor`]), since that is still the same strategy.
+
+use std::collections::BTreeSet;
+
+use balius_sdk::{_internal::Handler, Json, Params, Tx, WorkerResult, wit};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{ManagedStrategy, kv, kv::NamespaceKey, types::OutputReference};
+
+const KV_PAUSED: &str = "paused";
+
+/// What is paused, as returned by `pause` and `resume`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct PauseState {
+    /// Whether every order is paused, whatever `orders` holds
+    pub all: bool,
+    /// The orders paused individually, as `txHashHex#index`
+    pub orders: BTreeSet<String>,
+}
+
+impl PauseState {
+    fn is_paused(&self, order: &ManagedStrategy) -> bool {
+        self.all || self.orders.contains(&order.output.namespace_key())
+    }
+
+    fn set(&mut self, order_ref: Option<String>, paused: bool) {
+        match (order_ref, paused) {
+            (None, paused) => {
+                self.all = paused;
+                if !paused {
+                    self.orders.clear();
+                }
+            }
+            (Some(order_ref), true) => {
+                self.orders.insert(order_ref);
+            }
+            (Some(order_ref), false) => {
+                self.orders.remove(&order_ref);
+            }
+        }
+    }
+}
+
+pub(crate) fn pause_state() -> WorkerResult<PauseState> {
+    Ok(kv::get(KV_PAUSED)?.unwrap_or_default())
+}
+
+/// The orders among `orders` that aren't paused, or None if everything is.
+pub(crate) fn active(orders: Vec<ManagedStrategy>) -> WorkerResult<Option<Vec<ManagedStrategy>>> {
+    let state = pause_state()?;
+    if state.all {
+        return Ok(None);
+    }
+    Ok(Some(
+        orders
+            .into_iter()
+            .filter(|order| !state.is_paused(order))
+            .collect(),
+    ))
+}
+
+/// Move the pauses of orders `tx` spent onto their successors, dropping those it closed,
+/// since the spent output references won't recur.
+pub(crate) fn carry_over(tx: &Tx, spent: &[ManagedStrategy]) -> WorkerResult<()> {
+    let mut state = pause_state()?;
+    let mut changed = false;
+    for order in spent {
+        if !state.orders.remove(&order.output.namespace_key()) {
+            continue;
+        }
+        changed = true;
+        if let Some(successor) = order.successor(tx) {
+            info!(
+                from = ?order.output,
+                to = ?successor.output,
+                "pause carried to successor"
+            );
+            state.orders.insert(successor.output.namespace_key());
+        }
+    }
+    if changed {
+        kv::set(KV_PAUSED, &state)?;
+    }
+    Ok(())
+}
+
+/// Request parameters for pause and resume
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PauseParams {
+    /// The order to pause or resume, as `txHashHex#index`; every order if omitted
+    order_ref: Option<String>,
+}
+
+/// Handler for `pause` requests when `paused`, or `resume` requests otherwise.
+#[derive(Clone)]
+pub(crate) struct PauseHandler {
+    pub paused: bool,
+}
+
+impl Handler for PauseHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let params: Params<PauseParams> = event.try_into().map_err(|_| wit::HandleError {
+            message: "invalid request parameters".to_string(),
+            code: 400,
+        })?;
+        // Normalize the reference, so it matches however the order is looked up later
+        let order_ref = match &params.order_ref {
+            Some(order_ref) => {
+                let output: OutputReference = order_ref
+                    .parse()
+                    .map_err(|message| wit::HandleError { message, code: 400 })?;
+                Some(output.namespace_key())
+            }
+            None => None,
+        };
+
+        let state = kv::update(KV_PAUSED, |state: Option<PauseState>| {
+            let mut state = state.unwrap_or_default();
+            state.set(order_ref.clone(), self.paused);
+            state
+        })?;
+        info!(
+            order = order_ref.as_deref().unwrap_or("all"),
+            paused = self.paused,
+            "pause state changed"
+        );
+        Ok(Json(state).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Strategy, sim::Simulator, types::TransactionId};
+
+    fn order(index: u64) -> ManagedStrategy {
+        let mut order = ManagedStrategy::mock(&[]);
+        order.output = OutputReference {
+            transaction_id: TransactionId(vec![0xab; 32]),
+            output_index: index,
+        };
+        order
+    }
+
+    #[test]
+    fn pausing_one_order_leaves_the_rest_active() {
+        let mut state = PauseState::default();
+        state.set(Some(order(1).output.namespace_key()), true);
+        assert!(state.is_paused(&order(1)));
+        assert!(!state.is_paused(&order(2)));
+
+        state.set(None, true);
+        assert!(state.is_paused(&order(2)));
+
+        // Resuming everything clears individual pauses too
+        state.set(None, false);
+        assert_eq!(state, PauseState::default());
+    }
+
+    #[test]
+    fn filters_paused_orders() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let mut state = PauseState::default();
+        state.set(Some(order(1).output.namespace_key()), true);
+        kv::set(KV_PAUSED, &state).unwrap();
+
+        let active = active(vec![order(1), order(2)]).unwrap().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].output.output_index, 2);
+
+        // Closing the order drops its pause
+        let mut close = order(1).mock_execution(10, &[]);
+        close.tx.outputs.clear();
+        carry_over(&close, &[order(1)]).unwrap();
+        assert_eq!(pause_state().unwrap(), PauseState::default());
+
+        state.set(None, true);
+        kv::set(KV_PAUSED, &state).unwrap();
+        assert!(active(vec![order(2)]).unwrap().is_none());
+    }
+
+    #[test]
+    fn pauses_follow_the_order_to_its_successor() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let mut state = PauseState::default();
+        state.set(Some(order(1).output.namespace_key()), true);
+        kv::set(KV_PAUSED, &state).unwrap();
+
+        let tx = order(1).mock_execution(10, &[]);
+        let successor = order(1).successor(&tx).unwrap();
+        carry_over(&tx, &[order(1), order(2)]).unwrap();
+
+        let active = active(vec![successor, order(2)]).unwrap().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].output.output_index, 2);
+    }
+}
@@ -0,0 +1,189 @@
+//! Prices that carry their units and orientation in their type.
+//!
+//! A pool quotes asset_a per asset_b in raw (base) units. Workers usually want whole units,
+//! and often the other orientation, and mixing those up silently gives prices that are off
+//! by a factor of `10^decimals` or inverted. [`Price`] makes each conversion explicit, so a
+//! raw price can't be compared with a decimal one, or one orientation with the other.
+
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+/// Marks a price in raw (base) units of each asset, e.g. lovelace per sprinkle.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Raw;
+
+/// Marks a price in whole units of each asset, e.g. ADA per SUNDAE.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Decimal;
+
+/// Marks a price of asset_b in asset_a: how much asset_a one asset_b is worth.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct APerB;
+
+/// Marks a price of asset_a in asset_b: how much asset_b one asset_a is worth.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct BPerA;
+
+/// Which asset of a pair a price is quoted in.
+pub trait Orientation {
+    type Inverse: Orientation;
+
+    /// The power of ten taking a raw price to a decimal one, given each asset's decimals.
+    fn decimal_exponent(token_a_decimals: u8, token_b_decimals: u8) -> i32;
+}
+
+impl Orientation for APerB {
+    type Inverse = BPerA;
+
+    fn decimal_exponent(token_a_decimals: u8, token_b_decimals: u8) -> i32 {
+        token_b_decimals as i32 - token_a_decimals as i32
+    }
+}
+
+impl Orientation for BPerA {
+    type Inverse = APerB;
+
+    fn decimal_exponent(token_a_decimals: u8, token_b_decimals: u8) -> i32 {
+        token_a_decimals as i32 - token_b_decimals as i32
+    }
+}
+
+/// A price in units `U` ([`Raw`] or [`Decimal`]) and orientation `O` ([`APerB`] or [`BPerA`]).
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Price<U, O> {
+    value: f64,
+    _units: PhantomData<(U, O)>,
+}
+
+/// A price as read from a pool: raw asset_a per raw asset_b.
+pub type RawPrice = Price<Raw, APerB>;
+
+/// Whole asset_a per whole asset_b.
+pub type DecimalPrice = Price<Decimal, APerB>;
+
+impl<U, O: Orientation> Price<U, O> {
+    pub fn new(value: f64) -> Self {
+        Price {
+            value,
+            _units: PhantomData,
+        }
+    }
+
+    pub fn value(self) -> f64 {
+        self.value
+    }
+
+    /// The same price quoted the other way around. A zero price, e.g. from an empty pool,
+    /// stays zero.
+    pub fn invert(self) -> Price<U, O::Inverse> {
+        Price::new(if self.value == 0.0 {
+            0.0
+        } else {
+            1.0 / self.value
+        })
+    }
+}
+
+impl<O: Orientation> Price<Raw, O> {
+    /// Scale to whole units, given the decimal places of the pool's asset_a and asset_b.
+    pub fn to_decimal(self, token_a_decimals: u8, token_b_decimals: u8) -> Price<Decimal, O> {
+        let exponent = O::decimal_exponent(token_a_decimals, token_b_decimals);
+        Price::new(self.value * 10f64.powi(exponent))
+    }
+}
+
+impl<O: Orientation> Price<Decimal, O> {
+    /// Scale back to raw units, given the decimal places of the pool's asset_a and asset_b.
+    pub fn to_raw(self, token_a_decimals: u8, token_b_decimals: u8) -> Price<Raw, O> {
+        let exponent = O::decimal_exponent(token_a_decimals, token_b_decimals);
+        Price::new(self.value * 10f64.powi(-exponent))
+    }
+}
+
+/// Which way a pool's price moved since its previous observation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+    /// Unchanged, or there's no previous observation to compare with
+    #[default]
+    Flat,
+}
+
+/// How a pool's price changed since the worker last observed that pool.
+///
+/// Prices are raw asset_a per raw asset_b, as [`RawPrice`] and
+/// [`crate::types::PoolDatum::raw_price`] give them; callers scale to whole units
+/// themselves. Since scaling and inverting are both monotonic, `pct_change` holds for the
+/// decimal price too, though inverting a price reverses its `direction`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct PriceChange {
+    /// The price at the previous observation of the pool, or None if this is the first
+    pub previous: Option<f64>,
+    /// The price at this observation
+    pub current: f64,
+    /// The change from `previous` as a fraction of it, e.g. 0.05 for a 5% rise, or None
+    /// without a positive previous price
+    pub pct_change: Option<f64>,
+    pub direction: Direction,
+}
+
+impl PriceChange {
+    pub fn new(previous: Option<f64>, current: f64) -> Self {
+        let direction = match previous {
+            Some(previous) if current > previous => Direction::Up,
+            Some(previous) if current < previous => Direction::Down,
+            _ => Direction::Flat,
+        };
+        PriceChange {
+            previous,
+            current,
+            pct_change: previous
+                .filter(|previous| *previous > 0.0)
+                .map(|previous| (current - previous) / previous),
+            direction,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimals_scale_with_the_orientation() {
+        // 168 lovelace per sprinkle-less token, with ADA at 6 decimals and the token at 0
+        let raw = RawPrice::new(168.0);
+        assert!((raw.to_decimal(6, 0).value() - 0.000168).abs() < 1e-12);
+        assert!((raw.invert().to_decimal(6, 0).value() - 1.0 / 0.000168).abs() < 1e-6);
+        assert!((raw.to_decimal(6, 0).to_raw(6, 0).value() - 168.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn inverting_twice_is_the_identity() {
+        let price = DecimalPrice::new(4.0);
+        assert_eq!(price.invert().value(), 0.25);
+        assert_eq!(price.invert().invert(), price);
+        assert_eq!(RawPrice::new(0.0).invert().value(), 0.0);
+    }
+
+    #[test]
+    fn price_change_compares_with_the_previous_price() {
+        let first = PriceChange::new(None, 2.0);
+        assert_eq!(first.direction, Direction::Flat);
+        assert_eq!(first.pct_change, None);
+
+        let up = PriceChange::new(Some(2.0), 2.5);
+        assert_eq!(up.direction, Direction::Up);
+        assert_eq!(up.pct_change, Some(0.25));
+
+        let down = PriceChange::new(Some(2.0), 1.0);
+        assert_eq!(down.direction, Direction::Down);
+        assert_eq!(down.pct_change, Some(-0.5));
+
+        assert_eq!(PriceChange::new(Some(0.0), 1.0).pct_change, None);
+        assert_eq!(PriceChange::new(Some(1.0), 1.0).direction, Direction::Flat);
+    }
+}
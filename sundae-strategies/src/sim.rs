@@ -0,0 +1,330 @@
+//! Run a strategy's callbacks in-process against a synthetic series of observations,
+//! capturing the executions it would submit instead of signing and posting them.
+//!
+//! While a [`Simulator`] is alive, the [`crate::kv`] functions on its thread use an
+//! in-memory store and the simulated clock, so strategies run unmodified.
+//!
+//! # Examples
+//! ```ignore
+//! let mut sim = Simulator::new(strategy(), &serde_json::json!({ "network": "preview", ... }))?;
+//! sim.add_order(ManagedStrategy::mock(&[(&sundae, 1_000)]))?;
+//! let executions = sim.run([
+//!     (1, PoolState::mock(100_000, 1_000, (&ada, &sundae))),
+//!     (2, PoolState::mock(80_000, 1_000, (&ada, &sundae))),
+//! ])?;
+//! assert_eq!(executions.len(), 1);
+//! ```
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use balius_sdk::{Config, Error, Tx, WorkerResult, wit::balius::app::kv::KvError};
+
+use crate::{ManagedStrategy, PoolState, Strategy, kv, options, types::StrategyExecution};
+
+#[derive(Default)]
+struct Simulation {
+    kv: BTreeMap<String, Vec<u8>>,
+    slot: u64,
+    executions: Vec<StrategyExecution>,
+}
+
+thread_local! {
+    static SIMULATION: RefCell<Option<Simulation>> = const { RefCell::new(None) };
+}
+
+fn with<R>(f: impl FnOnce(&mut Simulation) -> R) -> Option<R> {
+    SIMULATION.with(|simulation| simulation.borrow_mut().as_mut().map(f))
+}
+
+/// Feeds observations through a [`Strategy`]'s callbacks and records what it submits.
+///
+/// Only one simulator may run on a thread at a time; the in-memory store is discarded
+/// when it's dropped.
+pub struct Simulator<T> {
+    strategy: Strategy<T>,
+    config: Config<T>,
+}
+
+impl<T: Send + Sync + 'static> Simulator<T>
+where
+    Config<T>: TryFrom<Vec<u8>, Error = Error>,
+{
+    /// Prepare to simulate `strategy` with the worker config `config`, as JSON.
+    pub fn new(strategy: Strategy<T>, config: &serde_json::Value) -> WorkerResult<Self> {
+        let config = serde_json::to_vec(config)?;
+        options::observe_config(&config, strategy.validity_window_secs).map_err(Error::Internal)?;
+        let config = Config::try_from(config)?;
+        SIMULATION.with(|simulation| {
+            let mut simulation = simulation.borrow_mut();
+            if simulation.is_some() {
+                return Err(Error::Internal(
+                    "a simulation is already running on this thread".into(),
+                ));
+            }
+            *simulation = Some(Simulation::default());
+            Ok(())
+        })?;
+        Ok(Self { strategy, config })
+    }
+
+    /// Take custody of `order`, as if it had been observed on chain, running the new
+    /// strategy callback.
+    pub fn add_order(&mut self, order: ManagedStrategy) -> WorkerResult<()> {
+        kv::observe_slot(order.slot);
+        self.strategy.track_order(&self.config, order)?;
+        Ok(())
+    }
+
+    /// Observe `tx` as if it had been seen on chain: run the spent strategy callback for each
+    /// order it spends, then take custody of their [successors](ManagedStrategy::successor).
+    pub fn observe_tx(&mut self, tx: Tx) -> WorkerResult<()> {
+        kv::observe_slot(tx.block_slot);
+        let successors: Vec<ManagedStrategy> = crate::managed_strategies()?
+            .iter()
+            .filter_map(|order| order.successor(&tx))
+            .collect();
+        self.strategy.handle_tx(&self.config, tx)?;
+        for successor in successors {
+            self.add_order(successor)?;
+        }
+        Ok(())
+    }
+
+    /// Observe each pool state in order, at its paired slot, and return every execution
+    /// submitted along the way, in submission order.
+    pub fn run(
+        &mut self,
+        observations: impl IntoIterator<Item = (u64, PoolState)>,
+    ) -> WorkerResult<Vec<StrategyExecution>> {
+        for (slot, mut pool_state) in observations {
+            pool_state.slot = slot;
+            kv::observe_slot(slot);
+            self.strategy.observe_pool_state(&self.config, pool_state)?;
+        }
+        Ok(with(|simulation| std::mem::take(&mut simulation.executions)).unwrap_or_default())
+    }
+}
+
+impl<T> Drop for Simulator<T> {
+    fn drop(&mut self) {
+        SIMULATION.with(|simulation| simulation.borrow_mut().take());
+        options::reset();
+    }
+}
+
+pub(crate) fn kv_get(key: &str) -> Option<Result<Vec<u8>, KvError>> {
+    with(|simulation| {
+        simulation
+            .kv
+            .get(key)
+            .cloned()
+            .ok_or_else(|| KvError::NotFound(key.to_string()))
+    })
+}
+
+pub(crate) fn kv_set(key: &str, value: &[u8]) -> bool {
+    with(|simulation| simulation.kv.insert(key.to_string(), value.to_vec())).is_some()
+}
+
+pub(crate) fn kv_list(prefix: &str) -> Option<Vec<String>> {
+    with(|simulation| {
+        simulation
+            .kv
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect()
+    })
+}
+
+pub(crate) fn current_slot() -> Option<u64> {
+    with(|simulation| simulation.slot)
+}
+
+pub(crate) fn observe_slot(slot: u64) -> bool {
+    with(|simulation| simulation.slot = simulation.slot.max(slot)).is_some()
+}
+
+/// Record `execution` if a simulation is running, returning whether it was captured.
+pub(crate) fn capture(execution: &StrategyExecution) -> bool {
+    with(|simulation| simulation.executions.push(execution.clone())).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AssetId, DatumVersion, Order, OutputReference, TransactionId};
+    use balius_sdk::Ack;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct SellBelow {
+        network: crate::Network,
+        price: f64,
+    }
+
+    /// Sells the whole position whenever the price of SBERRY drops below the configured price.
+    #[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+    fn sell_below(
+        config: &Config<SellBelow>,
+        pool: &PoolState,
+        strategies: &Vec<ManagedStrategy>,
+    ) -> WorkerResult<Ack> {
+        let (ada, sberry) = (ada(), sberry());
+        if pool.pool_datum.raw_price(&pool.utxo).value() >= config.price {
+            return Ok(Ack);
+        }
+        for strategy in strategies {
+            let amount = strategy.balance(&sberry);
+            let validity_range = pool.get_validity_range(&config.network, 60);
+            let swap = Order::swap((&sberry, amount), (&ada, 1));
+            strategy.submit_execution(&config.network, validity_range, swap)?;
+        }
+        Ok(Ack)
+    }
+
+    fn ada() -> AssetId {
+        AssetId::from((vec![], vec![]))
+    }
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    #[test]
+    fn captures_executions_in_order() {
+        let strategy = Strategy::<SellBelow>::new().on_new_pool_state(sell_below);
+        let config = serde_json::json!({ "network": "preview", "price": 90.0 });
+        let mut sim = Simulator::new(strategy, &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sberry(), 1_000)]))
+            .unwrap();
+
+        let pool = |lovelace| PoolState::mock(lovelace, 1_000, (&ada(), &sberry()));
+        let executions = sim
+            .run([(10, pool(100_000)), (20, pool(95_000)), (30, pool(85_000))])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { offer, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 1_000);
+        assert_eq!(kv::current_slot(), 30);
+    }
+
+    #[test]
+    fn submits_for_orders_in_output_order() {
+        let strategy = Strategy::<SellBelow>::new().on_new_pool_state(sell_below);
+        let config = serde_json::json!({ "network": "preview", "price": 90.0 });
+        let mut sim = Simulator::new(strategy, &config).unwrap();
+        let order = |hash: u8, index: u64| {
+            let mut order = ManagedStrategy::mock(&[(&sberry(), 1_000)]);
+            order.output = OutputReference {
+                transaction_id: TransactionId(vec![hash; 32]),
+                output_index: index,
+            };
+            order
+        };
+        for (hash, index) in [(0xbb, 0), (0xaa, 10), (0xaa, 2)] {
+            sim.add_order(order(hash, index)).unwrap();
+        }
+
+        let pool = |lovelace| PoolState::mock(lovelace, 1_000, (&ada(), &sberry()));
+        let executions = sim.run([(10, pool(85_000))]).unwrap();
+        let submitted = executions
+            .iter()
+            .map(|execution| format!("{:?}", execution.tx_ref))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            submitted,
+            [
+                format!("{}#2", "aa".repeat(32)),
+                format!("{}#10", "aa".repeat(32)),
+                format!("{}#0", "bb".repeat(32)),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_skipped_order_does_not_stop_the_rest() {
+        let strategy = Strategy::<SellBelow>::new().on_new_pool_state(sell_below);
+        let config = serde_json::json!({
+            "network": "preview",
+            "price": 90.0,
+            "min_order_value": 500,
+            "base_token": format!("{}.{}", "99".repeat(28), hex::encode("SBERRY")),
+        });
+        let mut sim = Simulator::new(strategy, &config).unwrap();
+        for (hash, amount) in [(0xaa, 100), (0xbb, 1_000)] {
+            let mut order = ManagedStrategy::mock(&[(&sberry(), amount)]);
+            order.output = OutputReference {
+                transaction_id: TransactionId(vec![hash; 32]),
+                output_index: 0,
+            };
+            sim.add_order(order).unwrap();
+        }
+
+        // The first order is dust, and is skipped without failing the event
+        let pool = |lovelace| PoolState::mock(lovelace, 1_000, (&ada(), &sberry()));
+        let executions = sim.run([(10, pool(85_000))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        assert_eq!(
+            format!("{:?}", executions[0].tx_ref),
+            format!("{}#0", "bb".repeat(32))
+        );
+    }
+
+    #[test]
+    fn only_v3_pools_are_observed_by_default() {
+        let config = serde_json::json!({ "network": "preview", "price": 90.0 });
+        let v1_pool = || {
+            let mut pool = PoolState::mock(85_000, 1_000, (&ada(), &sberry()));
+            pool.version = DatumVersion::V1;
+            pool
+        };
+
+        let strategy = Strategy::<SellBelow>::new().on_new_pool_state(sell_below);
+        let mut sim = Simulator::new(strategy, &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sberry(), 1_000)]))
+            .unwrap();
+        assert!(sim.run([(10, v1_pool())]).unwrap().is_empty());
+        drop(sim);
+
+        let strategy = Strategy::<SellBelow>::new()
+            .on_new_pool_state(sell_below)
+            .with_pool_versions(&[DatumVersion::V1, DatumVersion::V3]);
+        let mut sim = Simulator::new(strategy, &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sberry(), 1_000)]))
+            .unwrap();
+        assert_eq!(sim.run([(10, v1_pool())]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn executions_replace_the_spent_order_with_its_successor() {
+        let strategy = Strategy::<SellBelow>::new().on_new_pool_state(sell_below);
+        let config = serde_json::json!({ "network": "preview", "price": 90.0 });
+        let mut sim = Simulator::new(strategy, &config).unwrap();
+        let order = ManagedStrategy::mock(&[(&sberry(), 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        sim.observe_tx(order.mock_execution(20, &[(&ada(), 84_000)]))
+            .unwrap();
+        let managed = crate::managed_strategies().unwrap();
+        assert_eq!(managed.len(), 1);
+        assert_eq!(managed[0].slot, 20);
+        assert_eq!(managed[0].balance(&ada()), 84_000);
+        assert_eq!(managed[0].balance(&sberry()), 0);
+    }
+
+    #[test]
+    fn stores_state_in_memory() {
+        let strategy = Strategy::<SellBelow>::new();
+        let config = serde_json::json!({ "network": "preview", "price": 90.0 });
+        let _sim = Simulator::new(strategy, &config).unwrap();
+
+        let peaks = kv::Namespace::<f64>::new("peak_price");
+        peaks.set("a", &1.5).unwrap();
+        assert_eq!(peaks.get("a").unwrap(), Some(1.5));
+        assert_eq!(peaks.iter().unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,170 @@
+//! Where signed strategy executions go once built.
+//!
+//! [`crate::submit_execution`] posts to the Sundae relay through [`HttpSink`];
+//! [`crate::submit_execution_to`] accepts any other [`ExecutionSink`], such as a
+//! [`RecordingSink`] in tests.
+
+use std::{cell::RefCell, fmt};
+
+use balius_sdk::{
+    Error,
+    http::{HttpRequest, HttpResponse},
+};
+use tracing::{info, warn};
+
+use crate::{Network, types::SubmitSSE};
+
+/// Delivers signed executions to the network of scoopers.
+pub trait ExecutionSink {
+    fn submit(&self, network: &Network, execution: &SubmitSSE) -> Result<HttpResponse, SinkError>;
+}
+
+/// Why a sink couldn't deliver an execution.
+#[derive(Debug)]
+pub enum SinkError {
+    /// The relay didn't answer in time. The execution may or may not have arrived, so it's
+    /// worth retrying; the relay drops duplicates.
+    Timeout(String),
+    /// Anything else that kept the execution from being delivered.
+    Failed(Error),
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkError::Timeout(message) => write!(f, "{TIMEOUT_PREFIX}{message}"),
+            SinkError::Failed(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<Error> for SinkError {
+    fn from(err: Error) -> Self {
+        SinkError::Failed(err)
+    }
+}
+
+const TIMEOUT_PREFIX: &str = "relay timed out: ";
+
+/// A timeout becomes an [`Error::Internal`] that [`is_timeout`] recognizes, so callers of
+/// [`crate::submit_execution`] can still tell it apart.
+impl From<SinkError> for Error {
+    fn from(err: SinkError) -> Self {
+        match err {
+            SinkError::Timeout(_) => Error::Internal(err.to_string()),
+            SinkError::Failed(err) => err,
+        }
+    }
+}
+
+/// Whether `err` is a submission that failed because the relay didn't answer in time.
+pub fn is_timeout(err: &Error) -> bool {
+    matches!(err, Error::Internal(message) if message.starts_with(TIMEOUT_PREFIX))
+}
+
+/// Sort an HTTP failure into a [`SinkError`] by how the host describes it, since the SDK
+/// reports timeouts as any other HTTP error.
+fn classify(err: Error) -> SinkError {
+    let message = err.to_string();
+    let lowercase = message.to_lowercase();
+    if lowercase.contains("timeout") || lowercase.contains("timed out") {
+        SinkError::Timeout(message)
+    } else {
+        SinkError::Failed(err)
+    }
+}
+
+/// Posts executions to the Sundae relay for `network`.
+///
+/// The request is bounded by the host's `submit_timeout_ms` (10 seconds by default in
+/// `balius-server`), so a hung relay fails the submission rather than stalling the worker.
+/// A relay that doesn't answer in time fails with [`SinkError::Timeout`], which callers can
+/// retry; a relay that answers, even with an error status, returns its response.
+pub struct HttpSink;
+
+impl ExecutionSink for HttpSink {
+    fn submit(&self, network: &Network, execution: &SubmitSSE) -> Result<HttpResponse, SinkError> {
+        info!("posting to {}", network.relay_url());
+        HttpRequest::post(network.relay_url())
+            .json(execution)
+            .map_err(Error::from)?
+            .send()
+            .map_err(|err| classify(Error::from(err)))
+            .inspect_err(|err| {
+                warn!(
+                    "relay at {} unreachable or timed out: {err}",
+                    network.relay_url()
+                )
+            })
+    }
+}
+
+/// Keeps every execution it's given, so tests can assert on exactly what would be posted.
+#[derive(Default)]
+pub struct RecordingSink {
+    executions: RefCell<Vec<SubmitSSE>>,
+}
+
+impl RecordingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every execution recorded so far, oldest first, leaving the sink empty.
+    pub fn take(&self) -> Vec<SubmitSSE> {
+        self.executions.take()
+    }
+}
+
+impl ExecutionSink for RecordingSink {
+    fn submit(&self, _network: &Network, execution: &SubmitSSE) -> Result<HttpResponse, SinkError> {
+        self.executions.borrow_mut().push(execution.clone());
+        Ok(accepted_response())
+    }
+}
+
+/// What the relay answers to an accepted execution.
+pub(crate) fn accepted_response() -> HttpResponse {
+    HttpResponse {
+        status: 202,
+        headers: Default::default(),
+        body: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_sink_keeps_executions_in_order() {
+        let sink = RecordingSink::new();
+        for tx_index in 0..2 {
+            let execution = SubmitSSE {
+                tx_hash: "abcd".to_string(),
+                tx_index,
+                data: "d8799f".to_string(),
+            };
+            let response = sink.submit(&Network::Preview, &execution).unwrap();
+            assert_eq!(response.status, 202);
+        }
+
+        let recorded = sink.take();
+        assert_eq!(
+            recorded.iter().map(|e| e.tx_index).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert!(sink.take().is_empty());
+    }
+
+    #[test]
+    fn timeouts_can_be_told_apart() {
+        let timeout = classify(Error::Internal("operation timed out".to_string()));
+        assert!(matches!(timeout, SinkError::Timeout(_)));
+        assert!(is_timeout(&Error::from(timeout)));
+
+        let refused = classify(Error::Internal("connection refused".to_string()));
+        assert!(matches!(refused, SinkError::Failed(_)));
+        assert!(!is_timeout(&Error::from(refused)));
+    }
+}
@@ -0,0 +1,142 @@
+//! A uniform way to ask any strategy what it currently holds for one of its orders.
+//!
+//! A worker whose config type implements [`StateSnapshot`] can register the `get-state`
+//! request handler with [`crate::Strategy::with_state`]. The request takes an `order_ref`
+//! (`txHashHex#index`) naming one managed order, and returns the order reference with the
+//! strategy's snapshot for it, e.g. a grid's center price and line offset, or a trailing
+//! stop's peak and trigger:
+//!
+//! ```json
+//! { "order_ref": "ab…#0", "state": { "peak_price": 10.5, "trigger_price": 9.45, "trailing": true } }
+//! ```
+//!
+//! `state` is null if the strategy hasn't recorded anything for the order yet. An order
+//! that isn't in the worker's custody is answered with a 404.
+
+use balius_sdk::{Config, Json, Params, WorkerResult, wit};
+use serde::{Deserialize, Serialize};
+
+use crate::{ManagedStrategy, kv::NamespaceKey, types::OutputReference};
+
+/// A strategy whose internal state for an order can be reported by `get-state`.
+///
+/// Implement it for the worker's config type, since a strategy's state is generally
+/// interpreted through its config.
+pub trait StateSnapshot {
+    /// What is reported for each order.
+    type Snapshot: Serialize;
+
+    /// The current state of the strategy for `order`, or None if there isn't any yet.
+    fn snapshot(&self, order: &ManagedStrategy) -> WorkerResult<Option<Self::Snapshot>>;
+}
+
+/// Request parameters for get-state
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GetStateParams {
+    /// The order to report on, as `txHashHex#index`
+    order_ref: String,
+}
+
+/// Response for get-state
+#[derive(Serialize)]
+struct GetStateResponse<S> {
+    /// The order reported on, as `txHashHex#index`
+    order_ref: String,
+    /// The strategy's snapshot for the order, or null if it hasn't any yet
+    state: Option<S>,
+}
+
+/// How a registered `get-state` handler answers, fixed to the worker's config type.
+pub(crate) type StateFn = fn(wit::Config, wit::Event) -> Result<wit::Response, wit::HandleError>;
+
+/// Handler for `get-state` requests.
+#[derive(Clone)]
+pub(crate) struct StateHandler(pub StateFn);
+
+impl balius_sdk::_internal::Handler for StateHandler {
+    fn handle(
+        &self,
+        config: wit::Config,
+        event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        (self.0)(config, event)
+    }
+}
+
+pub(crate) fn handle<T: StateSnapshot>(
+    config: wit::Config,
+    event: wit::Event,
+) -> Result<wit::Response, wit::HandleError>
+where
+    Config<T>: TryFrom<Vec<u8>, Error = balius_sdk::Error>,
+{
+    let config: Config<T> = config.try_into()?;
+    let params: Params<GetStateParams> = event.try_into().map_err(|_| wit::HandleError {
+        message: "invalid request parameters".to_string(),
+        code: 400,
+    })?;
+    let response = state_of(&*config, &params.order_ref)?;
+    Ok(Json(response).try_into()?)
+}
+
+fn state_of<T: StateSnapshot>(
+    config: &T,
+    order_ref: &str,
+) -> Result<GetStateResponse<T::Snapshot>, wit::HandleError> {
+    let output: OutputReference = order_ref
+        .parse()
+        .map_err(|message| wit::HandleError { message, code: 400 })?;
+    let order_ref = output.namespace_key();
+    let Some(order) = crate::managed_strategies()?
+        .into_iter()
+        .find(|order| order.output.namespace_key() == order_ref)
+    else {
+        return Err(wit::HandleError {
+            message: format!("{output:?} is not a strategy order in this worker's custody"),
+            code: 404,
+        });
+    };
+    Ok(GetStateResponse {
+        state: config.snapshot(&order)?,
+        order_ref,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Strategy, kv, sim::Simulator};
+
+    /// Reports how many times each order has been counted
+    struct Counter;
+
+    impl StateSnapshot for Counter {
+        type Snapshot = u64;
+
+        fn snapshot(&self, order: &ManagedStrategy) -> WorkerResult<Option<u64>> {
+            kv::Namespace::new("count").get(&order.output)
+        }
+    }
+
+    #[test]
+    fn reports_the_snapshot_of_managed_orders() {
+        let mut sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let order = ManagedStrategy::mock(&[]);
+        sim.add_order(order.clone()).unwrap();
+        let order_ref = order.output.namespace_key();
+
+        let response = state_of(&Counter, &order_ref).unwrap();
+        assert_eq!(response.order_ref, order_ref);
+        assert_eq!(response.state, None);
+
+        kv::Namespace::new("count")
+            .set(&order.output, &3u64)
+            .unwrap();
+        assert_eq!(state_of(&Counter, &order_ref).unwrap().state, Some(3));
+
+        assert_eq!(state_of(&Counter, "00ff#9").err().unwrap().code, 404);
+        assert_eq!(state_of(&Counter, "not an order").err().unwrap().code, 400);
+    }
+}
@@ -0,0 +1,83 @@
+use balius_sdk::{_internal::Handler, Json, wit};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{KV_LAST_PROCESSED_SLOT, Network, keys, kv, pause};
+
+/// The body returned by the `status` request handler.
+#[derive(Serialize)]
+struct Status {
+    /// The `network` from the worker's config, or null if it couldn't be read
+    network: Option<Network>,
+    managed_orders: usize,
+    /// The slot of the most recent transaction or output the worker processed, or 0 if none
+    last_processed_slot: u64,
+    /// Hex of the key strategy orders must be authorized by, or empty if it isn't available
+    signer: String,
+    /// Whether every order is paused by a `pause` request
+    paused: bool,
+    /// The orders paused individually, as `txHashHex#index`
+    paused_orders: Vec<String>,
+}
+
+/// The one config field every strategy worker shares.
+#[derive(Deserialize)]
+struct NetworkConfig {
+    network: Network,
+}
+
+fn network_of(config: &[u8]) -> Option<Network> {
+    serde_json::from_slice::<NetworkConfig>(config)
+        .ok()
+        .map(|config| config.network)
+}
+
+/// Handler for `status` requests, a liveness and summary check for operators.
+///
+/// This never fails: anything that can't be read is reported as a zeroed field.
+#[derive(Clone)]
+pub(crate) struct StatusHandler;
+
+impl Handler for StatusHandler {
+    fn handle(
+        &self,
+        config: wit::Config,
+        _event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let managed_orders = crate::managed_strategies()
+            .inspect_err(|err| warn!("failed to read managed orders: {err}"))
+            .map(|orders| orders.len())
+            .unwrap_or_default();
+        let last_processed_slot = kv::get::<u64>(KV_LAST_PROCESSED_SLOT)
+            .inspect_err(|err| warn!("failed to read last processed slot: {err}"))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let signer = keys::public_key_hex().unwrap_or_default();
+        let pause_state = pause::pause_state()
+            .inspect_err(|err| warn!("failed to read pause state: {err}"))
+            .unwrap_or_default();
+        let status = Status {
+            network: network_of(&config),
+            managed_orders,
+            last_processed_slot,
+            signer,
+            paused: pause_state.all,
+            paused_orders: pause_state.orders.into_iter().collect(),
+        };
+        Ok(Json(status).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_network_from_any_worker_config() {
+        let config = br#"{"network": "preview", "slippage": 0.05, "token_a": "ada"}"#;
+        assert!(matches!(network_of(config), Some(Network::Preview)));
+        assert!(network_of(b"{}").is_none());
+        assert!(network_of(b"").is_none());
+    }
+}
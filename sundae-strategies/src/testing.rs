@@ -0,0 +1,204 @@
+//! Builders for the library's event types, for unit testing strategy logic without
+//! decoding real transaction outputs.
+//!
+//! Available to this crate's own tests, and to other crates through the `testing` feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! sundae-strategies = { path = "../../sundae-strategies", features = ["testing"] }
+//! ```
+
+use balius_sdk::Tx;
+use utxorpc_spec::utxorpc::v1alpha::cardano::{self, Asset, Datum, Multiasset, TxInput, TxOutput};
+
+use crate::{
+    ManagedStrategy, PoolState,
+    price::PriceChange,
+    types::{
+        self, AssetId, DatumVersion, Destination, MultisigScript, Order, OrderDatum,
+        OutputReference, PoolDatum, StrategyAuthorization, TransactionId,
+    },
+};
+
+/// The identifier given to mock pools.
+pub const MOCK_POOL_IDENT: [u8; 28] = [0x11; 28];
+
+/// An output holding each `(asset, amount)`; ADA goes to the coin field, and entries
+/// for the same asset add up.
+pub fn mock_output(balances: &[(&AssetId, u64)]) -> TxOutput {
+    let mut output = TxOutput::default();
+    for (asset, amount) in balances {
+        if asset.is_ada() {
+            output.coin += amount;
+            continue;
+        }
+        let token = Asset {
+            name: asset.asset_name.clone().into(),
+            output_coin: *amount,
+            ..Default::default()
+        };
+        match output
+            .assets
+            .iter_mut()
+            .find(|multiasset| multiasset.policy_id.as_ref() == asset.policy_id.as_slice())
+        {
+            Some(multiasset) => multiasset.assets.push(token),
+            None => output.assets.push(Multiasset {
+                policy_id: asset.policy_id.clone().into(),
+                assets: vec![token],
+                ..Default::default()
+            }),
+        }
+    }
+    output
+}
+
+fn mock_output_reference() -> OutputReference {
+    OutputReference {
+        transaction_id: TransactionId(vec![0; 32]),
+        output_index: 0,
+    }
+}
+
+impl PoolState {
+    /// A v3 pool of `assets` (asset_a, asset_b) holding the given reserves, with no swap or
+    /// protocol fees, identified by [`MOCK_POOL_IDENT`]. Its circulating LP is the geometric
+    /// mean of the reserves, as minted by a pool's first deposit.
+    pub fn mock(reserves_a: u64, reserves_b: u64, assets: (&AssetId, &AssetId)) -> PoolState {
+        let (asset_a, asset_b) = assets;
+        let mut pool = PoolState {
+            slot: 0,
+            output: mock_output_reference(),
+            utxo: mock_output(&[(asset_a, reserves_a), (asset_b, reserves_b)]),
+            pool_datum: PoolDatum {
+                identifier: MOCK_POOL_IDENT.to_vec(),
+                assets: (
+                    (asset_a.policy_id.clone(), asset_a.asset_name.clone()),
+                    (asset_b.policy_id.clone(), asset_b.asset_name.clone()),
+                ),
+                circulating_lp: types::from_u64(
+                    ((reserves_a as f64) * (reserves_b as f64)).sqrt() as u64
+                ),
+                bid_fees_per_10_thousand: types::from_u64(0),
+                ask_fees_per_10_thousand: types::from_u64(0),
+                fee_manager: None,
+                market_open: types::from_u64(0),
+                protocol_fees: types::from_u64(0),
+            },
+            version: DatumVersion::V3,
+            price_change: PriceChange::default(),
+        };
+        pool.price_change = PriceChange::new(None, pool.pool_datum.raw_price(&pool.utxo).value());
+        pool
+    }
+
+    /// This pool, charging `fee_per_10_thousand` on swaps in either direction.
+    pub fn with_swap_fee(mut self, fee_per_10_thousand: u64) -> PoolState {
+        self.pool_datum.bid_fees_per_10_thousand = types::from_u64(fee_per_10_thousand);
+        self.pool_datum.ask_fees_per_10_thousand = types::from_u64(fee_per_10_thousand);
+        self
+    }
+}
+
+impl ManagedStrategy {
+    /// A strategy order holding `balances`, usable against any pool and authorized by an
+    /// empty signer.
+    pub fn mock(balances: &[(&AssetId, u64)]) -> ManagedStrategy {
+        ManagedStrategy {
+            slot: 0,
+            output: mock_output_reference(),
+            utxo: mock_output(balances),
+            order: OrderDatum {
+                pool_ident: None,
+                owner: MultisigScript::Signature { key_hash: vec![] },
+                max_protocol_fee: types::from_u64(u64::MAX),
+                destination: Destination::Self_,
+                details: Order::Strategy {
+                    auth: StrategyAuthorization::Signature { signer: vec![] },
+                },
+                extra: vec![],
+            },
+            version: DatumVersion::V3,
+        }
+    }
+
+    /// A transaction at `slot` executing this order, which pays `balances` back to it under
+    /// the same datum. Its hash is derived from the spent output's, so each execution in a
+    /// chain of them gets a new one.
+    pub fn mock_execution(&self, slot: u64, balances: &[(&AssetId, u64)]) -> Tx {
+        let mut hash = self.output.transaction_id.0.clone();
+        hash[0] = hash[0].wrapping_add(1);
+        let mut output = mock_output(balances);
+        output.datum = Some(Datum {
+            original_cbor: types::serialize(self.order.clone()).into(),
+            ..Default::default()
+        });
+        Tx {
+            block_hash: vec![].into(),
+            block_height: slot,
+            block_slot: slot,
+            hash: hash.into(),
+            tx: cardano::Tx {
+                inputs: vec![TxInput {
+                    tx_hash: self.output.transaction_id.0.clone().into(),
+                    output_index: self.output.output_index as u32,
+                    ..Default::default()
+                }],
+                outputs: vec![output],
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::asset_amount;
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    #[test]
+    fn mock_pool_holds_its_reserves() {
+        let ada = AssetId::from((vec![], vec![]));
+        let pool = PoolState::mock(2_000, 1_000, (&ada, &sberry()));
+        assert_eq!(pool.pool_datum.reserves(&pool.utxo), (2_000, 1_000));
+        assert_eq!(pool.pool_datum.raw_price(&pool.utxo).value(), 2.0);
+        assert!(pool.is_correct_pool(&ManagedStrategy::mock(&[]).order, &sberry(), &ada));
+        assert_eq!(pool.expected_output(&ada, 1_000), Some(333));
+
+        let pool = pool.with_swap_fee(100);
+        assert_eq!(pool.expected_output(&ada, 1_000), Some(331));
+        assert_eq!(pool.expected_output(&sberry(), 1_000), Some(994));
+    }
+
+    #[test]
+    fn mock_executions_pay_back_to_a_successor() {
+        let ada = AssetId::from((vec![], vec![]));
+        let strategy = ManagedStrategy::mock(&[(&ada, 1_000)]);
+        let tx = strategy.mock_execution(5, &[(&ada, 400), (&sberry(), 30)]);
+        let fill = strategy.swap_fill(&tx, &ada, &sberry()).unwrap();
+        assert_eq!((fill.sold, fill.received), (600, 30));
+        assert_eq!(fill.successor.slot, 5);
+        assert_ne!(
+            fill.successor.output.transaction_id.0,
+            strategy.output.transaction_id.0
+        );
+        assert!(strategy.swap_fill(&tx, &sberry(), &ada).is_none());
+
+        // An owner cancelling the order pays it elsewhere, under no datum
+        let mut cancel = strategy.mock_execution(5, &[(&ada, 1_000)]);
+        cancel.tx.outputs[0].datum = None;
+        assert!(strategy.successor(&cancel).is_none());
+    }
+
+    #[test]
+    fn mock_strategy_holds_its_balances() {
+        let ada = AssetId::from((vec![], vec![]));
+        let strategy = ManagedStrategy::mock(&[(&ada, 5), (&sberry(), 7), (&sberry(), 3)]);
+        assert_eq!(asset_amount(&strategy.utxo, &ada), 5);
+        assert_eq!(asset_amount(&strategy.utxo, &sberry()), 10);
+    }
+}
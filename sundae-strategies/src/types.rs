@@ -1,10 +1,16 @@
 use std::fmt::{self, Debug};
 
-use balius_sdk::txbuilder::{codec::minicbor, plutus::BigInt};
+use balius_sdk::txbuilder::{
+    codec::{minicbor, utils::Int},
+    plutus::BigInt,
+};
 use plutus_parser::AsPlutus;
 use serde::{Deserialize, Serialize, de};
+use tracing::trace;
 use utxorpc_spec::utxorpc::v1alpha::cardano::TxOutput;
 
+use crate::price::RawPrice;
+
 #[derive(Serialize, PartialEq)]
 pub struct AssetId {
     pub policy_id: Vec<u8>,
@@ -15,6 +21,11 @@ impl AssetId {
         self.policy_id.is_empty() && self.asset_name.is_empty()
     }
 
+    /// An `amount` of this asset, in the form used by orders.
+    pub fn singleton(&self, amount: u64) -> SingletonValue {
+        (self.policy_id.clone(), self.asset_name.clone(), amount)
+    }
+
     pub fn name_to_string(&self) -> String {
         if self.is_ada() {
             "ADA".to_string()
@@ -73,6 +84,8 @@ impl PartialEq<InlineAssetId> for AssetId {
 
 pub type InlineAssetId = (Vec<u8>, Vec<u8>);
 
+/// The datum of a two-asset pool, in the v3 layout. Pools of any other shape can't be
+/// described; [`parse_pool_datum`] reports them as [`PoolDatumError::UnsupportedPool`].
 #[derive(AsPlutus, Serialize, Deserialize, Debug, Clone)]
 pub struct PoolDatum {
     pub identifier: Vec<u8>,
@@ -85,7 +98,195 @@ pub struct PoolDatum {
     pub protocol_fees: BigInt,
 }
 
-fn to_u64(big_int: &BigInt) -> Option<u64> {
+/// Which version of the Sundae contracts a datum was parsed as.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DatumVersion {
+    /// The original Sundae contracts. There was never a public v2 deployment.
+    V1,
+    #[default]
+    V3,
+}
+
+#[derive(AsPlutus, Debug, Clone)]
+pub struct AssetClassV1 {
+    pub policy_id: Vec<u8>,
+    pub asset_name: Vec<u8>,
+}
+
+#[derive(AsPlutus, Debug, Clone)]
+pub struct CoinPairV1 {
+    pub coin_a: AssetClassV1,
+    pub coin_b: AssetClassV1,
+}
+
+#[derive(AsPlutus, Debug, Clone)]
+pub struct SwapFeeV1 {
+    pub numerator: BigInt,
+    pub denominator: BigInt,
+}
+
+#[derive(AsPlutus, Debug, Clone)]
+pub struct PoolDatumV1 {
+    pub coin_pair: CoinPairV1,
+    pub pool_ident: Vec<u8>,
+    pub circulating_lp: BigInt,
+    pub swap_fee: SwapFeeV1,
+}
+
+impl From<PoolDatumV1> for PoolDatum {
+    /// Normalize a v1 pool into the v3 layout. v1 pools have a single swap fee (used for
+    /// both bid and ask), no fee manager, no market-open delay and no protocol fees.
+    fn from(v1: PoolDatumV1) -> Self {
+        let fee_per_10_thousand = match (
+            to_u64(&v1.swap_fee.numerator),
+            to_u64(&v1.swap_fee.denominator),
+        ) {
+            (Some(numerator), Some(denominator)) if denominator > 0 => {
+                numerator.saturating_mul(10_000) / denominator
+            }
+            _ => 0,
+        };
+        let CoinPairV1 { coin_a, coin_b } = v1.coin_pair;
+        PoolDatum {
+            identifier: v1.pool_ident,
+            assets: (
+                (coin_a.policy_id, coin_a.asset_name),
+                (coin_b.policy_id, coin_b.asset_name),
+            ),
+            circulating_lp: v1.circulating_lp,
+            bid_fees_per_10_thousand: from_u64(fee_per_10_thousand),
+            ask_fees_per_10_thousand: from_u64(fee_per_10_thousand),
+            fee_manager: None,
+            market_open: from_u64(0),
+            protocol_fees: from_u64(0),
+        }
+    }
+}
+
+/// Parse a pool datum from any known contract version, normalized to the v3 layout.
+///
+/// When no version matches, the reason each one was rejected is logged at trace level.
+pub fn try_parse_pool_datum(bytes: &[u8]) -> Option<(PoolDatum, DatumVersion)> {
+    parse_pool_datum(bytes).ok()
+}
+
+/// Why a datum couldn't be read as a pool datum.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolDatumError {
+    /// The datum isn't laid out like any pool
+    NotAPool,
+    /// The datum is laid out like a v3 pool, but not a two-asset one, e.g. a pool of three
+    /// assets. [`PoolDatum`] and everything built on it (reserves, prices, pool matching)
+    /// only describe pairs, so such a pool can't be read.
+    UnsupportedPool { identifier: Vec<u8>, reason: String },
+}
+
+impl fmt::Display for PoolDatumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolDatumError::NotAPool => f.write_str("not a sundae pool datum"),
+            PoolDatumError::UnsupportedPool { identifier, reason } => write!(
+                f,
+                "unsupported pool type for pool {}: {reason}",
+                hex::encode(identifier)
+            ),
+        }
+    }
+}
+
+/// Parse a pool datum from any known contract version, normalized to the v3 layout, telling
+/// a pool of an unsupported shape apart from a datum that isn't a pool at all.
+///
+/// Any datum starting with a byte string and a list looks like a v3 pool of an unknown
+/// shape, so an [`PoolDatumError::UnsupportedPool`] is only conclusive for an output known
+/// to be a pool, e.g. one holding the pool's NFT.
+pub fn parse_pool_datum(bytes: &[u8]) -> Result<(PoolDatum, DatumVersion), PoolDatumError> {
+    if !may_be_pool_datum(bytes) {
+        return Err(PoolDatumError::NotAPool);
+    }
+    let v3_error = match parse::<PoolDatum>(bytes) {
+        Ok(datum) => return Ok((datum, DatumVersion::V3)),
+        Err(err) => err,
+    };
+    let v1_error = match parse::<PoolDatumV1>(bytes) {
+        Ok(datum) => return Ok((datum.into(), DatumVersion::V1)),
+        Err(err) => err,
+    };
+    trace!(
+        datum = hex::encode(bytes),
+        v3_error = ?v3_error,
+        v1_error = ?v1_error,
+        "datum is not a sundae pool",
+    );
+    Err(unsupported_pool_shape(bytes).unwrap_or(PoolDatumError::NotAPool))
+}
+
+/// How a datum laid out like a v3 pool (constructor 0 led by the pool identifier and the
+/// list of assets) departs from the two-asset layout, or None if it isn't laid out like one.
+fn unsupported_pool_shape(bytes: &[u8]) -> Option<PoolDatumError> {
+    let mut decoder = minicbor::Decoder::new(bytes.strip_prefix(&[0xd8, 0x79])?);
+    let fields = decoder.array().ok()?;
+    let identifier = decoder.bytes().ok()?.to_vec();
+    let assets = match decoder.array().ok()? {
+        Some(assets) => assets,
+        None => {
+            let mut assets = 0;
+            while decoder.datatype().ok()? != minicbor::data::Type::Break {
+                decoder.skip().ok()?;
+                assets += 1;
+            }
+            assets
+        }
+    };
+    let reason = if assets != 2 {
+        format!("the pool holds {assets} assets, and only two-asset pools are supported")
+    } else {
+        match fields {
+            Some(fields) if fields != 8 => {
+                format!("the pool datum has {fields} fields, where a v3 pool has 8")
+            }
+            _ => return None,
+        }
+    };
+    Some(PoolDatumError::UnsupportedPool { identifier, reason })
+}
+
+/// A cheap check of the leading CBOR bytes, so the many datums that can't be a pool (orders,
+/// other protocols' datums) are rejected without a full decode.
+///
+/// Both pool layouts are constructor 0 (tag 121) holding an indefinite list, or a definite
+/// one of 8 (v3) or 4 (v1) fields. In v3 the first field is the pool identifier (a byte
+/// string). In v1 it's the coin pair, a constructor 0 of two fields, each itself a
+/// constructor 0; an order's first field, its optional pool identifier, is a constructor
+/// too, but holds a byte string or nothing.
+///
+/// Of the datums in `test_pool_datum_precheck_rejects_orders`, which stands in for a block
+/// of Sundae orders and other protocols' datums, only the pools reach a full decode, where
+/// each of the others took two (v3, then v1) before this check.
+fn may_be_pool_datum(bytes: &[u8]) -> bool {
+    matches!(
+        bytes,
+        [0xd8, 0x79, 0x9f | 0x88, 0x40..=0x5f, ..]
+            | [
+                0xd8,
+                0x79,
+                0x9f | 0x84,
+                0xd8,
+                0x79,
+                0x9f | 0x82,
+                0xd8,
+                0x79,
+                ..
+            ]
+    )
+}
+
+pub(crate) fn from_u64(value: u64) -> BigInt {
+    BigInt::Int(Int(minicbor::data::Int::from(value)))
+}
+
+pub(crate) fn to_u64(big_int: &BigInt) -> Option<u64> {
     match big_int {
         BigInt::Int(int) => u64::try_from(int.0).ok(),
         BigInt::BigUInt(_) | BigInt::BigNInt(_) => None,
@@ -96,27 +297,15 @@ pub fn asset_amount(output: &TxOutput, find_asset: &AssetId) -> u64 {
     if find_asset.is_ada() {
         output.coin
     } else {
+        // A well-formed output lists each asset once, but sum every match in case one doesn't
         output
             .assets
             .iter()
-            .filter_map(|multiasset| {
-                let policy_id = multiasset.policy_id.to_vec();
-                multiasset
-                    .assets
-                    .iter()
-                    .filter_map(|asset| {
-                        let asset_name = asset.name.to_vec();
-                        if policy_id == find_asset.policy_id && asset_name == find_asset.asset_name
-                        {
-                            Some(asset.output_coin)
-                        } else {
-                            None
-                        }
-                    })
-                    .next()
-            })
-            .next()
-            .unwrap_or(0u64)
+            .filter(|multiasset| multiasset.policy_id.as_ref() == find_asset.policy_id.as_slice())
+            .flat_map(|multiasset| multiasset.assets.iter())
+            .filter(|asset| asset.name.as_ref() == find_asset.asset_name.as_slice())
+            .map(|asset| asset.output_coin)
+            .sum()
     }
 }
 
@@ -125,7 +314,18 @@ impl PoolDatum {
     /// for an ADA/SUNDAE pair, this will give the lovelace per sprinkles
     /// If the decimal places on the token are the same, this will work out to the same value, but if they
     /// have different decimal places, this could be non-intuitive
-    pub fn raw_price(&self, output: &TxOutput) -> f64 {
+    pub fn raw_price(&self, output: &TxOutput) -> RawPrice {
+        let (reserves_a, reserves_b) = self.reserves(output);
+
+        if reserves_b == 0 {
+            return RawPrice::new(0.0);
+        }
+
+        RawPrice::new((reserves_a as f64) / (reserves_b as f64))
+    }
+
+    /// The tradable reserves of (asset_a, asset_b) held by the pool UTXO, excluding accrued protocol fees.
+    pub fn reserves(&self, output: &TxOutput) -> (u64, u64) {
         let asset_a: AssetId = self.assets.0.clone().into();
         let asset_b: AssetId = self.assets.1.clone().into();
 
@@ -137,12 +337,51 @@ impl PoolDatum {
         };
         let reserves_b = asset_amount(output, &asset_b);
 
-        if reserves_b == 0 {
-            return 0.0;
-        }
+        (reserves_a, reserves_b)
+    }
+}
+
+/// Convert a raw price (base units of asset_a per base unit of asset_b) into whole units of
+/// asset_a per whole unit of asset_b.
+pub fn decimal_price(raw_price: f64, token_a_decimals: u8, token_b_decimals: u8) -> f64 {
+    raw_price * 10f64.powi(token_b_decimals as i32 - token_a_decimals as i32)
+}
+
+/// The least a swap of `offer_amount` should accept at `price` (raw units received per raw
+/// unit offered) less `slippage`, e.g. 0.03 for 3%. Rounded down, and never below 1, since
+/// a minimum of 0 would accept any fill at all.
+pub fn min_received(offer_amount: u64, price: f64, slippage: f64) -> u64 {
+    ((offer_amount as f64 * price * (1.0 - slippage)).floor() as u64).max(1)
+}
+
+/// [`min_received`] for a `price` quoted in whole received tokens per whole offered token,
+/// scaled to raw units by each token's decimal places.
+pub fn min_received_with_decimals(
+    offer_amount: u64,
+    price: f64,
+    slippage: f64,
+    offer_decimals: u8,
+    receive_decimals: u8,
+) -> u64 {
+    let scale = 10f64.powi(receive_decimals as i32 - offer_decimals as i32);
+    min_received(offer_amount, price * scale, slippage)
+}
 
-        (reserves_a as f64) / (reserves_b as f64)
+/// The output of a constant-product swap of `offer_amount` into a pool holding
+/// `reserve_in` of the offered asset and `reserve_out` of the received one.
+pub fn constant_product_output(
+    reserve_in: u64,
+    reserve_out: u64,
+    offer_amount: u64,
+    fee_per_10_thousand: u64,
+) -> u64 {
+    let fee_per_10_thousand = fee_per_10_thousand.min(10_000) as u128;
+    let offer_after_fee = offer_amount as u128 * (10_000 - fee_per_10_thousand);
+    let denominator = reserve_in as u128 * 10_000 + offer_after_fee;
+    if denominator == 0 {
+        return 0;
     }
+    (reserve_out as u128 * offer_after_fee / denominator) as u64
 }
 
 #[derive(AsPlutus, Serialize, Deserialize, Debug, Clone)]
@@ -155,6 +394,21 @@ pub struct OrderDatum {
     pub extra: Vec<u8>,
 }
 
+impl OrderDatum {
+    /// Whether this is a strategy order that `key` is authorized to execute.
+    ///
+    /// Strategy orders are authorized by a single signature today, so this is a comparison
+    /// against its signer; it'll need revisiting if other authorization kinds are added.
+    pub fn is_owned_by(&self, key: &[u8]) -> bool {
+        match &self.details {
+            Order::Strategy {
+                auth: StrategyAuthorization::Signature { signer },
+            } => signer.as_slice() == key,
+            _ => false,
+        }
+    }
+}
+
 #[derive(AsPlutus)]
 pub struct SignedStrategyExecution {
     pub execution: StrategyExecution,
@@ -169,7 +423,7 @@ pub struct StrategyExecution {
     pub extensions: Vec<u8>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Debug)]
 pub struct SubmitSSE {
     pub tx_hash: String,
     pub tx_index: u64,
@@ -196,6 +450,30 @@ pub enum Order {
         offer: SingletonValue,
         min_received: SingletonValue,
     },
+    Deposit {
+        assets: (SingletonValue, SingletonValue),
+    },
+    Withdraw {
+        lp: SingletonValue,
+    },
+}
+
+impl Order {
+    /// Build a swap offering `offer.1` of `offer.0` for at least `min_received.1` of `min_received.0`.
+    pub fn swap(offer: (&AssetId, u64), min_received: (&AssetId, u64)) -> Order {
+        Order::Swap {
+            offer: offer.0.singleton(offer.1),
+            min_received: min_received.0.singleton(min_received.1),
+        }
+    }
+
+    /// Build a deposit of `a.1` of `a.0` and `b.1` of `b.0`, in the pool's asset order.
+    /// Whatever the pool doesn't take at its current ratio is returned with the LP tokens.
+    pub fn deposit(a: (&AssetId, u64), b: (&AssetId, u64)) -> Order {
+        Order::Deposit {
+            assets: (a.0.singleton(a.1), b.0.singleton(b.1)),
+        }
+    }
 }
 
 pub type SingletonValue = (Vec<u8>, Vec<u8>, u64);
@@ -227,6 +505,25 @@ impl Debug for OutputReference {
     }
 }
 
+impl std::str::FromStr for OutputReference {
+    type Err = String;
+
+    /// Parse the `txHashHex#index` form used in logs and request handlers.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tx_hash, index) = s
+            .split_once('#')
+            .ok_or_else(|| format!("expected txHashHex#index, got {s:?}"))?;
+        let tx_hash = hex::decode(tx_hash).map_err(|_| format!("{tx_hash:?} is not hex"))?;
+        let output_index = index
+            .parse()
+            .map_err(|_| format!("{index:?} is not an output index"))?;
+        Ok(OutputReference {
+            transaction_id: TransactionId(tx_hash),
+            output_index,
+        })
+    }
+}
+
 #[derive(AsPlutus, Clone)]
 pub struct Interval {
     pub lower_bound: IntervalBound,
@@ -277,6 +574,22 @@ pub fn try_parse<T: AsPlutus>(bytes: &[u8]) -> Option<T> {
     T::from_plutus(data).ok()
 }
 
+/// Like [`try_parse`], but logs the offending bytes and why they were rejected at trace level.
+pub fn parse_logged<T: AsPlutus>(bytes: &[u8]) -> Option<T> {
+    match parse(bytes) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            trace!(
+                datum = hex::encode(bytes),
+                error = ?err,
+                "datum is not a {}",
+                std::any::type_name::<T>(),
+            );
+            None
+        }
+    }
+}
+
 pub fn serialize<T: AsPlutus>(value: T) -> Vec<u8> {
     let mut bytes = vec![];
     minicbor::encode(value.to_plutus(), &mut bytes).expect("infallible");
@@ -324,3 +637,295 @@ pub fn test_strategy_serialization() {
     let expected_bytes = hex::decode("d8799fd8799fd8799f5820da432ef16b7aa9b3972bdd42f86e6605c14444e75678f4e6fd75baa01168086fff00ffd8799fd8799fd87a9f1b00000197fb75e4e8ffd87a80ffd8799fd87a9f1b00000197fb9a83e8ffd87a80ffffd87a9f9f40401a00989680ff9f581c99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e154653424552525901ffff40ff").unwrap();
     assert_eq!(bytes, expected_bytes);
 }
+
+#[test]
+pub fn test_deposit_round_trip() {
+    let sberry = (
+        hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+        hex::decode("534245525259").unwrap(),
+    );
+    let deposit = Order::Deposit {
+        assets: ((vec![], vec![], 10000000), (sberry.0, sberry.1, 500)),
+    };
+
+    let bytes = serialize(deposit);
+    assert_eq!(hex::encode(&bytes[..4]), "d87b9f9f");
+
+    let parsed: Order = parse(&bytes).unwrap();
+    let Order::Deposit {
+        assets: ((_, _, amount_a), (_, asset_name_b, amount_b)),
+    } = &parsed
+    else {
+        panic!("expected a deposit, got {parsed:?}");
+    };
+    assert_eq!(*amount_a, 10000000);
+    assert_eq!(asset_name_b, b"SBERRY");
+    assert_eq!(*amount_b, 500);
+    assert_eq!(serialize(parsed), bytes);
+}
+
+#[test]
+pub fn test_withdraw_round_trip() {
+    let lp = (
+        hex::decode("44a1eb2d9f58add4eb1932bd0048e6a1947e85e3fe4f32956a110414").unwrap(),
+        hex::decode("000de140").unwrap(),
+        1234,
+    );
+    let withdraw = Order::Withdraw { lp };
+
+    let bytes = serialize(withdraw);
+    assert_eq!(hex::encode(&bytes[..3]), "d87c9f");
+
+    let parsed: Order = parse(&bytes).unwrap();
+    let Order::Withdraw {
+        lp: (_, lp_name, lp_amount),
+    } = &parsed
+    else {
+        panic!("expected a withdrawal, got {parsed:?}");
+    };
+    assert_eq!(hex::encode(lp_name), "000de140");
+    assert_eq!(*lp_amount, 1234);
+    assert_eq!(serialize(parsed), bytes);
+}
+
+#[test]
+pub fn test_deposit_builder_matches_literal() {
+    let ada = AssetId::from((vec![], vec![]));
+    let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+
+    let built = Order::deposit((&ada, 10000000), (&sberry, 500));
+    let literal = Order::Deposit {
+        assets: (
+            (vec![], vec![], 10000000),
+            (vec![0x99; 28], b"SBERRY".to_vec(), 500),
+        ),
+    };
+    assert_eq!(serialize(built), serialize(literal));
+}
+
+#[test]
+pub fn test_swap_builder_matches_literal() {
+    let ada = AssetId {
+        policy_id: vec![],
+        asset_name: vec![],
+    };
+    let sberry = AssetId {
+        policy_id: hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+        asset_name: hex::decode("534245525259").unwrap(),
+    };
+
+    let built = Order::swap((&ada, 10000000), (&sberry, 1));
+    let literal = Order::Swap {
+        offer: (vec![], vec![], 10000000),
+        min_received: (sberry.policy_id.clone(), sberry.asset_name.clone(), 1),
+    };
+    assert_eq!(serialize(built), serialize(literal));
+}
+
+#[test]
+pub fn test_pool_datum_precheck() {
+    let v3 = PoolDatum {
+        identifier: vec![0x01; 28],
+        assets: ((vec![], vec![]), (vec![0x99; 28], b"SBERRY".to_vec())),
+        circulating_lp: from_u64(1000),
+        bid_fees_per_10_thousand: from_u64(30),
+        ask_fees_per_10_thousand: from_u64(30),
+        fee_manager: None,
+        market_open: from_u64(0),
+        protocol_fees: from_u64(0),
+    };
+    assert!(may_be_pool_datum(&serialize(v3)));
+
+    // An integer, a list, and a constructor 1 are never pools
+    assert!(!may_be_pool_datum(&hex::decode("1864").unwrap()));
+    assert!(!may_be_pool_datum(&hex::decode("9f0102ff").unwrap()));
+    assert!(!may_be_pool_datum(&hex::decode("d87a9f5820").unwrap()));
+    assert!(!may_be_pool_datum(&[]));
+    assert!(try_parse_pool_datum(&hex::decode("d87a9f5820").unwrap()).is_none());
+}
+
+#[test]
+pub fn test_pool_datum_precheck_rejects_orders() {
+    let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+    let ada = AssetId::from((vec![], vec![]));
+    let v3 = crate::PoolState::mock(2_000, 1_000, (&ada, &sberry)).pool_datum;
+    let v1 = PoolDatumV1 {
+        coin_pair: CoinPairV1 {
+            coin_a: AssetClassV1 {
+                policy_id: vec![],
+                asset_name: vec![],
+            },
+            coin_b: AssetClassV1 {
+                policy_id: sberry.policy_id.clone(),
+                asset_name: sberry.asset_name.clone(),
+            },
+        },
+        pool_ident: vec![0x01],
+        circulating_lp: from_u64(1000),
+        swap_fee: SwapFeeV1 {
+            numerator: from_u64(3),
+            denominator: from_u64(1000),
+        },
+    };
+    let strategy_order = crate::ManagedStrategy::mock(&[]).order;
+    let mut swap_order = strategy_order.clone();
+    swap_order.pool_ident = Some(vec![0x11; 28]);
+    swap_order.details = Order::swap((&ada, 10_000_000), (&sberry, 1));
+    let mut deposit_order = strategy_order.clone();
+    deposit_order.details = Order::Deposit {
+        assets: ((vec![], vec![], 10_000_000), (vec![0x99; 28], vec![], 500)),
+    };
+
+    let pools = [serialize(v3), serialize(v1)];
+    let others = [
+        serialize(strategy_order),
+        serialize(swap_order),
+        serialize(deposit_order),
+        hex::decode("1864").unwrap(),
+        hex::decode("581c01020304").unwrap(),
+        hex::decode("d87a9f5820").unwrap(),
+        hex::decode("d8799f0102ff").unwrap(),
+    ];
+    let full_decodes = pools
+        .iter()
+        .chain(&others)
+        .filter(|datum| may_be_pool_datum(datum))
+        .count();
+    assert_eq!(full_decodes, pools.len());
+    for pool in &pools {
+        assert!(try_parse_pool_datum(pool).is_some());
+    }
+}
+
+#[test]
+pub fn test_three_asset_pool_is_unsupported() {
+    let datum = hex::decode(format!(
+        "d8799f581c{}9f9f4040ff9f581c{}46534245525259ff9f581c{}414dffff01ff",
+        "01".repeat(28),
+        "99".repeat(28),
+        "77".repeat(28),
+    ))
+    .unwrap();
+    let Err(PoolDatumError::UnsupportedPool { identifier, reason }) = parse_pool_datum(&datum)
+    else {
+        panic!("expected an unsupported pool");
+    };
+    assert_eq!(identifier, vec![0x01; 28]);
+    assert!(reason.contains("holds 3 assets"));
+    assert!(try_parse_pool_datum(&datum).is_none());
+
+    // Anything else that fails to parse isn't a pool at all
+    assert_eq!(
+        parse_pool_datum(&hex::decode("d8799f0102ff").unwrap()).unwrap_err(),
+        PoolDatumError::NotAPool
+    );
+}
+
+#[test]
+pub fn test_parse_v1_pool_datum() {
+    let sberry = (
+        hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+        hex::decode("534245525259").unwrap(),
+    );
+    let v1 = PoolDatumV1 {
+        coin_pair: CoinPairV1 {
+            coin_a: AssetClassV1 {
+                policy_id: vec![],
+                asset_name: vec![],
+            },
+            coin_b: AssetClassV1 {
+                policy_id: sberry.0.clone(),
+                asset_name: sberry.1.clone(),
+            },
+        },
+        pool_ident: vec![0x01],
+        circulating_lp: from_u64(1000),
+        swap_fee: SwapFeeV1 {
+            numerator: from_u64(3),
+            denominator: from_u64(1000),
+        },
+    };
+
+    let (datum, version) = try_parse_pool_datum(&serialize(v1)).unwrap();
+    assert_eq!(version, DatumVersion::V1);
+    assert_eq!(datum.identifier, vec![0x01]);
+    assert_eq!(datum.assets.1, sberry);
+    assert_eq!(to_u64(&datum.bid_fees_per_10_thousand), Some(30));
+    assert_eq!(to_u64(&datum.ask_fees_per_10_thousand), Some(30));
+}
+
+#[test]
+pub fn test_constant_product_output() {
+    // No fee: 1000 * 100 / (1000 + 100)
+    assert_eq!(constant_product_output(1000, 1000, 100, 0), 90);
+    // 0.3% fee shaves a little off
+    assert_eq!(constant_product_output(1_000_000, 1_000_000, 1000, 30), 996);
+    // Empty pools pay nothing
+    assert_eq!(constant_product_output(0, 0, 100, 30), 0);
+}
+
+#[test]
+pub fn test_min_received() {
+    assert_eq!(min_received(1000, 8.0, 0.25), 6000);
+    assert_eq!(min_received(3, 0.5, 0.0), 1);
+    assert_eq!(min_received(1, 0.1, 0.0), 1);
+    // 1000 raw units of a token with no decimals, at 2.5 of one with 6 decimals
+    assert_eq!(
+        min_received_with_decimals(1000, 2.5, 0.5, 0, 6),
+        1_250_000_000
+    );
+}
+
+#[test]
+pub fn test_decimal_price() {
+    // 168 lovelace per SBERRY (0 decimals) is 0.000168 ADA per SBERRY
+    assert!((decimal_price(168.0, 6, 0) - 0.000168).abs() < 1e-12);
+    // 2 sprinkles per lovelace is 2 SUNDAE per ADA when decimals match
+    assert_eq!(decimal_price(2.0, 6, 6), 2.0);
+}
+
+#[test]
+pub fn test_asset_amount_sums_split_entries() {
+    use utxorpc_spec::utxorpc::v1alpha::cardano::{Asset, Multiasset};
+
+    let policy = vec![0x99; 28];
+    let sberry = AssetId::from((policy.clone(), b"SBERRY".to_vec()));
+    let asset = |name: &[u8], output_coin| Asset {
+        name: name.to_vec().into(),
+        output_coin,
+        ..Default::default()
+    };
+    let output = TxOutput {
+        coin: 2_000_000,
+        assets: vec![
+            Multiasset {
+                policy_id: policy.clone().into(),
+                assets: vec![asset(b"SBERRY", 100), asset(b"OTHER", 7)],
+                ..Default::default()
+            },
+            Multiasset {
+                policy_id: policy.into(),
+                assets: vec![asset(b"SBERRY", 50)],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(asset_amount(&output, &sberry), 150);
+    assert_eq!(
+        asset_amount(&output, &AssetId::from((vec![], vec![]))),
+        2_000_000
+    );
+}
+
+#[test]
+pub fn test_output_reference_round_trips_through_str() {
+    let output: OutputReference = "00ff#3".parse().unwrap();
+    assert_eq!(output.transaction_id.0, vec![0x00, 0xff]);
+    assert_eq!(output.output_index, 3);
+    assert_eq!(format!("{output:?}"), "00ff#3");
+    assert!("00ff".parse::<OutputReference>().is_err());
+    assert!("zz#3".parse::<OutputReference>().is_err());
+    assert!("00ff#x".parse::<OutputReference>().is_err());
+}
@@ -0,0 +1,24 @@
+//! An optional check that an order is still unspent before submitting an execution of it.
+//!
+//! The orders passed to strategy callbacks are the library's view of the chain, which can
+//! lag behind it: an order may have been spent by a transaction that hasn't been observed
+//! yet, or was observed out of order. Submitting against a spent order wastes a relay
+//! round-trip and records a success that can never land. With `check_unspent` set in the
+//! worker config, [`crate::submit_execution`] reads the order's output from the ledger
+//! first, and refuses the submission if it's gone. It's off by default, since it costs a
+//! ledger query per submission.
+
+use balius_sdk::WorkerResult;
+use tracing::info;
+
+use crate::{ledger, types::OutputReference};
+
+/// Why a submission against `utxo` should be skipped because the ledger no longer has it,
+/// or None if it's still unspent.
+pub(crate) fn check(utxo: &OutputReference) -> WorkerResult<Option<String>> {
+    if ledger::read_output(utxo)?.is_none() {
+        info!("skipping submission for {utxo:?}: it has already been spent");
+        return Ok(Some(format!("{utxo:?} has already been spent")));
+    }
+    Ok(None)
+}
@@ -0,0 +1,335 @@
+//! A volume-weighted average price for a pair, built from the swaps observed against its
+//! Sundae pools.
+//!
+//! Call [`record_swaps`] for every transaction from an [`on_each_tx`](crate::Strategy::on_each_tx)
+//! callback, then read the result with [`vwap`], or over HTTP with the `get-vwap` request:
+//!
+//! ```ignore
+//! fn on_each_tx(config: &Config<MyConfig>, tx: &Tx, _: &Vec<ManagedStrategy>) -> WorkerResult<Ack> {
+//!     vwap::record_swaps(&config.network, tx, &config.token_a, &config.token_b)?;
+//!     Ok(Ack)
+//! }
+//! ```
+//!
+//! A transaction counts as trading against a pool when it spends the last pool output
+//! observed and produces the next one. Outputs only count as pool outputs if they hold the
+//! pool's NFT, so transfers of look-alike datums are ignored. The swap is read from the
+//! change in the pool's reserves: one side grows while the other shrinks. Scoops that also
+//! mint or burn LP tokens are skipped, since their deposits and withdrawals can't be told
+//! apart from their swaps, and a scoop batching swaps in both directions contributes only
+//! its net flow.
+
+use std::collections::BTreeMap;
+
+use balius_sdk::{_internal::Handler, Json, Tx, WorkerResult, wit};
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+use utxorpc_spec::utxorpc::v1alpha::cardano::TxOutput;
+
+use crate::{
+    Network, is_spent, kv, ledger, pair_key,
+    types::{self, AssetId, DatumVersion, OutputReference, PoolDatum, TransactionId, asset_amount},
+};
+
+const KV_VWAP: &str = "vwap";
+
+/// The volume swapped through the pools of one pair since the worker started tracking it,
+/// as returned by `get-vwap`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VwapAccumulator {
+    /// The pool's asset_a and asset_b, as `hexPolicyId.hexAssetName`
+    pub assets: (String, String),
+    /// Total asset_a swapped, in either direction, in raw units
+    pub volume_a: u64,
+    /// Total asset_b swapped, in either direction, in raw units
+    pub volume_b: u64,
+    pub swaps: u64,
+    pub first_slot: u64,
+    pub last_slot: u64,
+}
+
+impl VwapAccumulator {
+    /// The volume-weighted average price in raw asset_a per raw asset_b, or None before any
+    /// swap has been recorded.
+    pub fn price(&self) -> Option<f64> {
+        (self.volume_b > 0).then(|| self.volume_a as f64 / self.volume_b as f64)
+    }
+}
+
+/// The last observed output of a pool, which the next swap against it must spend.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PoolCheckpoint {
+    output: OutputReference,
+    reserves: (u64, u64),
+    circulating_lp: Option<u64>,
+}
+
+fn pool_checkpoints() -> kv::Namespace<PoolCheckpoint> {
+    kv::Namespace::new("vwap_pool")
+}
+
+fn asset_key(asset: &(Vec<u8>, Vec<u8>)) -> String {
+    format!("{}.{}", hex::encode(&asset.0), hex::encode(&asset.1))
+}
+
+/// Accumulate the swaps `tx` makes against any Sundae v3 pool trading `token_a` and
+/// `token_b`, in either order.
+pub fn record_swaps(
+    network: &Network,
+    tx: &Tx,
+    token_a: &AssetId,
+    token_b: &AssetId,
+) -> WorkerResult<()> {
+    let spent_inputs = tx
+        .tx
+        .inputs
+        .iter()
+        .map(|input| (input.tx_hash.to_vec(), input.output_index as u64))
+        .collect::<Vec<_>>();
+    record_pool_outputs(
+        network,
+        &tx.hash.to_vec(),
+        &spent_inputs,
+        tx.block_slot,
+        &tx.tx.outputs,
+        (token_a, token_b),
+    )
+}
+
+/// The volume-weighted average price of `token_b` in raw `token_a`, or None if no swap
+/// between them has been recorded.
+pub fn vwap(token_a: &AssetId, token_b: &AssetId) -> WorkerResult<Option<f64>> {
+    let key = pair_key(
+        (&token_a.policy_id, &token_a.asset_name),
+        (&token_b.policy_id, &token_b.asset_name),
+    );
+    let Some(accumulator) = accumulators()?.remove(&key) else {
+        return Ok(None);
+    };
+    let price = accumulator.price();
+    let token_a_first =
+        asset_key(&(token_a.policy_id.clone(), token_a.asset_name.clone())) == accumulator.assets.0;
+    Ok(if token_a_first {
+        price
+    } else {
+        price.map(|price| 1.0 / price)
+    })
+}
+
+fn accumulators() -> WorkerResult<BTreeMap<String, VwapAccumulator>> {
+    Ok(kv::get(KV_VWAP)?.unwrap_or_default())
+}
+
+/// The pool datum of `output`, if it's a v3 pool output holding its pool's NFT.
+fn pool_datum(network: &Network, output: &TxOutput) -> Option<PoolDatum> {
+    let (datum, version) = types::try_parse_pool_datum(&output.datum.as_ref()?.original_cbor)?;
+    let nft = AssetId::from((
+        network.pool_script_hash(),
+        ledger::pool_nft_name(&datum.identifier),
+    ));
+    (version == DatumVersion::V3 && asset_amount(output, &nft) == 1).then_some(datum)
+}
+
+/// The (asset_a, asset_b) swapped between two states of a pool, or None if its reserves
+/// didn't move in opposite directions.
+fn swapped(before: (u64, u64), after: (u64, u64)) -> Option<(u64, u64)> {
+    let a_in = after.0 > before.0;
+    let b_in = after.1 > before.1;
+    let volume = (after.0.abs_diff(before.0), after.1.abs_diff(before.1));
+    (a_in != b_in && volume.0 > 0 && volume.1 > 0).then_some(volume)
+}
+
+fn record_pool_outputs(
+    network: &Network,
+    tx_hash: &[u8],
+    spent_inputs: &[(Vec<u8>, u64)],
+    slot: u64,
+    outputs: &[TxOutput],
+    pair: (&AssetId, &AssetId),
+) -> WorkerResult<()> {
+    let key = pair_key(
+        (&pair.0.policy_id, &pair.0.asset_name),
+        (&pair.1.policy_id, &pair.1.asset_name),
+    );
+    let checkpoints = pool_checkpoints();
+    for (index, output) in outputs.iter().enumerate() {
+        let Some(datum) = pool_datum(network, output) else {
+            continue;
+        };
+        let (asset_a, asset_b) = &datum.assets;
+        if pair_key((&asset_a.0, &asset_a.1), (&asset_b.0, &asset_b.1)) != key {
+            continue;
+        }
+
+        let pool_id = hex::encode(&datum.identifier);
+        let checkpoint = PoolCheckpoint {
+            output: OutputReference {
+                transaction_id: TransactionId(tx_hash.to_vec()),
+                output_index: index as u64,
+            },
+            reserves: datum.reserves(output),
+            circulating_lp: types::to_u64(&datum.circulating_lp),
+        };
+        let previous = checkpoints.get(pool_id.as_str())?;
+        checkpoints.set(pool_id.as_str(), &checkpoint)?;
+
+        // Without the state this one replaced, there's nothing to measure the swap against
+        let Some(previous) = previous.filter(|previous| is_spent(spent_inputs, &previous.output))
+        else {
+            trace!(pool = pool_id, "no prior pool state, not counting volume");
+            continue;
+        };
+        if previous.circulating_lp != checkpoint.circulating_lp {
+            trace!(pool = pool_id, "liquidity changed, not counting volume");
+            continue;
+        }
+        let Some((volume_a, volume_b)) = swapped(previous.reserves, checkpoint.reserves) else {
+            continue;
+        };
+
+        kv::update(KV_VWAP, |all: Option<BTreeMap<String, VwapAccumulator>>| {
+            let mut all = all.unwrap_or_default();
+            let accumulator = all.entry(key.clone()).or_insert_with(|| VwapAccumulator {
+                assets: (asset_key(asset_a), asset_key(asset_b)),
+                volume_a: 0,
+                volume_b: 0,
+                swaps: 0,
+                first_slot: slot,
+                last_slot: slot,
+            });
+            accumulator.volume_a = accumulator.volume_a.saturating_add(volume_a);
+            accumulator.volume_b = accumulator.volume_b.saturating_add(volume_b);
+            accumulator.swaps += 1;
+            accumulator.last_slot = slot;
+            all
+        })?;
+    }
+    Ok(())
+}
+
+/// One pair's entry in the `get-vwap` response.
+#[derive(Serialize)]
+struct VwapSummary {
+    #[serde(flatten)]
+    accumulator: VwapAccumulator,
+    /// Raw asset_a per raw asset_b
+    vwap: Option<f64>,
+}
+
+/// Handler for `get-vwap` requests, returning the accumulated volume and VWAP of every pair
+/// recorded with [`record_swaps`].
+#[derive(Clone)]
+pub(crate) struct VwapHandler;
+
+impl Handler for VwapHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        _event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let summaries: Vec<VwapSummary> = accumulators()?
+            .into_values()
+            .map(|accumulator| VwapSummary {
+                vwap: accumulator.price(),
+                accumulator,
+            })
+            .collect();
+        Ok(Json(summaries).try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use utxorpc_spec::utxorpc::v1alpha::cardano::Datum;
+
+    use super::*;
+    use crate::{PoolState, Strategy, sim::Simulator, testing::mock_output};
+
+    fn ada() -> AssetId {
+        AssetId::from((vec![], vec![]))
+    }
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    /// A preview pool output holding `reserves`, whose datum is `datum` if given, or that of
+    /// a fresh pool holding them otherwise. Only holds the pool NFT if `nft`.
+    fn pool_output(reserves: (u64, u64), datum: Option<&PoolDatum>, nft: bool) -> TxOutput {
+        let fresh = PoolState::mock(reserves.0, reserves.1, (&ada(), &sberry())).pool_datum;
+        let datum = datum.unwrap_or(&fresh);
+        let nft_asset = AssetId::from((
+            Network::Preview.pool_script_hash(),
+            ledger::pool_nft_name(&datum.identifier),
+        ));
+        let mut output = mock_output(&[
+            (&ada(), reserves.0),
+            (&sberry(), reserves.1),
+            (&nft_asset, nft as u64),
+        ]);
+        output.datum = Some(Datum {
+            original_cbor: types::serialize(datum.clone()).into(),
+            ..Default::default()
+        });
+        output
+    }
+
+    /// Observe a tx with hash `[n; 32]` spending the first output of tx `n - 1`.
+    fn observe(n: u8, outputs: &[TxOutput]) {
+        let spent = [(vec![n - 1; 32], 0)];
+        record_pool_outputs(
+            &Network::Preview,
+            &[n; 32],
+            &spent,
+            n as u64,
+            outputs,
+            (&sberry(), &ada()),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn swaps_move_reserves_in_opposite_directions() {
+        assert_eq!(swapped((1_000, 10), (1_200, 8)), Some((200, 2)));
+        assert_eq!(swapped((1_000, 10), (900, 11)), Some((100, 1)));
+        assert_eq!(swapped((1_000, 10), (1_200, 12)), None);
+        assert_eq!(swapped((1_000, 10), (1_000, 10)), None);
+    }
+
+    #[test]
+    fn accumulates_swaps_against_the_pool() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let datum = PoolState::mock(1_000_000, 10_000, (&ada(), &sberry())).pool_datum;
+
+        observe(1, &[pool_output((1_000_000, 10_000), Some(&datum), true)]);
+        assert_eq!(vwap(&ada(), &sberry()).unwrap(), None);
+
+        // Buying 20 SBERRY for 2_000 ADA, then selling 10 for 1_000
+        observe(2, &[pool_output((1_002_000, 9_980), Some(&datum), true)]);
+        observe(3, &[pool_output((1_001_000, 9_990), Some(&datum), true)]);
+        assert_eq!(vwap(&ada(), &sberry()).unwrap(), Some(100.0));
+        assert_eq!(vwap(&sberry(), &ada()).unwrap(), Some(0.01));
+
+        let accumulator = accumulators().unwrap().into_values().next().unwrap();
+        assert_eq!(accumulator.swaps, 2);
+        assert_eq!((accumulator.first_slot, accumulator.last_slot), (2, 3));
+    }
+
+    #[test]
+    fn ignores_liquidity_changes_and_look_alike_outputs() {
+        let _sim =
+            Simulator::new(Strategy::<serde_json::Value>::new(), &serde_json::json!({})).unwrap();
+        let datum = PoolState::mock(1_000_000, 10_000, (&ada(), &sberry())).pool_datum;
+
+        observe(1, &[pool_output((1_000_000, 10_000), Some(&datum), true)]);
+        // Without the pool NFT this is just a transfer, whatever its datum says
+        observe(2, &[pool_output((2_000_000, 5_000), Some(&datum), false)]);
+        observe(3, &[pool_output((1_010_000, 9_900), Some(&datum), true)]);
+        assert_eq!(vwap(&ada(), &sberry()).unwrap(), None);
+
+        // A deposit mints LP, so its reserve change isn't volume
+        observe(4, &[pool_output((2_020_000, 19_800), None, true)]);
+        assert_eq!(vwap(&ada(), &sberry()).unwrap(), None);
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::OnceLock;
+
+use balius_sdk::{Error, http::HttpRequest};
+use serde::Serialize;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{
+    kv,
+    types::{Interval, IntervalBoundType, Order, OutputReference, StrategyExecution},
+};
+
+/// Where to announce submitted executions and alerts, set once when the worker is built.
+static EXECUTION_WEBHOOK: OnceLock<Url> = OnceLock::new();
+
+pub(crate) fn set_execution_webhook(url: Url) {
+    if EXECUTION_WEBHOOK.set(url).is_err() {
+        warn!("execution webhook was already configured; keeping the first one");
+    }
+}
+
+/// The JSON body posted to the execution webhook.
+#[derive(Serialize)]
+struct ExecutionNotification<'a> {
+    /// The strategy order being executed, as `tx_hash#index`
+    order_ref: String,
+    details: &'a Order,
+    validity_range: ValidityRange,
+    /// The latest slot the worker had observed when submitting
+    slot: u64,
+}
+
+/// The JSON body posted to the execution webhook when a strategy raises an alert.
+#[derive(Serialize)]
+struct AlertNotification<'a> {
+    /// The strategy order the alert is about, as `tx_hash#index`
+    order_ref: String,
+    /// What kind of alert this is, e.g. `grid_side_depleted`
+    alert: &'a str,
+    message: &'a str,
+    /// The latest slot the worker had observed when raising the alert
+    slot: u64,
+}
+
+/// An execution's validity range in UNIX milliseconds; unbounded ends are null.
+#[derive(Serialize, Debug, PartialEq)]
+struct ValidityRange {
+    start_ms: Option<u64>,
+    end_ms: Option<u64>,
+}
+
+impl From<&Interval> for ValidityRange {
+    fn from(interval: &Interval) -> Self {
+        let finite = |bound: &IntervalBoundType| match bound {
+            IntervalBoundType::Finite(ms) => Some(*ms),
+            _ => None,
+        };
+        ValidityRange {
+            start_ms: finite(&interval.lower_bound.bound_type),
+            end_ms: finite(&interval.upper_bound.bound_type),
+        }
+    }
+}
+
+/// Tell the configured webhook, if any, about a submitted execution.
+///
+/// Failing to reach the webhook never fails the execution; it is only logged.
+pub(crate) fn notify_execution(execution: &StrategyExecution) {
+    let Some(url) = EXECUTION_WEBHOOK.get() else {
+        return;
+    };
+    let notification = ExecutionNotification {
+        order_ref: format!("{:?}", execution.tx_ref),
+        details: &execution.details,
+        validity_range: (&execution.validity_range).into(),
+        slot: kv::current_slot(),
+    };
+    post(url, &notification, &notification.order_ref);
+}
+
+/// Tell the configured webhook, if any, about an alert raised for `utxo`.
+pub(crate) fn notify_alert(utxo: &OutputReference, alert: &str, message: &str) {
+    let Some(url) = EXECUTION_WEBHOOK.get() else {
+        return;
+    };
+    let notification = AlertNotification {
+        order_ref: format!("{utxo:?}"),
+        alert,
+        message,
+        slot: kv::current_slot(),
+    };
+    post(
+        url,
+        &notification,
+        &format!("{alert} on {}", notification.order_ref),
+    );
+}
+
+/// POST `body` to the webhook, logging rather than returning any failure.
+fn post(url: &Url, body: &impl Serialize, what: &str) {
+    let send = || -> Result<(), Error> {
+        HttpRequest::post(url.clone()).json(body)?.send()?;
+        Ok(())
+    };
+    match send() {
+        Ok(_) => info!("notified execution webhook of {what}"),
+        Err(err) => warn!("failed to notify execution webhook: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validity_range_reports_finite_bounds() {
+        let range = ValidityRange::from(&Interval::inclusive_range(1_000, 2_000));
+        assert_eq!(
+            range,
+            ValidityRange {
+                start_ms: Some(1_000),
+                end_ms: Some(2_000),
+            }
+        );
+    }
+}
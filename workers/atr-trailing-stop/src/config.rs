@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use sundae_strategies::{
+    DEFAULT_SLIPPAGE_TOLERANCE, DEFAULT_VALIDITY_WINDOW_SECS, Network, types::AssetId,
+    validate_finite, validate_fraction, validate_validity_window,
+};
+
+/// Default trail used until `atr_window` price changes have been observed (15%)
+const DEFAULT_FALLBACK_TRAIL_PERCENT: f64 = 0.15;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token being protected (the position you're holding)
+    pub position_token: AssetId,
+    /// The token to swap into when the stop triggers (exit destination)
+    pub exit_token: AssetId,
+    /// The number of observed price changes the average true range is taken over
+    pub atr_window: usize,
+    /// How many average true ranges below the peak the stop triggers
+    pub atr_multiplier: f64,
+    /// How far below the peak the stop triggers until the ATR is available (e.g. 0.15 = 15%).
+    /// Must be in range (0.0, 1.0). Defaults to 15% if not specified.
+    pub fallback_trail_percent: f64,
+    /// Maximum acceptable slippage when executing the exit order (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+    /// How long, in seconds either side of the trigger, the exit order is valid for.
+    /// Must be within 60..=3600. Defaults to 20 minutes if not specified.
+    pub validity_window_secs: u64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    position_token: AssetId,
+    exit_token: AssetId,
+    atr_window: usize,
+    atr_multiplier: f64,
+    fallback_trail_percent: Option<f64>,
+    slippage_tolerance: Option<f64>,
+    validity_window_secs: Option<u64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.atr_window == 0 {
+            return Err("atr_window must be >= 1".to_string());
+        }
+        validate_finite("atr_multiplier", raw.atr_multiplier)?;
+        if raw.atr_multiplier <= 0.0 {
+            return Err(format!(
+                "atr_multiplier must be > 0.0, got {}",
+                raw.atr_multiplier
+            ));
+        }
+
+        let fallback_trail_percent = validate_fraction(
+            "fallback_trail_percent",
+            raw.fallback_trail_percent
+                .unwrap_or(DEFAULT_FALLBACK_TRAIL_PERCENT),
+        )?;
+        let slippage_tolerance = validate_fraction(
+            "slippage_tolerance",
+            raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE),
+        )?;
+        let validity_window_secs = validate_validity_window(
+            raw.validity_window_secs
+                .unwrap_or(DEFAULT_VALIDITY_WINDOW_SECS),
+        )?;
+
+        if raw.position_token == raw.exit_token {
+            return Err("position_token and exit_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            position_token: raw.position_token,
+            exit_token: raw.exit_token,
+            atr_window: raw.atr_window,
+            atr_multiplier: raw.atr_multiplier,
+            fallback_trail_percent,
+            slippage_tolerance,
+            validity_window_secs,
+        })
+    }
+}
@@ -0,0 +1,360 @@
+//! # ATR Trailing Stop Strategy
+//!
+//! A trailing stop loss whose trail distance follows the market's recent volatility,
+//! rather than being a fixed percentage of the peak.
+//!
+//! ## How It Works
+//!
+//! The strategy expects to receive a UTxO that already contains the position token.
+//! On every pool observation it records the price and tracks the peak, like the
+//! trailing stop loss, but triggers at:
+//!
+//! `peak_price - atr_multiplier * atr`
+//!
+//! where `atr` is the average true range over the last `atr_window` observations. Pool
+//! observations only carry a single price, so the true range of an observation is the
+//! absolute change from the previous price. A fixed trail is too tight when the market
+//! is choppy and too loose when it's calm; an ATR trail widens and narrows with it.
+//!
+//! Until `atr_window` price changes have been observed, the stop falls back to a
+//! fixed trail of `peak_price * (1 - fallback_trail_percent)`.
+//!
+//! The peak is kept until the order is spent, so an exit that lapses unfilled resumes
+//! from the same peak. While an exit is in flight, its order isn't exited again until the
+//! exit's validity window has passed.
+//!
+//! ## Example
+//!
+//! With `atr_window = 3` and `atr_multiplier = 2`:
+//!
+//! 1. Prices 100 → 102 → 101 → 104: the ATR is (2 + 1 + 3) / 3 = 2, peak is 104,
+//!    so the trigger is 104 - 2 * 2 = 100
+//! 2. Price drops to 99 → the ATR becomes (1 + 3 + 5) / 3 = 3, trigger 104 - 2 * 3 = 98
+//! 3. Price drops to 97 → the ATR becomes (3 + 5 + 2) / 3 ≈ 3.33, trigger ≈ 97.33,
+//!    and 97 is below it, so the stop fires and the position exits.
+//!
+//! ## Configuration
+//!
+//! - `position_token`: The token being protected (what you're holding)
+//! - `exit_token`: The token to swap into when the stop triggers
+//! - `atr_window`: The number of price changes the ATR averages over
+//! - `atr_multiplier`: How many ATRs below the peak the stop sits
+//! - `fallback_trail_percent`: Fixed trail used until the ATR is available (default 0.15)
+//! - `slippage_tolerance`: Maximum acceptable slippage on exit (default 0.03)
+//! - `validity_window_secs`: How long the exit order is valid for, either side of the
+//!   trigger (60 to 3600, default 1200)
+//!
+//! Price is "how much exit_token per 1 position_token", so it falls as the position
+//! loses value.
+
+mod config;
+
+use std::time::Duration;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    EventTime, ManagedStrategy, PoolState, Strategy,
+    history::PriceHistory,
+    kv,
+    types::{AssetId, Interval, Order, min_received},
+};
+use tracing::info;
+
+/// The peak and recent prices of one strategy order.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TrailState {
+    peak_price: f64,
+    history: PriceHistory,
+}
+
+impl TrailState {
+    fn new(config: &StrategyConfig, price: f64) -> Self {
+        // One more price than the window, to measure `atr_window` changes
+        Self {
+            peak_price: price,
+            history: PriceHistory::new(config.atr_window + 1),
+        }
+    }
+
+    fn observe(&mut self, slot: u64, price: f64) {
+        self.history.push(slot, price);
+        self.peak_price = self.peak_price.max(price);
+    }
+
+    /// The price below which the stop fires.
+    fn trigger_price(&self, config: &StrategyConfig) -> f64 {
+        match average_true_range(&self.history, config.atr_window) {
+            Some(atr) => self.peak_price - config.atr_multiplier * atr,
+            None => self.peak_price * (1.0 - config.fallback_trail_percent),
+        }
+    }
+}
+
+/// Trail state, stored per strategy output
+fn trail_states() -> kv::Namespace<TrailState> {
+    kv::Namespace::new("atr_trail")
+}
+
+/// Orders with an exit in flight; entries expire with the submitted validity window
+fn pending_exits() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_exit")
+}
+
+/// The mean absolute change between consecutive prices over the last `window` changes,
+/// or None until that many changes have been observed.
+fn average_true_range(history: &PriceHistory, window: usize) -> Option<f64> {
+    if window == 0 || history.len() < window + 1 {
+        return None;
+    }
+    let prices: Vec<f64> = history
+        .samples()
+        .skip(history.len() - window - 1)
+        .map(|sample| sample.price)
+        .collect();
+    let total: f64 = prices.windows(2).map(|w| (w[1] - w[0]).abs()).sum();
+    Some(total / window as f64)
+}
+
+/// How much exit_token one position_token is worth in this pool.
+fn get_position_price(pool_state: &PoolState, position_token: &AssetId) -> f64 {
+    // raw_price is asset_a per asset_b, so invert it when the position is asset_a
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let (pool_asset_a, _) = &pool_state.pool_datum.assets;
+    if *position_token == *pool_asset_a {
+        raw_price.invert().value()
+    } else {
+        raw_price.value()
+    }
+}
+
+fn on_strategy_spent(
+    _config: &Config<StrategyConfig>,
+    _tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    trail_states().delete(&strategy.output)?;
+    pending_exits().delete(&strategy.output)?;
+    Ok(Ack)
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let pool_price = get_position_price(pool_state, &config.position_token);
+    let now = pool_state.now_ms(&config.network);
+
+    // Empty or malformed pools report prices that aren't real trades
+    if !(pool_price.is_finite() && pool_price > 0.0) {
+        tracing::warn!("ignoring unusable pool price {pool_price}");
+        return Ok(Ack);
+    }
+
+    let trail_states = trail_states();
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.position_token, &config.exit_token)
+        {
+            continue;
+        }
+        if strategy.is_empty(&[&config.position_token]) {
+            continue;
+        }
+
+        let mut state = trail_states
+            .get(&strategy.output)?
+            .unwrap_or_else(|| TrailState::new(config, pool_price));
+        state.observe(pool_state.slot, pool_price);
+        trail_states.set(&strategy.output, &state)?;
+        let trigger_price = state.trigger_price(config);
+
+        info!(
+            "strategy {:?}: price={:.8}, peak={:.8}, trigger={:.8}",
+            strategy.output, pool_price, state.peak_price, trigger_price
+        );
+
+        if pool_price < trigger_price {
+            if pending_exits().get(&strategy.output)?.is_some() {
+                info!(
+                    "exit already submitted for {:?}, waiting for it to land",
+                    strategy.output
+                );
+                continue;
+            }
+            info!(
+                "ATR stop triggered for {:?}: price {:.8} < trigger {:.8}",
+                strategy.output, pool_price, trigger_price
+            );
+            trigger_exit(config, now, strategy, trigger_price)?;
+        }
+    }
+
+    Ok(Ack)
+}
+
+/// Exit: swap all position_token for exit_token, accepting at least the trigger price
+/// less the slippage tolerance.
+fn trigger_exit(
+    config: &Config<StrategyConfig>,
+    now: u64,
+    strategy: &ManagedStrategy,
+    trigger_price: f64,
+) -> WorkerResult<Ack> {
+    let valid_for = Duration::from_secs(config.validity_window_secs);
+    let validity_range = Interval::inclusive_range(
+        now.saturating_sub(valid_for.as_millis() as u64),
+        now.saturating_add(valid_for.as_millis() as u64),
+    );
+
+    let position_amount = strategy.balance(&config.position_token);
+    let min_received = min_received(position_amount, trigger_price, config.slippage_tolerance);
+
+    info!(
+        "exit order: selling {} {} for min {} {}",
+        position_amount,
+        config.position_token.name_to_string(),
+        min_received,
+        config.exit_token.name_to_string(),
+    );
+
+    let swap = Order::swap(
+        (&config.position_token, position_amount),
+        (&config.exit_token, min_received),
+    );
+    let submission = strategy.submit_execution(&config.network, validity_range, swap)?;
+    if submission.is_skipped() {
+        return Ok(Ack);
+    }
+    info!("exit order submitted successfully");
+    pending_exits().set_with_ttl(&strategy.output, &true, config.validity_window_secs)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::sim::Simulator;
+
+    fn config(atr_window: usize) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "position_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+            "exit_token": ".",
+            "atr_window": atr_window,
+            "atr_multiplier": 2.0,
+        }))
+        .unwrap()
+    }
+
+    fn observe_all(config: &StrategyConfig, prices: &[f64]) -> TrailState {
+        let mut state = TrailState::new(config, prices[0]);
+        for (slot, price) in prices.iter().enumerate() {
+            state.observe(slot as u64, *price);
+        }
+        state
+    }
+
+    #[test]
+    fn atr_averages_absolute_changes() {
+        let state = observe_all(&config(3), &[100.0, 102.0, 101.0, 104.0]);
+        assert_eq!(average_true_range(&state.history, 3), Some(2.0));
+        assert_eq!(average_true_range(&state.history, 4), None);
+    }
+
+    #[test]
+    fn trigger_trails_the_peak_by_the_atr() {
+        let config = config(3);
+        let state = observe_all(&config, &[100.0, 102.0, 101.0, 104.0]);
+        assert_eq!(state.peak_price, 104.0);
+        assert_eq!(state.trigger_price(&config), 100.0);
+
+        let state = observe_all(&config, &[100.0, 102.0, 101.0, 104.0, 99.0]);
+        assert_eq!(state.trigger_price(&config), 98.0);
+    }
+
+    #[test]
+    fn falls_back_to_a_fixed_trail_without_enough_history() {
+        let config = config(3);
+        let state = observe_all(&config, &[100.0, 110.0, 105.0]);
+        assert!((state.trigger_price(&config) - 110.0 * 0.85).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_lapsed_exit_keeps_its_peak() {
+        let ada = AssetId::from((vec![], vec![]));
+        let rberry = AssetId::from((
+            hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+            b"RBERRY".to_vec(),
+        ));
+        let mut sim = Simulator::new(
+            strategy(),
+            &serde_json::json!({
+                "network": "preview",
+                "position_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+                "exit_token": ".",
+                "atr_window": 3,
+                "atr_multiplier": 2.0,
+            }),
+        )
+        .unwrap();
+        let order = ManagedStrategy::mock(&[(&rberry, 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        // Below the fallback trigger of 85; the exit is in flight at the further fall to 75
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &rberry));
+        let executions = sim
+            .run([(1, pool(100)), (2, pool(80)), (3, pool(75))])
+            .unwrap();
+        assert_eq!(executions.len(), 1);
+        let state = trail_states().get(&order.output).unwrap().unwrap();
+        assert_eq!(state.peak_price, 100.0);
+
+        // Once its window has passed unfilled, the order exits again
+        let executions = sim.run([(2_000, pool(75))]).unwrap();
+        assert_eq!(executions.len(), 1);
+
+        sim.observe_tx(order.mock_execution(2_001, &[(&ada, 75_000_000)]))
+            .unwrap();
+        assert!(trail_states().get(&order.output).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let parse = |value: serde_json::Value| serde_json::from_value::<StrategyConfig>(value);
+        let base = serde_json::json!({
+            "network": "preview",
+            "position_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+            "exit_token": ".",
+            "atr_window": 14,
+            "atr_multiplier": 3.0,
+        });
+        assert!(parse(base.clone()).is_ok());
+        for (field, value) in [
+            ("atr_window", serde_json::json!(0)),
+            ("atr_multiplier", serde_json::json!(0.0)),
+            ("fallback_trail_percent", serde_json::json!(1.0)),
+            ("slippage_tolerance", serde_json::json!(0.0)),
+            ("validity_window_secs", serde_json::json!(30)),
+        ] {
+            let mut invalid = base.clone();
+            invalid[field] = value;
+            assert!(parse(invalid).is_err(), "{field} should be rejected");
+        }
+    }
+}
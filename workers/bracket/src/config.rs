@@ -0,0 +1,92 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+use tracing::info;
+
+#[derive(Deserialize)]
+#[serde(try_from = "BracketConfigRaw")]
+pub struct BracketConfig {
+    pub network: Network,
+    // Tokens must be in alphanumeric order with token_a < token_b when sorted
+    pub token_a: AssetId,
+    pub token_a_decimals: u8,
+    pub token_b: AssetId,
+    pub token_b_decimals: u8,
+    pub sell_token: AssetId,
+    /// Take-profit side: sell once the pool price rises above this
+    pub upper_price: f64,
+    /// Stop-loss side: sell once the pool price falls below this
+    pub lower_price: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct BracketConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_a_decimals: u8,
+    token_b: AssetId,
+    token_b_decimals: u8,
+    sell_token: AssetId,
+    upper_price: f64,
+    lower_price: f64,
+}
+
+impl TryFrom<BracketConfigRaw> for BracketConfig {
+    type Error = String;
+
+    fn try_from(raw: BracketConfigRaw) -> Result<Self, Self::Error> {
+        if raw.lower_price <= 0.0 {
+            return Err(format!(
+                "lower_price must be > 0.0, got {}",
+                raw.lower_price
+            ));
+        }
+        if raw.upper_price <= raw.lower_price {
+            return Err(format!(
+                "upper_price ({}) must be above lower_price ({})",
+                raw.upper_price, raw.lower_price
+            ));
+        }
+        if raw.sell_token != raw.token_a && raw.sell_token != raw.token_b {
+            return Err("sell_token must be one of token_a or token_b".to_string());
+        }
+
+        Ok(BracketConfig {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_a_decimals: raw.token_a_decimals,
+            token_b: raw.token_b,
+            token_b_decimals: raw.token_b_decimals,
+            sell_token: raw.sell_token,
+            upper_price: raw.upper_price,
+            lower_price: raw.lower_price,
+        })
+    }
+}
+
+impl BracketConfig {
+    /// The buy asset, and the minimum raw units of it to receive per raw unit of sell_token
+    pub fn trade_direction(&self, price: f64) -> (&AssetId, f64) {
+        // Prices are in whole token_a per whole token_b, so scale by the decimals
+        // difference to convert between raw amounts
+        if self.sell_token == self.token_a {
+            let scale = 10f64.powi(self.token_b_decimals as i32 - self.token_a_decimals as i32);
+            (&self.token_b, scale / price)
+        } else {
+            let scale = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
+            (&self.token_a, price * scale)
+        }
+    }
+
+    pub fn log_submission(&self, give_amount: u64, receive_amount: u64) {
+        info!(
+            "executing limit order to sell {give_amount} {} for {receive_amount} {}",
+            self.sell_token.name_to_string(),
+            if self.token_a == self.sell_token {
+                self.token_b.name_to_string()
+            } else {
+                self.token_a.name_to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! # Bracket (One-Cancels-Other) Strategy
+//!
+//! This strategy places a take-profit and a stop-loss around a position at the
+//! same time. Whichever side the pool price crosses first is the one that sells.
+//!
+//! ## How It Works
+//!
+//! - **Price rises above `upper_price`**: the take-profit side resolves the bracket.
+//! - **Price falls below `lower_price`**: the stop-loss side resolves the bracket.
+//!
+//! The winning side is written to KV *before* its swap is submitted. From then on
+//! the bracket only ever submits that side's swap, even if the price later swings
+//! through the other bound. This matters because the order UTxO isn't consumed the
+//! moment we submit: until the swap lands, the strategy keeps seeing the order on
+//! every pool observation, and a swing in the meantime must not fire the other side.
+//!
+//! If the submitted swap expires without landing, the same side is resubmitted
+//! once its validity window has lapsed.
+//!
+//! ## Configuration
+//!
+//! - `token_a` / `token_b`: The pool pair, with their decimals
+//! - `sell_token`: The token sold when either side triggers
+//! - `upper_price`: The take-profit price
+//! - `lower_price`: The stop-loss price (must be below `upper_price`)
+
+mod config;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::BracketConfig as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    ManagedStrategy, PoolState, Strategy, Submission, kv,
+    types::{Interval, Order, min_received},
+};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a sell order is valid for
+const VALIDITY_SECS: u64 = 20;
+
+/// The side of the bracket that fired
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Side {
+    TakeProfit,
+    StopLoss,
+}
+
+/// The side each order's bracket resolved to
+fn resolutions() -> kv::Namespace<Side> {
+    kv::Namespace::new("bracket_resolved")
+}
+
+/// Orders with a sell in flight; entries expire with the submitted validity window
+fn pending_sells() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_sell")
+}
+
+/// Which side should sell at `pool_price`, given any earlier resolution.
+///
+/// Once resolved, the bracket stays on that side regardless of price.
+fn resolve(config: &StrategyConfig, resolved: Option<Side>, pool_price: f64) -> Option<Side> {
+    if resolved.is_some() {
+        return resolved;
+    }
+    if pool_price > config.upper_price {
+        Some(Side::TakeProfit)
+    } else if pool_price < config.lower_price {
+        Some(Side::StopLoss)
+    } else {
+        None
+    }
+}
+
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    for strategy in strategies {
+        //  Skip processing for state changes of unrelated pools
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+        if strategy.is_empty(&[&config.sell_token]) {
+            info!(
+                "{:?} holds no {}, nothing to sell",
+                strategy.output,
+                config.sell_token.name_to_string()
+            );
+            continue;
+        }
+
+        // Get pool price and scale for decimals
+        let pool_price = pool_state
+            .price(config.token_a_decimals, config.token_b_decimals)
+            .value();
+        info!("pool update found, with price {}", pool_price);
+
+        let resolved = resolutions().get(&strategy.output)?;
+        let Some(side) = resolve(config, resolved, pool_price) else {
+            continue;
+        };
+        if resolved.is_none() {
+            info!("price {} resolved the bracket to {:?}", pool_price, side);
+            resolutions().set(&strategy.output, &side)?;
+        }
+
+        if pending_sells().get(&strategy.output)?.is_some() {
+            continue;
+        }
+        let price = match side {
+            Side::TakeProfit => config.upper_price,
+            Side::StopLoss => config.lower_price,
+        };
+        let validity_range = pool_state.get_validity_range(&config.network, VALIDITY_SECS);
+        if let Submission::Submitted(_) = trigger_sell(config, validity_range, strategy, price)? {
+            pending_sells().set_with_ttl(&strategy.output, &true, VALIDITY_SECS)?;
+        }
+    }
+    Ok(Ack)
+}
+
+fn trigger_sell(
+    config: &StrategyConfig,
+    validity_range: Interval,
+    order: &ManagedStrategy,
+    price: f64,
+) -> WorkerResult<Submission> {
+    let give_amount = order.balance(&config.sell_token);
+
+    // Get the buy asset and the minimum number of buy tokens per sell token at the bracket price
+    let (buy_token, price_ratio) = config.trade_direction(price);
+    let receive_amount = min_received(give_amount, price_ratio, 0.0);
+
+    let swap = Order::swap(
+        (&config.sell_token, give_amount),
+        (buy_token, receive_amount),
+    );
+
+    // Submit to relay and log
+    let submission = order.submit_execution(&config.network, validity_range, swap)?;
+    if !submission.is_skipped() {
+        config.log_submission(give_amount, receive_amount);
+    }
+    Ok(submission)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .with_validity_window(VALIDITY_SECS)
+        .worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_a_decimals": 6,
+            "token_b": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "token_b_decimals": 0,
+            "sell_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "upper_price": 120.0,
+            "lower_price": 80.0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn nothing_fires_inside_the_bracket() {
+        assert_eq!(resolve(&config(), None, 100.0), None);
+    }
+
+    #[test]
+    fn first_crossing_picks_the_side() {
+        assert_eq!(resolve(&config(), None, 121.0), Some(Side::TakeProfit));
+        assert_eq!(resolve(&config(), None, 79.0), Some(Side::StopLoss));
+    }
+
+    #[test]
+    fn resolved_side_survives_a_later_swing() {
+        let config = config();
+        assert_eq!(
+            resolve(&config, Some(Side::TakeProfit), 50.0),
+            Some(Side::TakeProfit)
+        );
+        assert_eq!(
+            resolve(&config, Some(Side::StopLoss), 150.0),
+            Some(Side::StopLoss)
+        );
+    }
+}
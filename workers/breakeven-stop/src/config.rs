@@ -0,0 +1,112 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance on the exit (3%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token being protected (the position you're holding)
+    pub position_token: AssetId,
+    /// The token to swap into when the stop triggers
+    pub exit_token: AssetId,
+    /// The price the position was entered at, in raw exit_token per raw position_token
+    pub entry_price: f64,
+    /// How far above entry_price the price must rise before the stop moves to breakeven
+    /// (e.g., 0.1 = +10%)
+    pub activation_gain: f64,
+    /// The stop before breakeven activates, in raw exit_token per raw position_token.
+    /// Must be below entry_price. If not specified, nothing protects the position until
+    /// breakeven activates.
+    pub initial_stop_price: Option<f64>,
+    /// How far above entry_price the breakeven stop sits, to cover the round trip's fees
+    /// (e.g., 0.005 = 0.5%). Defaults to 0.
+    pub fee_buffer: f64,
+    /// Maximum acceptable slippage when executing the exit order (e.g., 0.03 = 3%).
+    /// Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    position_token: AssetId,
+    exit_token: AssetId,
+    entry_price: f64,
+    activation_gain: f64,
+    initial_stop_price: Option<f64>,
+    fee_buffer: Option<f64>,
+    slippage_tolerance: Option<f64>,
+}
+
+impl Config {
+    /// The price at which breakeven activates
+    pub fn activation_price(&self) -> f64 {
+        self.entry_price * (1.0 + self.activation_gain)
+    }
+
+    /// The stop once breakeven has activated
+    pub fn breakeven_price(&self) -> f64 {
+        self.entry_price * (1.0 + self.fee_buffer)
+    }
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.entry_price <= 0.0 {
+            return Err(format!(
+                "entry_price must be > 0.0, got {}",
+                raw.entry_price
+            ));
+        }
+        if raw.activation_gain <= 0.0 {
+            return Err(format!(
+                "activation_gain must be > 0.0, got {}",
+                raw.activation_gain
+            ));
+        }
+        if let Some(price) = raw.initial_stop_price
+            && !(price > 0.0 && price < raw.entry_price)
+        {
+            return Err(format!(
+                "initial_stop_price must be between 0.0 and entry_price ({}), got {price}",
+                raw.entry_price
+            ));
+        }
+
+        let fee_buffer = raw.fee_buffer.unwrap_or(0.0);
+        if fee_buffer < 0.0 || fee_buffer >= raw.activation_gain {
+            return Err(format!(
+                "fee_buffer must be at least 0.0 and below activation_gain ({}), got {fee_buffer}",
+                raw.activation_gain
+            ));
+        }
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 || slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be between 0.0 and 1.0 exclusive, got {slippage_tolerance}"
+            ));
+        }
+
+        if raw.position_token == raw.exit_token {
+            return Err("position_token and exit_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            position_token: raw.position_token,
+            exit_token: raw.exit_token,
+            entry_price: raw.entry_price,
+            activation_gain: raw.activation_gain,
+            initial_stop_price: raw.initial_stop_price,
+            fee_buffer,
+            slippage_tolerance,
+        })
+    }
+}
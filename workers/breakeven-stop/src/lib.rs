@@ -0,0 +1,230 @@
+//! # Breakeven Stop Strategy
+//!
+//! This strategy protects a token position with a stop that moves up to the entry
+//! price once the position is in profit, so a winning trade can't turn into a loss.
+//!
+//! ## How It Works
+//!
+//! Until the price reaches `entry_price * (1 + activation_gain)`, the strategy
+//! behaves like a fixed stop at `initial_stop_price`, if one is configured. Once the
+//! price reaches that level, breakeven activates and the stop snaps to
+//! `entry_price * (1 + fee_buffer)`, where it stays.
+//!
+//! Activation is persisted per strategy output, so a pullback after activation
+//! doesn't lower the stop again. When the price falls below the current stop, the
+//! strategy exits the position, selling all `position_token` for `exit_token`.
+//!
+//! ## Example
+//!
+//! With `entry_price = 100`, `activation_gain = 0.1` and `initial_stop_price = 80`:
+//!
+//! 1. Price dips to 99 → above the initial stop of 80, nothing happens
+//! 2. Price rises to 112 → above 110, breakeven activates and the stop moves to 100
+//! 3. Price pulls back to 104 → the stop stays at 100
+//! 4. Price drops to 99 → below 100, the position is exited
+//!
+//! ## Configuration
+//!
+//! - `position_token`: The token being protected (what you're holding)
+//! - `exit_token`: The token to swap into when the stop triggers
+//! - `entry_price`: The entry, in raw `exit_token` per raw `position_token`
+//! - `activation_gain`: The gain that activates breakeven (0.1 = +10%)
+//! - `initial_stop_price`: Optional stop before activation, below `entry_price`
+//! - `fee_buffer`: How far above entry the breakeven stop sits, to cover fees (default 0)
+//! - `slippage_tolerance`: Maximum acceptable slippage on exit (default 3%)
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, ManagedStrategy, PoolState, Strategy, kv,
+    types::{Order, min_received},
+};
+use tracing::info;
+
+/// Whether breakeven has activated, stored per strategy output
+fn activations() -> kv::StrategyState<bool> {
+    kv::StrategyState::per_output("breakeven_active")
+}
+
+/// Orders with an exit in flight; entries expire with the submitted validity window
+fn pending_exits() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_exit")
+}
+
+/// The price of position_token in raw exit_token, or None if the pool is empty.
+fn position_price(config: &StrategyConfig, pool_state: &PoolState) -> Option<f64> {
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let price = if config.position_token == pool_state.pool_datum.assets.0 {
+        raw_price.invert().value()
+    } else {
+        raw_price.value()
+    };
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+/// The stop in effect, if any, given whether breakeven has activated.
+fn stop_price(config: &StrategyConfig, activated: bool) -> Option<f64> {
+    if activated {
+        Some(config.breakeven_price())
+    } else {
+        config.initial_stop_price
+    }
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let Some(price) = position_price(config, pool_state) else {
+        info!("pool has no usable price, skipping this observation");
+        return Ok(Ack);
+    };
+    let activations = activations();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.position_token, &config.exit_token)
+        {
+            continue;
+        }
+        let position_amount = strategy.balance(&config.position_token);
+        if position_amount == 0 {
+            continue;
+        }
+
+        let mut activated = activations.load(strategy)?.unwrap_or_default();
+        if !activated && price >= config.activation_price() {
+            info!(
+                "strategy {:?}: price {price} reached {}, moving the stop to breakeven at {}",
+                strategy.output,
+                config.activation_price(),
+                config.breakeven_price()
+            );
+            activations.store(strategy, &true)?;
+            activated = true;
+        }
+
+        let Some(stop) = stop_price(config, activated) else {
+            continue;
+        };
+        if price >= stop {
+            continue;
+        }
+        if pending_exits().get(&strategy.output)?.is_some() {
+            info!(
+                "exit already submitted for {:?}, waiting for it to land",
+                strategy.output
+            );
+            continue;
+        }
+
+        let receive = min_received(position_amount, stop, config.slippage_tolerance);
+        info!(
+            "strategy {:?}: price {price} fell below the stop at {stop}, selling {position_amount} {} for min {receive} {}",
+            strategy.output,
+            config.position_token.name_to_string(),
+            config.exit_token.name_to_string()
+        );
+        let swap = Order::swap(
+            (&config.position_token, position_amount),
+            (&config.exit_token, receive),
+        );
+        let validity_range =
+            pool_state.get_validity_range(&config.network, DEFAULT_VALIDITY_WINDOW_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+        pending_exits().set_with_ttl(&strategy.output, &true, DEFAULT_VALIDITY_WINDOW_SECS)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    _config: &Config<StrategyConfig>,
+    _tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    activations().clear(strategy)?;
+    pending_exits().delete(&strategy.output)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "entry_price": 100.0,
+            "activation_gain": 0.1,
+            "initial_stop_price": 80.0,
+        })
+    }
+
+    #[test]
+    fn the_stop_moves_to_breakeven_once_activated() {
+        let mut json = config_json();
+        json["fee_buffer"] = 0.01.into();
+        let config: StrategyConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(stop_price(&config, false), Some(80.0));
+        assert!((stop_price(&config, true).unwrap() - 101.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn initial_stop_must_be_below_entry() {
+        let mut json = config_json();
+        json["initial_stop_price"] = 100.0.into();
+        assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+    }
+
+    #[test]
+    fn a_pullback_does_not_deactivate_breakeven() {
+        // The example from the module docs
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sundae, 1_000)]))
+            .unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000, 1_000, (&ada, &sundae));
+        let executions = sim
+            .run([(1, pool(99)), (2, pool(112)), (3, pool(104)), (4, pool(99))])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 1_000);
+        // 1,000 SUNDAE at the breakeven stop of 100, less 3% slippage
+        assert!(min_received.2.abs_diff(97_000) <= 1);
+    }
+}
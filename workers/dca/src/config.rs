@@ -0,0 +1,74 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance (3%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token spent on each purchase
+    pub base_token: AssetId,
+    /// The token being accumulated
+    pub target_token: AssetId,
+    /// How much base_token to spend on each purchase
+    pub amount_per_interval: u64,
+    /// Minimum number of seconds between purchases
+    pub interval_secs: u64,
+    /// Total base_token to spend across all purchases; the strategy stops once it is spent
+    pub total_budget: u64,
+    /// Maximum acceptable slippage on each purchase (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    base_token: AssetId,
+    target_token: AssetId,
+    amount_per_interval: u64,
+    interval_secs: u64,
+    total_budget: u64,
+    slippage_tolerance: Option<f64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.amount_per_interval == 0 {
+            return Err("amount_per_interval must be > 0".to_string());
+        }
+        if raw.interval_secs == 0 {
+            return Err("interval_secs must be > 0".to_string());
+        }
+        if raw.total_budget == 0 {
+            return Err("total_budget must be > 0".to_string());
+        }
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 || slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be in (0.0, 1.0), got {}",
+                slippage_tolerance
+            ));
+        }
+
+        if raw.base_token == raw.target_token {
+            return Err("base_token and target_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            base_token: raw.base_token,
+            target_token: raw.target_token,
+            amount_per_interval: raw.amount_per_interval,
+            interval_secs: raw.interval_secs,
+            total_budget: raw.total_budget,
+            slippage_tolerance,
+        })
+    }
+}
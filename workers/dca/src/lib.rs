@@ -0,0 +1,315 @@
+//! # Dollar Cost Averaging (DCA) Strategy
+//!
+//! This strategy accumulates `target_token` by spending a fixed amount of
+//! `base_token` at a regular interval, regardless of price, until a total
+//! budget has been spent.
+//!
+//! ## How It Works
+//!
+//! Each observation of the pool for the configured pair acts as a clock tick.
+//! Once `interval_secs` have passed since the previous purchase, the strategy
+//! swaps `amount_per_interval` of `base_token` into `target_token`. The
+//! minimum received is derived from the pool's expected output (after fees),
+//! reduced by `slippage_tolerance`.
+//!
+//! A purchase only counts towards the budget once its order UTxO has been consumed
+//! (reported through `on_strategy_spent`) by a transaction that paid the bought
+//! `target_token` back to the order; the interval to the next purchase then runs
+//! from when it was submitted. Until then no other purchase is submitted, and one
+//! whose validity window lapses first, or whose order is consumed without it, is
+//! resubmitted.
+//!
+//! The amount spent so far is tracked per strategy authorization, so it
+//! survives the order UTxO being replaced after each purchase.
+//!
+//! ## Example
+//!
+//! With `amount_per_interval = 50`, `interval_secs = 86400` and `total_budget = 120`:
+//!
+//! 1. Day 1: buys with 50 `base_token` (spent: 50)
+//! 2. Day 2: buys with 50 `base_token` (spent: 100)
+//! 3. Day 3: only 20 of the budget remains, so buys with 20 (spent: 120)
+//! 4. Day 4 onward: the budget is exhausted, nothing more is bought.
+//!
+//! > **Note:** Purchases only happen when the pool is observed, so a quiet pool
+//! > can delay a purchase past its interval. An empty pool is skipped rather
+//! > than traded against.
+//!
+//! ## Configuration
+//!
+//! - `base_token`: The token spent on each purchase
+//! - `target_token`: The token being accumulated
+//! - `amount_per_interval`: How much `base_token` to spend per purchase
+//! - `interval_secs`: Minimum seconds between purchases
+//! - `total_budget`: Total `base_token` to spend before stopping
+//! - `slippage_tolerance`: Maximum acceptable slippage per purchase (0.03 = 3%)
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{EventTime, ManagedStrategy, PoolState, Strategy, kv, types::Order};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a purchase is valid for
+const VALIDITY_SECS: u64 = 20 * 60;
+
+/// A purchase submitted to the market and not yet consumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Purchase {
+    amount: u64,
+    /// UNIX time (ms) it was submitted at
+    submitted_ms: u64,
+    /// UNIX time (ms) after which the purchase can no longer fill
+    expires_ms: u64,
+}
+
+/// Progress of a DCA strategy, persisted per strategy authorization
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DcaState {
+    /// Total base_token spent so far
+    spent: u64,
+    /// UNIX time (ms) the most recent filled purchase was submitted at
+    last_purchase_ms: Option<u64>,
+    #[serde(default)]
+    in_flight: Option<Purchase>,
+}
+
+fn dca_states() -> kv::StrategyState<DcaState> {
+    kv::StrategyState::per_authorization("dca_state")
+}
+
+/// How much base_token to spend right now, if anything.
+fn next_purchase(config: &StrategyConfig, state: &DcaState, now_ms: u64) -> Option<u64> {
+    if let Some(purchase) = &state.in_flight
+        && now_ms <= purchase.expires_ms
+    {
+        return None;
+    }
+    let remaining = config.total_budget.saturating_sub(state.spent);
+    if remaining == 0 {
+        return None;
+    }
+    if let Some(last) = state.last_purchase_ms
+        && now_ms.saturating_sub(last) < config.interval_secs.saturating_mul(1000)
+    {
+        return None;
+    }
+    // The final purchase may be a partial tranche
+    Some(config.amount_per_interval.min(remaining))
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let now_ms = pool_state.now_ms(&config.network);
+    let dca_states = dca_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.base_token, &config.target_token) {
+            continue;
+        }
+
+        let mut state = dca_states.load(strategy)?.unwrap_or_default();
+        let Some(amount) = next_purchase(config, &state, now_ms) else {
+            info!(
+                "strategy {:?}: nothing to buy (spent {} of {})",
+                strategy.output, state.spent, config.total_budget
+            );
+            continue;
+        };
+
+        let available = strategy.balance(&config.base_token);
+        let amount = amount.min(available);
+        if amount == 0 {
+            info!(
+                "strategy {:?}: no {} left to spend",
+                strategy.output,
+                config.base_token.name_to_string()
+            );
+            continue;
+        }
+
+        let Some(expected) = pool_state.expected_output(&config.base_token, amount) else {
+            info!("pool has no usable price, skipping this tick");
+            continue;
+        };
+        let min_received =
+            ((expected as f64 * (1.0 - config.slippage_tolerance)).floor() as u64).max(1);
+
+        info!(
+            "strategy {:?}: buying with {amount} {} for min {min_received} {} (spent {} of {})",
+            strategy.output,
+            config.base_token.name_to_string(),
+            config.target_token.name_to_string(),
+            state.spent,
+            config.total_budget
+        );
+
+        let swap = Order::swap(
+            (&config.base_token, amount),
+            (&config.target_token, min_received),
+        );
+        let validity_range = pool_state.get_validity_range(&config.network, VALIDITY_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        state.in_flight = Some(Purchase {
+            amount,
+            submitted_ms: now_ms,
+            expires_ms: now_ms.saturating_add(VALIDITY_SECS * 1000),
+        });
+        dca_states.store(strategy, &state)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let dca_states = dca_states();
+    let Some(mut state) = dca_states.load(strategy)? else {
+        return Ok(Ack);
+    };
+    let Some(purchase) = state.in_flight.take() else {
+        return Ok(Ack);
+    };
+    match strategy.swap_fill(tx, &config.base_token, &config.target_token) {
+        Some(fill) => {
+            state.spent += purchase.amount;
+            state.last_purchase_ms = Some(purchase.submitted_ms);
+            info!(
+                "strategy {:?}: purchase with {} bought {} {} (spent {} of {})",
+                strategy.output,
+                purchase.amount,
+                fill.received,
+                config.target_token.name_to_string(),
+                state.spent,
+                config.total_budget
+            );
+        }
+        None => info!(
+            "strategy {:?}: spent without the purchase with {}, it'll be resubmitted",
+            strategy.output, purchase.amount
+        ),
+    }
+    dca_states.store(strategy, &state)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+        .with_validity_window(VALIDITY_SECS)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{Network, sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "base_token": ".",
+            "target_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "amount_per_interval": 50,
+            "interval_secs": 60,
+            "total_budget": 120,
+        })
+    }
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(config_json()).unwrap()
+    }
+
+    #[test]
+    fn first_purchase_is_immediate() {
+        let config = config();
+        assert!(matches!(config.network, Network::Preview));
+        assert_eq!(next_purchase(&config, &DcaState::default(), 0), Some(50));
+    }
+
+    #[test]
+    fn waits_for_the_interval() {
+        let config = config();
+        let state = DcaState {
+            spent: 50,
+            last_purchase_ms: Some(1_000_000),
+            in_flight: None,
+        };
+        assert_eq!(next_purchase(&config, &state, 1_059_999), None);
+        assert_eq!(next_purchase(&config, &state, 1_060_000), Some(50));
+    }
+
+    #[test]
+    fn final_tranche_is_partial() {
+        let config = config();
+        let state = DcaState {
+            spent: 100,
+            last_purchase_ms: Some(0),
+            in_flight: None,
+        };
+        assert_eq!(next_purchase(&config, &state, 60_000), Some(20));
+    }
+
+    #[test]
+    fn stops_when_budget_is_spent() {
+        let config = config();
+        let state = DcaState {
+            spent: 120,
+            last_purchase_ms: Some(0),
+            in_flight: None,
+        };
+        assert_eq!(next_purchase(&config, &state, u64::MAX), None);
+    }
+
+    #[test]
+    fn only_filled_purchases_are_spent() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((
+            hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+            b"SBERRY".to_vec(),
+        ));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let pool = || PoolState::mock(1_000_000, 1_000_000, (&ada, &sberry));
+
+        // Nothing else is bought while the first purchase is in flight
+        assert_eq!(sim.run([(1, pool()), (2, pool())]).unwrap().len(), 1);
+        assert_eq!(dca_states().load(&order).unwrap().unwrap().spent, 0);
+
+        // Once it fills, the next one waits out the interval from its submission
+        sim.observe_tx(order.mock_execution(3, &[(&ada, 950), (&sberry, 48)]))
+            .unwrap();
+        let state = dca_states().load(&order).unwrap().unwrap();
+        assert_eq!(state.spent, 50);
+        assert!(state.in_flight.is_none());
+        assert!(sim.run([(60, pool())]).unwrap().is_empty());
+        assert_eq!(sim.run([(61, pool())]).unwrap().len(), 1);
+
+        // A purchase that lapses unfilled is resubmitted, and isn't spent
+        assert_eq!(sim.run([(61 + 1_201, pool())]).unwrap().len(), 1);
+        assert_eq!(dca_states().load(&order).unwrap().unwrap().spent, 50);
+    }
+}
@@ -8,7 +8,7 @@ use tracing::info;
 use crate::config::DCAConfig;
 
 use sundae_strategies::{
-    ManagedStrategy, Strategy,
+    EventTime, ManagedStrategy, Strategy,
     types::{Interval, Order},
 };
 
@@ -21,7 +21,7 @@ fn on_each_tx(
         let slots_elapsed = tx.block_slot - seen.slot;
         if slots_elapsed > config.interval {
             info!("{} slots elapsed, triggering a buy order", slots_elapsed);
-            trigger_buy(config, config.network.to_unix_time(tx.block_slot), seen)?;
+            trigger_buy(config, tx.now_ms(&config.network), seen)?;
         } else {
             info!(
                 "{} slots elapsed, out of {}; {} slots remaining before we trigger a buy...",
@@ -41,20 +41,12 @@ fn trigger_buy(config: &config::DCAConfig, now: u64, order: &ManagedStrategy) ->
         now + valid_for.as_millis() as u64,
     );
 
-    let swap = Order::Swap {
-        offer: (
-            config.offer_token.policy_id.clone(),
-            config.offer_token.asset_name.clone(),
-            config.offer_amount,
-        ),
-        min_received: (
-            config.receive_token.policy_id.clone(),
-            config.receive_token.asset_name.clone(),
-            config.receive_amount_min,
-        ),
-    };
+    let swap = Order::swap(
+        (&config.offer_token, config.offer_amount),
+        (&config.receive_token, config.receive_amount_min),
+    );
 
-    sundae_strategies::submit_execution(&config.network, &order.output, validity_range, swap)?;
+    order.submit_execution(&config.network, validity_range, swap)?;
 
     Ok(())
 }
@@ -0,0 +1,64 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token being sold
+    pub sell_token: AssetId,
+    /// The token received for it
+    pub buy_token: AssetId,
+    /// The minimum amount of buy_token accepted per unit of sell_token
+    pub limit_price: f64,
+    /// How much sell_token each clip exposes to the market
+    pub clip_size: u64,
+    /// Total sell_token to sell across all clips; the strategy stops once it is filled
+    pub total_size: u64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    sell_token: AssetId,
+    buy_token: AssetId,
+    limit_price: f64,
+    clip_size: u64,
+    total_size: u64,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.limit_price <= 0.0 {
+            return Err(format!(
+                "limit_price must be > 0.0, got {}",
+                raw.limit_price
+            ));
+        }
+        if raw.clip_size == 0 {
+            return Err("clip_size must be > 0".to_string());
+        }
+        if raw.total_size < raw.clip_size {
+            return Err(format!(
+                "total_size ({}) must be at least clip_size ({})",
+                raw.total_size, raw.clip_size
+            ));
+        }
+
+        if raw.sell_token == raw.buy_token {
+            return Err("sell_token and buy_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            sell_token: raw.sell_token,
+            buy_token: raw.buy_token,
+            limit_price: raw.limit_price,
+            clip_size: raw.clip_size,
+            total_size: raw.total_size,
+        })
+    }
+}
@@ -0,0 +1,270 @@
+//! # Iceberg Strategy
+//!
+//! This strategy sells a large position discreetly, by only ever exposing a
+//! small `clip_size` of the `total_size` to the market at a time.
+//!
+//! ## How It Works
+//!
+//! When the pool for the pair is observed and no clip is in flight, the
+//! strategy submits a swap of one clip of `sell_token`, accepting no less than
+//! `limit_price` `buy_token` per unit sold.
+//!
+//! The next clip is only submitted once the previous clip's order UTxO has been
+//! consumed (reported through `on_strategy_spent`), at which point the clip is
+//! counted as filled if the consuming transaction paid its proceeds back to the
+//! order. A clip whose order is consumed without them, e.g. cancelled by its owner,
+//! or whose validity window lapses without the order being consumed, is resubmitted. Progress is tracked per strategy authorization, so it
+//! survives the order UTxO being replaced after each clip.
+//!
+//! ## Example
+//!
+//! With `clip_size = 1000` and `total_size = 2500`:
+//!
+//! 1. Clip 1 sells 1000 (remaining: 1500)
+//! 2. Clip 2 sells 1000 (remaining: 500)
+//! 3. Clip 3 sells the final 500, and the strategy stops.
+//!
+//! ## Configuration
+//!
+//! - `sell_token`: The token being sold
+//! - `buy_token`: The token received
+//! - `limit_price`: Minimum `buy_token` per `sell_token`, in raw units
+//! - `clip_size`: How much `sell_token` each clip exposes
+//! - `total_size`: Total `sell_token` to sell
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{EventTime, ManagedStrategy, PoolState, Strategy, kv, types::Order};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a clip is valid for
+const VALIDITY_SECS: u64 = 20 * 60;
+
+/// A clip submitted to the market and not yet consumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Clip {
+    amount: u64,
+    /// UNIX time (ms) after which the clip can no longer fill
+    expires_ms: u64,
+}
+
+/// Progress of an iceberg strategy, persisted per strategy authorization
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IcebergState {
+    /// Total sell_token filled so far
+    filled: u64,
+    in_flight: Option<Clip>,
+}
+
+fn iceberg_states() -> kv::StrategyState<IcebergState> {
+    kv::StrategyState::per_authorization("iceberg_state")
+}
+
+/// How much sell_token the next clip should offer, if a clip should be submitted now.
+fn next_clip(config: &StrategyConfig, state: &IcebergState, now_ms: u64) -> Option<u64> {
+    if let Some(clip) = &state.in_flight
+        && now_ms <= clip.expires_ms
+    {
+        return None;
+    }
+    let remaining = config.total_size.saturating_sub(state.filled);
+    if remaining == 0 {
+        return None;
+    }
+    // The final clip may be partial
+    Some(config.clip_size.min(remaining))
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let now_ms = pool_state.now_ms(&config.network);
+    let iceberg_states = iceberg_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.sell_token, &config.buy_token) {
+            continue;
+        }
+
+        let mut state = iceberg_states.load(strategy)?.unwrap_or_default();
+        let Some(amount) = next_clip(config, &state, now_ms) else {
+            continue;
+        };
+        let amount = amount.min(strategy.balance(&config.sell_token));
+        if amount == 0 {
+            continue;
+        }
+
+        let min_received = ((amount as f64 * config.limit_price).floor() as u64).max(1);
+        info!(
+            "strategy {:?}: submitting clip of {amount} {} for min {min_received} {} (filled {} of {})",
+            strategy.output,
+            config.sell_token.name_to_string(),
+            config.buy_token.name_to_string(),
+            state.filled,
+            config.total_size
+        );
+
+        let swap = Order::swap(
+            (&config.sell_token, amount),
+            (&config.buy_token, min_received),
+        );
+        let validity_range = pool_state.get_validity_range(&config.network, VALIDITY_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        state.in_flight = Some(Clip {
+            amount,
+            expires_ms: now_ms.saturating_add(VALIDITY_SECS * 1000),
+        });
+        iceberg_states.store(strategy, &state)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let iceberg_states = iceberg_states();
+    let Some(mut state) = iceberg_states.load(strategy)? else {
+        return Ok(Ack);
+    };
+    let Some(clip) = state.in_flight.take() else {
+        return Ok(Ack);
+    };
+    match strategy.swap_fill(tx, &config.sell_token, &config.buy_token) {
+        Some(fill) => {
+            state.filled += clip.amount;
+            info!(
+                "strategy {:?}: clip of {} filled for {} {} (filled {} so far)",
+                strategy.output,
+                clip.amount,
+                fill.received,
+                config.buy_token.name_to_string(),
+                state.filled
+            );
+        }
+        None => info!(
+            "strategy {:?}: spent without filling the clip of {}, it'll be resubmitted",
+            strategy.output, clip.amount
+        ),
+    }
+    iceberg_states.store(strategy, &state)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .with_validity_window(VALIDITY_SECS)
+        .on_strategy_spent(on_strategy_spent)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "sell_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "buy_token": ".",
+            "limit_price": 168.0,
+            "clip_size": 1000,
+            "total_size": 2500,
+        })
+    }
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(config_json()).unwrap()
+    }
+
+    #[test]
+    fn waits_for_the_clip_in_flight() {
+        let state = IcebergState {
+            filled: 0,
+            in_flight: Some(Clip {
+                amount: 1000,
+                expires_ms: 5_000,
+            }),
+        };
+        assert_eq!(next_clip(&config(), &state, 5_000), None);
+    }
+
+    #[test]
+    fn resubmits_an_expired_clip() {
+        let state = IcebergState {
+            filled: 1000,
+            in_flight: Some(Clip {
+                amount: 1000,
+                expires_ms: 5_000,
+            }),
+        };
+        assert_eq!(next_clip(&config(), &state, 5_001), Some(1000));
+    }
+
+    #[test]
+    fn final_clip_is_partial_and_then_stops() {
+        let config = config();
+        let mut state = IcebergState {
+            filled: 2000,
+            in_flight: None,
+        };
+        assert_eq!(next_clip(&config, &state, 0), Some(500));
+        state.filled = 2500;
+        assert_eq!(next_clip(&config, &state, 0), None);
+    }
+
+    #[test]
+    fn only_delivered_clips_count_as_filled() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((
+            hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+            b"SBERRY".to_vec(),
+        ));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&sberry, 2_500)]);
+        sim.add_order(order.clone()).unwrap();
+        let pool = || PoolState::mock(1_000_000_000, 10_000, (&ada, &sberry));
+        assert_eq!(sim.run([(1, pool())]).unwrap().len(), 1);
+
+        // The clip's proceeds are paid back to the order
+        sim.observe_tx(order.mock_execution(2, &[(&sberry, 1_500), (&ada, 168_000)]))
+            .unwrap();
+        let successor = sundae_strategies::managed_strategies().unwrap().remove(0);
+        assert_eq!(
+            iceberg_states().load(&successor).unwrap().unwrap().filled,
+            1_000
+        );
+
+        // The owner cancels the order while the next clip is in flight
+        assert_eq!(sim.run([(3, pool())]).unwrap().len(), 1);
+        let mut cancel = successor.mock_execution(4, &[(&sberry, 1_500)]);
+        cancel.tx.outputs[0].datum = None;
+        sim.observe_tx(cancel).unwrap();
+        let state = iceberg_states().load(&successor).unwrap().unwrap();
+        assert_eq!(state.filled, 1_000);
+        assert!(state.in_flight.is_none());
+    }
+}
@@ -0,0 +1,51 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default minimum time between compounds (1 day)
+const DEFAULT_COMPOUND_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The two assets of the pool the position provides liquidity to, in either order
+    pub pool_tokens: [AssetId; 2],
+    /// Minimum number of seconds between compounds. Defaults to 1 day.
+    pub compound_interval_secs: u64,
+    /// The least a compound may deposit, valued in raw units of the first of `pool_tokens`.
+    /// Smaller deposits would lose more to fees than they add to the position.
+    pub min_compound_value: u64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    pool_tokens: [AssetId; 2],
+    compound_interval_secs: Option<u64>,
+    min_compound_value: u64,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        let [token_a, token_b] = &raw.pool_tokens;
+        if token_a == token_b {
+            return Err("pool_tokens must be two different tokens".to_string());
+        }
+        let compound_interval_secs = raw
+            .compound_interval_secs
+            .unwrap_or(DEFAULT_COMPOUND_INTERVAL_SECS);
+        if compound_interval_secs == 0 {
+            return Err("compound_interval_secs must be > 0".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            pool_tokens: raw.pool_tokens,
+            compound_interval_secs,
+            min_compound_value: raw.min_compound_value,
+        })
+    }
+}
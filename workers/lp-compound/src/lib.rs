@@ -0,0 +1,274 @@
+//! # LP Compounding Strategy
+//!
+//! This strategy keeps a liquidity position in a Sundae pool fully invested, by
+//! periodically depositing any of the pool's assets the strategy UTxO holds
+//! outside of its LP tokens.
+//!
+//! ## How It Works
+//!
+//! Sundae pools keep swap fees in their reserves, so the fees earned by LP tokens
+//! already compound on their own: each LP token is a claim on a growing share of
+//! the reserves, and there is nothing separate to withdraw. What is left idle is
+//! any `pool_tokens` balance held next to the LP tokens: the position's initial
+//! funding, the change a previous deposit returned, or anything else sent to it.
+//!
+//! On each observation of the pool, once `compound_interval_secs` have passed since
+//! the last compound, the strategy sizes the largest deposit of its idle balances
+//! at the pool's current ratio (keeping a little ADA back for the order itself)
+//! and submits it, provided it is worth at least `min_compound_value`. The LP
+//! tokens, and whatever the pool doesn't take, come back to the strategy UTxO.
+//!
+//! The position's LP holdings are logged with the reserves they are a pro-rata
+//! claim on, computed from the pool's circulating LP.
+//!
+//! ## Example
+//!
+//! A pool of 1,000 ADA and 500 SBERRY, and a strategy UTxO holding 25 ADA and
+//! 5 SBERRY next to its LP tokens:
+//!
+//! 1. 5 ADA is kept back, leaving 20 ADA and 5 SBERRY idle
+//! 2. At 2 ADA per SBERRY, 5 SBERRY pairs with 10 ADA, worth 20 ADA in all
+//! 3. The strategy deposits 10 ADA and 5 SBERRY; 10 ADA stays idle until more SBERRY arrives
+//!
+//! ## Configuration
+//!
+//! - `pool_tokens`: The two assets of the pool, in either order
+//! - `compound_interval_secs`: Minimum time between compounds (default 1 day)
+//! - `min_compound_value`: The smallest deposit worth making, in raw units of the
+//!   first of `pool_tokens`
+
+mod config;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::Config as StrategyConfig;
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, ManagedStrategy, PoolState, Strategy, kv,
+    types::{AssetId, Order},
+};
+use tracing::info;
+
+/// Lovelace left in the strategy UTxO when depositing ADA, to cover the order's
+/// minimum UTxO value and scooper fee
+const ADA_RESERVE: u64 = 5_000_000;
+
+/// The slot of each strategy's most recent compound
+fn last_compound_slots() -> kv::StrategyState<u64> {
+    kv::StrategyState::per_authorization("last_compound_slot")
+}
+
+/// How much of `asset` the strategy can deposit.
+fn idle_balance(strategy: &ManagedStrategy, asset: &AssetId) -> u64 {
+    let balance = strategy.balance(asset);
+    if asset.is_ada() {
+        balance.saturating_sub(ADA_RESERVE)
+    } else {
+        balance
+    }
+}
+
+/// The largest deposit of the `idle` (asset_a, asset_b) amounts at the ratio of the
+/// pool's `reserves`, or None if either side would be empty.
+fn deposit_amounts(idle: (u64, u64), reserves: (u64, u64)) -> Option<(u64, u64)> {
+    let (idle_a, idle_b) = idle;
+    let (reserves_a, reserves_b) = (reserves.0 as u128, reserves.1 as u128);
+    if reserves_a == 0 || reserves_b == 0 {
+        return None;
+    }
+    let deposit_a = (idle_a as u128).min(idle_b as u128 * reserves_a / reserves_b);
+    let deposit_b = (idle_b as u128).min(idle_a as u128 * reserves_b / reserves_a);
+    if deposit_a == 0 || deposit_b == 0 {
+        return None;
+    }
+    Some((deposit_a as u64, deposit_b as u64))
+}
+
+/// The value of an (asset_a, asset_b) deposit in raw units of asset_a, or of asset_b if
+/// `in_asset_b`, at the ratio of the pool's `reserves`.
+fn deposit_value(deposit: (u64, u64), reserves: (u64, u64), in_asset_b: bool) -> u64 {
+    let (deposit_a, deposit_b) = (deposit.0 as u128, deposit.1 as u128);
+    let (reserves_a, reserves_b) = (reserves.0 as u128, reserves.1 as u128);
+    let value = if in_asset_b {
+        deposit_b + deposit_a * reserves_b / reserves_a
+    } else {
+        deposit_a + deposit_b * reserves_a / reserves_b
+    };
+    value.min(u64::MAX as u128) as u64
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let [token_a, token_b] = &config.pool_tokens;
+    let asset_a = AssetId::from(pool_state.pool_datum.assets.0.clone());
+    let asset_b = AssetId::from(pool_state.pool_datum.assets.1.clone());
+    let reserves = pool_state.pool_datum.reserves(&pool_state.utxo);
+    let value_in_asset_b = *token_a == asset_b;
+    let last_compound_slots = last_compound_slots();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, token_a, token_b) {
+            continue;
+        }
+
+        if let Some(last) = last_compound_slots.load(strategy)?
+            && pool_state.slot.saturating_sub(last) < config.compound_interval_secs
+        {
+            continue;
+        }
+
+        let lp_held = strategy.balance(&pool_state.lp_asset(&config.network));
+        if let Some((share_a, share_b)) = pool_state.lp_share(lp_held) {
+            info!(
+                "strategy {:?}: {lp_held} LP, a claim on {share_a} {} and {share_b} {}",
+                strategy.output,
+                asset_a.name_to_string(),
+                asset_b.name_to_string(),
+            );
+        }
+
+        let idle = (
+            idle_balance(strategy, &asset_a),
+            idle_balance(strategy, &asset_b),
+        );
+        let Some(deposit) = deposit_amounts(idle, reserves) else {
+            continue;
+        };
+        let value = deposit_value(deposit, reserves, value_in_asset_b);
+        if value < config.min_compound_value {
+            info!(
+                "strategy {:?}: idle deposit worth {value} is below min_compound_value {}",
+                strategy.output, config.min_compound_value
+            );
+            continue;
+        }
+
+        info!(
+            "strategy {:?}: compounding {} {} and {} {} (worth {value} {})",
+            strategy.output,
+            deposit.0,
+            asset_a.name_to_string(),
+            deposit.1,
+            asset_b.name_to_string(),
+            token_a.name_to_string(),
+        );
+        let order = Order::deposit((&asset_a, deposit.0), (&asset_b, deposit.1));
+        let validity_range =
+            pool_state.get_validity_range(&config.network, DEFAULT_VALIDITY_WINDOW_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, order)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        last_compound_slots.store(strategy, &pool_state.slot)?;
+    }
+
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new().on_new_pool_state(on_new_pool_state)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::sim::Simulator;
+
+    #[test]
+    fn config_is_validated() {
+        let config = |tokens: [&str; 2], interval: serde_json::Value| {
+            serde_json::from_value::<StrategyConfig>(serde_json::json!({
+                "network": "preview",
+                "pool_tokens": tokens,
+                "compound_interval_secs": interval,
+                "min_compound_value": 1_000_000,
+            }))
+        };
+        let sberry = "99999999999999999999999999999999999999999999999999999999534245525259";
+        assert_eq!(
+            config([".", sberry], serde_json::Value::Null)
+                .unwrap()
+                .compound_interval_secs,
+            86_400
+        );
+        assert!(config([".", sberry], 0.into()).is_err());
+        assert!(config([sberry, sberry], 3600.into()).is_err());
+    }
+
+    #[test]
+    fn deposits_at_the_pool_ratio() {
+        // 2 of asset_a per asset_b
+        assert_eq!(deposit_amounts((20, 5), (2_000, 1_000)), Some((10, 5)));
+        assert_eq!(deposit_amounts((6, 5), (2_000, 1_000)), Some((6, 3)));
+        assert_eq!(deposit_amounts((20, 0), (2_000, 1_000)), None);
+        assert_eq!(deposit_amounts((20, 5), (0, 0)), None);
+    }
+
+    #[test]
+    fn values_the_deposit_in_either_asset() {
+        assert_eq!(deposit_value((10, 5), (2_000, 1_000), false), 20);
+        assert_eq!(deposit_value((10, 5), (2_000, 1_000), true), 10);
+    }
+
+    #[test]
+    fn compounds_idle_balances_once_per_interval() {
+        // The example from the module docs
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "pool_tokens": [".", "99999999999999999999999999999999999999999999999999999999534245525259"],
+            "compound_interval_secs": 3600,
+            "min_compound_value": 1_000_000,
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 25_000_000),
+            (&sberry, 5_000_000),
+        ]))
+        .unwrap();
+
+        let pool = PoolState::mock(1_000_000_000, 500_000_000, (&ada, &sberry));
+        let executions = sim.run([(1, pool.clone()), (10, pool)]).unwrap();
+
+        assert_eq!(executions.len(), 1);
+        assert!(matches!(
+            &executions[0].details,
+            Order::Deposit {
+                assets: ((_, _, 10_000_000), (_, _, 5_000_000)),
+            }
+        ));
+    }
+
+    #[test]
+    fn skips_deposits_below_the_minimum() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "pool_tokens": [".", "99999999999999999999999999999999999999999999999999999999534245525259"],
+            "min_compound_value": 100_000_000,
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 25_000_000),
+            (&sberry, 5_000_000),
+        ]))
+        .unwrap();
+
+        let pool = PoolState::mock(1_000_000_000, 500_000_000, (&ada, &sberry));
+        assert!(sim.run([(1, pool)]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance (3%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+/// The longest window allowed, which bounds the price history kept in KV
+pub const MAX_WINDOW: usize = 500;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    // Tokens must be in alphanumeric order with token_a < token_b when sorted
+    pub token_a: AssetId,
+    /// The token bought and sold on crossovers
+    pub token_b: AssetId,
+    /// Number of pool observations in the fast moving average
+    pub fast_window: usize,
+    /// Number of pool observations in the slow moving average; must be larger than fast_window
+    pub slow_window: usize,
+    /// The value of each trade, in raw units of token_a
+    pub trade_size: u64,
+    /// Maximum acceptable slippage on each trade (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_b: AssetId,
+    fast_window: usize,
+    slow_window: usize,
+    trade_size: u64,
+    slippage_tolerance: Option<f64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.fast_window == 0 {
+            return Err("fast_window must be > 0".to_string());
+        }
+        if raw.slow_window <= raw.fast_window {
+            return Err(format!(
+                "slow_window ({}) must be larger than fast_window ({})",
+                raw.slow_window, raw.fast_window
+            ));
+        }
+        if raw.slow_window > MAX_WINDOW {
+            return Err(format!(
+                "slow_window must be at most {MAX_WINDOW}, got {}",
+                raw.slow_window
+            ));
+        }
+        if raw.trade_size == 0 {
+            return Err("trade_size must be > 0".to_string());
+        }
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 || slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be in (0.0, 1.0), got {}",
+                slippage_tolerance
+            ));
+        }
+
+        if raw.token_a == raw.token_b {
+            return Err("token_a and token_b must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_b: raw.token_b,
+            fast_window: raw.fast_window,
+            slow_window: raw.slow_window,
+            trade_size: raw.trade_size,
+            slippage_tolerance,
+        })
+    }
+}
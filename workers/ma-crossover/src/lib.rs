@@ -0,0 +1,224 @@
+//! # Moving-Average Crossover Strategy
+//!
+//! This strategy trades `token_b` against `token_a` when a fast moving average of
+//! the pool price crosses a slow one.
+//!
+//! ## How It Works
+//!
+//! Every observation of the pool appends its price (`token_a` per `token_b`) to a
+//! history kept in KV. The history is a ring buffer capped at `slow_window` entries,
+//! so its size stays bounded however long the worker runs.
+//!
+//! Once the history holds `slow_window` prices, the strategy compares the average of
+//! the last `fast_window` prices with the average of all of them:
+//!
+//! - **Fast crosses above slow**: buy `token_b`, spending `trade_size` of `token_a`
+//! - **Fast crosses below slow**: sell `token_b` worth `trade_size` of `token_a`
+//!
+//! Only the crossing itself trades. Which side of the slow average the fast one was
+//! on is stored alongside the history, and nothing happens while it stays there.
+//!
+//! ## Configuration
+//!
+//! - `token_a` / `token_b`: The pool pair; `token_b` is the token traded
+//! - `fast_window`: Observations in the fast average
+//! - `slow_window`: Observations in the slow average (at most 500)
+//! - `trade_size`: Value of each trade, in raw units of `token_a`
+//! - `slippage_tolerance`: Maximum acceptable slippage per trade (0.03 = 3%)
+
+mod config;
+
+use std::collections::VecDeque;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{ManagedStrategy, PoolState, Strategy, kv, types::Order};
+use tracing::info;
+
+/// A crossover of the fast moving average through the slow one
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Signal {
+    Buy,
+    Sell,
+}
+
+/// Recent prices for a pool, persisted per pool identifier
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PriceHistory {
+    /// The most recent prices, oldest first
+    prices: VecDeque<f64>,
+    /// Whether the fast average was above the slow one at the last observation
+    fast_above: Option<bool>,
+}
+
+impl PriceHistory {
+    /// Record a new price, dropping the oldest ones beyond `capacity`.
+    fn push(&mut self, price: f64, capacity: usize) {
+        self.prices.push_back(price);
+        while self.prices.len() > capacity {
+            self.prices.pop_front();
+        }
+    }
+
+    fn average(&self, window: usize) -> Option<f64> {
+        if window == 0 || self.prices.len() < window {
+            return None;
+        }
+        let sum: f64 = self.prices.iter().rev().take(window).sum();
+        Some(sum / window as f64)
+    }
+
+    /// Record a new price and report whether the averages crossed because of it.
+    fn observe(&mut self, config: &StrategyConfig, price: f64) -> Option<Signal> {
+        self.push(price, config.slow_window);
+        let fast = self.average(config.fast_window)?;
+        let slow = self.average(config.slow_window)?;
+
+        let fast_above = fast > slow;
+        let previous = self.fast_above.replace(fast_above);
+        match (previous, fast_above) {
+            (Some(false), true) => Some(Signal::Buy),
+            (Some(true), false) => Some(Signal::Sell),
+            _ => None,
+        }
+    }
+}
+
+fn price_histories() -> kv::Namespace<PriceHistory> {
+    kv::Namespace::new("price_history")
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let (asset_a, asset_b) = &pool_state.pool_datum.assets;
+    if config.token_a != *asset_a || config.token_b != *asset_b {
+        return Ok(Ack);
+    }
+
+    let price = pool_state.pool_datum.raw_price(&pool_state.utxo).value();
+    if price <= 0.0 {
+        return Ok(Ack);
+    }
+
+    let pool_id = hex::encode(&pool_state.pool_datum.identifier);
+    let mut history = price_histories().get(pool_id.as_str())?.unwrap_or_default();
+    let signal = history.observe(config, price);
+    price_histories().set(pool_id.as_str(), &history)?;
+
+    let Some(signal) = signal else {
+        return Ok(Ack);
+    };
+    info!("moving averages crossed at price {price}: {:?}", signal);
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+
+        let (offer, receive, offer_amount) = match signal {
+            Signal::Buy => (&config.token_a, &config.token_b, config.trade_size),
+            Signal::Sell => (
+                &config.token_b,
+                &config.token_a,
+                (config.trade_size as f64 / price) as u64,
+            ),
+        };
+        let offer_amount = offer_amount.min(strategy.balance(offer));
+        if offer_amount == 0 {
+            info!(
+                "strategy {:?}: no {} to trade",
+                strategy.output,
+                offer.name_to_string()
+            );
+            continue;
+        }
+
+        let Some(expected) = pool_state.expected_output(offer, offer_amount) else {
+            continue;
+        };
+        let min_received =
+            ((expected as f64 * (1.0 - config.slippage_tolerance)).floor() as u64).max(1);
+
+        let swap = Order::swap((offer, offer_amount), (receive, min_received));
+        let validity_range = pool_state.get_validity_range(&config.network, 20 * 60);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+        info!(
+            "strategy {:?}: swapping {offer_amount} {} for min {min_received} {}",
+            strategy.output,
+            offer.name_to_string(),
+            receive.name_to_string()
+        );
+    }
+
+    Ok(Ack)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_b": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "fast_window": 2,
+            "slow_window": 4,
+            "trade_size": 1000,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = PriceHistory::default();
+        for price in 0..10 {
+            history.push(price as f64, 4);
+        }
+        assert_eq!(history.prices, [6.0, 7.0, 8.0, 9.0]);
+    }
+
+    #[test]
+    fn trades_once_per_cross() {
+        let config = config();
+        let mut history = PriceHistory::default();
+        let signals: Vec<_> = [10.0, 10.0, 10.0, 9.0, 12.0, 13.0, 14.0, 8.0, 7.0]
+            .into_iter()
+            .map(|price| history.observe(&config, price))
+            .collect();
+        assert_eq!(
+            signals,
+            [
+                None,
+                None,
+                None,
+                // Fast is below slow once the window fills; that's a state, not a cross
+                None,
+                Some(Signal::Buy),
+                None,
+                None,
+                Some(Signal::Sell),
+                None,
+            ]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token bought low and sold high
+    pub token_a: AssetId,
+    /// The token it is priced in, and held between round trips
+    pub token_b: AssetId,
+    /// Buy token_a once it falls to this price, in raw token_b per raw token_a
+    pub buy_price: f64,
+    /// Sell token_a once it rises to this price, in raw token_b per raw token_a
+    pub sell_price: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_b: AssetId,
+    buy_price: f64,
+    sell_price: f64,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.buy_price <= 0.0 {
+            return Err(format!("buy_price must be > 0.0, got {}", raw.buy_price));
+        }
+        if raw.sell_price <= raw.buy_price {
+            return Err(format!(
+                "sell_price ({}) must be above buy_price ({})",
+                raw.sell_price, raw.buy_price
+            ));
+        }
+        if raw.token_a == raw.token_b {
+            return Err("token_a and token_b must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_b: raw.token_b,
+            buy_price: raw.buy_price,
+            sell_price: raw.sell_price,
+        })
+    }
+}
@@ -0,0 +1,351 @@
+//! # Ping-Pong Strategy
+//!
+//! This strategy trades a single price band indefinitely: it buys `token_a` when
+//! the price falls to `buy_price`, sells it when the price rises to `sell_price`,
+//! and repeats, flipping its whole inventory between the two tokens each time.
+//!
+//! ## How It Works
+//!
+//! The strategy persists which side it is `holding` per strategy authorization,
+//! starting from whichever token holds more of the position's value when it is
+//! first observed.
+//!
+//! - **Holding `token_b`**: when the price crosses down to `buy_price`, it swaps all
+//!   of its `token_b` for `token_a`, accepting no worse than `buy_price`.
+//! - **Holding `token_a`**: when the price crosses up to `sell_price`, it swaps all
+//!   of its `token_a` for `token_b`, accepting no worse than `sell_price`.
+//!
+//! Trades fire on the crossing only, not on every observation past the price, so a
+//! price that stays below `buy_price` buys once. While a trade is in flight nothing
+//! else is submitted; once its order UTxO is consumed (reported through
+//! `on_strategy_spent`) by a transaction paying the swap's proceeds back to the order,
+//! the held side flips. A trade whose order is consumed without them, or whose
+//! validity window lapses first, is resubmitted if the price is still past its threshold.
+//!
+//! ## Example
+//!
+//! With `buy_price = 150` and `sell_price = 180`, holding `token_b`:
+//!
+//! 1. The price falls from 160 to 148: all `token_b` is swapped for `token_a`
+//! 2. The price keeps falling to 140: nothing happens
+//! 3. The price rises to 185: all `token_a` is swapped back for `token_b`
+//!
+//! ## Configuration
+//!
+//! - `token_a`: The token bought low and sold high
+//! - `token_b`: The token it is priced in, and held between round trips
+//! - `buy_price`: Buy `token_a` at or below this price, in raw `token_b` per raw `token_a`
+//! - `sell_price`: Sell `token_a` at or above this price, in raw `token_b` per raw `token_a`
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, EventTime, ManagedStrategy, PoolState, Strategy, kv,
+    types::{Order, min_received},
+};
+use tracing::info;
+
+/// Which token a ping-pong strategy is holding
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Holding {
+    /// Holding token_a, waiting to sell it at sell_price
+    TokenA,
+    /// Holding token_b, waiting to buy token_a at buy_price
+    TokenB,
+}
+
+impl Holding {
+    fn flipped(self) -> Self {
+        match self {
+            Holding::TokenA => Holding::TokenB,
+            Holding::TokenB => Holding::TokenA,
+        }
+    }
+}
+
+/// Progress of a ping-pong strategy, persisted per strategy authorization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PingPongState {
+    holding: Holding,
+    /// The price at the previous observation, to detect crossings
+    last_price: f64,
+    /// UNIX time (ms) after which the trade in flight, if any, can no longer fill
+    in_flight_until: Option<u64>,
+}
+
+fn ping_pong_states() -> kv::StrategyState<PingPongState> {
+    kv::StrategyState::per_authorization("ping_pong_state")
+}
+
+/// The price of token_a in raw token_b, or None if the pool is empty.
+fn token_a_price(config: &StrategyConfig, pool_state: &PoolState) -> Option<f64> {
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let price = if config.token_b == pool_state.pool_datum.assets.0 {
+        raw_price.value()
+    } else {
+        raw_price.invert().value()
+    };
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+/// Whether `price` is at or past the threshold the `holding` side trades at.
+fn is_past_threshold(config: &StrategyConfig, holding: Holding, price: f64) -> bool {
+    match holding {
+        Holding::TokenA => price >= config.sell_price,
+        Holding::TokenB => price <= config.buy_price,
+    }
+}
+
+/// Whether moving from `last_price` to `price` crossed the threshold the `holding` side
+/// trades at.
+fn crossed_threshold(
+    config: &StrategyConfig,
+    holding: Holding,
+    last_price: f64,
+    price: f64,
+) -> bool {
+    !is_past_threshold(config, holding, last_price) && is_past_threshold(config, holding, price)
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let Some(price) = token_a_price(config, pool_state) else {
+        info!("pool has no usable price, skipping this observation");
+        return Ok(Ack);
+    };
+    let now_ms = pool_state.now_ms(&config.network);
+    let ping_pong_states = ping_pong_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+
+        let Some(mut state) = ping_pong_states.load(strategy)? else {
+            let value_a = strategy.balance(&config.token_a) as f64 * price;
+            let value_b = strategy.balance(&config.token_b) as f64;
+            let holding = if value_a >= value_b {
+                Holding::TokenA
+            } else {
+                Holding::TokenB
+            };
+            info!(
+                "strategy {:?}: starting out holding {holding:?} at {price}",
+                strategy.output
+            );
+            ping_pong_states.store(
+                strategy,
+                &PingPongState {
+                    holding,
+                    last_price: price,
+                    in_flight_until: None,
+                },
+            )?;
+            continue;
+        };
+
+        let crossed = crossed_threshold(config, state.holding, state.last_price, price);
+        state.last_price = price;
+        let should_trade = match state.in_flight_until {
+            Some(expires_ms) if now_ms <= expires_ms => false,
+            // The last trade lapsed without filling; retry while the price is still past
+            Some(_) => is_past_threshold(config, state.holding, price),
+            None => crossed,
+        };
+        if !should_trade {
+            ping_pong_states.store(strategy, &state)?;
+            continue;
+        }
+
+        let (offer_token, receive_token, receive_price) = match state.holding {
+            Holding::TokenA => (&config.token_a, &config.token_b, config.sell_price),
+            Holding::TokenB => (&config.token_b, &config.token_a, 1.0 / config.buy_price),
+        };
+        let offer = strategy.balance(offer_token);
+        if offer == 0 {
+            info!(
+                "strategy {:?}: holds no {} to trade",
+                strategy.output,
+                offer_token.name_to_string()
+            );
+            ping_pong_states.store(strategy, &state)?;
+            continue;
+        }
+        let receive = min_received(offer, receive_price, 0.0);
+        info!(
+            "strategy {:?}: price {price} crossed, swapping {offer} {} for min {receive} {}",
+            strategy.output,
+            offer_token.name_to_string(),
+            receive_token.name_to_string()
+        );
+
+        let swap = Order::swap((offer_token, offer), (receive_token, receive));
+        let validity_range =
+            pool_state.get_validity_range(&config.network, DEFAULT_VALIDITY_WINDOW_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        state.in_flight_until = Some(now_ms.saturating_add(DEFAULT_VALIDITY_WINDOW_SECS * 1000));
+        ping_pong_states.store(strategy, &state)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let ping_pong_states = ping_pong_states();
+    let Some(mut state) = ping_pong_states.load(strategy)? else {
+        return Ok(Ack);
+    };
+    if state.in_flight_until.is_none() {
+        return Ok(Ack);
+    }
+    let (offer_token, receive_token) = match state.holding {
+        Holding::TokenA => (&config.token_a, &config.token_b),
+        Holding::TokenB => (&config.token_b, &config.token_a),
+    };
+    if strategy.swap_fill(tx, offer_token, receive_token).is_some() {
+        state.in_flight_until = None;
+        state.holding = state.holding.flipped();
+        info!(
+            "strategy {:?}: trade filled, now holding {:?}",
+            strategy.output, state.holding
+        );
+    } else {
+        // The trade can no longer fill, so it lapses now and is retried while the price
+        // is still past its threshold
+        state.in_flight_until = Some(tx.now_ms(&config.network));
+        info!(
+            "strategy {:?}: spent without the trade filling, still holding {:?}",
+            strategy.output, state.holding
+        );
+    }
+    ping_pong_states.store(strategy, &state)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "token_a": "99999999999999999999999999999999999999999999999999999999534245525259",
+            "token_b": ".",
+            "buy_price": 150.0,
+            "sell_price": 180.0,
+        })
+    }
+
+    #[test]
+    fn sell_price_must_be_above_buy_price() {
+        let mut json = config_json();
+        json["sell_price"] = 150.0.into();
+        assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+    }
+
+    #[test]
+    fn only_the_crossing_triggers() {
+        let config: StrategyConfig = serde_json::from_value(config_json()).unwrap();
+        assert!(crossed_threshold(&config, Holding::TokenB, 160.0, 148.0));
+        assert!(crossed_threshold(&config, Holding::TokenB, 160.0, 150.0));
+        assert!(!crossed_threshold(&config, Holding::TokenB, 148.0, 140.0));
+        assert!(!crossed_threshold(&config, Holding::TokenB, 100.0, 185.0));
+
+        assert!(crossed_threshold(&config, Holding::TokenA, 170.0, 185.0));
+        assert!(!crossed_threshold(&config, Holding::TokenA, 185.0, 190.0));
+        assert!(!crossed_threshold(&config, Holding::TokenA, 160.0, 148.0));
+    }
+
+    #[test]
+    fn waits_for_the_trade_in_flight() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 2_000_000),
+            (&sberry, 100_000),
+        ]))
+        .unwrap();
+
+        // Holding SBERRY; the price rises through sell_price and keeps going
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim
+            .run([
+                (1, pool(170)),
+                (2, pool(185)),
+                (3, pool(190)),
+                (4, pool(175)),
+            ])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 100_000);
+        assert_eq!(min_received.2, 18_000_000);
+    }
+
+    #[test]
+    fn flips_only_once_the_trade_is_delivered() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 2_000_000), (&sberry, 100_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        assert_eq!(sim.run([(1, pool(170)), (2, pool(185))]).unwrap().len(), 1);
+
+        // The order is paid back untouched, so the sell is retried
+        sim.observe_tx(order.mock_execution(3, &[(&ada, 2_000_000), (&sberry, 100_000)]))
+            .unwrap();
+        let order = sundae_strategies::managed_strategies().unwrap().remove(0);
+        assert_eq!(sim.run([(4, pool(190))]).unwrap().len(), 1);
+
+        // Once it's delivered, the strategy holds ADA and buys on the way back down
+        sim.observe_tx(order.mock_execution(5, &[(&ada, 20_000_000)]))
+            .unwrap();
+        let executions = sim.run([(6, pool(170)), (7, pool(148))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { offer, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 20_000_000);
+    }
+}
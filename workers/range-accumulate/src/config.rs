@@ -0,0 +1,82 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token accumulated near the bottom of the channel
+    pub target_token: AssetId,
+    /// The token accumulated near the top of the channel, and the one prices are quoted in
+    pub base_token: AssetId,
+    /// The bottom of the channel, in raw base_token per raw target_token
+    pub low: f64,
+    /// The top of the channel, in raw base_token per raw target_token
+    pub high: f64,
+    /// How many equal steps the channel is divided into
+    pub steps: u64,
+    /// How much target_token is bought or sold at each line
+    pub clip_size: u64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    target_token: AssetId,
+    base_token: AssetId,
+    low: f64,
+    high: f64,
+    steps: u64,
+    clip_size: u64,
+}
+
+impl Config {
+    /// The channel's lines, from `low` to `high` inclusive, `steps` apart.
+    pub fn lines(&self) -> Vec<f64> {
+        let step = (self.high - self.low) / self.steps as f64;
+        (0..=self.steps)
+            .map(|i| self.low + step * i as f64)
+            .collect()
+    }
+
+    /// Whether `price` is within the channel.
+    pub fn contains(&self, price: f64) -> bool {
+        (self.low..=self.high).contains(&price)
+    }
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.low <= 0.0 {
+            return Err(format!("low must be > 0.0, got {}", raw.low));
+        }
+        if raw.high <= raw.low {
+            return Err(format!(
+                "high ({}) must be above low ({})",
+                raw.high, raw.low
+            ));
+        }
+        if raw.steps == 0 {
+            return Err("steps must be > 0".to_string());
+        }
+        if raw.clip_size == 0 {
+            return Err("clip_size must be > 0".to_string());
+        }
+        if raw.target_token == raw.base_token {
+            return Err("target_token and base_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            target_token: raw.target_token,
+            base_token: raw.base_token,
+            low: raw.low,
+            high: raw.high,
+            steps: raw.steps,
+            clip_size: raw.clip_size,
+        })
+    }
+}
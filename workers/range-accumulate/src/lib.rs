@@ -0,0 +1,389 @@
+//! # Range Accumulation Strategy
+//!
+//! This strategy trades a fixed price channel chosen by the user, buying the dips
+//! and selling the rips between `low` and `high`, so it accumulates `target_token`
+//! near the bottom of the channel and `base_token` near the top.
+//!
+//! ## How It Works
+//!
+//! The channel is divided into `steps` equal steps, with a line at `low`, at `high`,
+//! and at each step between. Unlike the symmetrical grid, the lines are anchored to
+//! these absolute bounds rather than a discovered center, and there is no
+//! recentering or volatility spacing. Using the same crossing detection as the grid:
+//!
+//! - **Price falls through lines**: the strategy buys `clip_size` of `target_token`
+//!   per line, paying no more than each line's price in `base_token`.
+//! - **Price rises through lines**: the strategy sells `clip_size` of `target_token`
+//!   per line, accepting no less than each line's price.
+//!
+//! Each side is limited by the balance the order holds. The strategy's position in
+//! the channel and what it has accumulated on each side are persisted per strategy
+//! authorization. While the price is outside the channel the strategy does nothing,
+//! and it picks up from its last position once the price returns. Only one trade is
+//! in flight per order at a time, and it only moves the strategy's position, at the
+//! amounts it actually traded, once the transaction executing it is seen. A trade that
+//! lapses unfilled leaves the position where it was, so the lines it crossed are traded
+//! again.
+//!
+//! ## Example
+//!
+//! With `low = 150`, `high = 200`, `steps = 5` and `clip_size = 1000`, the lines are
+//! at 150, 160, 170, 180, 190 and 200:
+//!
+//! 1. The price falls from 185 to 165, through 180 and 170: the strategy buys 2000
+//! 2. The price rises to 195, through 170, 180 and 190: the strategy sells 3000
+//! 3. The price rises to 210, out of the channel: nothing happens
+//!
+//! ## Configuration
+//!
+//! - `target_token`: The token bought low and sold high
+//! - `base_token`: The token it is priced in
+//! - `low`, `high`: The channel, in raw `base_token` per raw `target_token`
+//! - `steps`: How many equal steps the channel is divided into
+//! - `clip_size`: How much `target_token` is bought or sold at each line
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, ManagedStrategy, PoolState, Strategy, grid, kv,
+    types::{AssetId, Order, min_received},
+};
+use tracing::info;
+
+/// A strategy's place in the channel and what it has accumulated, persisted per strategy
+/// authorization
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RangeState {
+    /// How many lines lay below the last price observed inside the channel, once there is one
+    line_index: Option<usize>,
+    /// target_token bought on the way down
+    target_accumulated: u64,
+    /// base_token received selling on the way up
+    base_accumulated: u64,
+}
+
+fn range_states() -> kv::StrategyState<RangeState> {
+    kv::StrategyState::per_authorization("range_state")
+}
+
+/// A trade in flight, and the line it leaves the strategy at once it fills
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingTrade {
+    line_index: usize,
+    selling: bool,
+}
+
+/// Orders with a trade in flight; entries expire with the submitted validity window
+fn pending_trades() -> kv::Namespace<PendingTrade> {
+    kv::Namespace::new("pending_trade")
+}
+
+/// The price of target_token in raw base_token, or None if the pool is empty.
+fn target_price(config: &StrategyConfig, pool_state: &PoolState) -> Option<f64> {
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let price = if config.base_token == pool_state.pool_datum.assets.0 {
+        raw_price.value()
+    } else {
+        raw_price.invert().value()
+    };
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+/// The total (offer, minimum receive) for trading a clip at each of the `crossed` lines,
+/// nearest first: selling target_token if `selling`, otherwise buying it with base_token.
+/// Stops at the first clip the `available` balance of the offered token can't cover.
+fn fill_amounts(
+    config: &StrategyConfig,
+    crossed: &[f64],
+    selling: bool,
+    available: u64,
+) -> Option<(u64, u64)> {
+    let (mut offer, mut receive) = (0u64, 0u64);
+    for line in crossed {
+        let (clip_offer, clip_receive) = if selling {
+            (config.clip_size, min_received(config.clip_size, *line, 0.0))
+        } else {
+            (
+                (config.clip_size as f64 * line).ceil() as u64,
+                config.clip_size,
+            )
+        };
+        if offer.saturating_add(clip_offer) > available {
+            break;
+        }
+        offer += clip_offer;
+        receive += clip_receive;
+    }
+    (offer > 0).then_some((offer, receive))
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let Some(price) = target_price(config, pool_state) else {
+        info!("pool has no usable price, skipping this observation");
+        return Ok(Ack);
+    };
+    if !config.contains(price) {
+        info!(
+            "price {price} is outside the channel [{}, {}], waiting for it to return",
+            config.low, config.high
+        );
+        return Ok(Ack);
+    }
+    let lines = config.lines();
+    let range_states = range_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.target_token, &config.base_token) {
+            continue;
+        }
+        if pending_trades().get(&strategy.output)?.is_some() {
+            continue;
+        }
+
+        let mut state = range_states.load(strategy)?.unwrap_or_default();
+        let Some(previous_index) = state.line_index else {
+            state.line_index = Some(grid::line_index(&lines, price));
+            info!(
+                "strategy {:?}: entering the channel at {price}",
+                strategy.output
+            );
+            range_states.store(strategy, &state)?;
+            continue;
+        };
+
+        let (new_index, crossed) = grid::crossed_lines(&lines, previous_index, price);
+        if crossed.is_empty() {
+            continue;
+        }
+        let selling = new_index > previous_index;
+        let (offer_token, receive_token): (&AssetId, &AssetId) = if selling {
+            (&config.target_token, &config.base_token)
+        } else {
+            (&config.base_token, &config.target_token)
+        };
+
+        let Some((offer, receive)) =
+            fill_amounts(config, &crossed, selling, strategy.balance(offer_token))
+        else {
+            info!(
+                "strategy {:?}: crossed {crossed:?} but holds too little {} to trade",
+                strategy.output,
+                offer_token.name_to_string()
+            );
+            state.line_index = Some(new_index);
+            range_states.store(strategy, &state)?;
+            continue;
+        };
+        // The line prices ignore the pool's fee, so don't ask for more than it delivers
+        let receive = pool_state
+            .min_received_after_fees(offer_token, offer, 0.0)
+            .map_or(receive, |after_fees| receive.min(after_fees));
+
+        info!(
+            "strategy {:?}: crossed {crossed:?}, swapping {offer} {} for min {receive} {}",
+            strategy.output,
+            offer_token.name_to_string(),
+            receive_token.name_to_string()
+        );
+        let swap = Order::swap((offer_token, offer), (receive_token, receive));
+        let validity_range =
+            pool_state.get_validity_range(&config.network, DEFAULT_VALIDITY_WINDOW_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+        let pending = PendingTrade {
+            line_index: new_index,
+            selling,
+        };
+        pending_trades().set_with_ttl(&strategy.output, &pending, DEFAULT_VALIDITY_WINDOW_SECS)?;
+    }
+
+    Ok(Ack)
+}
+
+/// Move the strategy to the line its trade in flight was left at, and book what the trade
+/// actually exchanged, if the transaction spending `strategy` delivered it.
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let Some(pending) = pending_trades().get(&strategy.output)? else {
+        return Ok(Ack);
+    };
+    pending_trades().delete(&strategy.output)?;
+    let (offer_token, receive_token) = if pending.selling {
+        (&config.target_token, &config.base_token)
+    } else {
+        (&config.base_token, &config.target_token)
+    };
+    let Some(fill) = strategy.swap_fill(tx, offer_token, receive_token) else {
+        info!(
+            "strategy {:?} was spent without its trade, which will be retried",
+            strategy.output
+        );
+        return Ok(Ack);
+    };
+
+    let range_states = range_states();
+    let mut state = range_states.load(strategy)?.unwrap_or_default();
+    state.line_index = Some(pending.line_index);
+    if pending.selling {
+        state.base_accumulated += fill.received;
+        state.target_accumulated = state.target_accumulated.saturating_sub(fill.sold);
+    } else {
+        state.target_accumulated += fill.received;
+        state.base_accumulated = state.base_accumulated.saturating_sub(fill.sold);
+    }
+    info!(
+        "strategy {:?}: traded {} {} for {} {}, now at line {}",
+        strategy.output,
+        fill.sold,
+        offer_token.name_to_string(),
+        fill.received,
+        receive_token.name_to_string(),
+        pending.line_index
+    );
+    range_states.store(strategy, &state)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::sim::Simulator;
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "target_token": "99999999999999999999999999999999999999999999999999999999534245525259",
+            "base_token": ".",
+            "low": 150.0,
+            "high": 200.0,
+            "steps": 5,
+            "clip_size": 1000,
+        })
+    }
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(config_json()).unwrap()
+    }
+
+    #[test]
+    fn lines_span_the_channel() {
+        assert_eq!(
+            config().lines(),
+            vec![150.0, 160.0, 170.0, 180.0, 190.0, 200.0]
+        );
+
+        let mut json = config_json();
+        json["high"] = 150.0.into();
+        assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+    }
+
+    #[test]
+    fn clips_are_limited_by_the_balance() {
+        let config = config();
+        // Buying at 180 then 170
+        assert_eq!(
+            fill_amounts(&config, &[180.0, 170.0], false, 1_000_000),
+            Some((350_000, 2_000))
+        );
+        assert_eq!(
+            fill_amounts(&config, &[180.0, 170.0], false, 200_000),
+            Some((180_000, 1_000))
+        );
+        // Selling at 170, 180 and 190 with only two clips held
+        assert_eq!(
+            fill_amounts(&config, &[170.0, 180.0, 190.0], true, 2_500),
+            Some((2_000, 350_000))
+        );
+        assert_eq!(fill_amounts(&config, &[170.0], true, 999), None);
+    }
+
+    #[test]
+    fn buys_dips_and_ignores_prices_outside_the_channel() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&ada, 1_000_000)]))
+            .unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim
+            .run([
+                (1, pool(185)),
+                (2, pool(210)),
+                (3, pool(140)),
+                (4, pool(165)),
+            ])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        // A clip at each of the 180 and 170 lines
+        assert_eq!(offer.2, 350_000);
+        assert_eq!(min_received.2, 2_000);
+    }
+
+    #[test]
+    fn trades_move_the_strategy_once_they_fill() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 1_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim.run([(1, pool(185)), (2, pool(165))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let entered = range_states().load(&order).unwrap().unwrap();
+        assert_eq!(entered.line_index, Some(4));
+        assert_eq!(entered.target_accumulated, 0);
+
+        // The buy lapses unfilled, so the same lines are bought again
+        let executions = sim.run([(2_000, pool(165))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { offer, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 350_000);
+
+        // It fills, for a little more SBERRY than the minimum
+        sim.observe_tx(order.mock_execution(2_001, &[(&ada, 650_000), (&sberry, 2_010)]))
+            .unwrap();
+        let state = range_states().load(&order).unwrap().unwrap();
+        assert_eq!(state.line_index, Some(2));
+        assert_eq!(state.target_accumulated, 2_010);
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance (3%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+/// Default minimum time between rebalances (1 hour)
+const DEFAULT_COOLDOWN_SECS: u64 = 60 * 60;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    // Tokens must be in alphanumeric order with token_a < token_b when sorted
+    pub token_a: AssetId,
+    pub token_a_decimals: u8,
+    pub token_b: AssetId,
+    pub token_b_decimals: u8,
+    /// The fraction of the portfolio's value to hold in token_a (e.g., 0.5 for a 50/50 split)
+    /// Must be in range [0.0, 1.0]; the rest is held in token_b.
+    pub target_ratio: f64,
+    /// How far token_a's share of the value may drift from target_ratio before rebalancing
+    /// (e.g., 0.05 rebalances a 50/50 portfolio once it reaches 55/45)
+    pub rebalance_threshold: f64,
+    /// Minimum number of seconds between rebalances. Defaults to 1 hour.
+    pub cooldown_secs: u64,
+    /// Maximum acceptable slippage on each rebalance (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_a_decimals: u8,
+    token_b: AssetId,
+    token_b_decimals: u8,
+    target_ratio: f64,
+    rebalance_threshold: f64,
+    cooldown_secs: Option<u64>,
+    slippage_tolerance: Option<f64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if !(0.0..=1.0).contains(&raw.target_ratio) {
+            return Err(format!(
+                "target_ratio must be in [0.0, 1.0], got {}",
+                raw.target_ratio
+            ));
+        }
+        if raw.rebalance_threshold <= 0.0 || raw.rebalance_threshold >= 1.0 {
+            return Err(format!(
+                "rebalance_threshold must be in (0.0, 1.0), got {}",
+                raw.rebalance_threshold
+            ));
+        }
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 || slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be in (0.0, 1.0), got {}",
+                slippage_tolerance
+            ));
+        }
+
+        if raw.token_a == raw.token_b {
+            return Err("token_a and token_b must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_a_decimals: raw.token_a_decimals,
+            token_b: raw.token_b,
+            token_b_decimals: raw.token_b_decimals,
+            target_ratio: raw.target_ratio,
+            rebalance_threshold: raw.rebalance_threshold,
+            cooldown_secs: raw.cooldown_secs.unwrap_or(DEFAULT_COOLDOWN_SECS),
+            slippage_tolerance,
+        })
+    }
+}
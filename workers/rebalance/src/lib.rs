@@ -0,0 +1,299 @@
+//! # Portfolio Rebalancing Strategy
+//!
+//! This strategy holds both tokens of a pair in the strategy UTxO and keeps the
+//! share of its value held in `token_a` close to `target_ratio`.
+//!
+//! ## How It Works
+//!
+//! On each observation of the pool for the pair, both holdings are valued in
+//! `token_a` at the current decimal-adjusted pool price. If `token_a`'s share of
+//! the total has drifted more than `rebalance_threshold` from `target_ratio`, the
+//! strategy sells the overweight token, sized to bring the split back to target.
+//! The size accounts for the pool's fee and the sale's price impact, using what the
+//! pool would actually deliver for it, so the split lands on target rather than short
+//! of it by the fee.
+//!
+//! The slot of the last rebalance is stored per strategy authorization, and no
+//! further rebalance happens until `cooldown_secs` have passed, so a price that
+//! oscillates around the threshold doesn't churn the portfolio.
+//!
+//! A sale that would leave less than 1% of the sold holding behind sells the whole
+//! holding instead, so one side isn't left with dust (e.g. a `target_ratio` of 0).
+//!
+//! ## Example
+//!
+//! With `target_ratio = 0.5`, `rebalance_threshold = 0.05`, and a price of 2 `token_a` per `token_b`:
+//!
+//! 1. Holding 120 A and 40 B (worth 80 A): A's share is 60%, past 55%
+//! 2. The strategy sells ~20 A (10% of the 200 A total), leaving ~100 A and ~50 B. In a
+//!    pool with a 1% fee, it sells ~20.1 A instead, since the 20 A would only buy 49.9 B
+//!
+//! ## Configuration
+//!
+//! - `token_a` / `token_b`: The pool pair, with their decimals
+//! - `target_ratio`: The share of value to hold in `token_a` (0.5 = 50/50)
+//! - `rebalance_threshold`: How far the share may drift before rebalancing (0.05 = 5 points)
+//! - `cooldown_secs`: Minimum time between rebalances (default 1 hour)
+//! - `slippage_tolerance`: Maximum acceptable slippage per rebalance (0.03 = 3%)
+
+mod config;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::Config as StrategyConfig;
+use sundae_strategies::{ManagedStrategy, PoolState, Strategy, kv, types::Order};
+use tracing::info;
+
+/// A sale that would leave less than this fraction of the holding sells all of it instead
+const DUST_FRACTION: f64 = 0.01;
+
+/// The sale needed to restore the target ratio, in raw units of the token sold
+#[derive(Debug, PartialEq)]
+enum Rebalance {
+    SellA(u64),
+    SellB(u64),
+}
+
+/// The slot of each strategy's most recent rebalance
+fn last_rebalance_slots() -> kv::StrategyState<u64> {
+    kv::StrategyState::per_authorization("last_rebalance_slot")
+}
+
+/// token_a's share of the value of `amount_a` and `amount_b`, or None if they're worthless.
+///
+/// `price` is the decimal-adjusted price of token_b in token_a.
+fn share_a(config: &StrategyConfig, amount_a: u64, amount_b: u64, price: f64) -> Option<f64> {
+    let value_a = amount_a as f64 / 10f64.powi(config.token_a_decimals as i32);
+    let value_b = amount_b as f64 / 10f64.powi(config.token_b_decimals as i32) * price;
+    let total = value_a + value_b;
+    (total > 0.0 && price > 0.0).then(|| value_a / total)
+}
+
+/// Work out whether, and how much, to sell to restore the target ratio.
+///
+/// `price` is the decimal-adjusted price of token_b in token_a. The sale is sized by what
+/// `pool_state` would actually deliver for it, after its fee and price impact, so the
+/// holdings left, valued at `price`, land on the target rather than short of it.
+fn rebalance(
+    config: &StrategyConfig,
+    pool_state: &PoolState,
+    amount_a: u64,
+    amount_b: u64,
+    price: f64,
+) -> Option<Rebalance> {
+    let drift = share_a(config, amount_a, amount_b, price)? - config.target_ratio;
+    if drift.abs() <= config.rebalance_threshold {
+        return None;
+    }
+
+    if drift > 0.0 {
+        let offer = sale_size(amount_a, |offer| {
+            pool_state
+                .expected_output(&config.token_a, offer)
+                .and_then(|received| {
+                    share_a(
+                        config,
+                        amount_a - offer,
+                        amount_b.saturating_add(received),
+                        price,
+                    )
+                })
+                .is_some_and(|share| share >= config.target_ratio)
+        });
+        Some(Rebalance::SellA(without_dust(offer, amount_a)))
+    } else {
+        let offer = sale_size(amount_b, |offer| {
+            pool_state
+                .expected_output(&config.token_b, offer)
+                .and_then(|received| {
+                    share_a(
+                        config,
+                        amount_a.saturating_add(received),
+                        amount_b - offer,
+                        price,
+                    )
+                })
+                .is_some_and(|share| share <= config.target_ratio)
+        });
+        Some(Rebalance::SellB(without_dust(offer, amount_b)))
+    }
+}
+
+/// The largest sale, up to `holding`, that doesn't carry the holdings past the target.
+///
+/// Found by bisection, since the share moves steadily towards the target, and then past
+/// it, as more is sold.
+fn sale_size(holding: u64, within_target: impl Fn(u64) -> bool) -> u64 {
+    let (mut low, mut high) = (0, holding);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if within_target(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+/// Cap `offer` at the holding, and round it up to the whole holding if the rest would be dust.
+fn without_dust(offer: u64, holding: u64) -> u64 {
+    let offer = offer.min(holding);
+    if ((holding - offer) as f64) < holding as f64 * DUST_FRACTION {
+        holding
+    } else {
+        offer
+    }
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let price = pool_state
+        .price(config.token_a_decimals, config.token_b_decimals)
+        .value();
+    let last_rebalance_slots = last_rebalance_slots();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+
+        if let Some(last) = last_rebalance_slots.load(strategy)?
+            && pool_state.slot.saturating_sub(last) < config.cooldown_secs
+        {
+            continue;
+        }
+
+        let (amount_a, amount_b) = strategy.balances(&config.token_a, &config.token_b);
+        let (offer, receive, offer_amount) =
+            match rebalance(config, pool_state, amount_a, amount_b, price) {
+                None => continue,
+                Some(Rebalance::SellA(amount)) => (&config.token_a, &config.token_b, amount),
+                Some(Rebalance::SellB(amount)) => (&config.token_b, &config.token_a, amount),
+            };
+        if offer_amount == 0 {
+            continue;
+        }
+
+        let Some(expected) = pool_state.expected_output(offer, offer_amount) else {
+            info!("pool has no usable price, skipping this observation");
+            continue;
+        };
+        let min_received =
+            ((expected as f64 * (1.0 - config.slippage_tolerance)).floor() as u64).max(1);
+
+        info!(
+            "strategy {:?}: rebalancing {amount_a} {} / {amount_b} {} at price {price}, selling {offer_amount} {} for min {min_received} {}",
+            strategy.output,
+            config.token_a.name_to_string(),
+            config.token_b.name_to_string(),
+            offer.name_to_string(),
+            receive.name_to_string(),
+        );
+
+        let swap = Order::swap((offer, offer_amount), (receive, min_received));
+        let validity_range = pool_state.get_validity_range(&config.network, 20 * 60);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        last_rebalance_slots.store(strategy, &pool_state.slot)?;
+    }
+
+    Ok(Ack)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(target_ratio: f64) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_a_decimals": 0,
+            "token_b": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "token_b_decimals": 0,
+            "target_ratio": target_ratio,
+            "rebalance_threshold": 0.05,
+        }))
+        .unwrap()
+    }
+
+    /// A pool deep enough for the sales under test to barely move, at 2 token_a per token_b
+    fn pool(config: &StrategyConfig) -> PoolState {
+        PoolState::mock(
+            2_000_000_000_000,
+            1_000_000_000_000,
+            (&config.token_a, &config.token_b),
+        )
+    }
+
+    #[test]
+    fn within_threshold_does_nothing() {
+        let config = config(0.5);
+        assert_eq!(rebalance(&config, &pool(&config), 104, 48, 2.0), None);
+    }
+
+    #[test]
+    fn sells_the_overweight_side_back_to_target() {
+        let config = config(0.5);
+        let pool = pool(&config);
+        assert_eq!(
+            rebalance(&config, &pool, 120_000, 40_000, 2.0),
+            Some(Rebalance::SellA(20_000))
+        );
+        assert_eq!(
+            rebalance(&config, &pool, 40_000, 60_000, 2.0),
+            Some(Rebalance::SellB(20_000))
+        );
+    }
+
+    #[test]
+    fn sizing_accounts_for_the_pool_fee() {
+        let config = config(0.5);
+        let pool = pool(&config).with_swap_fee(100);
+        let share_after_selling = |offer: u64| {
+            let received = pool.expected_output(&config.token_a, offer).unwrap();
+            share_a(&config, 120_000 - offer, 40_000 + received, 2.0).unwrap()
+        };
+
+        // Ignoring the fee, selling 20,000 token_a looks like enough, but the 1% fee keeps
+        // 200 of it, leaving 100,000 token_a against only 99,800 worth of token_b
+        let naive_miss = share_after_selling(20_000) - 0.5;
+        assert!((naive_miss - 100.0 / 199_800.0).abs() < 1e-5);
+
+        let Some(Rebalance::SellA(offer)) = rebalance(&config, &pool, 120_000, 40_000, 2.0) else {
+            panic!("expected a sale of token_a");
+        };
+        assert!(offer > 20_000);
+        assert!((share_after_selling(offer) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn does_not_leave_dust() {
+        let config = config(0.0);
+        assert_eq!(
+            rebalance(&config, &pool(&config), 1000, 0, 2.0),
+            Some(Rebalance::SellA(1000))
+        );
+        assert_eq!(without_dust(995, 1000), 1000);
+        assert_eq!(without_dust(900, 1000), 900);
+        assert_eq!(without_dust(2000, 1000), 1000);
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance (3%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+/// The longest RSI period allowed, which bounds the price history kept in KV
+pub const MAX_PERIOD: usize = 500;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token prices are quoted in, and spent on buys
+    pub token_a: AssetId,
+    /// The token bought when oversold and sold when overbought
+    pub token_b: AssetId,
+    /// Number of price changes the RSI averages over
+    pub rsi_period: usize,
+    /// Buy when the RSI falls below this; must be in (0, overbought)
+    pub oversold: f64,
+    /// Sell when the RSI rises above this; must be in (oversold, 100)
+    pub overbought: f64,
+    /// The value of each trade, in raw units of token_a
+    pub trade_size: u64,
+    /// Maximum acceptable slippage on each trade (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_b: AssetId,
+    rsi_period: usize,
+    oversold: f64,
+    overbought: f64,
+    trade_size: u64,
+    slippage_tolerance: Option<f64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.rsi_period == 0 {
+            return Err("rsi_period must be > 0".to_string());
+        }
+        if raw.rsi_period > MAX_PERIOD {
+            return Err(format!(
+                "rsi_period must be at most {MAX_PERIOD}, got {}",
+                raw.rsi_period
+            ));
+        }
+        if !(raw.oversold > 0.0 && raw.oversold < raw.overbought && raw.overbought < 100.0) {
+            return Err(format!(
+                "need 0 < oversold < overbought < 100, got oversold {} and overbought {}",
+                raw.oversold, raw.overbought
+            ));
+        }
+        if raw.trade_size == 0 {
+            return Err("trade_size must be > 0".to_string());
+        }
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 || slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be in (0.0, 1.0), got {}",
+                slippage_tolerance
+            ));
+        }
+
+        if raw.token_a == raw.token_b {
+            return Err("token_a and token_b must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_b: raw.token_b,
+            rsi_period: raw.rsi_period,
+            oversold: raw.oversold,
+            overbought: raw.overbought,
+            trade_size: raw.trade_size,
+            slippage_tolerance,
+        })
+    }
+}
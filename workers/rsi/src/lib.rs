@@ -0,0 +1,309 @@
+//! # RSI Strategy
+//!
+//! This strategy trades `token_b` against `token_a` on the Relative Strength Index of
+//! the pool price, buying when the market is oversold and selling when it's overbought.
+//!
+//! ## How It Works
+//!
+//! Every observation of a pool appends its price (`token_a` per `token_b`) to a
+//! [`PriceHistory`] kept in KV per pool, holding just enough prices for one RSI window.
+//! Once it holds `rsi_period + 1` prices, the strategy computes the RSI over the last
+//! `rsi_period` price changes and classifies it:
+//!
+//! - **Below `oversold`**: buy `token_b`, spending `trade_size` of `token_a`
+//! - **Above `overbought`**: sell `token_b` worth `trade_size` of `token_a`
+//!
+//! Only the crossing into a zone trades. The zone of the last RSI is stored alongside
+//! the history, and nothing happens while the RSI stays in it; the RSI has to leave
+//! and re-enter the zone to trade again. The zone the first RSI lands in is recorded
+//! without trading, since the crossing into it wasn't observed.
+//!
+//! ## Example
+//!
+//! With `rsi_period = 2`, `oversold = 30` and `overbought = 70`:
+//!
+//! 1. Prices 100 → 101 → 102: the RSI is 100, but it's the first one, so nothing trades
+//! 2. Price 101: one gain and one loss give an RSI of 50, between the zones
+//! 3. Price 100: two losses give an RSI of 0, crossing into oversold: buy
+//! 4. Price 99: still oversold, nothing trades
+//! 5. Prices 100 → 101: two gains give an RSI of 100, crossing into overbought: sell
+//!
+//! ## Configuration
+//!
+//! - `token_a` / `token_b`: The pool pair; `token_b` is the token traded
+//! - `rsi_period`: Price changes the RSI averages over (at most 500)
+//! - `oversold` / `overbought`: The RSI thresholds, with 0 < oversold < overbought < 100
+//! - `trade_size`: Value of each trade, in raw units of `token_a`
+//! - `slippage_tolerance`: Maximum acceptable slippage per trade (0.03 = 3%)
+
+mod config;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, ManagedStrategy, PoolState, Strategy, history::PriceHistory, kv,
+    types::Order,
+};
+use tracing::info;
+
+/// Where an RSI value sits relative to the thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Zone {
+    Oversold,
+    Neutral,
+    Overbought,
+}
+
+impl Zone {
+    fn of(config: &StrategyConfig, rsi: f64) -> Self {
+        if rsi < config.oversold {
+            Zone::Oversold
+        } else if rsi > config.overbought {
+            Zone::Overbought
+        } else {
+            Zone::Neutral
+        }
+    }
+}
+
+/// A crossing of the RSI into one of the zones
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Signal {
+    Buy,
+    Sell,
+}
+
+/// Recent prices for a pool and the zone of the last RSI, persisted per pool identifier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RsiState {
+    history: PriceHistory,
+    zone: Option<Zone>,
+}
+
+impl RsiState {
+    fn new(config: &StrategyConfig) -> Self {
+        // One more price than the period, to measure `rsi_period` changes
+        Self {
+            history: PriceHistory::new(config.rsi_period + 1),
+            zone: None,
+        }
+    }
+
+    /// Record a new price and report whether the RSI crossed into a zone because of it.
+    fn observe(&mut self, config: &StrategyConfig, slot: u64, price: f64) -> Option<Signal> {
+        self.history.push(slot, price);
+        let rsi = self.history.rsi(config.rsi_period)?;
+
+        let zone = Zone::of(config, rsi);
+        let previous = self.zone.replace(zone);
+        match (previous, zone) {
+            (Some(previous), zone) if previous == zone => None,
+            (Some(_), Zone::Oversold) => Some(Signal::Buy),
+            (Some(_), Zone::Overbought) => Some(Signal::Sell),
+            _ => None,
+        }
+    }
+}
+
+fn rsi_states() -> kv::Namespace<RsiState> {
+    kv::Namespace::new("rsi_state")
+}
+
+/// The price of token_b in raw token_a, or None if the pool is empty.
+fn token_b_price(config: &StrategyConfig, pool_state: &PoolState) -> Option<f64> {
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let price = if config.token_a == pool_state.pool_datum.assets.0 {
+        raw_price.value()
+    } else {
+        raw_price.invert().value()
+    };
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let (asset_a, asset_b) = &pool_state.pool_datum.assets;
+    let pair_matches = (config.token_a == *asset_a && config.token_b == *asset_b)
+        || (config.token_a == *asset_b && config.token_b == *asset_a);
+    if !pair_matches {
+        return Ok(Ack);
+    }
+    let Some(price) = token_b_price(config, pool_state) else {
+        info!("pool has no usable price, skipping this observation");
+        return Ok(Ack);
+    };
+
+    let pool_id = hex::encode(&pool_state.pool_datum.identifier);
+    let mut state = rsi_states()
+        .get(pool_id.as_str())?
+        .unwrap_or_else(|| RsiState::new(config));
+    let signal = state.observe(config, pool_state.slot, price);
+    rsi_states().set(pool_id.as_str(), &state)?;
+
+    let Some(signal) = signal else {
+        return Ok(Ack);
+    };
+    info!(
+        "RSI crossed into {:?} at price {price}: {:?}",
+        state.zone, signal
+    );
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+
+        let (offer, receive, offer_amount) = match signal {
+            Signal::Buy => (&config.token_a, &config.token_b, config.trade_size),
+            Signal::Sell => (
+                &config.token_b,
+                &config.token_a,
+                (config.trade_size as f64 / price) as u64,
+            ),
+        };
+        let offer_amount = offer_amount.min(strategy.balance(offer));
+        if offer_amount == 0 {
+            info!(
+                "strategy {:?}: no {} to trade",
+                strategy.output,
+                offer.name_to_string()
+            );
+            continue;
+        }
+
+        let Some(min_received) =
+            pool_state.min_received_after_fees(offer, offer_amount, config.slippage_tolerance)
+        else {
+            continue;
+        };
+
+        let swap = Order::swap((offer, offer_amount), (receive, min_received));
+        let validity_range =
+            pool_state.get_validity_range(&config.network, DEFAULT_VALIDITY_WINDOW_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+        info!(
+            "strategy {:?}: swapping {offer_amount} {} for min {min_received} {}",
+            strategy.output,
+            offer.name_to_string(),
+            receive.name_to_string()
+        );
+    }
+
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new().on_new_pool_state(on_new_pool_state)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_b": "99999999999999999999999999999999999999999999999999999999534245525259",
+            "rsi_period": 2,
+            "oversold": 30.0,
+            "overbought": 70.0,
+            "trade_size": 1_000_000,
+        })
+    }
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(config_json()).unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_thresholds() {
+        for (oversold, overbought) in [(0.0, 70.0), (70.0, 30.0), (50.0, 50.0), (30.0, 100.0)] {
+            let mut json = config_json();
+            json["oversold"] = oversold.into();
+            json["overbought"] = overbought.into();
+            assert!(
+                serde_json::from_value::<StrategyConfig>(json).is_err(),
+                "{oversold}/{overbought} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn trades_once_per_crossing_into_a_zone() {
+        let config = config();
+        let mut state = RsiState::new(&config);
+        let signals: Vec<_> = [100.0, 101.0, 102.0, 101.0, 100.0, 99.0, 100.0, 101.0, 102.0]
+            .into_iter()
+            .enumerate()
+            .map(|(slot, price)| state.observe(&config, slot as u64, price))
+            .collect();
+        assert_eq!(
+            signals,
+            [
+                None,
+                None,
+                // The first RSI is overbought; that's a state, not a crossing
+                None,
+                None,
+                Some(Signal::Buy),
+                None,
+                None,
+                Some(Signal::Sell),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn buys_oversold_and_sells_overbought() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 10_000_000),
+            (&sberry, 100_000),
+        ]))
+        .unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim
+            .run(
+                [100, 101, 102, 101, 100, 99, 100, 101]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slot, price)| (slot as u64 + 1, pool(price))),
+            )
+            .unwrap();
+
+        let offers: Vec<_> = executions
+            .iter()
+            .map(|execution| {
+                let Order::Swap { offer, .. } = &execution.details else {
+                    panic!("expected a swap");
+                };
+                (offer.0.clone(), offer.2)
+            })
+            .collect();
+        // Buy with trade_size of ADA, then sell trade_size worth of SBERRY at 101
+        assert_eq!(offers, [(vec![], 1_000_000), (vec![0x99; 28], 9_900)]);
+    }
+}
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token accumulated
+    pub buy_token: AssetId,
+    /// The token spent to buy it
+    pub base_token: AssetId,
+    /// How far below the entry price each level sits, as a fraction of it, e.g. 0.1 for 10%.
+    /// Sorted nearest the entry first.
+    pub levels: Vec<f64>,
+    /// How much base_token is spent at each level
+    pub clip_size: u64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    buy_token: AssetId,
+    base_token: AssetId,
+    levels: Vec<f64>,
+    clip_size: u64,
+}
+
+impl Config {
+    /// The price of each level for an `entry_price`, in ascending order, so the deepest level
+    /// comes first.
+    pub fn level_prices(&self, entry_price: f64) -> Vec<f64> {
+        self.levels
+            .iter()
+            .rev()
+            .map(|offset| entry_price * (1.0 - offset))
+            .collect()
+    }
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.levels.is_empty() {
+            return Err("levels must not be empty".to_string());
+        }
+        if let Some(offset) = raw.levels.iter().find(|o| !(**o > 0.0 && **o < 1.0)) {
+            return Err(format!(
+                "each of levels must be between 0.0 and 1.0 exclusive, got {offset}"
+            ));
+        }
+        let mut levels = raw.levels;
+        levels.sort_by(f64::total_cmp);
+        if levels.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("levels must not repeat".to_string());
+        }
+        if raw.clip_size == 0 {
+            return Err("clip_size must be > 0".to_string());
+        }
+        if raw.buy_token == raw.base_token {
+            return Err("buy_token and base_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            buy_token: raw.buy_token,
+            base_token: raw.base_token,
+            levels,
+            clip_size: raw.clip_size,
+        })
+    }
+}
@@ -0,0 +1,360 @@
+//! # Scaled Entry Strategy
+//!
+//! This strategy builds a position on the way down, buying a fixed clip of
+//! `buy_token` at each of a ladder of prices below its entry, so it accumulates
+//! more the further the price falls.
+//!
+//! ## How It Works
+//!
+//! The price of `buy_token` in `base_token` when the strategy is first observed is
+//! its entry, and each of `levels` places a line that fraction below it. Using the
+//! same crossing detection as the symmetrical grid, but only ever acting on the
+//! way down, the strategy spends `clip_size` of `base_token` for each line the
+//! price falls through, accepting no less than the line's price. It never sells.
+//!
+//! Bought levels are persisted per strategy authorization, so a level is only ever
+//! bought once: if the price rebounds above it and falls through it again, nothing
+//! happens. While a buy is in flight no further buys are submitted; once its order
+//! UTxO is consumed (reported through `on_strategy_spent`) by a transaction paying the
+//! bought tokens back to the order, its levels count as bought. If the order is
+//! consumed without them, e.g. cancelled by its owner, or the buy's validity window
+//! lapses first, its levels are crossed again on the next observation at or below them.
+//!
+//! ## Example
+//!
+//! With an entry of 100, `levels = [0.1, 0.2, 0.3]` and `clip_size = 10 ADA`:
+//!
+//! 1. The price falls to 85, crossing the 90 line: the strategy spends 10 ADA
+//! 2. The price rebounds to 95, then falls back to 85: the 90 line is already bought
+//! 3. The price falls to 65, crossing the 80 and 70 lines: the strategy spends 20 ADA
+//!
+//! ## Configuration
+//!
+//! - `buy_token`: The token accumulated
+//! - `base_token`: The token spent to buy it
+//! - `levels`: How far below the entry each line sits, as fractions of the entry price
+//! - `clip_size`: How much `base_token` is spent at each level
+
+mod config;
+
+use std::collections::BTreeSet;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, EventTime, ManagedStrategy, PoolState, Strategy, grid, kv,
+    types::{Order, min_received},
+};
+use tracing::info;
+
+/// A buy submitted to the market and not yet consumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Buy {
+    /// The levels it buys, as indices into the level prices
+    levels: Vec<usize>,
+    /// UNIX time (ms) after which the buy can no longer fill
+    expires_ms: u64,
+}
+
+/// Progress of a scaled entry, persisted per strategy authorization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EntryState {
+    /// Raw base_token per raw buy_token when the strategy was first observed
+    entry_price: f64,
+    /// How many level prices lay below the last observed price
+    line_index: usize,
+    /// Levels bought, as indices into the level prices
+    filled: BTreeSet<usize>,
+    in_flight: Option<Buy>,
+}
+
+impl EntryState {
+    fn new(entry_price: f64, level_count: usize) -> Self {
+        EntryState {
+            entry_price,
+            // Every level sits below the entry
+            line_index: level_count,
+            filled: BTreeSet::new(),
+            in_flight: None,
+        }
+    }
+
+    /// Give up on `buy`, which didn't fill, so its levels are crossed again.
+    fn release(&mut self, buy: &Buy) {
+        let deepest = buy.levels.iter().max().copied().unwrap_or_default();
+        self.line_index = self.line_index.max(deepest + 1);
+    }
+}
+
+fn entry_states() -> kv::StrategyState<EntryState> {
+    kv::StrategyState::per_authorization("entry_state")
+}
+
+/// The price of buy_token in raw base_token, or None if the pool is empty.
+fn buy_token_price(config: &StrategyConfig, pool_state: &PoolState) -> Option<f64> {
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let price = if config.base_token == pool_state.pool_datum.assets.0 {
+        raw_price.value()
+    } else {
+        raw_price.invert().value()
+    };
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+/// Move `state` to `price`, returning the levels newly crossed on the way down that
+/// haven't been bought yet, nearest first.
+fn levels_to_buy(state: &mut EntryState, level_prices: &[f64], price: f64) -> Vec<usize> {
+    let previous_index = state.line_index;
+    let (new_index, crossed) = grid::crossed_lines(level_prices, previous_index, price);
+    state.line_index = new_index;
+    if new_index >= previous_index {
+        // Rising prices never trigger anything
+        return vec![];
+    }
+    info!("crossed levels {crossed:?} going down");
+    (new_index..previous_index)
+        .rev()
+        .filter(|level| !state.filled.contains(level))
+        .collect()
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let Some(price) = buy_token_price(config, pool_state) else {
+        info!("pool has no usable price, skipping this observation");
+        return Ok(Ack);
+    };
+    let now_ms = pool_state.now_ms(&config.network);
+    let entry_states = entry_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.buy_token, &config.base_token) {
+            continue;
+        }
+
+        let Some(mut state) = entry_states.load(strategy)? else {
+            info!(
+                "strategy {:?}: entering at {price} {} per {}",
+                strategy.output,
+                config.base_token.name_to_string(),
+                config.buy_token.name_to_string()
+            );
+            entry_states.store(strategy, &EntryState::new(price, config.levels.len()))?;
+            continue;
+        };
+        let level_prices = config.level_prices(state.entry_price);
+
+        // A buy whose validity window lapsed didn't fill
+        if let Some(buy) = state.in_flight.take_if(|buy| now_ms > buy.expires_ms) {
+            state.release(&buy);
+        }
+        if state.in_flight.is_some() {
+            continue;
+        }
+
+        let mut levels = levels_to_buy(&mut state, &level_prices, price);
+        let affordable = (strategy.balance(&config.base_token) / config.clip_size) as usize;
+        if levels.len() > affordable {
+            info!(
+                "strategy {:?}: can only afford {affordable} of {} levels",
+                strategy.output,
+                levels.len()
+            );
+            levels.truncate(affordable);
+        }
+        if levels.is_empty() {
+            entry_states.store(strategy, &state)?;
+            continue;
+        }
+
+        let offer = config.clip_size * levels.len() as u64;
+        let receive: u64 = levels
+            .iter()
+            .map(|level| min_received(config.clip_size, 1.0 / level_prices[*level], 0.0))
+            .sum();
+        info!(
+            "strategy {:?}: buying {} levels, spending {offer} {} for min {receive} {}",
+            strategy.output,
+            levels.len(),
+            config.base_token.name_to_string(),
+            config.buy_token.name_to_string()
+        );
+
+        let swap = Order::swap((&config.base_token, offer), (&config.buy_token, receive));
+        let validity_range =
+            pool_state.get_validity_range(&config.network, DEFAULT_VALIDITY_WINDOW_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        state.in_flight = Some(Buy {
+            levels,
+            expires_ms: now_ms.saturating_add(DEFAULT_VALIDITY_WINDOW_SECS * 1000),
+        });
+        entry_states.store(strategy, &state)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let entry_states = entry_states();
+    let Some(mut state) = entry_states.load(strategy)? else {
+        return Ok(Ack);
+    };
+    let Some(buy) = state.in_flight.take() else {
+        return Ok(Ack);
+    };
+    match strategy.swap_fill(tx, &config.base_token, &config.buy_token) {
+        Some(fill) => {
+            info!(
+                "strategy {:?}: bought levels {:?} for {} {}",
+                strategy.output,
+                buy.levels,
+                fill.received,
+                config.buy_token.name_to_string()
+            );
+            state.filled.extend(buy.levels);
+        }
+        None => {
+            info!(
+                "strategy {:?}: spent without buying levels {:?}, they'll be crossed again",
+                strategy.output, buy.levels
+            );
+            state.release(&buy);
+        }
+    }
+    entry_states.store(strategy, &state)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "buy_token": "99999999999999999999999999999999999999999999999999999999534245525259",
+            "base_token": ".",
+            "levels": [0.2, 0.1, 0.3],
+            "clip_size": 10_000_000,
+        })
+    }
+
+    #[test]
+    fn levels_are_sorted_and_validated() {
+        let config: StrategyConfig = serde_json::from_value(config_json()).unwrap();
+        assert_eq!(config.levels, vec![0.1, 0.2, 0.3]);
+        let prices = config.level_prices(100.0);
+        assert!((prices[0] - 70.0).abs() < 1e-9);
+        assert!((prices[2] - 90.0).abs() < 1e-9);
+
+        for levels in [vec![], vec![0.1, 0.1], vec![1.0], vec![-0.1]] {
+            let mut json = config_json();
+            json["levels"] = serde_json::json!(levels);
+            assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn a_rebound_and_redrop_does_not_rebuy() {
+        // The example from the module docs
+        let prices = [70.0, 80.0, 90.0];
+        let mut state = EntryState::new(100.0, prices.len());
+
+        assert_eq!(levels_to_buy(&mut state, &prices, 85.0), vec![2]);
+        state.filled.insert(2);
+
+        assert!(levels_to_buy(&mut state, &prices, 95.0).is_empty());
+        assert!(levels_to_buy(&mut state, &prices, 85.0).is_empty());
+        assert_eq!(levels_to_buy(&mut state, &prices, 65.0), vec![1, 0]);
+    }
+
+    #[test]
+    fn buys_once_per_crossed_level_while_a_buy_is_in_flight() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&ada, 50_000_000)]))
+            .unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim
+            .run([(1, pool(100)), (2, pool(85)), (3, pool(75))])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 10_000_000);
+        // 10 ADA at the 90 line
+        assert_eq!(min_received.2, 111_111);
+    }
+
+    #[test]
+    fn levels_count_as_bought_once_the_buy_is_delivered() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 50_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        assert_eq!(sim.run([(1, pool(100)), (2, pool(85))]).unwrap().len(), 1);
+
+        // The owner cancels the order instead, and re-creates it: the 90 line wasn't bought
+        let mut cancel = order.mock_execution(3, &[(&ada, 50_000_000)]);
+        cancel.tx.outputs[0].datum = None;
+        sim.observe_tx(cancel).unwrap();
+        let mut recreated = ManagedStrategy::mock(&[(&ada, 50_000_000)]);
+        recreated.output.output_index = 1;
+        sim.add_order(recreated.clone()).unwrap();
+        assert_eq!(sim.run([(4, pool(85))]).unwrap().len(), 1);
+
+        // This time it's delivered, so only the 80 line is left to buy
+        sim.observe_tx(recreated.mock_execution(5, &[(&ada, 40_000_000), (&sberry, 111_111)]))
+            .unwrap();
+        let executions = sim.run([(6, pool(85)), (7, pool(75))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 10_000_000);
+        assert_eq!(min_received.2, 125_000);
+    }
+}
@@ -0,0 +1,95 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+use tracing::info;
+
+#[derive(Deserialize)]
+#[serde(try_from = "StopLimitConfigRaw")]
+pub struct StopLimitConfig {
+    pub network: Network,
+    // Tokens must be in alphanumeric order with token_a < token_b when sorted
+    pub token_a: AssetId,
+    pub token_a_decimals: u8,
+    pub token_b: AssetId,
+    pub token_b_decimals: u8,
+    pub sell_token: AssetId,
+    /// The pool price below which the stop arms and the limit order is submitted
+    pub stop_price: f64,
+    /// The worst price the sell is allowed to fill at; must not be above `stop_price`
+    pub limit_price: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct StopLimitConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_a_decimals: u8,
+    token_b: AssetId,
+    token_b_decimals: u8,
+    sell_token: AssetId,
+    stop_price: f64,
+    limit_price: f64,
+}
+
+impl TryFrom<StopLimitConfigRaw> for StopLimitConfig {
+    type Error = String;
+
+    fn try_from(raw: StopLimitConfigRaw) -> Result<Self, Self::Error> {
+        if raw.stop_price <= 0.0 {
+            return Err(format!("stop_price must be > 0.0, got {}", raw.stop_price));
+        }
+        if raw.limit_price <= 0.0 {
+            return Err(format!(
+                "limit_price must be > 0.0, got {}",
+                raw.limit_price
+            ));
+        }
+        if raw.limit_price > raw.stop_price {
+            return Err(format!(
+                "limit_price ({}) must not be above stop_price ({})",
+                raw.limit_price, raw.stop_price
+            ));
+        }
+        if raw.sell_token != raw.token_a && raw.sell_token != raw.token_b {
+            return Err("sell_token must be one of token_a or token_b".to_string());
+        }
+
+        Ok(StopLimitConfig {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_a_decimals: raw.token_a_decimals,
+            token_b: raw.token_b,
+            token_b_decimals: raw.token_b_decimals,
+            sell_token: raw.sell_token,
+            stop_price: raw.stop_price,
+            limit_price: raw.limit_price,
+        })
+    }
+}
+
+impl StopLimitConfig {
+    /// The buy asset, and the minimum raw units of it to receive per raw unit of sell_token
+    pub fn trade_direction(&self) -> (&AssetId, f64) {
+        // Prices are in whole token_a per whole token_b, so scale by the decimals
+        // difference to convert between raw amounts
+        if self.sell_token == self.token_a {
+            let scale = 10f64.powi(self.token_b_decimals as i32 - self.token_a_decimals as i32);
+            (&self.token_b, scale / self.limit_price)
+        } else {
+            let scale = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
+            (&self.token_a, self.limit_price * scale)
+        }
+    }
+
+    pub fn log_submission(&self, give_amount: u64, receive_amount: u64) {
+        info!(
+            "executing stop-limit order to sell {give_amount} {} for at least {receive_amount} {}",
+            self.sell_token.name_to_string(),
+            if self.token_a == self.sell_token {
+                self.token_b.name_to_string()
+            } else {
+                self.token_a.name_to_string()
+            }
+        );
+    }
+}
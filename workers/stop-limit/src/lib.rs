@@ -0,0 +1,165 @@
+//! # Stop-Limit Strategy
+//!
+//! A stop-loss that refuses to sell below a floor.
+//!
+//! ## How It Works
+//!
+//! Once the pool price falls below `stop_price`, the stop arms and the strategy
+//! submits a swap of all `sell_token` whose minimum received is derived from
+//! `limit_price` rather than the current price. The order only fills while the
+//! market is at or above the limit, so a fast drop can't fill it at a terrible price.
+//!
+//! Arming is persisted per order, so the stop condition is only evaluated once.
+//! After that, the limit order is resubmitted whenever the previous submission's
+//! validity window lapses without it filling, regardless of where the price is.
+//!
+//! ## Example
+//!
+//! With `stop_price = 100` and `limit_price = 95`:
+//!
+//! 1. Price falls to 99 → the stop arms, and a sell with a floor of 95 is submitted
+//! 2. Price gaps down to 90 → the order can't fill, and waits
+//! 3. Price recovers to 96 → the resubmitted order fills at or above 95
+//!
+//! ## Configuration
+//!
+//! - `token_a` / `token_b`: The pool pair, with their decimals
+//! - `sell_token`: The token sold when the stop arms
+//! - `stop_price`: The price that arms the stop
+//! - `limit_price`: The worst acceptable execution price (must be <= `stop_price`)
+
+mod config;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::StopLimitConfig as StrategyConfig;
+use sundae_strategies::{
+    ManagedStrategy, PoolState, Strategy, Submission, kv,
+    types::{Interval, Order, min_received},
+};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a sell order is valid for
+const VALIDITY_SECS: u64 = 20;
+
+/// Orders whose stop has been crossed
+fn armed_stops() -> kv::Namespace<bool> {
+    kv::Namespace::new("stop_armed")
+}
+
+/// Orders with a limit sell in flight; entries expire with the submitted validity window
+fn pending_sells() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_sell")
+}
+
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    for strategy in strategies {
+        //  Skip processing for state changes of unrelated pools
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+        if strategy.is_empty(&[&config.sell_token]) {
+            info!(
+                "{:?} holds no {}, nothing to sell",
+                strategy.output,
+                config.sell_token.name_to_string()
+            );
+            continue;
+        }
+
+        let armed = armed_stops().get(&strategy.output)?.unwrap_or_default();
+        if !armed {
+            // Get pool price and scale for decimals
+            let pool_price = pool_state
+                .price(config.token_a_decimals, config.token_b_decimals)
+                .value();
+            info!("pool update found, with price {}", pool_price);
+            if pool_price >= config.stop_price {
+                continue;
+            }
+            info!(
+                "price has fallen to {}, below stop price of {}. Arming stop...",
+                pool_price, config.stop_price
+            );
+            armed_stops().set(&strategy.output, &true)?;
+        }
+
+        if pending_sells().get(&strategy.output)?.is_some() {
+            continue;
+        }
+        let validity_range = pool_state.get_validity_range(&config.network, VALIDITY_SECS);
+        if let Submission::Submitted(_) = trigger_sell(config, validity_range, strategy)? {
+            pending_sells().set_with_ttl(&strategy.output, &true, VALIDITY_SECS)?;
+        }
+    }
+    Ok(Ack)
+}
+
+fn trigger_sell(
+    config: &StrategyConfig,
+    validity_range: Interval,
+    order: &ManagedStrategy,
+) -> WorkerResult<Submission> {
+    let give_amount = order.balance(&config.sell_token);
+
+    // The minimum received comes from the limit price, not the current pool price
+    let (buy_token, price_ratio) = config.trade_direction();
+    let receive_amount = min_received(give_amount, price_ratio, 0.0);
+
+    let swap = Order::swap(
+        (&config.sell_token, give_amount),
+        (buy_token, receive_amount),
+    );
+
+    // Submit to relay and log
+    let submission = order.submit_execution(&config.network, validity_range, swap)?;
+    if !submission.is_skipped() {
+        config.log_submission(give_amount, receive_amount);
+    }
+    Ok(submission)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .with_validity_window(VALIDITY_SECS)
+        .worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(stop_price: f64, limit_price: f64) -> Result<StrategyConfig, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_a_decimals": 6,
+            "token_b": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "token_b_decimals": 0,
+            "sell_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "stop_price": stop_price,
+            "limit_price": limit_price,
+        }))
+    }
+
+    #[test]
+    fn minimum_received_uses_the_limit_price() {
+        let config = config(100.0, 95.0).unwrap();
+        let (buy_token, price_ratio) = config.trade_direction();
+        assert!(buy_token == &config.token_a);
+        // token_a has 6 decimals and token_b none, so each unit sold is worth 95 * 10^6 raw units
+        assert_eq!(price_ratio, 95_000_000.0);
+    }
+
+    #[test]
+    fn limit_above_stop_is_rejected() {
+        assert!(config(100.0, 105.0).is_err());
+    }
+}
@@ -1,8 +1,12 @@
 use serde::Deserialize;
-use sundae_strategies::{Network, types::AssetId};
+use sundae_strategies::{Network, history::PriceSource, types::AssetId, validate_finite};
 use tracing::info;
 
+/// Default slippage tolerance (1%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.01;
+
 #[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
 pub struct StopLossConfig {
     pub network: Network,
     // Tokens must be in alphanumeric order with token_a < token_b when sorted
@@ -12,22 +16,95 @@ pub struct StopLossConfig {
     pub token_b_decimals: u8,
     pub sell_token: AssetId,
     pub execution_price: f64,
+    /// When the order stops being acted on, in UNIX seconds. Never, if unset.
+    ///
+    /// Expiry is checked against the time of each pool update's slot. A sell stays valid for
+    /// a few seconds after the slot it was submitted at, so one submitted just before expiry
+    /// could otherwise fill after it; its validity window is cut off at `expires_at` instead.
+    /// The worker can't cancel an expired order, since only its owner can spend it other
+    /// than by executing it, so the funds stay in the order until the owner cancels it.
+    pub expires_at: Option<u64>,
+    /// How far below the execution price a sell may fill (e.g., 0.01 = 1%), so it still
+    /// fills if the price keeps falling between the trigger and the scoop
+    pub slippage_tolerance: f64,
+    /// Whether the execution price is compared against each observed pool price, or a short
+    /// average of them (`{"twap": {"window": N}}`) so one brief dip can't trigger the sell.
+    /// Defaults to `spot`.
+    pub price_source: PriceSource,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_a_decimals: u8,
+    token_b: AssetId,
+    token_b_decimals: u8,
+    sell_token: AssetId,
+    execution_price: f64,
+    #[serde(default)]
+    expires_at: Option<u64>,
+    slippage_tolerance: Option<f64>,
+    #[serde(default)]
+    price_source: PriceSource,
+}
+
+impl TryFrom<ConfigRaw> for StopLossConfig {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.token_a == raw.token_b {
+            return Err("token_a and token_b must be different tokens".to_string());
+        }
+
+        validate_finite("execution_price", raw.execution_price)?;
+        if let Some(slippage_tolerance) = raw.slippage_tolerance {
+            validate_finite("slippage_tolerance", slippage_tolerance)?;
+        }
+
+        raw.price_source.validate()?;
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 {
+            return Err(format!(
+                "slippage_tolerance must be > 0.0, got {}",
+                slippage_tolerance
+            ));
+        }
+        if slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be < 1.0, got {}",
+                slippage_tolerance
+            ));
+        }
+
+        Ok(StopLossConfig {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_a_decimals: raw.token_a_decimals,
+            token_b: raw.token_b,
+            token_b_decimals: raw.token_b_decimals,
+            sell_token: raw.sell_token,
+            execution_price: raw.execution_price,
+            expires_at: raw.expires_at,
+            slippage_tolerance,
+            price_source: raw.price_source,
+        })
+    }
 }
 
 impl StopLossConfig {
-    pub fn trade_direction(&self) -> (Vec<u8>, Vec<u8>, f64) {
+    /// The buy asset, and the minimum raw units of it to receive per raw unit of sell_token
+    pub fn trade_direction(&self) -> (&AssetId, f64) {
+        // Prices are in whole token_a per whole token_b, so scale by the decimals
+        // difference to convert between raw amounts
         if self.sell_token == self.token_a {
-            (
-                self.token_b.policy_id.clone(),
-                self.token_b.asset_name.clone(),
-                1.0 / self.execution_price,
-            )
+            let scale = 10f64.powi(self.token_b_decimals as i32 - self.token_a_decimals as i32);
+            (&self.token_b, scale / self.execution_price)
         } else {
-            (
-                self.token_a.policy_id.clone(),
-                self.token_a.asset_name.clone(),
-                self.execution_price,
-            )
+            let scale = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
+            (&self.token_a, self.execution_price * scale)
         }
     }
 
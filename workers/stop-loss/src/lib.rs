@@ -3,34 +3,83 @@ mod config;
 use balius_sdk::{Ack, Config, WorkerResult};
 use config::StopLossConfig as StrategyConfig;
 use sundae_strategies::{
-    ManagedStrategy, PoolState, Strategy,
-    types::{Interval, Order, asset_amount},
+    ManagedStrategy, PoolState, Strategy, Submission, expiry, kv,
+    types::{Interval, Order, min_received},
 };
 use tracing::info;
 
+/// How long, in seconds, either side of the current slot a sell order is valid for
+const VALIDITY_SECS: u64 = 20;
+
+/// Orders with a sell in flight; entries expire with the submitted validity window
+fn pending_sells() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_sell")
+}
+
 fn on_new_pool_state(
     config: &Config<StrategyConfig>,
     pool_state: &PoolState,
     strategies: &Vec<ManagedStrategy>,
 ) -> WorkerResult<Ack> {
+    let now = pool_state.now_ms(&config.network);
+    if expiry::is_expired(config.expires_at, now) {
+        info!(
+            "orders expired at {}, no longer acting on them; cancel them to reclaim the funds",
+            config.expires_at.unwrap_or_default()
+        );
+        return Ok(Ack);
+    }
+
+    //  Skip state changes of pools no order trades in
+    if !strategies.iter().any(|strategy| {
+        pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b)
+    }) {
+        return Ok(Ack);
+    }
+
+    // Get pool price and scale for decimals, recording it whether or not an order holds
+    // anything to sell yet
+    let spot_price = pool_state
+        .price(config.token_a_decimals, config.token_b_decimals)
+        .value();
+    info!("pool update found, with price {}", spot_price);
+    let pool_price = config.price_source.price(pool_state, spot_price)?;
+    if pool_price != spot_price {
+        info!("acting on average price {}", pool_price);
+    }
+
     for strategy in strategies {
-        //  Skip processing for state changes of unrelated pools
         if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
             continue;
         }
-
-        // Get pool price and scale for decimals
-        let pool_price = pool_state.price(config.token_a_decimals, config.token_b_decimals);
-        info!("pool update found, with price {}", pool_price);
+        if strategy.is_empty(&[&config.sell_token]) {
+            info!(
+                "{:?} holds no {}, nothing to sell",
+                strategy.output,
+                config.sell_token.name_to_string()
+            );
+            continue;
+        }
 
         // Execute if pool_price is below execution price
         if pool_price < config.execution_price {
+            if pending_sells().get(&strategy.output)?.is_some() {
+                info!(
+                    "sell already submitted for {:?}, waiting for it to land",
+                    strategy.output
+                );
+                continue;
+            }
             info!(
                 "price has fallen to {}, below SL price of {}. Triggering a sell order...",
                 pool_price, config.execution_price
             );
-            let validity_range = pool_state.get_validity_range(&config.network, 20);
-            trigger_sell(config, validity_range, strategy)?;
+            let validity_range = expiry::validity_range(config.expires_at, now, VALIDITY_SECS);
+            if let Submission::Submitted(_) =
+                trigger_sell(config, pool_state, validity_range, strategy)?
+            {
+                pending_sells().set_with_ttl(&strategy.output, &true, VALIDITY_SECS)?;
+            }
         }
     }
     Ok(Ack)
@@ -38,28 +87,33 @@ fn on_new_pool_state(
 
 fn trigger_sell(
     config: &StrategyConfig,
+    pool_state: &PoolState,
     validity_range: Interval,
     order: &ManagedStrategy,
-) -> WorkerResult<Ack> {
-    let give_amount = asset_amount(&order.utxo, &config.sell_token);
+) -> WorkerResult<Submission> {
+    let give_amount = order.balance(&config.sell_token);
 
-    // Get the buy asset and the minimum number of buy tokens per sell token based on config
-    let (buy_policy, buy_name, price_ratio) = config.trade_direction();
-    let receive_amount = (give_amount as f64 * price_ratio) as u64;
+    // Get the buy asset and the minimum number of buy tokens per sell token based on config,
+    // less slippage so the sell still fills if the price keeps falling before the scoop
+    let (buy_token, price_ratio) = config.trade_direction();
+    let receive_amount = min_received(give_amount, price_ratio, config.slippage_tolerance);
 
-    let swap = Order::Swap {
-        offer: (
-            config.sell_token.policy_id.clone(),
-            config.sell_token.asset_name.clone(),
-            give_amount,
-        ),
-        min_received: (buy_policy, buy_name, receive_amount),
-    };
+    // Past the execution price, the pool may not deliver that much after its fee
+    let receive_amount = pool_state
+        .min_received_after_fees(&config.sell_token, give_amount, config.slippage_tolerance)
+        .map_or(receive_amount, |after_fees| receive_amount.min(after_fees));
+
+    let swap = Order::swap(
+        (&config.sell_token, give_amount),
+        (buy_token, receive_amount),
+    );
 
     // Submit to relay and log
-    order.submit_execution(&config.network, validity_range, swap)?;
-    config.log_submission(give_amount, receive_amount);
-    Ok(Ack)
+    let submission = order.submit_execution(&config.network, validity_range, swap)?;
+    if !submission.is_skipped() {
+        config.log_submission(give_amount, receive_amount);
+    }
+    Ok(submission)
 }
 
 #[balius_sdk::main]
@@ -68,5 +122,98 @@ fn main() -> Worker {
 
     Strategy::<StrategyConfig>::new()
         .on_new_pool_state(on_new_pool_state)
+        .with_validity_window(VALIDITY_SECS)
         .worker()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::history::PriceSource;
+
+    fn config(sell_token: &str) -> StrategyConfig {
+        try_config(sell_token, serde_json::json!(null)).unwrap()
+    }
+
+    fn try_config(
+        sell_token: &str,
+        slippage_tolerance: serde_json::Value,
+    ) -> Result<StrategyConfig, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_a_decimals": 6,
+            "token_b": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "token_b_decimals": 0,
+            "sell_token": sell_token,
+            "execution_price": 0.000168,
+            "slippage_tolerance": slippage_tolerance,
+        }))
+    }
+
+    #[test]
+    fn slippage_tolerance_defaults_and_is_validated() {
+        assert_eq!(config(".").slippage_tolerance, 0.01);
+        assert_eq!(
+            try_config(".", serde_json::json!(0.05))
+                .unwrap()
+                .slippage_tolerance,
+            0.05
+        );
+        assert!(try_config(".", serde_json::json!(0.0)).is_err());
+        assert!(try_config(".", serde_json::json!(1.0)).is_err());
+    }
+
+    #[test]
+    fn price_source_defaults_to_spot() {
+        assert_eq!(config(".").price_source, PriceSource::Spot);
+        let mut json = serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_a_decimals": 6,
+            "token_b": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "token_b_decimals": 0,
+            "sell_token": ".",
+            "execution_price": 0.000168,
+            "price_source": { "twap": { "window": 5 } },
+        });
+        let config = serde_json::from_value::<StrategyConfig>(json.clone()).unwrap();
+        assert_eq!(config.price_source, PriceSource::Twap { window: 5 });
+
+        json["price_source"] = serde_json::json!({ "twap": { "window": 0 } });
+        assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+    }
+
+    #[test]
+    fn rejects_trading_a_token_for_itself() {
+        let config = serde_json::from_value::<StrategyConfig>(serde_json::json!({
+            "network": "preview",
+            "token_a": ".",
+            "token_a_decimals": 6,
+            "token_b": ".",
+            "token_b_decimals": 6,
+            "sell_token": ".",
+            "execution_price": 1.0,
+        }));
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn selling_a_zero_decimal_token_for_ada() {
+        let config =
+            config("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259");
+        let (buy_token, price_ratio) = config.trade_direction();
+        assert!(buy_token.is_ada());
+        // 1000 SBERRY at 0.000168 ADA each is 0.168 ADA, or 168,000 lovelace
+        assert!((1000.0 * price_ratio - 168_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn selling_ada_for_a_zero_decimal_token() {
+        let config = config(".");
+        let (buy_token, price_ratio) = config.trade_direction();
+        assert!(!buy_token.is_ada());
+        // 1 ADA (1,000,000 lovelace) at 0.000168 ADA per SBERRY buys ~5952 SBERRY
+        assert!((1_000_000.0 * price_ratio - 1.0 / 0.000168).abs() < 1e-6);
+    }
+}
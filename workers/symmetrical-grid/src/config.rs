@@ -1,64 +1,331 @@
-use serde::{Deserialize, Serialize};
-use sundae_strategies::{Network, types::AssetId};
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeMap};
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, Network, types::AssetId, validate_finite,
+    validate_validity_window,
+};
+
+/// The most grid lines allowed per side.
+///
+/// The full grid is rebuilt (levels_above + levels_below prices) and scanned on every pool
+/// observation, so this bounds the memory and CPU each observation can cost.
+pub const MAX_LEVELS_PER_SIDE: u64 = 1000;
 
 #[derive(Serialize, Deserialize)]
 #[serde(try_from = "ConfigRaw")]
 pub struct Config {
     pub network: Network,
-    /// Current price
-    pub center_price: f64,
+    /// The price to center the grid on; the pool price at the first observation if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center_price: Option<f64>,
     /// The token to buy or sell when a grid line is crossed
     pub strategy_token: AssetId,
+    // Decimals default to 0, which leaves prices in raw units; omitted when 0 so
+    // configs predating these fields keep their grid state id
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub strategy_token_decimals: u8,
     /// The token to trade against the strategy token
     pub base_token: AssetId,
-    /// The percentage between each grid line
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub base_token_decimals: u8,
+    /// The percentage between each grid line; with `volatility_spacing`, the starting spacing
     pub spacing_percent: f64,
-    /// The number of grid lines per side of the grid
-    pub levels_per_side: u64,
+    /// The number of grid lines above and below the center
+    #[serde(flatten)]
+    pub levels: Levels,
+    /// How grid lines are spaced around the center price
+    // Omitted when geometric so configs predating this field keep their grid state id
+    #[serde(default, skip_serializing_if = "SpacingMode::is_geometric")]
+    pub spacing_mode: SpacingMode,
+    /// Rebuild the grid around the current price once price leaves the grid
+    /// and every level on that side has been filled
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub recenter: bool,
+    /// Derive the spacing from recent realized volatility instead of fixing it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volatility_spacing: Option<VolatilitySpacing>,
+    /// How long, in seconds either side of the observation, each fill is valid for
+    // Omitted when the default so configs predating this field keep their grid state id
+    #[serde(
+        default = "default_validity_window_secs",
+        skip_serializing_if = "is_default_validity_window"
+    )]
+    pub validity_window_secs: u64,
+}
+
+/// How many grid lines sit on each side of the center price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    /// Sell levels, above the center
+    pub above: u64,
+    /// Buy levels, below the center
+    pub below: u64,
+}
+
+impl Levels {
+    pub fn symmetric(levels_per_side: u64) -> Self {
+        Levels {
+            above: levels_per_side,
+            below: levels_per_side,
+        }
+    }
+}
+
+// A symmetric grid is written as `levels_per_side`, so configs predating the split keep
+// their grid state id
+impl Serialize for Levels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.above == self.below {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry("levels_per_side", &self.above)?;
+            map.end()
+        } else {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("levels_above", &self.above)?;
+            map.serialize_entry("levels_below", &self.below)?;
+            map.end()
+        }
+    }
+}
+
+/// Sets grid spacing to `base_spacing + volatility_multiplier * realized volatility`, so the
+/// grid widens when the market is choppy and tightens when it's calm.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VolatilitySpacing {
+    /// The spacing of a perfectly calm market, also used until enough history accumulates
+    pub base_spacing: f64,
+    /// How much spacing each unit of per-observation volatility adds
+    pub volatility_multiplier: f64,
+    /// The number of price changes volatility is measured over
+    #[serde(default = "default_volatility_window")]
+    pub window: usize,
+    /// Only rebuild the grid once the target spacing differs from the current spacing
+    /// by more than this fraction of it, e.g. `0.25` for 25%
+    #[serde(default = "default_rebuild_threshold")]
+    pub rebuild_threshold: f64,
+}
+
+fn default_volatility_window() -> usize {
+    20
+}
+
+fn default_rebuild_threshold() -> f64 {
+    0.25
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpacingMode {
+    /// Each line is `spacing_percent` further from the previous one: `center * (1 + spacing_percent)^i`
+    #[default]
+    Geometric,
+    /// Lines are evenly spaced by `center * spacing_percent`: `center ± i * step`
+    Arithmetic,
+}
+
+fn default_validity_window_secs() -> u64 {
+    DEFAULT_VALIDITY_WINDOW_SECS
+}
+
+fn is_default_validity_window(secs: &u64) -> bool {
+    *secs == DEFAULT_VALIDITY_WINDOW_SECS
+}
+
+fn is_zero(decimals: &u8) -> bool {
+    *decimals == 0
+}
+
+impl Config {
+    /// Raw `base_token` units worth `amount` raw `strategy_token` units at `price`
+    /// (whole base_token per whole strategy_token)
+    pub fn strategy_to_base(&self, amount: u64, price: f64) -> f64 {
+        amount as f64 * price * self.decimal_scale()
+    }
+
+    /// Raw `strategy_token` units worth `amount` raw `base_token` units at `price`
+    /// (whole base_token per whole strategy_token)
+    pub fn base_to_strategy(&self, amount: u64, price: f64) -> f64 {
+        amount as f64 / price / self.decimal_scale()
+    }
+
+    /// Raw base_token units per raw strategy_token unit, for a price of 1
+    fn decimal_scale(&self) -> f64 {
+        10f64.powi(self.base_token_decimals as i32 - self.strategy_token_decimals as i32)
+    }
+}
+
+impl SpacingMode {
+    fn is_geometric(&self) -> bool {
+        *self == SpacingMode::Geometric
+    }
 }
 
 /// Raw config for deserialization before validation
 #[derive(Deserialize)]
 struct ConfigRaw {
     network: Network,
-    center_price: f64,
+    #[serde(default)]
+    center_price: Option<f64>,
     strategy_token: AssetId,
+    #[serde(default)]
+    strategy_token_decimals: u8,
     base_token: AssetId,
-    spacing_percent: f64,
-    levels_per_side: u64,
+    #[serde(default)]
+    base_token_decimals: u8,
+    spacing_percent: Option<f64>,
+    /// Deprecated: sets both `levels_above` and `levels_below`
+    #[serde(default)]
+    levels_per_side: Option<u64>,
+    #[serde(default)]
+    levels_above: Option<u64>,
+    #[serde(default)]
+    levels_below: Option<u64>,
+    #[serde(default)]
+    spacing_mode: SpacingMode,
+    #[serde(default)]
+    recenter: bool,
+    #[serde(default)]
+    volatility_spacing: Option<VolatilitySpacing>,
+    #[serde(default = "default_validity_window_secs")]
+    validity_window_secs: u64,
 }
 
 impl TryFrom<ConfigRaw> for Config {
     type Error = String;
 
     fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
-        if raw.levels_per_side == 0 {
-            return Err("levels_per_side must be >= 1".to_string());
+        if raw.strategy_token == raw.base_token {
+            return Err("strategy_token and base_token must be different tokens".to_string());
+        }
+
+        let floats = [
+            ("center_price", raw.center_price),
+            ("spacing_percent", raw.spacing_percent),
+        ];
+        for (name, value) in floats {
+            if let Some(value) = value {
+                validate_finite(name, value)?;
+            }
+        }
+        if let Some(volatility) = &raw.volatility_spacing {
+            validate_finite("volatility_spacing.base_spacing", volatility.base_spacing)?;
+            validate_finite(
+                "volatility_spacing.volatility_multiplier",
+                volatility.volatility_multiplier,
+            )?;
+            validate_finite(
+                "volatility_spacing.rebuild_threshold",
+                volatility.rebuild_threshold,
+            )?;
         }
 
-        if raw.spacing_percent <= 0.0 {
+        if let Some(volatility) = &raw.volatility_spacing {
+            if volatility.base_spacing <= 0.0 {
+                return Err(format!(
+                    "volatility_spacing.base_spacing must be > 0, got {}",
+                    volatility.base_spacing
+                ));
+            }
+            if volatility.volatility_multiplier < 0.0 {
+                return Err(format!(
+                    "volatility_spacing.volatility_multiplier must be >= 0, got {}",
+                    volatility.volatility_multiplier
+                ));
+            }
+            if volatility.window < 2 {
+                return Err(format!(
+                    "volatility_spacing.window must be >= 2, got {}",
+                    volatility.window
+                ));
+            }
+            if volatility.rebuild_threshold < 0.0 {
+                return Err(format!(
+                    "volatility_spacing.rebuild_threshold must be >= 0, got {}",
+                    volatility.rebuild_threshold
+                ));
+            }
+        }
+
+        // A volatility-scaled grid starts from its base spacing
+        let spacing_percent = match (raw.spacing_percent, &raw.volatility_spacing) {
+            (Some(spacing), _) => spacing,
+            (None, Some(volatility)) => volatility.base_spacing,
+            (None, None) => return Err("spacing_percent is required".to_string()),
+        };
+
+        let levels = Levels {
+            above: side_levels("levels_above", raw.levels_above, raw.levels_per_side)?,
+            below: side_levels("levels_below", raw.levels_below, raw.levels_per_side)?,
+        };
+
+        if spacing_percent <= 0.0 {
             return Err(format!(
                 "spacing_percent must be > 0, got {}",
-                raw.spacing_percent
+                spacing_percent
             ));
         }
 
-        if raw.spacing_percent * raw.levels_per_side as f64 >= 1.0 {
+        // Only the downward side can run into zero
+        if spacing_percent * levels.below as f64 >= 1.0 {
             return Err(format!(
-                "spacing_percent * levels_per_side must be < 1.0 (got {} * {} = {})",
-                raw.spacing_percent,
-                raw.levels_per_side,
-                raw.spacing_percent * raw.levels_per_side as f64
+                "spacing_percent * levels_below must be < 1.0 (got {} * {} = {})",
+                spacing_percent,
+                levels.below,
+                spacing_percent * levels.below as f64
             ));
         }
 
+        if let Some(center_price) = raw.center_price
+            && !(center_price.is_finite() && center_price > 0.0)
+        {
+            return Err(format!("center_price must be > 0, got {center_price}"));
+        }
+
+        if let (SpacingMode::Arithmetic, Some(center_price)) = (raw.spacing_mode, raw.center_price)
+        {
+            let step = center_price * spacing_percent;
+            let lowest = center_price - step * levels.below as f64;
+            if lowest <= 0.0 {
+                return Err(format!(
+                    "arithmetic spacing places the lowest grid line at {lowest}, which must be > 0"
+                ));
+            }
+        }
+
+        validate_validity_window(raw.validity_window_secs)?;
+
         Ok(Config {
             network: raw.network,
             center_price: raw.center_price,
             strategy_token: raw.strategy_token,
+            strategy_token_decimals: raw.strategy_token_decimals,
             base_token: raw.base_token,
-            spacing_percent: raw.spacing_percent,
-            levels_per_side: raw.levels_per_side,
+            base_token_decimals: raw.base_token_decimals,
+            spacing_percent,
+            levels,
+            spacing_mode: raw.spacing_mode,
+            recenter: raw.recenter,
+            volatility_spacing: raw.volatility_spacing,
+            validity_window_secs: raw.validity_window_secs,
         })
     }
 }
+
+/// The levels on one side, from its own field or else the deprecated `levels_per_side`.
+fn side_levels(
+    name: &str,
+    levels: Option<u64>,
+    levels_per_side: Option<u64>,
+) -> Result<u64, String> {
+    let Some(levels) = levels.or(levels_per_side) else {
+        return Err(format!("{name} (or levels_per_side) is required"));
+    };
+    if levels == 0 {
+        return Err(format!("{name} must be >= 1"));
+    }
+    if levels > MAX_LEVELS_PER_SIDE {
+        return Err(format!(
+            "{name} must be <= {MAX_LEVELS_PER_SIDE}, got {levels}"
+        ));
+    }
+    Ok(levels)
+}
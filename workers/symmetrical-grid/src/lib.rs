@@ -9,10 +9,15 @@
 //! and the base token with approximately equal value at initialization.
 //!
 //! Using the observed pool price at startup, the strategy computes a fixed
-//! center price and derives a symmetric grid of price levels above and below
-//! that center.
+//! center price and derives a grid of price levels above and below that
+//! center: symmetric by default, or with more levels on one side for a
+//! directional bias.
 //!
-//! The grid is static and does not move once initialized.
+//! By default the grid is static and does not move once initialized. With
+//! `recenter` enabled, once the price moves beyond the outermost grid line and
+//! every level on that side has been filled, the grid is rebuilt around the
+//! current price, and the inventory slices are recomputed from the UTxO's
+//! balances at that time.
 //!
 //! - **Price moves up**: The strategy checks how many grid levels were crossed
 //!   since the previous pool price and sells one fixed inventory slice of the
@@ -24,7 +29,7 @@
 //!
 //! ## Example
 //!
-//! With `spacing_percent = 0.05` (5%), `levels_per_side = 3`:
+//! With `spacing_percent = 0.05` (5%), `levels_above = 3` and `levels_below = 3`:
 //!
 //! Initial balances:
 //! - strategy_token: 100.00
@@ -53,34 +58,96 @@
 //!    - Two previously filled grid levels (1.10, 1.05) are recrossed
 //!      in the opposite direction.
 //!    - The strategy executes two grid fills in a single transaction,
-//!      buying back the previously sold `strategy_token`.
+//!      buying back the `strategy_token` each level sold, at that level's price.
 //!    - Crossing the center price does not trigger a grid fill.
 //!
 //!    New balances:
-//!    - strategy_token: 95.38 (33.34 + 33.33 / 1.10 + 33.33 / 1.05)
-//!    - base_token:     105.00 (171.66 - 33.33 * 2)
+//!    - strategy_token: 100.00 (33.34 + 33.33 * 2)
+//!    - base_token:     100.00 (171.66 - 33.33 * 1.10 - 33.33 * 1.05)
+//!
+//!    The grid earns whatever the pool delivers beyond each level's price.
 //!
 //! 4. User closes grid strategy and receives the final balances.
 //!
-//! > **Note:** The center price is set from the pool price at the worker's
-//! > first observation of the position, which may differ from the actual user entry
-//! > price if there is a delay or rapid price movement between entry and observation.
+//! > **Note:** Without a configured `center_price`, the center price is set from the pool
+//! > price at the worker's first observation of the position, which may differ from the
+//! > actual user entry price if there is a delay or rapid price movement between entry and
+//! > observation.
+//!
+//! Every fill is recorded per strategy once the transaction spending its order lands,
+//! at the amounts that transaction actually traded. The most recent 1000 are kept. The
+//! `get-grid-pnl` request handler reports the realized profit from those fills in
+//! `base_token` terms, along with the current `line_offset` and remaining inventory. The `get-state`
+//! request reports one order's `center_price`, `line_offset`, spacing, and inventory.
+//!
+//! When one observation crosses several lines, the order fills them all in a single
+//! execution, taking slices from the line nearest the previous offset outwards and asking
+//! each line's own price for its slice. Orders are handled, and their executions
+//! submitted, in output reference order.
+//!
+//! A fill takes a while to land on chain, and until it does the order UTxO still shows
+//! the balances from before it. So a fill is remembered as pending until the transaction
+//! spending its order is seen, or its validity window passes, and nothing else is traded
+//! against the order in the meantime, even if price crosses other lines. The grid only
+//! moves to the lines filled once the fill lands; one that lapses unfilled leaves the grid
+//! where it was, so its lines are traded again.
+//!
+//! When a side no longer holds enough inventory for its next level, the grid says so
+//! once: a `grid side depleted` warning, the `sundae_strategy_sides_depleted_total`
+//! metric, and a `grid_side_depleted` alert to the execution webhook if one is configured.
+//! The side is checked against the order's balance on every crossing, so it resumes as soon
+//! as it can fill a level again, whether a fill on the other side replenished it or not.
+//! `get-grid-pnl` lists the sides depleted since they last filled.
 //!
 //! ## Configuration
 //!
 //! - `strategy_token`: The token traded by the grid. It is sold as price moves up and
 //!   bought back as price moves down.
 //! - `base_token`: The counter asset used to settle trades and hold proceeds between fills.
+//! - `strategy_token_decimals` / `base_token_decimals`: Decimal places of each token (default 0).
+//!   Grid prices, including `center_price`, are in whole `base_token` per whole `strategy_token`.
+//! - `center_price`: The price to center the grid on, which must be positive. Set it to keep
+//!   the intended center, e.g. when modifying a position; defaults to the pool price at the
+//!   worker's first observation.
 //! - `spacing_percent`: Percentage distance between adjacent grid levels (e.g. `0.05` = 5%).
-//! - `levels_per_side`: Number of grid levels placed above and below the center price.
+//! - `levels_above` / `levels_below`: Number of grid levels placed above (sells) and below
+//!   (buys) the center price, each at most 1000. Uneven sides express a directional bias; the
+//!   strategy token inventory is split across the levels above, and the base token inventory
+//!   across the levels below. Crossing back over a filled level trades back what it sold,
+//!   however many levels the other side has. The whole grid is recomputed on every pool observation, so
+//!   more levels cost more.
+//! - `levels_per_side`: Deprecated; sets both `levels_above` and `levels_below`, which
+//!   override it when given.
+//! - `spacing_mode`: `"geometric"` (default) multiplies by `1 + spacing_percent` per level;
+//!   `"arithmetic"` places levels at `center ± i * center * spacing_percent`.
+//! - `recenter`: Rebuild the grid around the current price when price leaves the grid
+//!   and that side's inventory is exhausted (default `false`).
+//! - `volatility_spacing`: Optionally scale the spacing with the market instead of fixing it:
+//!   `{ "base_spacing": 0.02, "volatility_multiplier": 2.0, "window": 20, "rebuild_threshold": 0.25 }`.
+//!   The target spacing is `base_spacing + volatility_multiplier * v`, where `v` is the realized
+//!   volatility of the last `window` observed price changes. Once the target differs from the
+//!   current spacing by more than `rebuild_threshold` (as a fraction of it), the grid is rebuilt
+//!   around the current price with the new spacing, the same way `recenter` rebuilds it, so
+//!   fills and the line offset always refer to a single grid. `spacing_percent` may be omitted
+//!   and defaults to `base_spacing`.
+//! - `min_order_value`: Fills offering or receiving fewer raw `base_token` units than this are
+//!   left untraded instead of being submitted, which mostly affects the thin outermost levels.
+//! - `validity_window_secs`: How long each fill is valid for, either side of the observation
+//!   that triggered it (60 to 3600, default 1200).
 
 mod config;
 
-use balius_sdk::{Ack, Config, WorkerResult};
-use config::Config as StrategyConfig;
+use std::collections::{BTreeMap, VecDeque};
+
+use balius_sdk::{_internal::Handler, Ack, Config, Json, Params, Tx, WorkerResult, wit};
+use config::{Config as StrategyConfig, Levels, SpacingMode, VolatilitySpacing};
+use serde::{Deserialize, Serialize};
 use sundae_strategies::{
-    ManagedStrategy, PoolState, Strategy, kv,
-    types::{Interval, Order, asset_amount},
+    ManagedStrategy, PoolState, Strategy, grid,
+    history::PriceHistory,
+    kv, metrics,
+    state::StateSnapshot,
+    types::{AssetId, InlineAssetId, Interval, Order, StrategyAuthorization, decimal_price},
 };
 use tracing::info;
 
@@ -90,16 +157,99 @@ struct GridState {
     line_offset: i64,
     initial_strategy_amount: u64,
     initial_base_amount: u64,
+    /// Number of times the grid has been rebuilt around a new center
+    #[serde(default)]
+    recenter_count: u64,
+    /// The spacing the grid was last rebuilt with under `volatility_spacing`,
+    /// or None while it uses the configured `spacing_percent`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spacing_percent: Option<f64>,
+    /// Set once the sell side can't fill its next level, until it can again or a buy
+    /// replenishes it
+    #[serde(default)]
+    sell_depleted: bool,
+    /// Set once the buy side can't fill its next level, until it can again or a sell
+    /// replenishes it
+    #[serde(default)]
+    buy_depleted: bool,
+    /// What each filled line sold, in the token it sold, for crossing back over it to
+    /// trade back
+    #[serde(default)]
+    line_fills: BTreeMap<i64, u64>,
 }
 
 impl GridState {
-    fn new(strategy: &ManagedStrategy, config: &StrategyConfig) -> Self {
+    /// A grid centered on the configured `center_price`, or on `pool_price` without one.
+    fn new(strategy: &ManagedStrategy, config: &StrategyConfig, pool_price: f64) -> Self {
         Self {
-            center_price: config.center_price,
+            center_price: config.center_price.unwrap_or(pool_price),
             line_offset: 0,
-            initial_strategy_amount: asset_amount(&strategy.utxo, &config.strategy_token),
-            initial_base_amount: asset_amount(&strategy.utxo, &config.base_token),
+            initial_strategy_amount: strategy.balance(&config.strategy_token),
+            initial_base_amount: strategy.balance(&config.base_token),
+            recenter_count: 0,
+            spacing_percent: None,
+            sell_depleted: false,
+            buy_depleted: false,
+            line_fills: BTreeMap::new(),
+        }
+    }
+
+    /// The spacing between this grid's lines.
+    fn spacing(&self, config: &StrategyConfig) -> f64 {
+        self.spacing_percent.unwrap_or(config.spacing_percent)
+    }
+
+    /// Rebuild the grid around `center_price`, sizing the inventory slices from the current balances.
+    fn recenter(&mut self, center_price: f64, strategy_amount: u64, base_amount: u64) {
+        self.center_price = center_price;
+        self.line_offset = 0;
+        self.initial_strategy_amount = strategy_amount;
+        self.initial_base_amount = base_amount;
+        self.recenter_count += 1;
+        self.sell_depleted = false;
+        self.buy_depleted = false;
+        self.line_fills.clear();
+    }
+
+    /// Move the grid to the lines `fill` filled, once it has landed. Lines it moved away
+    /// from the center remember what they sold; lines it moved back over are unwound. The
+    /// fill replenishes the other side.
+    fn settle(&mut self, fill: &PendingFill) {
+        let up = fill.side == Side::Sell;
+        for (line, slice) in fill.lines.iter().zip(&fill.slices) {
+            if (*line >= 0) == up {
+                self.line_fills.insert(*line, *slice);
+            } else {
+                self.line_fills.remove(line);
+            }
         }
+        self.line_offset = fill.line_offset;
+        match fill.side {
+            Side::Sell => self.buy_depleted = false,
+            Side::Buy => self.sell_depleted = false,
+        }
+    }
+
+    /// Which sides have run out of inventory.
+    fn depleted(&self) -> Vec<Side> {
+        [
+            (Side::Sell, self.sell_depleted),
+            (Side::Buy, self.buy_depleted),
+        ]
+        .into_iter()
+        .filter_map(|(side, depleted)| depleted.then_some(side))
+        .collect()
+    }
+}
+
+/// Whether price has left the grid on a side whose levels have all been filled.
+fn should_recenter(grid_prices: &[f64], levels: Levels, line_offset: i64, price: f64) -> bool {
+    match (grid_prices.first(), grid_prices.last()) {
+        (Some(bottom), Some(top)) => {
+            (price > *top && line_offset >= levels.above as i64)
+                || (price < *bottom && line_offset <= -(levels.below as i64))
+        }
+        _ => false,
     }
 }
 
@@ -112,75 +262,389 @@ impl GridState {
 // - The frontend must prevent deploying multiple strategies with identical
 //   configs, as they would share the same GridState.
 //
-// The center price is included to reduce collisions. Additional fields
+// The center price, when configured, is included to reduce collisions. Additional fields
 // (e.g. `initial_strategy_amount`) can be added if stronger uniqueness
 // guarantees are required.
-fn grid_state_key(config: &StrategyConfig) -> Result<String, serde_json::Error> {
+fn grid_state_id(config: &StrategyConfig) -> Result<String, serde_json::Error> {
     let bytes = serde_json::to_vec(config)?;
     let hash = blake3::hash(&bytes);
-    let key = hex::encode(hash.as_bytes());
-    Ok(format!("grid_state:{key}"))
+    Ok(hex::encode(hash.as_bytes()))
+}
+
+fn grid_states() -> kv::Namespace<GridState> {
+    kv::Namespace::new("grid_state")
+}
+
+/// Recent pool prices per grid, kept for `volatility_spacing`
+fn grid_price_histories() -> kv::Namespace<PriceHistory> {
+    kv::Namespace::new("grid_price_history")
+}
+
+/// The spacing `volatility` calls for given the observed prices, capped so the lowest line
+/// stays above zero, or None until the history covers the volatility window.
+fn target_spacing(
+    volatility: &VolatilitySpacing,
+    history: &PriceHistory,
+    levels_below: u64,
+) -> Option<f64> {
+    let realized = history.realized_volatility(volatility.window)?;
+    let spacing = volatility.base_spacing + volatility.volatility_multiplier * realized;
+    Some(spacing.min(MAX_TOTAL_SPACING / levels_below as f64))
+}
+
+/// The furthest the lower side of the grid may reach from the center, as a fraction of it.
+const MAX_TOTAL_SPACING: f64 = 0.99;
+
+/// Whether `target` differs from `current` by more than `threshold`, as a fraction of `current`.
+fn spacing_changed(current: f64, target: f64, threshold: f64) -> bool {
+    (target - current).abs() > current * threshold
+}
+
+/// Which way a fill traded the strategy token
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Side {
+    Buy,
+    Sell,
+}
+
+/// A grid fill, as traded by the transaction that spent its order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Fill {
+    slot: u64,
+    side: Side,
+    /// The price it filled at, in whole base_token per whole strategy_token
+    price: f64,
+    strategy_amount: u64,
+    base_amount: u64,
 }
 
-fn compute_grid_prices(center_price: f64, spacing_percent: f64, levels_per_side: u64) -> Vec<f64> {
-    let step = 1.0 + spacing_percent;
+/// How many fills are kept per strategy. Older fills are dropped, and no longer count
+/// towards the realized profit.
+const MAX_FILLS: usize = 1000;
 
-    let mut prices = Vec::with_capacity((levels_per_side * 2) as usize);
+/// Fill history, oldest first, stored per strategy authorization
+fn grid_fills() -> kv::Namespace<VecDeque<Fill>> {
+    kv::Namespace::new("grid_fills")
+}
 
-    // Below center
-    for i in (1..=levels_per_side).rev() {
-        prices.push(center_price / step.powi(i as i32));
+fn record_fill(strategy: &ManagedStrategy, fill: Fill) -> WorkerResult<()> {
+    let Order::Strategy { auth } = &strategy.order.details else {
+        return Ok(());
+    };
+    let mut fills = grid_fills().get(auth)?.unwrap_or_default();
+    fills.push_back(fill);
+    while fills.len() > MAX_FILLS {
+        fills.pop_front();
     }
+    grid_fills().set(auth, &fills)
+}
 
-    // Above center
-    for i in 1..=levels_per_side {
-        prices.push(center_price * step.powi(i as i32));
+/// Realized profit in raw `base_token` units.
+///
+/// Each fill is matched against the most recent unmatched fill on the opposite side,
+/// which for a grid is the neighbouring line it is unwinding. A matched quantity of
+/// strategy token earns the difference between its sell and buy prices; anything still
+/// unmatched is open inventory and doesn't count.
+fn realized_pnl<'a>(fills: impl IntoIterator<Item = &'a Fill>) -> f64 {
+    struct Lot {
+        side: Side,
+        quantity: f64,
+        unit_price: f64,
     }
 
-    prices
+    let mut open: Vec<Lot> = vec![];
+    let mut pnl = 0.0;
+    for fill in fills {
+        if fill.strategy_amount == 0 {
+            continue;
+        }
+        let mut quantity = fill.strategy_amount as f64;
+        let unit_price = fill.base_amount as f64 / fill.strategy_amount as f64;
+        while quantity > 0.0 {
+            let Some(lot) = open.last_mut().filter(|lot| lot.side != fill.side) else {
+                break;
+            };
+            let matched = quantity.min(lot.quantity);
+            pnl += match fill.side {
+                Side::Sell => matched * (unit_price - lot.unit_price),
+                Side::Buy => matched * (lot.unit_price - unit_price),
+            };
+            quantity -= matched;
+            lot.quantity -= matched;
+            if lot.quantity <= 0.0 {
+                open.pop();
+            }
+        }
+        if quantity > 0.0 {
+            open.push(Lot {
+                side: fill.side,
+                quantity,
+                unit_price,
+            });
+        }
+    }
+    pnl
 }
 
-pub fn compute_crossed_prices(
-    grid_prices: &[f64],
-    previous_offset: i64,
-    price: f64,
-) -> (i64, Vec<f64>) {
-    let levels_per_side = (grid_prices.len() / 2) as i64;
+/// The inventory slice traded at `level` (0-based distance from the center line).
+///
+/// The inventory is split evenly across the `levels` on its side of the grid, with the
+/// division's remainder spread one unit at a time over the first levels, so no inventory
+/// is stranded.
+fn level_slice(inventory: u64, levels: u64, level: u64) -> u64 {
+    let slice = inventory / levels;
+    if level < inventory % levels {
+        slice + 1
+    } else {
+        slice
+    }
+}
 
-    let new_index = grid_prices.iter().take_while(|p| **p < price).count() as i64;
-    let new_offset = new_index - levels_per_side;
-    let delta = new_offset - previous_offset;
+/// The grid line traded `step` lines into a fill from `line_offset`, moving up or down.
+///
+/// Lines are numbered like `line_offset`: moving up from offset `n` fills line `n`, and
+/// moving down fills line `n - 1`, so a sell and the buy unwinding it share a line.
+fn fill_line(line_offset: i64, up: bool, step: usize) -> i64 {
+    if up {
+        line_offset + step as i64
+    } else {
+        line_offset - step as i64 - 1
+    }
+}
 
-    let mut crossed = Vec::new();
+/// A fill submitted against an order UTxO that hasn't been spent yet.
+///
+/// Until a fill lands, the order UTxO and its balances stay the same, so anything else
+/// traded against it would offer inventory that's already committed, and spend the same
+/// UTxO twice. The grid moves to the lines filled only once the fill is seen to land.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingFill {
+    side: Side,
+    /// The lines filled, nearest first
+    lines: Vec<i64>,
+    /// The slice offered at each line
+    slices: Vec<u64>,
+    /// The line offset the grid moves to once the fill lands
+    line_offset: i64,
+    /// The grid the lines belong to, as its `recenter_count`
+    recenter_count: u64,
+}
 
-    if delta > 0 {
-        for step in 1..=delta {
-            let idx = (levels_per_side + previous_offset + step - 1) as usize;
-            crossed.push(grid_prices[idx]);
+impl PendingFill {
+    /// A fill of `slices` on `side`, from the current offset of `grid_state`.
+    fn new(grid_state: &GridState, side: Side, slices: &[u64]) -> Self {
+        let up = side == Side::Sell;
+        let filled = slices.len();
+        let lines = (0..filled)
+            .map(|step| fill_line(grid_state.line_offset, up, step))
+            .collect();
+        let line_offset = if up {
+            grid_state.line_offset + filled as i64
+        } else {
+            grid_state.line_offset - filled as i64
+        };
+        Self {
+            side,
+            lines,
+            slices: slices.to_vec(),
+            line_offset,
+            recenter_count: grid_state.recenter_count,
         }
-    } else if delta < 0 {
-        for step in 1..=(-delta) {
-            let idx = (levels_per_side + previous_offset - step) as usize;
-            crossed.push(grid_prices[idx]);
+    }
+}
+
+/// Fills in flight, stored per order UTxO; entries expire with the submitted validity window
+fn pending_fills() -> kv::Namespace<PendingFill> {
+    kv::Namespace::new("grid_pending_fill")
+}
+
+/// The slice to offer filling `line` at its `price`, moving up or down.
+///
+/// Moving away from the center opens the line, trading its share of the inventory on its
+/// side of the grid. Moving back over it unwinds it, trading back what opening it sold, as
+/// recorded when the opening fill landed, or its share without a record.
+fn line_slice(
+    config: &StrategyConfig,
+    grid_state: &GridState,
+    line: i64,
+    up: bool,
+    price: f64,
+) -> u64 {
+    let above = line >= 0;
+    let share = if above {
+        level_slice(
+            grid_state.initial_strategy_amount,
+            config.levels.above,
+            line as u64,
+        )
+    } else {
+        level_slice(
+            grid_state.initial_base_amount,
+            config.levels.below,
+            (-line - 1) as u64,
+        )
+    };
+    if above == up {
+        return share;
+    }
+    let sold = grid_state.line_fills.get(&line).copied().unwrap_or(share);
+    let offer = if above {
+        config.strategy_to_base(sold, price)
+    } else {
+        config.base_to_strategy(sold, price)
+    };
+    offer.ceil() as u64
+}
+
+/// The slices to trade for each of the `crossed` line prices, starting from the grid's
+/// `line_offset` and moving up or down, stopping at the first slice the `available`
+/// balance can't cover.
+fn slices_to_fill(
+    config: &StrategyConfig,
+    grid_state: &GridState,
+    up: bool,
+    crossed: &[f64],
+    available: u64,
+) -> Vec<u64> {
+    let mut slices = Vec::with_capacity(crossed.len());
+    let mut remaining = available;
+    for (step, price) in crossed.iter().enumerate() {
+        let line = fill_line(grid_state.line_offset, up, step);
+        let slice = line_slice(config, grid_state, line, up, *price);
+        if slice == 0 || slice > remaining {
+            break;
         }
+        remaining -= slice;
+        slices.push(slice);
     }
+    slices
+}
 
-    (new_offset, crossed)
+/// The least to receive for filling one `slice` at its line's `price`, rounded down.
+fn level_min(config: &StrategyConfig, side: Side, slice: u64, price: f64) -> u64 {
+    let amount = match side {
+        Side::Sell => config.strategy_to_base(slice, price),
+        Side::Buy => config.base_to_strategy(slice, price),
+    };
+    amount.floor() as u64
 }
 
-fn get_or_init_grid_state(
-    key: &str,
-    strategy: &ManagedStrategy,
+/// The total (sell, minimum receive) amounts for filling `slices` at `prices`.
+///
+/// The minimum receive is the sum of each level's own minimum, each rounded down, so the
+/// combined order never asks for more than filling the levels one at a time would.
+///
+/// Returns None if the receive amount rounds down to zero, since submitting that
+/// swap would accept any fill at all.
+fn fill_amounts(
     config: &StrategyConfig,
-) -> Result<GridState, balius_sdk::Error> {
-    match kv::get::<GridState>(key)? {
-        Some(state) => Ok(state),
-        None => {
-            let state = GridState::new(strategy, config);
-            kv::set(key, &state)?;
-            Ok(state)
+    side: Side,
+    slices: &[u64],
+    prices: &[f64],
+) -> Option<(u64, u64)> {
+    let sell_amt = slices.iter().sum::<u64>();
+    let buy_amt = prices
+        .iter()
+        .zip(slices)
+        .map(|(price, slice)| level_min(config, side, *slice, *price))
+        .sum::<u64>();
+    if sell_amt == 0 || buy_amt == 0 {
+        return None;
+    }
+    Some((sell_amt, buy_amt))
+}
+
+/// Cap a fill's minimum receive at what the pool would deliver for `sell_amt` of `sell_token`
+/// after its fee. The line prices ignore the fee, so in a high-fee pool they can ask for
+/// more than the pool will deliver and the fill would never execute.
+fn fillable_min(pool_state: &PoolState, sell_token: &AssetId, sell_amt: u64, buy_amt: u64) -> u64 {
+    pool_state
+        .min_received_after_fees(sell_token, sell_amt, 0.0)
+        .map_or(buy_amt, |after_fees| buy_amt.min(after_fees))
+}
+
+/// The price of strategy_token in whole base_token, from a pool's raw price.
+///
+/// `raw_price` is asset_a per asset_b, and `is_correct_pool` accepts the pair in either
+/// order, so it has to be inverted when strategy_token is asset_a.
+fn strategy_token_price(
+    config: &StrategyConfig,
+    pool_asset_a: &InlineAssetId,
+    raw_price: f64,
+) -> f64 {
+    if config.strategy_token == *pool_asset_a {
+        let price = decimal_price(
+            raw_price,
+            config.strategy_token_decimals,
+            config.base_token_decimals,
+        );
+        if price == 0.0 { 0.0 } else { 1.0 / price }
+    } else {
+        decimal_price(
+            raw_price,
+            config.base_token_decimals,
+            config.strategy_token_decimals,
+        )
+    }
+}
+
+/// The grid's line prices in ascending order: `levels.below` lines under `center_price`,
+/// then `levels.above` lines over it.
+fn compute_grid_prices(
+    center_price: f64,
+    spacing_percent: f64,
+    levels: Levels,
+    spacing_mode: SpacingMode,
+) -> Vec<f64> {
+    let mut prices = Vec::with_capacity((levels.below + levels.above) as usize);
+
+    match spacing_mode {
+        SpacingMode::Geometric => {
+            let step = 1.0 + spacing_percent;
+
+            // Below center
+            for i in (1..=levels.below).rev() {
+                prices.push(center_price / step.powi(i as i32));
+            }
+
+            // Above center
+            for i in 1..=levels.above {
+                prices.push(center_price * step.powi(i as i32));
+            }
+        }
+        SpacingMode::Arithmetic => {
+            let step = center_price * spacing_percent;
+
+            // Below center
+            for i in (1..=levels.below).rev() {
+                prices.push(center_price - i as f64 * step);
+            }
+
+            // Above center
+            for i in 1..=levels.above {
+                prices.push(center_price + i as f64 * step);
+            }
         }
     }
+
+    prices
+}
+
+pub fn compute_crossed_prices(
+    grid_prices: &[f64],
+    levels_below: u64,
+    previous_offset: i64,
+    price: f64,
+) -> (i64, Vec<f64>) {
+    let center = levels_below as i64;
+
+    // Work in indices into `grid_prices`. The previous offset may be stale relative to this
+    // grid (e.g. it was recorded against a different config), so clamp it onto the grid.
+    let previous_index = (center + previous_offset).max(0) as usize;
+    let (new_index, crossed) = grid::crossed_lines(grid_prices, previous_index, price);
+
+    (new_index as i64 - center, crossed)
 }
 
 fn on_new_pool_state(
@@ -188,67 +652,142 @@ fn on_new_pool_state(
     pool_state: &PoolState,
     strategies: &Vec<ManagedStrategy>,
 ) -> WorkerResult<Ack> {
-    let pool_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let pool_price = strategy_token_price(
+        config,
+        &pool_state.pool_datum.assets.0,
+        pool_state.pool_datum.raw_price(&pool_state.utxo).value(),
+    );
 
     tracing::info!("Found new pool price: {pool_price}");
 
+    let id = grid_state_id(config)?;
+    let tracks_pool = strategies
+        .iter()
+        .any(|s| pool_state.is_correct_pool(&s.order, &config.strategy_token, &config.base_token));
+    let price_history = match &config.volatility_spacing {
+        Some(volatility) if tracks_pool => {
+            let mut history = grid_price_histories()
+                .get_or_init(id.as_str(), || PriceHistory::new(volatility.window + 1))?;
+            history.push(pool_state.slot, pool_price);
+            grid_price_histories().set(id.as_str(), &history)?;
+            Some(history)
+        }
+        _ => None,
+    };
+
     for s in strategies {
         // Filter for active strategies
         if pool_state.is_correct_pool(&s.order, &config.strategy_token, &config.base_token) {
             tracing::info!("Strategy found with the correct pool");
             // Get current UTxO balance for `strategy_token` and `base_token`
-            let strategy_amt = asset_amount(&s.utxo, &config.strategy_token);
+            let (strategy_amt, base_amt) = s.balances(&config.strategy_token, &config.base_token);
             tracing::info!("Strategy amount: {strategy_amt}");
-            let base_amt = asset_amount(&s.utxo, &config.base_token);
             tracing::info!("Base amount: {base_amt}");
-            if strategy_amt == 0 && base_amt == 0 {
+            if s.is_empty(&[&config.strategy_token, &config.base_token]) {
+                tracing::info!("{:?} holds neither token, skipping", s.output);
                 continue;
             }
 
             // Get center price and current line offset
-            let key = grid_state_key(config)?;
-            let mut grid_state = get_or_init_grid_state(&key, s, config)?;
+            let mut grid_state =
+                grid_states().get_or_init(id.as_str(), || GridState::new(s, config, pool_price))?;
             tracing::info!("Grid state: {:?}", grid_state);
 
+            // Nothing else is traded against the order until its last fill lands or lapses
+            if let Some(pending) = pending_fills().get(&s.output)? {
+                tracing::info!(
+                    "{:?} has a fill of lines {:?} in flight, waiting for it to land",
+                    s.output,
+                    pending.lines
+                );
+                continue;
+            }
+
+            if let (Some(volatility), Some(history)) = (&config.volatility_spacing, &price_history)
+                && let Some(target) = target_spacing(volatility, history, config.levels.below)
+                && spacing_changed(
+                    grid_state.spacing(config),
+                    target,
+                    volatility.rebuild_threshold,
+                )
+            {
+                grid_state.recenter(pool_price, strategy_amt, base_amt);
+                grid_state.spacing_percent = Some(target);
+                tracing::info!(
+                    "Volatility moved the target spacing to {target}; rebuilt the grid around {pool_price}"
+                );
+                grid_states().set(id.as_str(), &grid_state)?;
+                continue;
+            }
+
             // Compute grid lines
             let grid_prices = compute_grid_prices(
                 grid_state.center_price,
-                config.spacing_percent,
-                config.levels_per_side,
+                grid_state.spacing(config),
+                config.levels,
+                config.spacing_mode,
             );
 
             tracing::info!("Computed grid lines: {:?}", grid_prices);
 
+            if config.recenter
+                && should_recenter(
+                    &grid_prices,
+                    config.levels,
+                    grid_state.line_offset,
+                    pool_price,
+                )
+            {
+                grid_state.recenter(pool_price, strategy_amt, base_amt);
+                tracing::info!(
+                    "Price left the grid; recentered around {pool_price} (recenter #{})",
+                    grid_state.recenter_count
+                );
+                grid_states().set(id.as_str(), &grid_state)?;
+                continue;
+            }
+
+            // An offset recorded against a different grid may lie outside this one
+            grid_state.line_offset = grid_state
+                .line_offset
+                .clamp(-(config.levels.below as i64), config.levels.above as i64);
+
             // Check which grid lines (if any) were crossed
-            let (new_offset, crossed_prices) =
-                compute_crossed_prices(&grid_prices, grid_state.line_offset, pool_price);
+            let (new_offset, crossed_prices) = compute_crossed_prices(
+                &grid_prices,
+                config.levels.below,
+                grid_state.line_offset,
+                pool_price,
+            );
 
             tracing::info!("Crossed grids: {:?}", crossed_prices);
 
             // Execute buy or sell depending on direction of the new offset
             if !crossed_prices.is_empty() {
                 tracing::info!("Crossed {} grid lines", crossed_prices.len());
-                let validity_range = pool_state.get_validity_range(&config.network, 20 * 60);
+                let validity_range =
+                    pool_state.get_validity_range(&config.network, config.validity_window_secs);
                 if new_offset > grid_state.line_offset {
-                    // Compute `strategy_token` to sell per grid line
-                    let sell_per_grid = grid_state.initial_strategy_amount / config.levels_per_side;
-                    if sell_per_grid == 0 {
+                    // Compute `strategy_token` to sell per crossed grid line, limited by the current UTxO balance
+                    let slices =
+                        slices_to_fill(config, &grid_state, true, &crossed_prices, strategy_amt);
+                    if slices.is_empty() {
+                        if !grid_state.sell_depleted {
+                            grid_state.sell_depleted = true;
+                            grid_states().set(id.as_str(), &grid_state)?;
+                            report_depleted(s, Side::Sell, strategy_amt, &config.strategy_token);
+                        }
                         continue;
                     }
+                    grid_state.sell_depleted = false;
                     tracing::info!(
-                        "Selling {sell_per_grid} {} per crossed grid",
+                        "Selling {:?} {} for the crossed grids",
+                        slices,
                         config.strategy_token.name_to_string()
                     );
 
-                    // Compute max fillable grid lines based on current UTxO balance
-                    let max_fillable = strategy_amt / sell_per_grid;
-                    if max_fillable == 0 {
-                        continue;
-                    }
-                    tracing::info!("Number of fillable grids: {max_fillable}");
-
                     // Reduce crossed prices to only the prices that can be filled
-                    let grids_to_fill = crossed_prices.len().min(max_fillable as usize);
+                    let grids_to_fill = slices.len();
                     let prices_to_fill = &crossed_prices[..grids_to_fill];
 
                     tracing::info!(
@@ -257,43 +796,53 @@ fn on_new_pool_state(
                     );
 
                     // Calculate buy and sell amounts
-                    let sell_amt = sell_per_grid * grids_to_fill as u64;
-                    let buy_amt: u64 = prices_to_fill
-                        .iter()
-                        .map(|price| sell_per_grid as f64 * price)
-                        .sum::<f64>()
-                        .floor() as u64;
+                    let Some((sell_amt, buy_amt)) =
+                        fill_amounts(config, Side::Sell, &slices, prices_to_fill)
+                    else {
+                        tracing::info!("Fill would receive nothing at these prices, skipping");
+                        continue;
+                    };
+                    let buy_amt =
+                        fillable_min(pool_state, &config.strategy_token, sell_amt, buy_amt);
 
                     tracing::info!(
                         "Selling {sell_amt} {} for {buy_amt} {}",
                         config.strategy_token.name_to_string(),
                         config.base_token.name_to_string()
                     );
-                    trigger_sell_strategy(config, validity_range, s, sell_amt, buy_amt)?;
+                    if !trigger_sell_strategy(config, validity_range, s, sell_amt, buy_amt)? {
+                        continue;
+                    }
 
-                    // Update offset
-                    grid_state.line_offset += grids_to_fill as i64;
-                    kv::set(&key, &grid_state)?;
+                    // The grid moves to the filled lines once the fill lands
+                    let pending = PendingFill::new(&grid_state, Side::Sell, &slices);
+                    pending_fills().set_with_ttl(
+                        &s.output,
+                        &pending,
+                        config.validity_window_secs,
+                    )?;
+                    grid_states().set(id.as_str(), &grid_state)?;
                 } else {
-                    // Compute `base_token` to sell per grid line
-                    let sell_per_grid = grid_state.initial_base_amount / config.levels_per_side;
-                    if sell_per_grid == 0 {
+                    // Compute `base_token` to sell per crossed grid line, limited by the current UTxO balance
+                    let slices =
+                        slices_to_fill(config, &grid_state, false, &crossed_prices, base_amt);
+                    if slices.is_empty() {
+                        if !grid_state.buy_depleted {
+                            grid_state.buy_depleted = true;
+                            grid_states().set(id.as_str(), &grid_state)?;
+                            report_depleted(s, Side::Buy, base_amt, &config.base_token);
+                        }
                         continue;
                     }
+                    grid_state.buy_depleted = false;
                     tracing::info!(
-                        "Selling {sell_per_grid} {} per crossed grid",
+                        "Selling {:?} {} for the crossed grids",
+                        slices,
                         config.base_token.name_to_string()
                     );
 
-                    // Compute max fillable grid lines based on current UTxO balance
-                    let max_fillable = base_amt / sell_per_grid;
-                    if max_fillable == 0 {
-                        continue;
-                    }
-                    tracing::info!("Number of fillable grids: {max_fillable}");
-
                     // Reduce crossed prices to only the prices that can be filled
-                    let grids_to_fill = crossed_prices.len().min(max_fillable as usize);
+                    let grids_to_fill = slices.len();
                     let prices_to_fill = &crossed_prices[..grids_to_fill];
 
                     tracing::info!(
@@ -302,23 +851,31 @@ fn on_new_pool_state(
                     );
 
                     // Calculate buy and sell amounts
-                    let sell_amt = sell_per_grid * grids_to_fill as u64;
-                    let buy_amt: u64 = prices_to_fill
-                        .iter()
-                        .map(|price| sell_per_grid as f64 / price)
-                        .sum::<f64>()
-                        .floor() as u64;
+                    let Some((sell_amt, buy_amt)) =
+                        fill_amounts(config, Side::Buy, &slices, prices_to_fill)
+                    else {
+                        tracing::info!("Fill would receive nothing at these prices, skipping");
+                        continue;
+                    };
+                    let buy_amt = fillable_min(pool_state, &config.base_token, sell_amt, buy_amt);
                     tracing::info!(
                         "Selling {sell_amt} {} for {buy_amt} {}",
                         config.base_token.name_to_string(),
                         config.strategy_token.name_to_string(),
                     );
 
-                    trigger_buy_strategy(config, validity_range, s, sell_amt, buy_amt)?;
+                    if !trigger_buy_strategy(config, validity_range, s, sell_amt, buy_amt)? {
+                        continue;
+                    }
 
-                    // Update offset
-                    grid_state.line_offset -= grids_to_fill as i64;
-                    kv::set(&key, &grid_state)?
+                    // The grid moves to the filled lines once the fill lands
+                    let pending = PendingFill::new(&grid_state, Side::Buy, &slices);
+                    pending_fills().set_with_ttl(
+                        &s.output,
+                        &pending,
+                        config.validity_window_secs,
+                    )?;
+                    grid_states().set(id.as_str(), &grid_state)?
                 }
             }
         }
@@ -327,70 +884,300 @@ fn on_new_pool_state(
     Ok(Ack)
 }
 
-/// Buy Strategy: Swap `base_token` for `strategy_token` when grid line crossed going up
+/// Record the fill the transaction spending `strategy` traded, if it traded one, at the
+/// amounts it delivered rather than the minimums the fill asked for, and move the grid to
+/// the lines it filled.
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let pending = pending_fills().get(&strategy.output)?;
+    pending_fills().delete(&strategy.output)?;
+    let (strategy_token, base_token) = (&config.strategy_token, &config.base_token);
+    let (side, strategy_amount, base_amount) =
+        if let Some(fill) = strategy.swap_fill(tx, strategy_token, base_token) {
+            (Side::Sell, fill.sold, fill.received)
+        } else if let Some(fill) = strategy.swap_fill(tx, base_token, strategy_token) {
+            (Side::Buy, fill.received, fill.sold)
+        } else {
+            tracing::info!(
+                "{:?} was spent without a grid fill; any fill in flight will be retried",
+                strategy.output
+            );
+            return Ok(Ack);
+        };
+    let price = decimal_price(
+        base_amount as f64 / strategy_amount as f64,
+        config.base_token_decimals,
+        config.strategy_token_decimals,
+    );
+    tracing::info!(
+        "{:?} filled: {side:?} {strategy_amount} {} for {base_amount} {} at {price}",
+        strategy.output,
+        strategy_token.name_to_string(),
+        base_token.name_to_string()
+    );
+    record_fill(
+        strategy,
+        Fill {
+            slot: tx.block_slot,
+            side,
+            price,
+            strategy_amount,
+            base_amount,
+        },
+    )?;
+
+    let Some(pending) = pending.filter(|pending| pending.side == side) else {
+        return Ok(Ack);
+    };
+    let id = grid_state_id(config)?;
+    let Some(mut grid_state) = grid_states().get(id.as_str())? else {
+        return Ok(Ack);
+    };
+    if grid_state.recenter_count != pending.recenter_count {
+        tracing::info!(
+            "the grid was rebuilt while lines {:?} were in flight",
+            pending.lines
+        );
+        return Ok(Ack);
+    }
+    tracing::info!(
+        "lines {:?} filled, moving the grid to offset {}",
+        pending.lines,
+        pending.line_offset
+    );
+    grid_state.settle(&pending);
+    grid_states().set(id.as_str(), &grid_state)?;
+    Ok(Ack)
+}
+
+/// Tell the operator a side of the grid can no longer fill its next level, so they can
+/// rebalance or close the grid.
+fn report_depleted(strategy: &ManagedStrategy, side: Side, balance: u64, token: &AssetId) {
+    let side = match side {
+        Side::Sell => "sell",
+        Side::Buy => "buy",
+    };
+    let message = format!(
+        "grid {side} side depleted: {balance} {} left, not enough for the next level",
+        token.name_to_string()
+    );
+    tracing::warn!("{message}");
+    metrics::increment(metrics::Counter::SidesDepleted);
+    sundae_strategies::notify_alert(&strategy.output, "grid_side_depleted", &message);
+}
+
+/// Buy Strategy: Swap `base_token` for `strategy_token` when grid line crossed going up.
+/// Returns false if the swap is below the worker's `min_order_value`, without submitting,
+/// or if the submission is skipped.
 fn trigger_buy_strategy(
     config: &Config<StrategyConfig>,
     validity_range: Interval,
     strategy: &ManagedStrategy,
     sell_amt: u64,
     buy_amt: u64,
-) -> WorkerResult<Ack> {
-    let swap = Order::Swap {
-        offer: (
-            config.base_token.policy_id.clone(),
-            config.base_token.asset_name.clone(),
-            sell_amt,
-        ),
-        min_received: (
-            config.strategy_token.policy_id.clone(),
-            config.strategy_token.asset_name.clone(),
-            buy_amt,
-        ),
-    };
+) -> WorkerResult<bool> {
+    let swap = Order::swap(
+        (&config.base_token, sell_amt),
+        (&config.strategy_token, buy_amt),
+    );
+    if sundae_strategies::dust::is_dust(&swap) {
+        info!("fill is below min_order_value, leaving it untraded");
+        return Ok(false);
+    }
 
-    sundae_strategies::submit_execution(&config.network, &strategy.output, validity_range, swap)?;
+    let submission = strategy.submit_execution(&config.network, validity_range, swap)?;
+    if submission.is_skipped() {
+        return Ok(false);
+    }
     info!("sell base asset order submitted");
-    Ok(Ack)
+    Ok(true)
 }
 
-/// Sell Strategy: Swap `strategy_token` for `base_token` when grid line crossed going down
+/// Sell Strategy: Swap `strategy_token` for `base_token` when grid line crossed going down.
+/// Returns false if the swap is below the worker's `min_order_value`, without submitting,
+/// or if the submission is skipped.
 fn trigger_sell_strategy(
     config: &Config<StrategyConfig>,
     validity_range: Interval,
     strategy: &ManagedStrategy,
     sell_amt: u64,
     buy_amt: u64,
-) -> WorkerResult<Ack> {
-    let swap = Order::Swap {
-        offer: (
-            config.strategy_token.policy_id.clone(),
-            config.strategy_token.asset_name.clone(),
-            sell_amt,
-        ),
-        min_received: (
-            config.base_token.policy_id.clone(),
-            config.base_token.asset_name.clone(),
-            buy_amt,
-        ),
-    };
+) -> WorkerResult<bool> {
+    let swap = Order::swap(
+        (&config.strategy_token, sell_amt),
+        (&config.base_token, buy_amt),
+    );
+    if sundae_strategies::dust::is_dust(&swap) {
+        info!("fill is below min_order_value, leaving it untraded");
+        return Ok(false);
+    }
 
-    sundae_strategies::submit_execution(&config.network, &strategy.output, validity_range, swap)?;
+    let submission = strategy.submit_execution(&config.network, validity_range, swap)?;
+    if submission.is_skipped() {
+        return Ok(false);
+    }
     info!("sell base asset order submitted");
-    Ok(Ack)
+    Ok(true)
+}
+
+// ============================================================================
+// get-grid-pnl request handler
+// ============================================================================
+
+/// Request parameters for get-grid-pnl
+#[derive(Deserialize)]
+struct GetGridPnlParams {
+    /// The strategy's signing key (hex-encoded), as returned by get-signer-key
+    signer: String,
+}
+
+/// Response for get-grid-pnl
+#[derive(Serialize)]
+struct GetGridPnlResponse {
+    /// Realized profit in raw base_token units
+    realized_pnl: f64,
+    /// Number of fills recorded, at most the most recent 1000
+    fills: usize,
+    /// Current grid line offset from the center, or null if the grid hasn't started
+    line_offset: Option<i64>,
+    /// strategy_token held by the strategy's open orders
+    remaining_strategy_amount: u64,
+    /// base_token held by the strategy's open orders
+    remaining_base_amount: u64,
+    /// The sides of the grid that have run out of inventory
+    depleted: Vec<Side>,
+}
+
+/// Handler for get-grid-pnl requests
+#[derive(Clone)]
+struct GetGridPnlHandler;
+
+impl Handler for GetGridPnlHandler {
+    fn handle(
+        &self,
+        config: wit::Config,
+        event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let config: Config<StrategyConfig> = config.try_into()?;
+        let params: Params<GetGridPnlParams> = event.try_into().map_err(|_| wit::HandleError {
+            message: "invalid request parameters".to_string(),
+            code: 400,
+        })?;
+
+        let signer = hex::decode(&params.signer).map_err(|_| wit::HandleError {
+            message: "invalid signer hex encoding".to_string(),
+            code: 400,
+        })?;
+        let auth = StrategyAuthorization::Signature {
+            signer: signer.clone(),
+        };
+
+        let internal = |e: balius_sdk::Error| wit::HandleError {
+            message: e.to_string(),
+            code: 500,
+        };
+
+        let fills = grid_fills()
+            .get(&auth)
+            .map_err(internal)?
+            .unwrap_or_default();
+        let id = grid_state_id(&config).map_err(|e| internal(e.into()))?;
+        let grid_state = grid_states().get(id.as_str()).map_err(internal)?;
+
+        let (mut remaining_strategy_amount, mut remaining_base_amount) = (0, 0);
+        for strategy in sundae_strategies::managed_strategies().map_err(internal)? {
+            if let Order::Strategy {
+                auth: StrategyAuthorization::Signature { signer: owner },
+            } = &strategy.order.details
+                && *owner == signer
+            {
+                remaining_strategy_amount += strategy.balance(&config.strategy_token);
+                remaining_base_amount += strategy.balance(&config.base_token);
+            }
+        }
+
+        let response = GetGridPnlResponse {
+            realized_pnl: realized_pnl(&fills),
+            fills: fills.len(),
+            line_offset: grid_state.as_ref().map(|state| state.line_offset),
+            remaining_strategy_amount,
+            remaining_base_amount,
+            depleted: grid_state
+                .as_ref()
+                .map(GridState::depleted)
+                .unwrap_or_default(),
+        };
+        info!(
+            "get-grid-pnl for {}: {:?}",
+            params.signer, response.realized_pnl
+        );
+
+        Json(response).try_into().map_err(internal)
+    }
+}
+
+// ============================================================================
+// get-state snapshot
+// ============================================================================
+
+/// What get-state reports for a grid order
+#[derive(Serialize)]
+pub struct GridSnapshot {
+    /// The price the grid is centered on
+    center_price: f64,
+    /// Current grid line offset from the center
+    line_offset: i64,
+    /// The spacing between the grid's lines
+    spacing_percent: f64,
+    /// strategy_token held by the order
+    strategy_amount: u64,
+    /// base_token held by the order
+    base_amount: u64,
+    /// The sides of the grid that have run out of inventory
+    depleted: Vec<Side>,
+}
+
+impl StateSnapshot for StrategyConfig {
+    type Snapshot = GridSnapshot;
+
+    fn snapshot(&self, order: &ManagedStrategy) -> WorkerResult<Option<GridSnapshot>> {
+        let id = grid_state_id(self)?;
+        let Some(grid_state) = grid_states().get(id.as_str())? else {
+            return Ok(None);
+        };
+        let (strategy_amount, base_amount) = order.balances(&self.strategy_token, &self.base_token);
+        Ok(Some(GridSnapshot {
+            center_price: grid_state.center_price,
+            line_offset: grid_state.line_offset,
+            spacing_percent: grid_state.spacing(self),
+            strategy_amount,
+            base_amount,
+            depleted: grid_state.depleted(),
+        }))
+    }
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+        .with_state()
 }
 
 #[balius_sdk::main]
 fn main() -> Worker {
     balius_sdk::logging::init();
 
-    Strategy::<StrategyConfig>::new()
-        .on_new_pool_state(on_new_pool_state)
-        .worker()
+    strategy().worker_with(|w| w.with_request_handler("get-grid-pnl", GetGridPnlHandler))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sundae_strategies::{sim::Simulator, types::StrategyExecution};
 
     #[test]
     fn computes_expected_grid_prices() {
@@ -398,7 +1185,12 @@ mod tests {
         let spacing = 0.05;
         let levels = 3;
 
-        let grid = compute_grid_prices(center, spacing, levels);
+        let grid = compute_grid_prices(
+            center,
+            spacing,
+            Levels::symmetric(levels),
+            SpacingMode::Geometric,
+        );
 
         let step = 1.0 + spacing;
 
@@ -423,19 +1215,829 @@ mod tests {
         }
     }
 
+    #[test]
+    fn computes_arithmetic_grid_prices() {
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Arithmetic);
+
+        let expected = [0.85, 0.90, 0.95, 1.05, 1.10, 1.15];
+
+        assert_eq!(grid.len(), expected.len());
+
+        for (actual, expected) in grid.iter().zip(expected.iter()) {
+            assert!(
+                (actual - expected).abs() < 1e-10,
+                "expected {}, got {}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn places_each_side_independently() {
+        let levels = Levels { above: 3, below: 1 };
+        let grid = compute_grid_prices(1.0, 0.05, levels, SpacingMode::Arithmetic);
+
+        let expected = [0.95, 1.05, 1.10, 1.15];
+        assert_eq!(grid.len(), expected.len());
+        for (actual, expected) in grid.iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-10);
+        }
+
+        // Offsets run from -1 to 3 around the center index
+        let (new_offset, crossed) = compute_crossed_prices(&grid, levels.below, 0, 1.12);
+        assert_eq!(new_offset, 2);
+        assert_eq!(crossed.len(), 2);
+        let (new_offset, _) = compute_crossed_prices(&grid, levels.below, 0, 0.5);
+        assert_eq!(new_offset, -1);
+
+        assert!(should_recenter(&grid, levels, 3, 1.2));
+        assert!(!should_recenter(&grid, levels, 1, 1.2));
+        assert!(should_recenter(&grid, levels, -1, 0.9));
+    }
+
+    #[test]
+    fn levels_per_side_sets_both_sides() {
+        let config = |levels: serde_json::Value| {
+            let mut config = serde_json::json!({
+                "network": "preview",
+                "center_price": 1.0,
+                "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+                "base_token": ".",
+                "spacing_percent": 0.1,
+            });
+            config
+                .as_object_mut()
+                .unwrap()
+                .extend(levels.as_object().unwrap().clone());
+            serde_json::from_value::<StrategyConfig>(config)
+        };
+        let symmetric = config(serde_json::json!({ "levels_per_side": 4 })).unwrap();
+        assert_eq!(symmetric.levels, Levels::symmetric(4));
+        let biased =
+            config(serde_json::json!({ "levels_per_side": 4, "levels_above": 8 })).unwrap();
+        assert_eq!(biased.levels, Levels { above: 8, below: 4 });
+        assert!(config(serde_json::json!({ "levels_above": 8 })).is_err());
+
+        // Only the lower side can reach zero
+        assert!(config(serde_json::json!({ "levels_above": 20, "levels_below": 9 })).is_ok());
+        assert!(config(serde_json::json!({ "levels_above": 9, "levels_below": 10 })).is_err());
+
+        // A symmetric grid keeps the grid state id it had before the split
+        let json = serde_json::to_value(&symmetric).unwrap();
+        assert_eq!(json["levels_per_side"], 4);
+        assert!(json.get("levels_above").is_none());
+        let json = serde_json::to_value(&biased).unwrap();
+        assert_eq!(
+            (json["levels_above"].clone(), json["levels_below"].clone()),
+            (8.into(), 4.into())
+        );
+    }
+
+    #[test]
+    fn levels_per_side_is_bounded() {
+        let config = |levels_per_side: u64| {
+            serde_json::from_value::<StrategyConfig>(serde_json::json!({
+                "network": "preview",
+                "center_price": 1.0,
+                "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+                "base_token": ".",
+                "spacing_percent": 0.0001,
+                "levels_per_side": levels_per_side,
+            }))
+        };
+        assert!(config(config::MAX_LEVELS_PER_SIDE).is_ok());
+        assert!(config(config::MAX_LEVELS_PER_SIDE + 1).is_err());
+    }
+
+    #[test]
+    fn validity_window_is_bounded() {
+        let config = |window: u64| {
+            serde_json::from_value::<StrategyConfig>(serde_json::json!({
+                "network": "preview",
+                "center_price": 1.0,
+                "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+                "base_token": ".",
+                "spacing_percent": 0.01,
+                "levels_per_side": 10,
+                "validity_window_secs": window,
+            }))
+        };
+        assert!(config(3600).is_ok());
+        assert!(config(30).is_err());
+        assert!(config(7200).is_err());
+
+        // The default is left out of the grid state id
+        let default = config(1200).unwrap();
+        assert!(
+            !serde_json::to_string(&default)
+                .unwrap()
+                .contains("validity_window_secs")
+        );
+    }
+
+    #[test]
+    fn rejects_arithmetic_grid_reaching_zero() {
+        let config = serde_json::from_value::<StrategyConfig>(serde_json::json!({
+            "network": "preview",
+            "center_price": 1.0,
+            "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+            "base_token": ".",
+            "spacing_percent": 0.25,
+            "levels_per_side": 4,
+            "spacing_mode": "arithmetic",
+        }));
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn rejects_trading_a_token_for_itself() {
+        let config = serde_json::from_value::<StrategyConfig>(serde_json::json!({
+            "network": "preview",
+            "center_price": 1.0,
+            "strategy_token": ".",
+            "base_token": ".",
+            "spacing_percent": 0.05,
+            "levels_per_side": 3,
+        }));
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn center_price_is_optional_but_positive() {
+        let config = |center_price: Option<f64>| {
+            let mut config = serde_json::json!({
+                "network": "preview",
+                "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+                "base_token": ".",
+                "spacing_percent": 0.05,
+                "levels_per_side": 3,
+            });
+            if let Some(center_price) = center_price {
+                config["center_price"] = center_price.into();
+            }
+            serde_json::from_value::<StrategyConfig>(config)
+        };
+        assert_eq!(config(Some(1.5)).unwrap().center_price, Some(1.5));
+        assert!(config(Some(0.0)).is_err());
+        assert!(config(Some(-1.0)).is_err());
+
+        let observed = config(None).unwrap();
+        assert_eq!(observed.center_price, None);
+        let strategy = ManagedStrategy::mock(&[]);
+        assert_eq!(GridState::new(&strategy, &observed, 0.8).center_price, 0.8);
+        let configured = config(Some(1.5)).unwrap();
+        assert_eq!(
+            GridState::new(&strategy, &configured, 0.8).center_price,
+            1.5
+        );
+    }
+
+    fn volatility_config() -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "center_price": 1.0,
+            "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+            "base_token": ".",
+            "levels_per_side": 10,
+            "volatility_spacing": {
+                "base_spacing": 0.01,
+                "volatility_multiplier": 2.0,
+                "window": 4,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn volatility_grid_starts_from_its_base_spacing() {
+        let config = volatility_config();
+        assert_eq!(config.spacing_percent, 0.01);
+        let volatility = config.volatility_spacing.as_ref().unwrap();
+        assert_eq!(volatility.rebuild_threshold, 0.25);
+    }
+
+    #[test]
+    fn target_spacing_widens_with_volatility() {
+        let config = volatility_config();
+        let volatility = config.volatility_spacing.as_ref().unwrap();
+        let mut history = PriceHistory::new(volatility.window + 1);
+        for (slot, price) in [1.0, 1.1, 1.0, 1.1].into_iter().enumerate() {
+            history.push(slot as u64, price);
+        }
+        // Not enough history yet
+        assert_eq!(target_spacing(volatility, &history, 10), None);
+
+        history.push(4, 1.0);
+        let target = target_spacing(volatility, &history, 10).unwrap();
+        assert!((target - (0.01 + 2.0 * 1.1f64.ln())).abs() < 1e-12);
+
+        // Extreme volatility is capped so the grid stays above zero
+        let mut wild = PriceHistory::new(5);
+        for (slot, price) in [1.0, 10.0, 1.0, 10.0, 1.0].into_iter().enumerate() {
+            wild.push(slot as u64, price);
+        }
+        assert_eq!(
+            target_spacing(volatility, &wild, 10),
+            Some(MAX_TOTAL_SPACING / 10.0)
+        );
+    }
+
+    #[test]
+    fn small_spacing_changes_do_not_rebuild() {
+        assert!(!spacing_changed(0.02, 0.024, 0.25));
+        assert!(spacing_changed(0.02, 0.026, 0.25));
+        assert!(spacing_changed(0.02, 0.014, 0.25));
+    }
+
+    #[test]
+    fn recenters_only_once_a_side_is_exhausted() {
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
+
+        // Above the grid with sells still unfilled: keep selling instead
+        assert!(!should_recenter(&grid, Levels::symmetric(3), 1, 1.2));
+        // Above the grid with every sell filled
+        assert!(should_recenter(&grid, Levels::symmetric(3), 3, 1.2));
+        // Below the grid with every buy filled
+        assert!(should_recenter(&grid, Levels::symmetric(3), -3, 0.8));
+        // Exhausted, but price is back inside the grid
+        assert!(!should_recenter(&grid, Levels::symmetric(3), 3, 1.1));
+    }
+
+    #[test]
+    fn recentering_resets_the_grid() {
+        let mut state = GridState {
+            center_price: 1.0,
+            line_offset: 3,
+            initial_strategy_amount: 100,
+            initial_base_amount: 100,
+            recenter_count: 0,
+            spacing_percent: None,
+            sell_depleted: true,
+            buy_depleted: false,
+            line_fills: [(0, 33), (1, 33)].into(),
+        };
+
+        state.recenter(1.2, 0, 205);
+        assert!(state.line_fills.is_empty());
+
+        assert_eq!(state.center_price, 1.2);
+        assert_eq!(state.line_offset, 0);
+        assert_eq!(state.initial_strategy_amount, 0);
+        assert_eq!(state.initial_base_amount, 205);
+        assert_eq!(state.recenter_count, 1);
+        assert!(state.depleted().is_empty());
+    }
+
+    #[test]
+    fn level_slices_sum_to_the_inventory() {
+        for (inventory, levels) in [(100, 3), (7, 10), (1_000_003, 7), (10, 5)] {
+            let total: u64 = (0..levels)
+                .map(|level| level_slice(inventory, levels, level))
+                .sum();
+            assert_eq!(total, inventory, "{inventory} over {levels} levels");
+        }
+        assert_eq!(level_slice(100, 3, 0), 34);
+        assert_eq!(level_slice(100, 3, 2), 33);
+    }
+
+    /// A grid of `raw_config` holding 100 of each token
+    fn raw_grid() -> GridState {
+        let config = raw_config();
+        let token = &config.strategy_token;
+        let strategy_token = AssetId::from((token.policy_id.clone(), token.asset_name.clone()));
+        let ada = AssetId::from((vec![], vec![]));
+        let order = ManagedStrategy::mock(&[(&strategy_token, 100), (&ada, 100)]);
+        GridState::new(&order, &config, 1.0)
+    }
+
+    #[test]
+    fn filling_every_level_uses_the_whole_inventory() {
+        let (config, grid) = (raw_config(), raw_grid());
+        let slices = slices_to_fill(&config, &grid, true, &[1.05, 1.1025, 1.157625], 100);
+        assert_eq!(slices, [34, 33, 33]);
+
+        let slices = slices_to_fill(&config, &grid, false, &[0.95, 0.91, 0.86], 100);
+        assert_eq!(slices.iter().sum::<u64>(), 100);
+    }
+
+    #[test]
+    fn unwinding_a_line_trades_back_what_it_sold() {
+        let config = raw_config();
+        let mut grid = raw_grid();
+        grid.line_offset = 2;
+        grid.line_fills = [(0, 34), (1, 20)].into();
+        // Buying back line 1's 20 at 1.1025, then line 0's 34 at 1.05
+        let slices = slices_to_fill(&config, &grid, false, &[1.1025, 1.05], 1_000);
+        assert_eq!(slices, [23, 36]);
+
+        // Without a record, a line trades back its share
+        grid.line_fills.clear();
+        let slices = slices_to_fill(&config, &grid, false, &[1.1025, 1.05], 1_000);
+        assert_eq!(slices, [37, 36]);
+    }
+
+    /// A 5% grid of 3 levels around 1.0 trading SBERRY for ADA
+    fn sim_config() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "center_price": 1.0,
+            "strategy_token": "99999999999999999999999999999999999999999999999999999999.534245525259",
+            "base_token": ".",
+            "spacing_percent": 0.05,
+            "levels_per_side": 3,
+        })
+    }
+
+    #[test]
+    fn a_pending_fill_stops_every_other_fill() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 3_000_000), (&sberry, 3_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let config: StrategyConfig = serde_json::from_value(sim_config()).unwrap();
+        let id = grid_state_id(&config).unwrap();
+        let line_offset = || grid_states().get(id.as_str()).unwrap().unwrap().line_offset;
+
+        // The sell at 1.05 is in flight, so crossing 1.1025 too doesn't spend the order again
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        let executions = sim
+            .run([(1, pool(1.0)), (2, pool(1.06)), (3, pool(1.11))])
+            .unwrap();
+        assert_eq!(executions.len(), 1);
+        let pending = pending_fills().get(&order.output).unwrap().unwrap();
+        assert_eq!(pending.lines, [0]);
+        assert_eq!(line_offset(), 0);
+
+        // Once it lands, the grid moves up a line and the order's successor sells the next
+        let tx = order.mock_execution(4, &[(&ada, 4_050_000), (&sberry, 2_000_000)]);
+        let successor = order.successor(&tx).unwrap();
+        sim.observe_tx(tx).unwrap();
+        assert_eq!(line_offset(), 1);
+        sim.add_order(successor.clone()).unwrap();
+        let executions = sim.run([(5, pool(1.11))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        assert_eq!(
+            pending_fills()
+                .get(&successor.output)
+                .unwrap()
+                .unwrap()
+                .lines,
+            [1]
+        );
+    }
+
+    #[test]
+    fn a_lapsed_fill_leaves_the_grid_in_place() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 3_000_000),
+            (&sberry, 3_000_000),
+        ]))
+        .unwrap();
+
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        let executions = sim.run([(1, pool(1.0)), (2, pool(1.06))]).unwrap();
+        assert_eq!(executions.len(), 1);
+
+        // Past the fill's validity window, the same line is sold again
+        let executions = sim.run([(2_000, pool(1.06))]).unwrap();
+        assert_eq!(executions.len(), 1);
+    }
+
+    #[test]
+    fn rapid_observations_do_not_refill_a_pending_line() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 3_000_000),
+            (&sberry, 3_000_000),
+        ]))
+        .unwrap();
+
+        // Up across 1.05, back down across it, and up again, all before the first fill lands
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        let executions = sim
+            .run([
+                (1, pool(1.0)),
+                (2, pool(1.06)),
+                (3, pool(1.0)),
+                (4, pool(1.06)),
+            ])
+            .unwrap();
+        assert_eq!(executions.len(), 1);
+    }
+
+    #[test]
+    fn a_multi_level_crossing_is_one_execution_per_order() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        // The odd unit of SBERRY goes to the level nearest the center
+        let order = ManagedStrategy::mock(&[(&ada, 3_000_000), (&sberry, 3_000_001)]);
+        sim.add_order(order.clone()).unwrap();
+
+        // Straight past the 1.05 and 1.1025 lines
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        let swap = |executions: &[StrategyExecution]| match &executions[0].details {
+            Order::Swap {
+                offer,
+                min_received,
+            } => (offer.2, min_received.2),
+            _ => panic!("expected a swap"),
+        };
+        let executions = sim.run([(1, pool(1.0)), (2, pool(1.11))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        // A slice for each line, asking each line's price for its own slice
+        let (offer, min_received) = swap(&executions);
+        assert_eq!(offer, 2_000_001);
+        assert!(min_received.abs_diff(1_050_001 + 1_102_500) <= 1);
+        // Nearest line first
+        let pending = pending_fills().get(&order.output).unwrap().unwrap();
+        assert_eq!(pending.lines, [0, 1]);
+        assert_eq!(pending.slices, [1_000_001, 1_000_000]);
+
+        let tx = order.mock_execution(3, &[(&ada, 5_152_501), (&sberry, 1_000_000)]);
+        let successor = order.successor(&tx).unwrap();
+        sim.observe_tx(tx).unwrap();
+        sim.add_order(successor.clone()).unwrap();
+
+        // Back down past 1.1025, 1.05 and 0.952: the upper lines are bought back, the
+        // nearest first, before the line below the center is opened
+        let executions = sim.run([(4, pool(0.94))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let pending = pending_fills().get(&successor.output).unwrap().unwrap();
+        assert_eq!(pending.lines, [1, 0, -1]);
+        assert_eq!(pending.slices, [1_102_500, 1_050_002, 1_000_000]);
+        assert_eq!(swap(&executions).0, pending.slices.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn snapshot_reports_the_grid_and_inventory() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 3_000_000), (&sberry, 3_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let config = serde_json::from_value::<StrategyConfig>(sim_config()).unwrap();
+        assert!(config.snapshot(&order).unwrap().is_none());
+
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        sim.run([(1, pool(1.0)), (2, pool(1.06))]).unwrap();
+        sim.observe_tx(order.mock_execution(3, &[(&ada, 4_050_000), (&sberry, 2_000_000)]))
+            .unwrap();
+        let snapshot = config.snapshot(&order).unwrap().unwrap();
+        assert_eq!(snapshot.center_price, 1.0);
+        assert_eq!(snapshot.line_offset, 1);
+        assert_eq!(snapshot.strategy_amount, 3_000_000);
+        assert!(snapshot.depleted.is_empty());
+    }
+
+    #[test]
+    fn reports_a_depleted_side_once() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 3_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        // No SBERRY to sell as price rises through the grid
+        let executions = sim
+            .run([(1, pool(1.0)), (2, pool(1.06)), (3, pool(1.12))])
+            .unwrap();
+        assert!(executions.is_empty());
+        assert_eq!(metrics::get(metrics::Counter::SidesDepleted).unwrap(), 1);
+
+        let config: StrategyConfig = serde_json::from_value(sim_config()).unwrap();
+        let id = grid_state_id(&config).unwrap();
+        let state = grid_states().get(id.as_str()).unwrap().unwrap();
+        assert_eq!(state.depleted(), [Side::Sell]);
+
+        // Buying SBERRY replenishes the sell side, once the buy lands
+        let executions = sim.run([(4, pool(0.94))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        sim.observe_tx(order.mock_execution(5, &[(&ada, 2_000_000), (&sberry, 1_050_000)]))
+            .unwrap();
+        let state = grid_states().get(id.as_str()).unwrap().unwrap();
+        assert!(state.depleted().is_empty());
+    }
+
+    #[test]
+    fn a_depleted_side_resumes_once_it_can_fill() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[
+            (&ada, 3_000_000),
+            (&sberry, 3_000_000),
+        ]))
+        .unwrap();
+
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        sim.run([(1, pool(1.0))]).unwrap();
+
+        // A side marked depleted while its balance looked short still sells once it can
+        let config: StrategyConfig = serde_json::from_value(sim_config()).unwrap();
+        let id = grid_state_id(&config).unwrap();
+        let mut state = grid_states().get(id.as_str()).unwrap().unwrap();
+        state.sell_depleted = true;
+        grid_states().set(id.as_str(), &state).unwrap();
+
+        let executions = sim.run([(2, pool(1.06))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let state = grid_states().get(id.as_str()).unwrap().unwrap();
+        assert!(state.depleted().is_empty());
+    }
+
+    #[test]
+    fn fills_stop_at_the_available_balance() {
+        let (config, grid) = (raw_config(), raw_grid());
+        assert_eq!(
+            slices_to_fill(&config, &grid, true, &[1.05, 1.1025, 1.157625], 70),
+            [34, 33]
+        );
+    }
+
+    #[test]
+    fn an_asymmetric_grid_buys_back_what_it_sold() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut config = sim_config();
+        config["levels_above"] = 3.into();
+        config["levels_below"] = 1.into();
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 3_000_000), (&sberry, 3_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        let swap = |executions: &[StrategyExecution]| match &executions[0].details {
+            Order::Swap {
+                offer,
+                min_received,
+            } => (offer.2, min_received.2),
+            _ => panic!("expected a swap"),
+        };
+
+        // Sell a third of the SBERRY at 1.05
+        let executions = sim.run([(1, pool(1.0)), (2, pool(1.06))]).unwrap();
+        assert_eq!(swap(&executions).0, 1_000_000);
+        let tx = order.mock_execution(3, &[(&ada, 4_050_000), (&sberry, 2_000_000)]);
+        let successor = order.successor(&tx).unwrap();
+        sim.observe_tx(tx).unwrap();
+        sim.add_order(successor.clone()).unwrap();
+
+        // Crossing back buys the same SBERRY back at 1.05, not the single buy level's
+        // whole base inventory
+        let executions = sim.run([(4, pool(1.0))]).unwrap();
+        let (offer, min_received) = swap(&executions);
+        assert_eq!(offer, 1_050_000);
+        assert!(min_received.abs_diff(1_000_000) <= 1);
+
+        let tx = successor.mock_execution(5, &[(&ada, 3_000_000), (&sberry, 3_000_000)]);
+        sim.observe_tx(tx).unwrap();
+        let config: StrategyConfig = serde_json::from_value(config).unwrap();
+        let id = grid_state_id(&config).unwrap();
+        let state = grid_states().get(id.as_str()).unwrap().unwrap();
+        assert_eq!(state.line_offset, 0);
+        assert!(state.line_fills.is_empty());
+    }
+
+    #[test]
+    fn fill_amounts_account_for_decimals() {
+        let config = serde_json::from_value::<StrategyConfig>(serde_json::json!({
+            "network": "preview",
+            "center_price": 0.000168,
+            "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "strategy_token_decimals": 0,
+            "base_token": ".",
+            "base_token_decimals": 6,
+            "spacing_percent": 0.05,
+            "levels_per_side": 3,
+        }))
+        .unwrap();
+
+        // 1000 SBERRY at 0.000168 ADA each is 0.168 ADA, i.e. 168,000 lovelace
+        assert!((config.strategy_to_base(1000, 0.000168) - 168_000.0).abs() < 1e-6);
+        // and back again
+        assert!((config.base_to_strategy(168_000, 0.000168) - 1000.0).abs() < 1e-6);
+    }
+
+    fn raw_config() -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "network": "preview",
+            "center_price": 1.0,
+            "strategy_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+            "base_token": ".",
+            "spacing_percent": 0.05,
+            "levels_per_side": 3,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn price_is_oriented_to_the_strategy_token() {
+        let config = raw_config();
+        let strategy: InlineAssetId = (
+            config.strategy_token.policy_id.clone(),
+            config.strategy_token.asset_name.clone(),
+        );
+        let ada: InlineAssetId = (vec![], vec![]);
+
+        // Pool is [ADA, strategy]: raw price is already base per strategy
+        assert_eq!(strategy_token_price(&config, &ada, 2.0), 2.0);
+        // Pool is [strategy, ADA]: raw price is strategy per base, so invert it
+        assert_eq!(strategy_token_price(&config, &strategy, 0.5), 2.0);
+    }
+
+    #[test]
+    fn fill_amounts_sum_every_slice() {
+        let config = raw_config();
+        assert_eq!(
+            fill_amounts(&config, Side::Sell, &[10, 10], &[1.05, 1.10]),
+            Some((20, 21))
+        );
+    }
+
+    #[test]
+    fn aggregate_minimum_is_the_sum_of_level_minimums() {
+        let config = raw_config();
+        let slices = [10, 10, 10];
+        let prices = [1.05, 1.05, 1.05];
+        let per_level = slices
+            .iter()
+            .zip(prices)
+            .map(|(slice, price)| level_min(&config, Side::Sell, *slice, price))
+            .collect::<Vec<_>>();
+        assert_eq!(per_level, [10, 10, 10]);
+
+        // Summing 10.5 three times before rounding would ask for 31, more than the
+        // levels are worth filled one at a time
+        assert_eq!(
+            fill_amounts(&config, Side::Sell, &slices, &prices),
+            Some((30, per_level.iter().sum()))
+        );
+    }
+
+    #[test]
+    fn no_fill_when_receive_amount_rounds_to_zero() {
+        let config = raw_config();
+        // Selling 1 unit at a tiny price receives 0.001 base units
+        assert_eq!(fill_amounts(&config, Side::Sell, &[1], &[0.001]), None);
+        // Buying with 1 unit at a huge price receives 0.001 strategy units
+        assert_eq!(fill_amounts(&config, Side::Buy, &[1], &[1000.0]), None);
+    }
+
+    fn fill(side: Side, strategy_amount: u64, base_amount: u64) -> Fill {
+        Fill {
+            slot: 0,
+            side,
+            price: base_amount as f64 / strategy_amount as f64,
+            strategy_amount,
+            base_amount,
+        }
+    }
+
+    #[test]
+    fn realized_pnl_pairs_round_trips() {
+        let fills = [
+            fill(Side::Sell, 100, 105),
+            fill(Side::Sell, 100, 110),
+            // Unwinds the 110 sell
+            fill(Side::Buy, 100, 105),
+            // Unwinds the 105 sell
+            fill(Side::Buy, 100, 100),
+        ];
+        assert!((realized_pnl(&fills) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn open_inventory_is_not_realized() {
+        let fills = [fill(Side::Sell, 100, 105), fill(Side::Sell, 100, 110)];
+        assert_eq!(realized_pnl(&fills), 0.0);
+    }
+
+    #[test]
+    fn fills_are_recorded_as_delivered() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()));
+        let mut sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        let order = ManagedStrategy::mock(&[(&ada, 3_000_000), (&sberry, 3_000_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let pool = |price: f64| {
+            PoolState::mock(
+                (price * 1_000_000_000.0) as u64,
+                1_000_000_000,
+                (&ada, &sberry),
+            )
+        };
+        sim.run([(1, pool(1.0)), (2, pool(1.06))]).unwrap();
+        let Order::Strategy { auth } = &order.order.details else {
+            panic!("expected a strategy order");
+        };
+        assert!(grid_fills().get(auth).unwrap().is_none());
+
+        // The sell asked for at least 1,050,000 lovelace, and got more
+        sim.observe_tx(order.mock_execution(3, &[(&ada, 4_060_000), (&sberry, 2_000_000)]))
+            .unwrap();
+        let fills = grid_fills().get(auth).unwrap().unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].side, Side::Sell);
+        assert_eq!(
+            (fills[0].strategy_amount, fills[0].base_amount),
+            (1_000_000, 1_060_000)
+        );
+        assert!((fills[0].price - 1.06).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fill_history_keeps_the_most_recent() {
+        let _sim = Simulator::new(strategy(), &sim_config()).unwrap();
+        let order = ManagedStrategy::mock(&[]);
+        for slot in 0..=MAX_FILLS as u64 {
+            let mut fill = fill(Side::Sell, 100, 105);
+            fill.slot = slot;
+            record_fill(&order, fill).unwrap();
+        }
+        let Order::Strategy { auth } = &order.order.details else {
+            panic!("expected a strategy order");
+        };
+        let fills = grid_fills().get(auth).unwrap().unwrap();
+        assert_eq!(fills.len(), MAX_FILLS);
+        assert_eq!(fills.front().unwrap().slot, 1);
+    }
+
     #[test]
     fn detects_upward_crossing() {
         let center = 1.0;
         let spacing = 0.05;
         let levels = 3;
 
-        let grid = compute_grid_prices(center, spacing, levels);
+        let grid = compute_grid_prices(
+            center,
+            spacing,
+            Levels::symmetric(levels),
+            SpacingMode::Geometric,
+        );
 
         let previous_offset = 0;
 
         let new_price = 1.12;
 
-        let (new_offset, crossed) = compute_crossed_prices(&grid, previous_offset, new_price);
+        let (new_offset, crossed) =
+            compute_crossed_prices(&grid, levels, previous_offset, new_price);
 
         let step = 1.0 + spacing;
 
@@ -456,13 +2058,19 @@ mod tests {
         let spacing = 0.05;
         let levels = 3;
 
-        let grid = compute_grid_prices(center, spacing, levels);
+        let grid = compute_grid_prices(
+            center,
+            spacing,
+            Levels::symmetric(levels),
+            SpacingMode::Geometric,
+        );
 
         let previous_offset = 0;
 
         let new_price = 0.89;
 
-        let (new_offset, crossed) = compute_crossed_prices(&grid, previous_offset, new_price);
+        let (new_offset, crossed) =
+            compute_crossed_prices(&grid, levels, previous_offset, new_price);
 
         let step = 1.0 + spacing;
 
@@ -479,13 +2087,13 @@ mod tests {
 
     #[test]
     fn no_grid_crossed_when_price_moves_within_band() {
-        let grid = compute_grid_prices(1.0, 0.05, 3);
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
 
         let previous_offset = 0;
 
         let new_price = 1.04;
 
-        let (new_offset, crossed) = compute_crossed_prices(&grid, previous_offset, new_price);
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, previous_offset, new_price);
 
         assert!(crossed.is_empty());
         assert_eq!(new_offset, previous_offset);
@@ -493,13 +2101,13 @@ mod tests {
 
     #[test]
     fn crossing_center_does_not_trigger_fill() {
-        let grid = compute_grid_prices(1.0, 0.05, 3);
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
 
         let previous_offset = 0;
 
         let new_price = 0.999;
 
-        let (new_offset, crossed) = compute_crossed_prices(&grid, previous_offset, new_price);
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, previous_offset, new_price);
 
         assert!(crossed.is_empty());
         assert_eq!(new_offset, 0);
@@ -507,30 +2115,80 @@ mod tests {
 
     #[test]
     fn continues_from_existing_offset() {
-        let grid = compute_grid_prices(1.0, 0.05, 3);
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
 
         let previous_offset = 1;
 
         let new_price = 1.16;
 
-        let (new_offset, crossed) = compute_crossed_prices(&grid, previous_offset, new_price);
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, previous_offset, new_price);
 
         assert_eq!(crossed.len(), 2);
         assert_eq!(new_offset, 3);
     }
 
+    #[test]
+    fn price_above_the_top_line_fills_every_remaining_level() {
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
+
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, 0, 10.0);
+
+        assert_eq!(crossed, grid[3..].to_vec());
+        assert_eq!(new_offset, 3);
+    }
+
+    #[test]
+    fn price_below_the_bottom_line_fills_every_remaining_level() {
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
+
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, 1, 0.01);
+
+        let expected: Vec<f64> = grid[..4].iter().rev().copied().collect();
+        assert_eq!(crossed, expected);
+        assert_eq!(new_offset, -3);
+    }
+
+    #[test]
+    fn price_exactly_on_a_lower_line_fills_it() {
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
+
+        // Lines are crossed once price is no longer above them
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, 0, grid[2]);
+
+        assert_eq!(crossed, vec![grid[2]]);
+        assert_eq!(new_offset, -1);
+    }
+
+    #[test]
+    fn stale_offset_outside_the_grid_does_not_panic() {
+        let grid = compute_grid_prices(1.0, 0.05, Levels::symmetric(3), SpacingMode::Geometric);
+
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, 10, 1.0);
+        assert_eq!(crossed.len(), 3);
+        assert_eq!(new_offset, 0);
+
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, -10, 1.0);
+        assert_eq!(crossed.len(), 3);
+        assert_eq!(new_offset, 0);
+    }
+
     #[test]
     fn price_exactly_on_grid_line_does_not_fill() {
         let center = 1.0;
         let spacing = 0.05;
 
-        let grid = compute_grid_prices(center, spacing, 3);
+        let grid = compute_grid_prices(
+            center,
+            spacing,
+            Levels::symmetric(3),
+            SpacingMode::Geometric,
+        );
 
         let previous_offset = 0;
 
         let new_price = grid[3];
 
-        let (new_offset, crossed) = compute_crossed_prices(&grid, previous_offset, new_price);
+        let (new_offset, crossed) = compute_crossed_prices(&grid, 3, previous_offset, new_price);
 
         assert!(crossed.is_empty());
         assert_eq!(new_offset, 0);
@@ -0,0 +1,51 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+use tracing::info;
+
+#[derive(Deserialize)]
+pub struct TakeProfitConfig {
+    pub network: Network,
+    // Tokens must be in alphanumeric order with token_a < token_b when sorted
+    pub token_a: AssetId,
+    pub token_a_decimals: u8,
+    pub token_b: AssetId,
+    pub token_b_decimals: u8,
+    pub sell_token: AssetId,
+    pub execution_price: f64,
+    /// When the order stops being acted on, in UNIX seconds. Never, if unset.
+    ///
+    /// Expiry is checked against the time of each pool update's slot. A sell stays valid for
+    /// a few seconds after the slot it was submitted at, so one submitted just before expiry
+    /// could otherwise fill after it; its validity window is cut off at `expires_at` instead.
+    /// The worker can't cancel an expired order, since only its owner can spend it other
+    /// than by executing it, so the funds stay in the order until the owner cancels it.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+impl TakeProfitConfig {
+    /// The buy asset, and the minimum raw units of it to receive per raw unit of sell_token
+    pub fn trade_direction(&self) -> (&AssetId, f64) {
+        // Prices are in whole token_a per whole token_b, so scale by the decimals
+        // difference to convert between raw amounts
+        if self.sell_token == self.token_a {
+            let scale = 10f64.powi(self.token_b_decimals as i32 - self.token_a_decimals as i32);
+            (&self.token_b, scale / self.execution_price)
+        } else {
+            let scale = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
+            (&self.token_a, self.execution_price * scale)
+        }
+    }
+
+    pub fn log_submission(&self, give_amount: u64, receive_amount: u64) {
+        info!(
+            "executing limit order to sell {give_amount} {} for {receive_amount} {}",
+            self.sell_token.name_to_string(),
+            if self.token_a == self.sell_token {
+                self.token_b.name_to_string()
+            } else {
+                self.token_a.name_to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,107 @@
+mod config;
+
+use balius_sdk::{Ack, Config, WorkerResult};
+use config::TakeProfitConfig as StrategyConfig;
+use sundae_strategies::{
+    ManagedStrategy, PoolState, Strategy, Submission, expiry, kv,
+    types::{Interval, Order, min_received},
+};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a sell order is valid for
+const VALIDITY_SECS: u64 = 20;
+
+/// Orders with a sell in flight; entries expire with the submitted validity window
+fn pending_sells() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_sell")
+}
+
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let now = pool_state.now_ms(&config.network);
+    if expiry::is_expired(config.expires_at, now) {
+        info!(
+            "orders expired at {}, no longer acting on them; cancel them to reclaim the funds",
+            config.expires_at.unwrap_or_default()
+        );
+        return Ok(Ack);
+    }
+
+    for strategy in strategies {
+        //  Skip processing for state changes of unrelated pools
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+        if strategy.is_empty(&[&config.sell_token]) {
+            info!(
+                "{:?} holds no {}, nothing to sell",
+                strategy.output,
+                config.sell_token.name_to_string()
+            );
+            continue;
+        }
+
+        // Get pool price and scale for decimals
+        let pool_price = pool_state
+            .price(config.token_a_decimals, config.token_b_decimals)
+            .value();
+        info!("pool update found, with price {}", pool_price);
+
+        // Execute if pool_price is above execution price
+        if pool_price > config.execution_price {
+            if pending_sells().get(&strategy.output)?.is_some() {
+                info!(
+                    "sell already submitted for {:?}, waiting for it to land",
+                    strategy.output
+                );
+                continue;
+            }
+            info!(
+                "price has risen to {}, above TP price of {}. Triggering a sell order...",
+                pool_price, config.execution_price
+            );
+            let validity_range = expiry::validity_range(config.expires_at, now, VALIDITY_SECS);
+            if let Submission::Submitted(_) = trigger_sell(config, validity_range, strategy)? {
+                pending_sells().set_with_ttl(&strategy.output, &true, VALIDITY_SECS)?;
+            }
+        }
+    }
+    Ok(Ack)
+}
+
+fn trigger_sell(
+    config: &StrategyConfig,
+    validity_range: Interval,
+    order: &ManagedStrategy,
+) -> WorkerResult<Submission> {
+    let give_amount = order.balance(&config.sell_token);
+
+    // Get the buy asset and the minimum number of buy tokens per sell token based on config
+    let (buy_token, price_ratio) = config.trade_direction();
+    let receive_amount = min_received(give_amount, price_ratio, 0.0);
+
+    let swap = Order::swap(
+        (&config.sell_token, give_amount),
+        (buy_token, receive_amount),
+    );
+
+    // Submit to relay and log
+    let submission = order.submit_execution(&config.network, validity_range, swap)?;
+    if !submission.is_skipped() {
+        config.log_submission(give_amount, receive_amount);
+    }
+    Ok(submission)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .with_validity_window(VALIDITY_SECS)
+        .worker()
+}
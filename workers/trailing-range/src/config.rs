@@ -0,0 +1,70 @@
+use serde::Deserialize;
+use sundae_strategies::{
+    DEFAULT_SLIPPAGE_TOLERANCE, DEFAULT_VALIDITY_WINDOW_SECS, Network, types::AssetId,
+    validate_fraction, validate_validity_window,
+};
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token bought after a rebound and sold after a pullback
+    pub token_a: AssetId,
+    /// The token it is priced in, and held between round trips
+    pub token_b: AssetId,
+    /// While holding token_a, sell it once the price falls this far below its peak
+    /// (e.g., 0.1 = 10%). Must be in range (0.0, 1.0).
+    pub sell_trail: f64,
+    /// While holding token_b, buy token_a once the price rises this far above its trough
+    /// (e.g., 0.05 = 5%). Must be in range (0.0, 1.0).
+    pub buy_trail: f64,
+    /// Maximum acceptable slippage when executing either trade (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+    /// How long, in seconds either side of the trigger, each trade is valid for.
+    /// Must be within 60..=3600. Defaults to 20 minutes if not specified.
+    pub validity_window_secs: u64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    token_a: AssetId,
+    token_b: AssetId,
+    sell_trail: f64,
+    buy_trail: f64,
+    slippage_tolerance: Option<f64>,
+    validity_window_secs: Option<u64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        let sell_trail = validate_fraction("sell_trail", raw.sell_trail)?;
+        let buy_trail = validate_fraction("buy_trail", raw.buy_trail)?;
+        let slippage_tolerance = validate_fraction(
+            "slippage_tolerance",
+            raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE),
+        )?;
+        let validity_window_secs = validate_validity_window(
+            raw.validity_window_secs
+                .unwrap_or(DEFAULT_VALIDITY_WINDOW_SECS),
+        )?;
+
+        if raw.token_a == raw.token_b {
+            return Err("token_a and token_b must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            token_a: raw.token_a,
+            token_b: raw.token_b,
+            sell_trail,
+            buy_trail,
+            slippage_tolerance,
+            validity_window_secs,
+        })
+    }
+}
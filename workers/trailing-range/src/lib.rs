@@ -0,0 +1,534 @@
+//! # Trailing Range Strategy
+//!
+//! This strategy combines a trailing stop loss and a trailing take profit: it sells
+//! `token_a` once the price pulls back from its peak, buys it back once the price
+//! rebounds from its trough, and repeats, flipping its whole inventory between the
+//! two tokens each time.
+//!
+//! ## How It Works
+//!
+//! The strategy persists which side each position is `holding`, along with the peak and
+//! trough it trails, starting from whichever token holds more of the position's value
+//! when it is first observed, with both extremes at that first price. Each execution pays
+//! the position back to a new order UTxO, which the state moves to.
+//!
+//! - **Holding `token_a`**: the peak follows the price up, and once the price falls
+//!   `sell_trail` below it, all `token_a` is swapped for `token_b`.
+//! - **Holding `token_b`**: the trough follows the price down, and once the price rises
+//!   `buy_trail` above it, all `token_b` is swapped for `token_a`.
+//!
+//! While a trade is in flight nothing else is submitted; once its order UTxO is spent
+//! (reported through `on_strategy_spent`) by a transaction that delivered the swap, the
+//! held side flips. A trade whose validity window lapses first, or whose order is spent
+//! without it, e.g. by the owner, is resubmitted if the price is still past its trigger.
+//!
+//! On a flip, the extreme the new side trails restarts from the price the trade filled
+//! at. Left alone, it would still hold the trough or peak from before the previous flip,
+//! which the price has already moved well away from, so the next trade would trigger
+//! straight away.
+//!
+//! ## Example
+//!
+//! With `sell_trail = 0.1` and `buy_trail = 0.05`, holding `token_a`:
+//!
+//! 1. The price rises from 100 to 120: the peak follows it, so the sell triggers at 108
+//! 2. The price falls to 105: all `token_a` is sold, and the trough restarts at the
+//!    price it fills at, say 105
+//! 3. The price falls to 90: the trough follows it, so the buy triggers at 94.5
+//! 4. The price rises to 95: all `token_b` buys `token_a`, and the peak restarts at the
+//!    price that fills at, say 95
+//!
+//! ## Configuration
+//!
+//! - `token_a`: The token bought after a rebound and sold after a pullback
+//! - `token_b`: The token it is priced in, and held between round trips
+//! - `sell_trail`: How far below the peak `token_a` is sold (0.1 = 10%)
+//! - `buy_trail`: How far above the trough `token_a` is bought (0.05 = 5%)
+//! - `slippage_tolerance`: Maximum acceptable slippage on either trade (0.03 = 3%)
+//! - `validity_window_secs`: How long each trade is valid for, either side of the trigger
+//!   (60 to 3600, default 1200)
+//!
+//! ## Price Calculation
+//!
+//! Price is always calculated as: "how much raw token_b per 1 raw token_a"
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    EventTime, ManagedStrategy, PoolState, Strategy, kv,
+    state::StateSnapshot,
+    types::{AssetId, Order, min_received},
+};
+use tracing::info;
+
+/// Which token a trailing range strategy is holding
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Holding {
+    /// Holding token_a, trailing its peak to sell
+    TokenA,
+    /// Holding token_b, trailing the trough of token_a to buy
+    TokenB,
+}
+
+impl Holding {
+    /// The tokens a trade from this side offers and receives.
+    fn trade(self, config: &StrategyConfig) -> (&AssetId, &AssetId) {
+        match self {
+            Holding::TokenA => (&config.token_a, &config.token_b),
+            Holding::TokenB => (&config.token_b, &config.token_a),
+        }
+    }
+}
+
+/// A trade submitted and not yet seen to fill
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct InFlight {
+    /// UNIX time (ms) after which it can no longer fill
+    until_ms: u64,
+    /// The pool price it was submitted at
+    price: f64,
+}
+
+/// Progress of a trailing range position, persisted per order UTxO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RangeState {
+    holding: Holding,
+    /// The highest price since token_a was last bought
+    peak_price: f64,
+    /// The lowest price since token_a was last sold
+    trough_price: f64,
+    in_flight: Option<InFlight>,
+}
+
+impl RangeState {
+    fn new(holding: Holding, price: f64) -> Self {
+        Self {
+            holding,
+            peak_price: price,
+            trough_price: price,
+            in_flight: None,
+        }
+    }
+
+    /// Follow `price` with the extreme the held side trails.
+    fn observe(&mut self, price: f64) {
+        match self.holding {
+            Holding::TokenA => self.peak_price = self.peak_price.max(price),
+            Holding::TokenB => self.trough_price = self.trough_price.min(price),
+        }
+    }
+
+    /// The price the held side trades at.
+    fn trigger_price(&self, config: &StrategyConfig) -> f64 {
+        match self.holding {
+            Holding::TokenA => self.peak_price * (1.0 - config.sell_trail),
+            Holding::TokenB => self.trough_price * (1.0 + config.buy_trail),
+        }
+    }
+
+    /// Whether `price` is at or past the trigger of the held side.
+    fn is_triggered(&self, config: &StrategyConfig, price: f64) -> bool {
+        let trigger_price = self.trigger_price(config);
+        match self.holding {
+            Holding::TokenA => price <= trigger_price,
+            Holding::TokenB => price >= trigger_price,
+        }
+    }
+
+    /// Switch sides once a trade has filled at `fill_price`, restarting the extreme the
+    /// new side trails from it.
+    fn flip(&mut self, fill_price: f64) {
+        match self.holding {
+            Holding::TokenA => {
+                self.holding = Holding::TokenB;
+                self.trough_price = fill_price;
+            }
+            Holding::TokenB => {
+                self.holding = Holding::TokenA;
+                self.peak_price = fill_price;
+            }
+        }
+    }
+}
+
+fn range_states() -> kv::StrategyState<RangeState> {
+    kv::StrategyState::per_output("trailing_range_state")
+}
+
+/// The price of token_a in raw token_b, or None if the pool is empty.
+fn token_a_price(config: &StrategyConfig, pool_state: &PoolState) -> Option<f64> {
+    let raw_price = pool_state.pool_datum.raw_price(&pool_state.utxo);
+    let price = if config.token_b == pool_state.pool_datum.assets.0 {
+        raw_price.value()
+    } else {
+        raw_price.invert().value()
+    };
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let Some(price) = token_a_price(config, pool_state) else {
+        info!("pool has no usable price, skipping this observation");
+        return Ok(Ack);
+    };
+    let now_ms = pool_state.now_ms(&config.network);
+    let range_states = range_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.token_a, &config.token_b) {
+            continue;
+        }
+
+        let Some(mut state) = range_states.load(strategy)? else {
+            let value_a = strategy.balance(&config.token_a) as f64 * price;
+            let value_b = strategy.balance(&config.token_b) as f64;
+            let holding = if value_a >= value_b {
+                Holding::TokenA
+            } else {
+                Holding::TokenB
+            };
+            info!(
+                "strategy {:?}: starting out holding {holding:?} at {price}",
+                strategy.output
+            );
+            range_states.store(strategy, &RangeState::new(holding, price))?;
+            continue;
+        };
+
+        state.observe(price);
+        if let Some(in_flight) = state.in_flight {
+            if now_ms <= in_flight.until_ms {
+                range_states.store(strategy, &state)?;
+                continue;
+            }
+            // The last trade lapsed without filling; retry while the price is still past
+            info!(
+                "strategy {:?}: trade submitted at {} lapsed",
+                strategy.output, in_flight.price
+            );
+            state.in_flight = None;
+        }
+        if !state.is_triggered(config, price) {
+            range_states.store(strategy, &state)?;
+            continue;
+        }
+
+        let (offer_token, receive_token) = state.holding.trade(config);
+        let receive_price = match state.holding {
+            Holding::TokenA => price,
+            Holding::TokenB => 1.0 / price,
+        };
+        let offer = strategy.balance(offer_token);
+        if offer == 0 {
+            info!(
+                "strategy {:?}: holds no {} to trade",
+                strategy.output,
+                offer_token.name_to_string()
+            );
+            range_states.store(strategy, &state)?;
+            continue;
+        }
+        let receive = pool_state
+            .min_received_after_fees(offer_token, offer, config.slippage_tolerance)
+            .unwrap_or_else(|| min_received(offer, receive_price, config.slippage_tolerance));
+        info!(
+            "strategy {:?}: price {price} passed trigger {}, swapping {offer} {} for min {receive} {}",
+            strategy.output,
+            state.trigger_price(config),
+            offer_token.name_to_string(),
+            receive_token.name_to_string()
+        );
+
+        let swap = Order::swap((offer_token, offer), (receive_token, receive));
+        let validity_range =
+            pool_state.get_validity_range(&config.network, config.validity_window_secs);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        state.in_flight = Some(InFlight {
+            until_ms: now_ms.saturating_add(config.validity_window_secs * 1000),
+            price,
+        });
+        range_states.store(strategy, &state)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let range_states = range_states();
+    let Some(mut state) = range_states.load(strategy)? else {
+        return Ok(Ack);
+    };
+    range_states.clear(strategy)?;
+
+    let (offer_token, receive_token) = state.holding.trade(config);
+    let fill = state
+        .in_flight
+        .and_then(|_| strategy.swap_fill(tx, offer_token, receive_token));
+    let successor = match fill {
+        Some(fill) => {
+            // In raw token_b per raw token_a, whichever way the trade went
+            let fill_price = match state.holding {
+                Holding::TokenA => 1.0 / fill.price(),
+                Holding::TokenB => fill.price(),
+            };
+            state.in_flight = None;
+            state.flip(fill_price);
+            info!(
+                "strategy {:?}: trade filled at {fill_price}, now holding {:?}",
+                strategy.output, state.holding
+            );
+            fill.successor
+        }
+        None => {
+            let Some(successor) = strategy.successor(tx) else {
+                info!("strategy {:?}: closed", strategy.output);
+                return Ok(Ack);
+            };
+            if let Some(in_flight) = state.in_flight.take() {
+                // The trade can no longer fill, so it's retried while the price is still
+                // past its trigger
+                info!(
+                    "strategy {:?}: spent without the trade submitted at {} filling",
+                    strategy.output, in_flight.price
+                );
+            }
+            successor
+        }
+    };
+    range_states.store(&successor, &state)?;
+    Ok(Ack)
+}
+
+// ============================================================================
+// get-state snapshot
+// ============================================================================
+
+/// What get-state reports for a trailing range order
+#[derive(Serialize)]
+pub struct RangeSnapshot {
+    holding: Holding,
+    /// The highest price since token_a was last bought
+    peak_price: f64,
+    /// The lowest price since token_a was last sold
+    trough_price: f64,
+    /// The price the held side trades at
+    trigger_price: f64,
+    /// Whether a trade is waiting to fill
+    in_flight: bool,
+}
+
+impl StateSnapshot for StrategyConfig {
+    type Snapshot = RangeSnapshot;
+
+    fn snapshot(&self, order: &ManagedStrategy) -> WorkerResult<Option<RangeSnapshot>> {
+        Ok(range_states().load(order)?.map(|state| RangeSnapshot {
+            holding: state.holding,
+            peak_price: state.peak_price,
+            trough_price: state.trough_price,
+            trigger_price: state.trigger_price(self),
+            in_flight: state.in_flight.is_some(),
+        }))
+    }
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+        .with_state()
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::sim::Simulator;
+
+    fn sberry() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SBERRY".to_vec()))
+    }
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "token_a": "99999999999999999999999999999999999999999999999999999999534245525259",
+            "token_b": ".",
+            "sell_trail": 0.1,
+            "buy_trail": 0.05,
+        })
+    }
+
+    #[test]
+    fn trails_must_be_fractions() {
+        for (field, value) in [
+            ("sell_trail", 0.0),
+            ("sell_trail", 1.0),
+            ("buy_trail", 0.0),
+            ("buy_trail", 1.5),
+            ("slippage_tolerance", 1.0),
+        ] {
+            let mut json = config_json();
+            json[field] = value.into();
+            assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+        }
+
+        let mut json = config_json();
+        json["token_b"] = json["token_a"].clone();
+        assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+
+        let mut json = config_json();
+        json["validity_window_secs"] = 30.into();
+        assert!(serde_json::from_value::<StrategyConfig>(json).is_err());
+    }
+
+    #[test]
+    fn a_flip_restarts_the_opposite_extreme_from_the_fill() {
+        // The example from the module docs
+        let config: StrategyConfig = serde_json::from_value(config_json()).unwrap();
+        let mut state = RangeState::new(Holding::TokenA, 100.0);
+        state.observe(120.0);
+        assert!((state.trigger_price(&config) - 108.0).abs() < 1e-9);
+        assert!(state.is_triggered(&config, 105.0));
+
+        // The trough of 100 from before trailing the peak would trigger a buy at 105
+        state.flip(105.0);
+        assert_eq!(state.holding, Holding::TokenB);
+        assert_eq!(state.trough_price, 105.0);
+        assert!(!state.is_triggered(&config, 105.0));
+
+        state.observe(90.0);
+        assert!((state.trigger_price(&config) - 94.5).abs() < 1e-9);
+        assert!(state.is_triggered(&config, 95.0));
+
+        state.flip(95.0);
+        assert_eq!(state.holding, Holding::TokenA);
+        assert_eq!(state.peak_price, 95.0);
+        assert!(!state.is_triggered(&config, 95.0));
+    }
+
+    #[test]
+    fn sells_once_the_price_pulls_back_from_its_peak() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = sberry();
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sberry, 1_000)]))
+            .unwrap();
+
+        // Holding SBERRY; the sell waits in flight while the price keeps falling
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim
+            .run([
+                (1, pool(100)),
+                (2, pool(120)),
+                (3, pool(110)),
+                (4, pool(105)),
+                (5, pool(100)),
+            ])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 1_000);
+        // What the pool at 105 delivers for 1,000 SBERRY, less 3% slippage
+        assert!(min_received.2.abs_diff(101_748) <= 1);
+    }
+
+    #[test]
+    fn each_position_trails_on_its_own() {
+        let ada = AssetId::from((vec![], vec![]));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let holds_sberry = ManagedStrategy::mock(&[(&sberry(), 1_000)]);
+        let mut holds_ada = ManagedStrategy::mock(&[(&ada, 100_000)]);
+        holds_ada.output.output_index = 1;
+        sim.add_order(holds_sberry.clone()).unwrap();
+        sim.add_order(holds_ada.clone()).unwrap();
+
+        let pool = PoolState::mock(80_000_000, 1_000_000, (&ada, &sberry()));
+        sim.run([(1, pool)]).unwrap();
+        let holding = |order| range_states().load(order).unwrap().unwrap().holding;
+        assert_eq!(holding(&holds_sberry), Holding::TokenA);
+        assert_eq!(holding(&holds_ada), Holding::TokenB);
+    }
+
+    #[test]
+    fn sells_then_buys_back_once_each_trade_fills() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = sberry();
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&sberry, 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sberry));
+        let executions = sim
+            .run([(1, pool(100)), (2, pool(120)), (3, pool(105))])
+            .unwrap();
+        assert_eq!(executions.len(), 1);
+
+        // Spending the order without the swap, e.g. to top it up, doesn't flip it, and
+        // the sell is retried from the new UTxO
+        let top_up = order.mock_execution(4, &[(&sberry, 1_500)]);
+        let topped_up = order.successor(&top_up).unwrap();
+        sim.observe_tx(top_up).unwrap();
+        assert!(range_states().load(&order).unwrap().is_none());
+        let state = range_states().load(&topped_up).unwrap().unwrap();
+        assert_eq!(state.holding, Holding::TokenA);
+        assert!(state.in_flight.is_none());
+        let executions = sim.run([(5, pool(105))]).unwrap();
+        assert_eq!(executions.len(), 1);
+
+        // The sell fills at 102, below the 105 it was submitted at
+        let sell = topped_up.mock_execution(6, &[(&ada, 153_000)]);
+        let sold = topped_up.successor(&sell).unwrap();
+        sim.observe_tx(sell).unwrap();
+        let state = range_states().load(&sold).unwrap().unwrap();
+        assert_eq!(state.holding, Holding::TokenB);
+        assert!((state.trough_price - 102.0).abs() < 1e-9);
+
+        // The trough follows the price down to 90, and the rebound to 95 buys SBERRY back
+        // with everything the sell delivered
+        let executions = sim.run([(7, pool(90)), (8, pool(95))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { offer, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 153_000);
+
+        let buy = sold.mock_execution(9, &[(&sberry, 1_560)]);
+        let bought = sold.successor(&buy).unwrap();
+        sim.observe_tx(buy).unwrap();
+        let state = range_states().load(&bought).unwrap().unwrap();
+        assert_eq!(state.holding, Holding::TokenA);
+        assert!((state.peak_price - 153_000.0 / 1_560.0).abs() < 1e-9);
+    }
+}
@@ -1,11 +1,44 @@
 use serde::Deserialize;
-use sundae_strategies::{Network, types::AssetId};
+use sundae_strategies::{
+    DEFAULT_VALIDITY_WINDOW_SECS, Network, history::PriceSource, types::AssetId, validate_finite,
+    validate_validity_window,
+};
 
 /// Default slippage tolerance (3%)
 /// This allows the exit order to fill even if price drops slightly between
 /// trigger and execution, while still protecting against catastrophic fills.
 const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
 
+/// How the `trail_mode` config measures the distance between the peak and the trigger
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+enum TrailMode {
+    /// A fraction of the peak, given as `trail_percent`
+    #[default]
+    Percent,
+    /// A fixed price distance, given as `trail_amount`
+    Absolute,
+}
+
+/// How far below the peak price the stop triggers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trail {
+    /// A fraction of the peak (e.g., 0.15 = 15% below the peak)
+    Percent(f64),
+    /// A fixed distance below the peak, in raw exit_token per raw position_token
+    Absolute(f64),
+}
+
+impl Trail {
+    /// The trigger price `steps` trail distances below `peak_price`.
+    pub fn trigger_price(&self, peak_price: f64, steps: usize) -> f64 {
+        match self {
+            Trail::Percent(percent) => peak_price * (1.0 - percent * steps as f64),
+            Trail::Absolute(amount) => peak_price - amount * steps as f64,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(try_from = "ConfigRaw")]
 pub struct Config {
@@ -14,9 +47,10 @@ pub struct Config {
     pub position_token: AssetId,
     /// The token to swap into when TSL triggers (exit destination)
     pub exit_token: AssetId,
-    /// How far below the peak price the stop triggers (e.g., 0.15 = 15%)
-    /// Must be in range (0.0, 1.0) - e.g., 0.15 means trigger at 15% below peak
-    pub trail_percent: f64,
+    /// How far below the peak price the stop triggers, set by `trail_mode`:
+    /// - `"percent"` (the default): `trail_percent` of the peak, in range (0.0, 1.0)
+    /// - `"absolute"`: `trail_amount` below the peak, in raw exit_token per raw position_token
+    pub trail: Trail,
     /// Maximum acceptable slippage when executing the exit order (e.g., 0.03 = 3%)
     /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
     /// This determines the minimum amount of exit_token accepted in the swap.
@@ -27,6 +61,21 @@ pub struct Config {
     /// an existing position (cancel + recreate) to preserve the previous peak.
     /// Not displayed on frontend - populated automatically during position modify.
     pub entry_price: Option<f64>,
+    /// Optional price the pool must first reach before trailing begins.
+    /// Until then the peak isn't updated, so the trigger stays fixed at the initial
+    /// peak less the trail. If not specified, trailing begins immediately.
+    pub activation_price: Option<f64>,
+    /// Optional fractions of the original position to sell in tranches, each at a further
+    /// trail distance below the peak than the last. Must sum to at most 1.
+    /// If not specified, the whole position is sold when the stop triggers.
+    pub scale_out: Option<Vec<f64>>,
+    /// How long, in seconds either side of the trigger, the exit order is valid for.
+    /// Must be within 60..=3600. Defaults to 20 minutes if not specified.
+    pub validity_window_secs: u64,
+    /// Which price the peak and trigger follow: `"spot"` (the default), each observed pool
+    /// price, or `{"twap": {"window": N}}`, the average of the last N, so a brief spike
+    /// can't raise the peak and a brief dip can't trigger the exit.
+    pub price_source: PriceSource,
 }
 
 /// Raw config for deserialization before validation
@@ -35,29 +84,70 @@ struct ConfigRaw {
     network: Network,
     position_token: AssetId,
     exit_token: AssetId,
-    trail_percent: f64,
+    #[serde(default)]
+    trail_mode: TrailMode,
+    trail_percent: Option<f64>,
+    trail_amount: Option<f64>,
     slippage_tolerance: Option<f64>,
     entry_price: Option<f64>,
+    activation_price: Option<f64>,
+    scale_out: Option<Vec<f64>>,
+    validity_window_secs: Option<u64>,
+    #[serde(default)]
+    price_source: PriceSource,
 }
 
 impl TryFrom<ConfigRaw> for Config {
     type Error = String;
 
     fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
-        // Validate trail_percent is in valid range
-        if raw.trail_percent <= 0.0 {
-            return Err(format!(
-                "trail_percent must be > 0.0, got {}",
-                raw.trail_percent
-            ));
+        let floats = [
+            ("trail_percent", raw.trail_percent),
+            ("trail_amount", raw.trail_amount),
+            ("slippage_tolerance", raw.slippage_tolerance),
+            ("entry_price", raw.entry_price),
+            ("activation_price", raw.activation_price),
+        ];
+        for (name, value) in floats {
+            if let Some(value) = value {
+                validate_finite(name, value)?;
+            }
         }
-        if raw.trail_percent >= 1.0 {
-            return Err(format!(
-                "trail_percent must be < 1.0, got {}",
-                raw.trail_percent
-            ));
+        for fraction in raw.scale_out.iter().flatten() {
+            validate_finite("scale_out", *fraction)?;
         }
 
+        let trail = match raw.trail_mode {
+            TrailMode::Percent => {
+                let Some(trail_percent) = raw.trail_percent else {
+                    return Err("trail_percent is required in percent trail_mode".to_string());
+                };
+                // Validate trail_percent is in valid range
+                if trail_percent <= 0.0 {
+                    return Err(format!(
+                        "trail_percent must be > 0.0, got {}",
+                        trail_percent
+                    ));
+                }
+                if trail_percent >= 1.0 {
+                    return Err(format!(
+                        "trail_percent must be < 1.0, got {}",
+                        trail_percent
+                    ));
+                }
+                Trail::Percent(trail_percent)
+            }
+            TrailMode::Absolute => {
+                let Some(trail_amount) = raw.trail_amount else {
+                    return Err("trail_amount is required in absolute trail_mode".to_string());
+                };
+                if trail_amount <= 0.0 {
+                    return Err(format!("trail_amount must be > 0.0, got {}", trail_amount));
+                }
+                Trail::Absolute(trail_amount)
+            }
+        };
+
         // Use default slippage tolerance if not provided
         let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
 
@@ -82,18 +172,75 @@ impl TryFrom<ConfigRaw> for Config {
             return Err(format!("entry_price must be > 0.0, got {}", price));
         }
 
+        // Validate activation_price if provided
+        if let Some(price) = raw.activation_price
+            && price <= 0.0
+        {
+            return Err(format!("activation_price must be > 0.0, got {}", price));
+        }
+
+        // Validate scale_out tranches if provided
+        if let Some(fractions) = &raw.scale_out {
+            if fractions.is_empty() {
+                return Err("scale_out must not be empty".to_string());
+            }
+            if let Some(fraction) = fractions.iter().find(|f| !(**f > 0.0 && **f <= 1.0)) {
+                return Err(format!(
+                    "each of scale_out must be in (0.0, 1.0], got {}",
+                    fraction
+                ));
+            }
+            let total: f64 = fractions.iter().sum();
+            if total > 1.0 + 1e-9 {
+                return Err(format!("scale_out must sum to at most 1.0, got {}", total));
+            }
+            // The deepest tranche's trigger must stay above zero
+            if let Trail::Percent(trail_percent) = trail
+                && trail_percent * fractions.len() as f64 >= 1.0
+            {
+                return Err(format!(
+                    "trail_percent ({}) is too wide for {} scale_out tranches",
+                    trail_percent,
+                    fractions.len()
+                ));
+            }
+        }
+
+        // An absolute trail wider than the entry price would put the initial trigger
+        // below zero, where it can never fire
+        if let (Trail::Absolute(_), Some(entry_price)) = (trail, raw.entry_price) {
+            let steps = raw.scale_out.as_ref().map_or(1, Vec::len);
+            if trail.trigger_price(entry_price, steps) <= 0.0 {
+                return Err(format!(
+                    "trail_amount is too wide for entry_price {}: the initial trigger would not be above 0.0",
+                    entry_price
+                ));
+            }
+        }
+
         // Validate position_token and exit_token are different
         if raw.position_token == raw.exit_token {
             return Err("position_token and exit_token must be different tokens".to_string());
         }
 
+        raw.price_source.validate()?;
+
+        let validity_window_secs = validate_validity_window(
+            raw.validity_window_secs
+                .unwrap_or(DEFAULT_VALIDITY_WINDOW_SECS),
+        )?;
+
         Ok(Config {
             network: raw.network,
             position_token: raw.position_token,
             exit_token: raw.exit_token,
-            trail_percent: raw.trail_percent,
+            trail,
             slippage_tolerance,
             entry_price: raw.entry_price,
+            activation_price: raw.activation_price,
+            scale_out: raw.scale_out,
+            validity_window_secs,
+            price_source: raw.price_source,
         })
     }
 }
@@ -8,8 +8,8 @@
 //! (via atomic entry from the frontend). It then monitors price movements:
 //!
 //! - **Price goes up**: The peak price updates to the new high, and trigger price
-//!   is recalculated as `peak_price * (1 - trail_percent)`. This locks in gains
-//!   as the price rises.
+//!   is recalculated as `peak_price * (1 - trail_percent)`, or `peak_price - trail_amount`
+//!   with an absolute `trail_mode`. This locks in gains as the price rises.
 //!
 //! - **Price goes down**: If the price drops below the trigger price, the strategy
 //!   exits the position, selling all `position_token` for `exit_token`.
@@ -29,15 +29,45 @@
 //! > (if provided) or the pool price at the worker's first observation of the position.
 //! > When modifying an existing position, use the `get-peak-price` request handler to
 //! > retrieve the current peak and pass it as `entry_price` to preserve trailing gains.
+//! > The `get-state` request reports the peak along with the current trigger price.
+//!
+//! If an `activation_price` is configured, trailing only begins once the pool price
+//! first reaches it. Until then the peak stays at its initial value, so the trigger is
+//! a fixed floor, and a position that starts underwater doesn't trail a peak below its
+//! entry. Activation is persisted per order, so it survives restarts.
+//!
+//! With `scale_out` configured, the position is sold in tranches instead of all at once.
+//! Tranche `n` (from 0) triggers `n + 1` trail distances below the peak, e.g. at
+//! `peak_price * (1 - trail_percent * (n + 1))`, and sells
+//! its fraction of the original position, with deeper drops crossing, and selling, more
+//! tranches together. Each tranche is a partial fill, so the rest of the position returns
+//! to a new order UTxO, which resumes trailing from the peak the tranche sold at. A
+//! tranche only counts as sold once the transaction delivering it is seen; one that lapses
+//! unfilled is sold again. Any part of the position not covered by the fractions is left
+//! alone once every tranche is sold.
+//!
+//! Peak prices and activations are kept until the order is spent, so an exit that lapses
+//! unfilled resumes from the same peak. While an exit is in flight, its order isn't exited
+//! again until the exit's validity window has passed.
 //!
 //! ## Configuration
 //!
 //! - `position_token`: The token being protected (what you're holding)
 //! - `exit_token`: The token to swap into when TSL triggers
-//! - `trail_percent`: How far below the peak the stop triggers (0.15 = 15%)
+//! - `trail_mode`: `"percent"` (the default) or `"absolute"`
+//! - `trail_percent`: In percent mode, how far below the peak the stop triggers (0.15 = 15%)
+//! - `trail_amount`: In absolute mode, how far below the peak the stop triggers, in raw
+//!   `exit_token` per raw `position_token` (e.g., 5 ADA per token is 5,000,000 with ADA as
+//!   the exit and a 0-decimal position token)
 //! - `slippage_tolerance`: Maximum acceptable slippage on exit (0.03 = 3%)
 //! - `entry_price`: Optional initial peak price. If set, used instead of discovering
 //!   from pool price. Useful when modifying positions to preserve the previous peak.
+//! - `activation_price`: Optional price the pool must reach before trailing begins
+//! - `scale_out`: Optional fractions of the position to sell at successive trailing levels
+//! - `validity_window_secs`: How long the exit order is valid for, either side of the
+//!   trigger (60 to 3600, default 1200)
+//! - `price_source`: `"spot"` (the default) to trail each observed pool price, or
+//!   `{"twap": {"window": N}}` to trail the average of the last N observations
 //!
 //! ## Price Calculation
 //!
@@ -49,25 +79,99 @@ mod config;
 
 use std::time::Duration;
 
-use balius_sdk::{_internal::Handler, Ack, Config, Json, Params, WorkerResult, wit};
+use balius_sdk::{_internal::Handler, Ack, Config, Json, Params, Tx, WorkerResult, wit};
 use config::Config as StrategyConfig;
 use serde::{Deserialize, Serialize};
 use sundae_strategies::{
-    ManagedStrategy, PoolState, Strategy, kv,
-    types::{AssetId, Interval, Order, OutputReference, TransactionId, asset_amount},
+    EventTime, ManagedStrategy, PoolState, Strategy, Submission,
+    kv::{self, NamespaceKey},
+    state::StateSnapshot,
+    types::{AssetId, Interval, Order, OutputReference, TransactionId, min_received},
 };
 use tracing::info;
 
-/// Key prefix for storing peak prices per strategy
-pub const PEAK_PRICE_PREFIX: &str = "peak_price:";
+/// Peak prices, stored per strategy output
+fn peak_prices() -> kv::StrategyState<f64> {
+    kv::StrategyState::per_output("peak_price")
+}
+
+/// Whether trailing has begun, stored per strategy output for configs with an `activation_price`
+fn activations() -> kv::StrategyState<bool> {
+    kv::StrategyState::per_output("trailing_active")
+}
+
+/// Orders with an exit in flight; entries expire with the submitted validity window
+fn pending_exits() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_exit")
+}
+
+/// Whether `strategy` has begun trailing, activating it now if `pool_price` has reached the
+/// configured `activation_price`. Without an `activation_price`, trailing is always active.
+fn is_trailing(config: &StrategyConfig, strategy: &ManagedStrategy, pool_price: f64) -> bool {
+    let Some(activation_price) = config.activation_price else {
+        return true;
+    };
+    let activations = activations();
+    match activations.load(strategy) {
+        Ok(Some(true)) => return true,
+        Ok(_) => {}
+        Err(e) => tracing::error!("kv get for activation failed: {e}"),
+    }
+    if pool_price < activation_price {
+        return false;
+    }
+    info!(
+        "activating trailing for {}#{}: price {pool_price} reached {activation_price}",
+        hex::encode(&strategy.output.transaction_id.0),
+        strategy.output.output_index,
+    );
+    if let Err(e) = activations.store(strategy, &true) {
+        tracing::error!("failed to store activation: {e}");
+    }
+    true
+}
+
+/// The ids of stored per-output state whose order is no longer managed by the worker.
+fn orphaned_ids(stored: impl Iterator<Item = String>, managed: &[OutputReference]) -> Vec<String> {
+    let live: Vec<String> = managed
+        .iter()
+        .map(|output| output.namespace_key())
+        .collect();
+    stored.filter(|id| !live.contains(id)).collect()
+}
 
-fn peak_price_key(output: &OutputReference) -> String {
-    format!(
-        "{}{}#{}",
-        PEAK_PRICE_PREFIX,
-        hex::encode(&output.transaction_id.0),
-        output.output_index
-    )
+/// Carry scale-out progress to the order UTxO a tranche leaves behind, then drop the
+/// pending exit of the spent order, and the peak price and activation of every order that
+/// has left the managed set, including any left behind before orders were cleaned up as
+/// they were spent.
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    if config.scale_out.is_some() {
+        carry_scale_out(config, tx, strategy)?;
+    }
+    pending_exits().delete(&strategy.output)?;
+    let managed: Vec<OutputReference> = sundae_strategies::managed_strategies()?
+        .into_iter()
+        .map(|s| s.output)
+        .collect();
+    let peak_prices = peak_prices();
+    let stored = peak_prices.namespace().ids()?.into_iter();
+    for id in orphaned_ids(stored, &managed) {
+        info!(
+            "removing peak price for {id}, spent by {:?}",
+            strategy.output
+        );
+        peak_prices.namespace().delete(id.as_str())?;
+    }
+    let activations = activations();
+    let stored = activations.namespace().ids()?.into_iter();
+    for id in orphaned_ids(stored, &managed) {
+        activations.namespace().delete(id.as_str())?;
+    }
+    Ok(Ack)
 }
 
 // ============================================================================
@@ -114,18 +218,20 @@ fn get_position_price(pool_state: &PoolState, position_token: &AssetId) -> f64 {
         // position is asset_a, exit is asset_b
         // raw_price = asset_a/asset_b = position/exit (INVERTED from what we want)
         // We want exit/position, so invert
-        if raw_price == 0.0 {
-            0.0
-        } else {
-            1.0 / raw_price
-        }
+        raw_price.invert().value()
     } else {
         // position is asset_b, exit is asset_a
         // raw_price = asset_a/asset_b = exit/position (exactly what we want!)
-        raw_price
+        raw_price.value()
     }
 }
 
+/// Whether a price can be compared against the peak; zero and non-finite prices come from
+/// empty or malformed pools rather than real trades.
+fn is_usable_price(price: f64) -> bool {
+    price.is_finite() && price > 0.0
+}
+
 #[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
 fn on_new_pool_state(
     config: &Config<StrategyConfig>,
@@ -134,14 +240,22 @@ fn on_new_pool_state(
 ) -> WorkerResult<Ack> {
     // Calculate price correctly based on token ordering in pool
     let pool_price = get_position_price(pool_state, &config.position_token);
-    let now = config.network.to_unix_time(pool_state.slot);
+    let now = pool_state.now_ms(&config.network);
     tracing::info!(
         "New pool price for {}: {pool_price}",
         hex::encode(pool_state.pool_datum.identifier.clone())
     );
 
+    // An empty pool reports a price of zero, which is below every trigger; skip the
+    // observation rather than treating it as a crash in value
+    if !is_usable_price(pool_price) {
+        tracing::warn!("ignoring unusable pool price {pool_price}");
+        return Ok(Ack);
+    }
+
     // Filter to strategies with positions in this pool
     let mut active = Vec::new();
+    let mut in_pool = false;
 
     if strategies.is_empty() {
         tracing::info!("No strategy orders are queued");
@@ -151,7 +265,7 @@ fn on_new_pool_state(
         let correct_pool =
             pool_state.is_correct_pool(&s.order, &config.position_token, &config.exit_token);
 
-        let position_amt = asset_amount(&s.utxo, &config.position_token);
+        let position_amt = s.balance(&config.position_token);
 
         if !correct_pool {
             if let Some(ident) = &s.order.pool_ident {
@@ -175,9 +289,10 @@ fn on_new_pool_state(
             }
             continue;
         }
+        in_pool = true;
 
         if position_amt == 0 {
-            let exit_amt = asset_amount(&s.utxo, &config.position_token);
+            let exit_amt = s.balance(&config.position_token);
             tracing::info!("strategy skipped: 0 position amount (exit_token amount: {exit_amt}");
             continue;
         }
@@ -185,45 +300,70 @@ fn on_new_pool_state(
         active.push(s);
     }
 
+    if !in_pool {
+        return Ok(Ack);
+    }
+    // Record the observation for any pool an order trades in, whether or not it holds a
+    // position yet
+    let pool_price = config.price_source.price(pool_state, pool_price)?;
+
     if active.is_empty() {
         tracing::info!("No active strategies");
         return Ok(Ack);
     }
 
     // Process each strategy individually (per-strategy peak prices)
+    let peak_prices = peak_prices();
     for strategy in active {
-        let peak_key = peak_price_key(&strategy.output);
-        let stored_peak = match kv::get::<f64>(&peak_key) {
-            Ok(v) => v,
-            Err(e) => {
-                tracing::error!("kv get failed: {e}");
+        if pending_exits().get(&strategy.output)?.is_some() {
+            info!(
+                "exit already submitted for {}#{}, waiting for it to land",
+                hex::encode(&strategy.output.transaction_id.0),
+                strategy.output.output_index,
+            );
+            continue;
+        }
+
+        let scale_out = match config.scale_out {
+            Some(_) => scale_outs().load(strategy).unwrap_or_else(|e| {
+                tracing::error!("kv get for scale-out progress failed: {e}");
                 None
-            }
+            }),
+            None => None,
         };
 
-        // Compute peak price from stored value or initialize from entry_price/pool_price
-        let peak_price = match stored_peak {
-            None => {
-                // Use entry_price from config if provided, otherwise use current pool price
-                let initial_peak = config.entry_price.unwrap_or(pool_price);
-                info!(
-                    "initializing peak price for {}#{} to {} (entry_price: {:?})",
-                    hex::encode(&strategy.output.transaction_id.0),
-                    strategy.output.output_index,
-                    initial_peak,
-                    config.entry_price
+        // Resume from the peak a previous tranche was sold at, otherwise use entry_price
+        // from config if provided, otherwise use current pool price
+        let initial_peak = scale_out
+            .as_ref()
+            .map(|progress| progress.peak_price)
+            .or(config.entry_price)
+            .unwrap_or(pool_price);
+        let stored_peak = peak_prices.load_or_init(strategy, || {
+            info!(
+                "initializing peak price for {}#{} to {} (entry_price: {:?})",
+                hex::encode(&strategy.output.transaction_id.0),
+                strategy.output.output_index,
+                initial_peak,
+                config.entry_price
+            );
+            if config.trail.trigger_price(initial_peak, 1) <= 0.0 {
+                tracing::warn!(
+                    "trail {:?} is wider than the initial peak {}, so the stop can't trigger until the price rises",
+                    config.trail,
+                    initial_peak
                 );
-                if let Err(e) = kv::set(&peak_key, &initial_peak) {
-                    tracing::error!(
-                        "failed to initialize peak price for {}#{}: {}",
-                        hex::encode(&strategy.output.transaction_id.0),
-                        strategy.output.output_index,
-                        e
-                    );
-                }
+            }
+            initial_peak
+        });
+
+        // Compute peak price from stored value, raising it if the pool price is a new high
+        let peak_price = match stored_peak {
+            Err(e) => {
+                tracing::error!("kv get_or_init failed: {e}");
                 initial_peak
             }
-            Some(peak) if pool_price > peak => {
+            Ok(peak) if pool_price > peak && is_trailing(config, strategy, pool_price) => {
                 // Update peak price (only goes up)
                 info!(
                     "updating peak price for {}#{} to {}",
@@ -231,7 +371,7 @@ fn on_new_pool_state(
                     strategy.output.output_index,
                     pool_price
                 );
-                if let Err(e) = kv::set(&peak_key, &pool_price) {
+                if let Err(e) = peak_prices.store(strategy, &pool_price) {
                     tracing::error!(
                         "failed to update peak price for {}#{}: {}",
                         hex::encode(&strategy.output.transaction_id.0),
@@ -241,11 +381,26 @@ fn on_new_pool_state(
                 }
                 pool_price
             }
-            Some(peak) => peak,
+            Ok(peak) => peak,
         };
 
-        // Calculate trigger from peak - always uses current trail_percent
-        let trigger_price = peak_price * (1.0 - config.trail_percent);
+        if config.scale_out.is_some() {
+            let progress = scale_out.unwrap_or_default();
+            if let Err(e) = scale_out_step(
+                config, pool_state, now, strategy, peak_price, pool_price, &progress,
+            ) {
+                tracing::error!(
+                    "failed to scale out of {}#{}: {}",
+                    hex::encode(&strategy.output.transaction_id.0),
+                    strategy.output.output_index,
+                    e
+                );
+            }
+            continue;
+        }
+
+        // Calculate trigger from peak - always uses the current trail
+        let trigger_price = config.trail.trigger_price(peak_price, 1);
 
         info!(
             "strategy {}#{}: price={:.8}, peak={:.8}, trigger={:.8}",
@@ -265,7 +420,7 @@ fn on_new_pool_state(
                 pool_price,
                 trigger_price
             );
-            if let Err(e) = trigger_exit(config, now, strategy, trigger_price) {
+            if let Err(e) = trigger_exit(config, pool_state, now, strategy, trigger_price) {
                 tracing::error!(
                     "failed to trigger exit for {}#{}: {}",
                     hex::encode(&strategy.output.transaction_id.0),
@@ -279,6 +434,167 @@ fn on_new_pool_state(
     Ok(Ack)
 }
 
+/// Cap the `min_received` for selling `position_amount` at what the pool would actually
+/// deliver after its fee, less slippage. Once the price has fallen through the trigger, a
+/// minimum at the trigger price can exceed that in a high-fee pool, and the exit would
+/// never fill.
+fn capped_min_received(
+    config: &StrategyConfig,
+    pool_state: &PoolState,
+    position_amount: u64,
+    min_received: u64,
+) -> u64 {
+    pool_state
+        .min_received_after_fees(
+            &config.position_token,
+            position_amount,
+            config.slippage_tolerance,
+        )
+        .map_or(min_received, |after_fees| min_received.min(after_fees))
+}
+
+/// Progress scaling out of a position in tranches, persisted per order and carried over to
+/// the order UTxO each partial fill leaves behind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ScaleOut {
+    /// How many of the configured tranches have been sold
+    tranches_sold: usize,
+    /// The fraction of the original position sold so far
+    sold_fraction: f64,
+    /// The peak price when the last tranche was sold, which later orders resume trailing from
+    peak_price: f64,
+}
+
+fn scale_outs() -> kv::StrategyState<ScaleOut> {
+    kv::StrategyState::per_output("scale_out")
+}
+
+/// The progress each order will have made once its tranches in flight fill; entries expire
+/// with the submitted validity window
+fn pending_scale_outs() -> kv::Namespace<ScaleOut> {
+    kv::Namespace::new("pending_scale_out")
+}
+
+/// Move the spent order's scale-out progress to the order UTxO its spend left behind, if
+/// any, counting the tranches in flight as sold if the spend delivered them.
+fn carry_scale_out(
+    config: &StrategyConfig,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<()> {
+    let pending = pending_scale_outs().get(&strategy.output)?;
+    pending_scale_outs().delete(&strategy.output)?;
+    let progress = scale_outs().load(strategy)?;
+    scale_outs().clear(strategy)?;
+
+    let (successor, progress) =
+        match strategy.swap_fill(tx, &config.position_token, &config.exit_token) {
+            Some(fill) => (fill.successor, pending.or(progress)),
+            None => match strategy.successor(tx) {
+                Some(successor) => (successor, progress),
+                None => return Ok(()),
+            },
+        };
+    if let Some(progress) = progress {
+        info!(
+            "{:?} has sold {} tranches, resuming at {:?}",
+            strategy.output, progress.tranches_sold, successor.output
+        );
+        scale_outs().store(&successor, &progress)?;
+    }
+    if activations().load(strategy)? == Some(true) {
+        activations().store(&successor, &true)?;
+    }
+    Ok(())
+}
+
+/// The trigger price of tranche `index` (from 0): each tranche sits a further trail
+/// distance below the last.
+fn tranche_trigger(config: &StrategyConfig, peak_price: f64, index: usize) -> f64 {
+    config.trail.trigger_price(peak_price, index + 1)
+}
+
+/// How many tranches, after the `tranches_sold` already sold, `pool_price` has fallen through.
+fn tranches_crossed(
+    config: &StrategyConfig,
+    tranches_sold: usize,
+    peak_price: f64,
+    pool_price: f64,
+) -> usize {
+    let tranches = config.scale_out.as_deref().unwrap_or_default().len();
+    (tranches_sold..tranches)
+        .take_while(|index| pool_price < tranche_trigger(config, peak_price, *index))
+        .count()
+}
+
+/// Scale out: sell every tranche the price has fallen through since the last one was sold,
+/// as a partial fill that leaves the rest of the position in a new order UTxO.
+fn scale_out_step(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    now: u64,
+    strategy: &ManagedStrategy,
+    peak_price: f64,
+    pool_price: f64,
+    progress: &ScaleOut,
+) -> WorkerResult<Ack> {
+    let fractions = config.scale_out.as_deref().unwrap_or_default();
+    let crossed = tranches_crossed(config, progress.tranches_sold, peak_price, pool_price);
+    if crossed == 0 {
+        return Ok(Ack);
+    }
+    let tranches = progress.tranches_sold..progress.tranches_sold + crossed;
+    let selling: f64 = fractions[tranches.clone()].iter().sum();
+    let deepest_trigger = tranche_trigger(config, peak_price, tranches.end - 1);
+
+    // The order holds what's left of the original position, so the tranches are a larger
+    // fraction of its balance than of the original
+    let held = 1.0 - progress.sold_fraction;
+    let offer_fraction = (selling / held).min(1.0);
+
+    info!(
+        "TSL tranches {:?} triggered for {}#{}: price {:.8} < trigger {:.8}, selling {:.4} of the position",
+        tranches,
+        hex::encode(&strategy.output.transaction_id.0),
+        strategy.output.output_index,
+        pool_price,
+        deepest_trigger,
+        selling
+    );
+
+    let valid_for = Duration::from_secs(config.validity_window_secs).as_millis() as u64;
+    let validity_range =
+        Interval::inclusive_range(now.saturating_sub(valid_for), now.saturating_add(valid_for));
+    let submission = strategy.submit_partial_execution(
+        &config.network,
+        validity_range,
+        &config.position_token,
+        offer_fraction,
+        &config.exit_token,
+        |amount| {
+            capped_min_received(
+                config,
+                pool_state,
+                amount,
+                min_received(amount, deepest_trigger, config.slippage_tolerance),
+            )
+        },
+    )?;
+    if submission.is_skipped() {
+        return Ok(Ack);
+    }
+
+    let filled = ScaleOut {
+        tranches_sold: tranches.end,
+        sold_fraction: progress.sold_fraction + selling,
+        peak_price,
+    };
+    let ttl_secs = config.validity_window_secs;
+    pending_scale_outs().set_with_ttl(&strategy.output, &filled, ttl_secs)?;
+    pending_exits().set_with_ttl(&strategy.output, &true, ttl_secs)?;
+    Ok(Ack)
+}
+
 /// Exit: Swap position_token back to exit_token when TSL triggers
 ///
 /// ## Slippage Protection
@@ -296,28 +612,27 @@ fn on_new_pool_state(
 /// - Minimum:  8000 * (1 - 0.03) = 7760 ADA
 fn trigger_exit(
     config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
     now: u64,
     strategy: &ManagedStrategy,
     trigger_price: f64,
 ) -> WorkerResult<Ack> {
-    let valid_for = Duration::from_secs(20 * 60);
+    let valid_for = Duration::from_secs(config.validity_window_secs);
     // Validity range extends into the past to handle clock skew and tx propagation delays
     let validity_range = Interval::inclusive_range(
         now.saturating_sub(valid_for.as_millis() as u64),
         now.saturating_add(valid_for.as_millis() as u64),
     );
 
-    let position_amount = asset_amount(&strategy.utxo, &config.position_token);
-
-    // Calculate minimum received with slippage protection
-    // trigger_price = exit_token per position_token
-    // expected_output = position_amount * trigger_price
-    // min_output = expected_output * (1 - slippage_tolerance)
-    let expected_output = position_amount as f64 * trigger_price;
-    let min_received = (expected_output * (1.0 - config.slippage_tolerance)) as u64;
+    let position_amount = strategy.balance(&config.position_token);
 
-    // Ensure we receive at least 1 unit (sanity check)
-    let min_received = min_received.max(1);
+    // trigger_price is exit_token per position_token, in raw units
+    let min_received = capped_min_received(
+        config,
+        pool_state,
+        position_amount,
+        min_received(position_amount, trigger_price, config.slippage_tolerance),
+    );
 
     info!(
         "exit order: selling {} {} for min {} {} (trigger_price={:.8}, slippage={}%)",
@@ -329,31 +644,30 @@ fn trigger_exit(
         config.slippage_tolerance * 100.0
     );
 
-    let swap = Order::Swap {
-        offer: (
-            config.position_token.policy_id.clone(),
-            config.position_token.asset_name.clone(),
-            position_amount,
-        ),
-        min_received: (
-            config.exit_token.policy_id.clone(),
-            config.exit_token.asset_name.clone(),
-            min_received,
-        ),
-    };
+    let swap = Order::swap(
+        (&config.position_token, position_amount),
+        (&config.exit_token, min_received),
+    );
 
+    match strategy.submit_execution(&config.network, validity_range, swap) {
+        Ok(Submission::Submitted(_)) => {}
+        Ok(Submission::Skipped(_)) => return Ok(Ack),
+        Err(e) => {
+            tracing::error!(
+                "failed to submit exit execution for {}#{}: {}",
+                hex::encode(&strategy.output.transaction_id.0),
+                strategy.output.output_index,
+                e
+            );
+            return Ok(Ack);
+        }
+    }
+    info!("exit order submitted successfully");
     if let Err(e) =
-        sundae_strategies::submit_execution(&config.network, &strategy.output, validity_range, swap)
+        pending_exits().set_with_ttl(&strategy.output, &true, config.validity_window_secs)
     {
-        tracing::error!(
-            "failed to submit exit execution for {}#{}: {}",
-            hex::encode(&strategy.output.transaction_id.0),
-            strategy.output.output_index,
-            e
-        );
-        return Ok(Ack);
+        tracing::error!("failed to record the pending exit: {e}");
     }
-    info!("exit order submitted successfully");
     Ok(Ack)
 }
 
@@ -403,11 +717,14 @@ impl Handler for GetPeakPriceHandler {
             output_index: params.output_index,
         };
 
-        let key = peak_price_key(&output_ref);
-        let peak_price = kv::get::<f64>(&key).map_err(|e| wit::HandleError {
-            message: e.to_string(),
-            code: 500,
-        })?;
+        let peak_price =
+            peak_prices()
+                .namespace()
+                .get(&output_ref)
+                .map_err(|e| wit::HandleError {
+                    message: e.to_string(),
+                    code: 500,
+                })?;
 
         info!(
             "get-peak-price for {}#{}: {:?}",
@@ -425,11 +742,433 @@ impl Handler for GetPeakPriceHandler {
     }
 }
 
+// ============================================================================
+// get-state snapshot
+// ============================================================================
+
+/// What get-state reports for a trailing stop order
+#[derive(Serialize)]
+pub struct TrailSnapshot {
+    /// The highest price seen since trailing began
+    peak_price: f64,
+    /// The price the stop exits below
+    trigger_price: f64,
+    /// Whether trailing has begun, which is always true without an `activation_price`
+    trailing: bool,
+}
+
+impl StateSnapshot for StrategyConfig {
+    type Snapshot = TrailSnapshot;
+
+    fn snapshot(&self, order: &ManagedStrategy) -> WorkerResult<Option<TrailSnapshot>> {
+        let Some(peak_price) = peak_prices().load(order)? else {
+            return Ok(None);
+        };
+        let trailing =
+            self.activation_price.is_none() || activations().load(order)?.unwrap_or_default();
+        Ok(Some(TrailSnapshot {
+            peak_price,
+            trigger_price: self.trail.trigger_price(peak_price, 1),
+            trailing,
+        }))
+    }
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+        .with_state()
+}
+
 #[balius_sdk::main]
 fn main() -> Worker {
     balius_sdk::logging::init();
 
-    Strategy::<StrategyConfig>::new()
-        .on_new_pool_state(on_new_pool_state)
-        .worker_with(|w| w.with_request_handler("get-peak-price", GetPeakPriceHandler))
+    strategy().worker_with(|w| w.with_request_handler("get-peak-price", GetPeakPriceHandler))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::sim::Simulator;
+
+    fn output(index: u64) -> OutputReference {
+        OutputReference {
+            transaction_id: TransactionId(vec![0xab; 32]),
+            output_index: index,
+        }
+    }
+
+    #[test]
+    fn position_price_is_exit_per_position() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        // 10,000 ADA and 1,000 SUNDAE: 10 ADA per SUNDAE
+        let pool = PoolState::mock(10_000, 1_000, (&ada, &sundae));
+
+        assert_eq!(get_position_price(&pool, &sundae), 10.0);
+        assert_eq!(get_position_price(&pool, &ada), 0.1);
+    }
+
+    #[test]
+    fn exits_once_price_falls_below_the_trailing_trigger() {
+        // The example from the module docs
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.15,
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sundae, 1_000)]))
+            .unwrap();
+
+        // ADA per SUNDAE: 100 -> 120 -> 150 -> 130 -> 125
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        let executions = sim
+            .run([
+                (1, pool(100)),
+                (2, pool(120)),
+                (3, pool(150)),
+                (4, pool(130)),
+                (5, pool(125)),
+            ])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 1_000);
+        // The pool has already fallen through the 127.5 trigger, so the minimum is what it
+        // delivers for 1,000 SUNDAE at 125, less 3% slippage
+        assert!(min_received.2.abs_diff(121_128) <= 1);
+    }
+
+    #[test]
+    fn a_lapsed_exit_keeps_its_peak() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.15,
+            "validity_window_secs": 60,
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        let order = ManagedStrategy::mock(&[(&sundae, 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+
+        // The exit at 125 is in flight, so a further fall doesn't submit another
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        let executions = sim
+            .run([(1, pool(150)), (2, pool(125)), (3, pool(120))])
+            .unwrap();
+        assert_eq!(executions.len(), 1);
+        assert_eq!(peak_prices().load(&order).unwrap(), Some(150.0));
+
+        // Once its window has passed unfilled, the order exits again against the same peak
+        let executions = sim.run([(100, pool(120))]).unwrap();
+        assert_eq!(executions.len(), 1);
+
+        // The order is gone once the exit lands
+        sim.observe_tx(order.mock_execution(101, &[(&ada, 120_000)]))
+            .unwrap();
+        assert!(peak_prices().load(&order).unwrap().is_none());
+        assert!(pending_exits().get(&order.output).unwrap().is_none());
+    }
+
+    #[test]
+    fn twap_rides_out_a_brief_dip() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.15,
+            "price_source": { "twap": { "window": 3 } },
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sundae, 1_000)]))
+            .unwrap();
+
+        // ADA per SUNDAE: 100 -> 100 -> 100 -> 70 -> 100 averages to 90 at its lowest,
+        // above the 85 trigger, though the spot price fell through it
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        let executions = sim
+            .run([
+                (1, pool(100)),
+                (2, pool(100)),
+                (3, pool(100)),
+                (4, pool(70)),
+                (5, pool(100)),
+            ])
+            .unwrap();
+        assert!(executions.is_empty());
+
+        // A second fall to 70 brings the average to 80
+        let executions = sim.run([(6, pool(70))]).unwrap();
+        assert_eq!(executions.len(), 1);
+    }
+
+    #[test]
+    fn snapshot_reports_the_peak_and_trigger() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.15,
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        let order = ManagedStrategy::mock(&[(&sundae, 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let config = serde_json::from_value::<StrategyConfig>(config).unwrap();
+        assert!(config.snapshot(&order).unwrap().is_none());
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        sim.run([(1, pool(100)), (2, pool(120))]).unwrap();
+        let snapshot = config.snapshot(&order).unwrap().unwrap();
+        assert_eq!(snapshot.peak_price, 120.0);
+        assert!((snapshot.trigger_price - 102.0).abs() < 1e-9);
+        assert!(snapshot.trailing);
+    }
+
+    #[test]
+    fn trailing_waits_for_the_activation_price() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.15,
+            "activation_price": 120.0,
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sundae, 1_000)]))
+            .unwrap();
+
+        // ADA per SUNDAE: 100 -> 110 -> 90 -> 125 -> 105. Before activation the trigger
+        // stays at 85, so the dip to 90 holds; after it, the trigger trails up to 106.25.
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        let executions = sim
+            .run([
+                (1, pool(100)),
+                (2, pool(110)),
+                (3, pool(90)),
+                (4, pool(125)),
+                (5, pool(105)),
+            ])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { min_received, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        // What the pool at 105 delivers for 1,000 SUNDAE, less 3% slippage
+        assert!(min_received.2.abs_diff(101_748) <= 1);
+    }
+
+    #[test]
+    fn deeper_drops_cross_more_tranches() {
+        let config = serde_json::from_value::<StrategyConfig>(serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.1,
+            "scale_out": [0.25, 0.25, 0.5],
+        }))
+        .unwrap();
+        // Tranches trigger at 90, 80 and 70 below a peak of 100
+        assert_eq!(tranches_crossed(&config, 0, 100.0, 95.0), 0);
+        assert_eq!(tranches_crossed(&config, 0, 100.0, 85.0), 1);
+        assert_eq!(tranches_crossed(&config, 0, 100.0, 65.0), 3);
+        assert_eq!(tranches_crossed(&config, 1, 100.0, 75.0), 1);
+        assert_eq!(tranches_crossed(&config, 3, 100.0, 10.0), 0);
+    }
+
+    #[test]
+    fn scale_out_must_fit_the_position() {
+        let config = |scale_out: serde_json::Value| {
+            serde_json::from_value::<StrategyConfig>(serde_json::json!({
+                "network": "preview",
+                "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+                "exit_token": ".",
+                "trail_percent": 0.3,
+                "scale_out": scale_out,
+            }))
+        };
+        assert!(config(serde_json::json!([0.5, 0.5])).is_ok());
+        assert!(config(serde_json::json!([0.6, 0.6])).is_err());
+        assert!(config(serde_json::json!([])).is_err());
+        // A fourth tranche would trigger below zero
+        assert!(config(serde_json::json!([0.25, 0.25, 0.25, 0.25])).is_err());
+    }
+
+    #[test]
+    fn the_first_tranche_sells_its_fraction() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.1,
+            "scale_out": [0.5, 0.5],
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&sundae, 1_000)]))
+            .unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        let executions = sim.run([(1, pool(100)), (2, pool(85))]).unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 500);
+        // What the pool at 85 delivers for 500 SUNDAE, below the first tranche's 90 trigger,
+        // less 3% slippage
+        assert!(min_received.2.abs_diff(41_203) <= 1);
+    }
+
+    #[test]
+    fn tranches_count_once_filled_and_per_position() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sundae = AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()));
+        let config = serde_json::json!({
+            "network": "preview",
+            "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "exit_token": ".",
+            "trail_percent": 0.1,
+            "scale_out": [0.5, 0.5],
+        });
+        let mut sim = Simulator::new(strategy(), &config).unwrap();
+        let first = ManagedStrategy::mock(&[(&sundae, 1_000)]);
+        let mut second = ManagedStrategy::mock(&[(&sundae, 1_000)]);
+        second.output.output_index = 1;
+        sim.add_order(first.clone()).unwrap();
+        sim.add_order(second.clone()).unwrap();
+
+        let pool = |price| PoolState::mock(price * 1_000_000, 1_000_000, (&ada, &sundae));
+        let executions = sim.run([(1, pool(100)), (2, pool(85))]).unwrap();
+        assert_eq!(executions.len(), 2);
+
+        // The first position's tranche fills; the second's lapses
+        let balances = [(&sundae, 500), (&ada, 42_500)];
+        let successor = first
+            .swap_fill(&first.mock_execution(3, &balances), &sundae, &ada)
+            .unwrap()
+            .successor;
+        sim.observe_tx(first.mock_execution(3, &balances)).unwrap();
+        let progress = scale_outs().load(&successor).unwrap().unwrap();
+        assert_eq!(progress.tranches_sold, 1);
+        assert_eq!(progress.peak_price, 100.0);
+        assert!(scale_outs().load(&second).unwrap().is_none());
+
+        // Once its window has passed, the second position sells its first tranche again,
+        // while the rest of the first waits for the second tranche's trigger at 80
+        sim.add_order(successor).unwrap();
+        let executions = sim.run([(2_000, pool(85))]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { offer, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 500);
+    }
+
+    #[test]
+    fn absolute_trail_is_a_fixed_distance_below_the_peak() {
+        let config = |trail: serde_json::Value| {
+            let mut json = serde_json::json!({
+                "network": "preview",
+                "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+                "exit_token": ".",
+                "entry_price": 20.0,
+            });
+            json.as_object_mut()
+                .unwrap()
+                .extend(trail.as_object().unwrap().clone());
+            serde_json::from_value::<StrategyConfig>(json)
+        };
+
+        // Percent stays the default
+        let percent = config(serde_json::json!({ "trail_percent": 0.15 })).unwrap();
+        assert_eq!(percent.trail, config::Trail::Percent(0.15));
+
+        let absolute =
+            config(serde_json::json!({ "trail_mode": "absolute", "trail_amount": 5.0 })).unwrap();
+        assert_eq!(absolute.trail.trigger_price(150.0, 1), 145.0);
+        assert_eq!(absolute.trail.trigger_price(150.0, 2), 140.0);
+
+        assert!(config(serde_json::json!({ "trail_mode": "absolute" })).is_err());
+        assert!(
+            config(serde_json::json!({ "trail_mode": "absolute", "trail_amount": 0.0 })).is_err()
+        );
+        // The initial trigger would be at or below zero
+        assert!(
+            config(serde_json::json!({ "trail_mode": "absolute", "trail_amount": 20.0 })).is_err()
+        );
+    }
+
+    #[test]
+    fn validity_window_is_bounded() {
+        let config = |window: serde_json::Value| {
+            serde_json::from_value::<StrategyConfig>(serde_json::json!({
+                "network": "preview",
+                "position_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+                "exit_token": ".",
+                "trail_percent": 0.15,
+                "validity_window_secs": window,
+            }))
+        };
+        assert_eq!(
+            config(serde_json::Value::Null)
+                .unwrap()
+                .validity_window_secs,
+            1200
+        );
+        assert_eq!(config(60.into()).unwrap().validity_window_secs, 60);
+        assert!(config(59.into()).is_err());
+        assert!(config(3601.into()).is_err());
+    }
+
+    #[test]
+    fn empty_pool_prices_are_ignored() {
+        assert!(!is_usable_price(0.0));
+        assert!(!is_usable_price(f64::INFINITY));
+        assert!(!is_usable_price(f64::NAN));
+        assert!(is_usable_price(0.000168));
+    }
+
+    #[test]
+    fn peaks_of_spent_orders_are_orphaned() {
+        let stored = [output(0), output(1)].map(|o| o.namespace_key());
+
+        // Output 1 has left the managed set; output 0 is still live
+        let orphaned = orphaned_ids(stored.into_iter(), &[output(0)]);
+        assert_eq!(orphaned, [output(1).namespace_key()]);
+
+        // An orphan's id deletes exactly the key the peak was stored under
+        assert_eq!(
+            peak_prices().namespace().key(orphaned[0].as_str()),
+            peak_prices().namespace().key(&output(1))
+        );
+    }
 }
@@ -0,0 +1,114 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance (3%)
+/// This allows the buy order to fill even if price rises slightly between
+/// trigger and execution, while still protecting against catastrophic fills.
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token held by the strategy and spent when the buy triggers
+    pub spend_token: AssetId,
+    pub spend_token_decimals: u8,
+    /// The token being accumulated on dips
+    pub target_token: AssetId,
+    pub target_token_decimals: u8,
+    /// How far above the trough price the buy triggers (e.g., 0.05 = 5%)
+    /// Must be in range (0.0, 1.0) - e.g., 0.05 means trigger at 5% above trough
+    pub rebound_percent: f64,
+    /// Maximum acceptable slippage when executing the buy order (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    /// This determines the minimum amount of target_token accepted in the swap.
+    pub slippage_tolerance: f64,
+    /// Optional initial trough price for the strategy, in whole spend_token per whole
+    /// target_token.
+    /// If provided, this value is used as the initial trough price instead of
+    /// discovering it from the current pool price. This is useful when modifying
+    /// an existing position (cancel + recreate) to preserve the previous trough.
+    /// Not displayed on frontend - populated automatically during position modify.
+    pub entry_price: Option<f64>,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    spend_token: AssetId,
+    spend_token_decimals: u8,
+    target_token: AssetId,
+    target_token_decimals: u8,
+    rebound_percent: f64,
+    slippage_tolerance: Option<f64>,
+    entry_price: Option<f64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        // Validate rebound_percent is in valid range
+        if raw.rebound_percent <= 0.0 {
+            return Err(format!(
+                "rebound_percent must be > 0.0, got {}",
+                raw.rebound_percent
+            ));
+        }
+        if raw.rebound_percent >= 1.0 {
+            return Err(format!(
+                "rebound_percent must be < 1.0, got {}",
+                raw.rebound_percent
+            ));
+        }
+
+        // Use default slippage tolerance if not provided
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+
+        // Validate slippage_tolerance is in valid range
+        if slippage_tolerance <= 0.0 {
+            return Err(format!(
+                "slippage_tolerance must be > 0.0, got {}",
+                slippage_tolerance
+            ));
+        }
+        if slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be < 1.0, got {}",
+                slippage_tolerance
+            ));
+        }
+
+        // Validate entry_price if provided
+        if let Some(price) = raw.entry_price
+            && price <= 0.0
+        {
+            return Err(format!("entry_price must be > 0.0, got {}", price));
+        }
+
+        // Validate spend_token and target_token are different
+        if raw.spend_token == raw.target_token {
+            return Err("spend_token and target_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            spend_token: raw.spend_token,
+            spend_token_decimals: raw.spend_token_decimals,
+            target_token: raw.target_token,
+            target_token_decimals: raw.target_token_decimals,
+            rebound_percent: raw.rebound_percent,
+            slippage_tolerance,
+            entry_price: raw.entry_price,
+        })
+    }
+}
+
+impl Config {
+    /// Convert `price`, in whole spend_token per whole target_token, to raw units of
+    /// spend_token per raw unit of target_token.
+    pub fn to_raw_price(&self, price: f64) -> f64 {
+        price * 10f64.powi(self.spend_token_decimals as i32 - self.target_token_decimals as i32)
+    }
+}
@@ -0,0 +1,430 @@
+//! # Trailing Take Profit Strategy
+//!
+//! This strategy accumulates a token on dips by buying once the price rebounds
+//! from its lowest point. It is the mirror image of the trailing stop loss.
+//!
+//! ## How It Works
+//!
+//! The strategy expects to receive a UTxO that already contains the spend token.
+//! It then monitors price movements:
+//!
+//! - **Price goes down**: The trough price updates to the new low, and trigger price
+//!   is recalculated as `trough_price * (1 + rebound_percent)`. This follows the
+//!   price down as it falls.
+//!
+//! - **Price goes up**: If the price rises above the trigger price, the strategy
+//!   buys, swapping all `spend_token` for `target_token`.
+//!
+//! ## Example
+//!
+//! With `rebound_percent = 0.05` (5%):
+//!
+//! 1. Worker first observes pool price at 100 → initial trigger price = 105
+//! 2. Price falls to 90 → trigger price trails down to 94.5
+//! 3. Price falls to 80 → trigger price trails down to 84
+//! 4. Price rises to 85 → above trigger price 84? Buy triggered!
+//!
+//! The strategy bought at 85 instead of at 100, without having to guess the bottom.
+//!
+//! > **Note:** The initial trigger price is set from either the configured `entry_price`
+//! > (if provided) or the pool price at the worker's first observation of the position.
+//! > When modifying an existing position, use the `get-trough-price` request handler to
+//! > retrieve the current trough and pass it as `entry_price` to preserve it.
+//!
+//! ## Configuration
+//!
+//! - `spend_token`: The token spent when the buy triggers (what you're holding)
+//! - `target_token`: The token being accumulated
+//! - `spend_token_decimals`, `target_token_decimals`: The decimal places of each token
+//! - `rebound_percent`: How far above the trough the buy triggers (0.05 = 5%)
+//! - `slippage_tolerance`: Maximum acceptable slippage on entry (0.03 = 3%)
+//! - `entry_price`: Optional initial trough price. If set, used instead of discovering
+//!   from pool price. Useful when modifying positions to preserve the previous trough.
+//!
+//! ## Price Calculation
+//!
+//! Price is always calculated as: "how much whole spend_token per 1 whole target_token"
+//! This means when target_token gets cheaper, the price goes DOWN, deepening the trough.
+
+mod config;
+
+use balius_sdk::{_internal::Handler, Ack, Config, Json, Params, WorkerResult, wit};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{
+    EventTime, ManagedStrategy, PoolState, Strategy, Submission, kv,
+    types::{AssetId, Interval, Order, OutputReference, TransactionId},
+};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a buy order is valid for
+const VALIDITY_SECS: u64 = 20 * 60;
+
+/// Trough prices, stored per strategy output
+fn trough_prices() -> kv::Namespace<f64> {
+    kv::Namespace::new("trough_price")
+}
+
+/// Orders with a buy in flight; entries expire with the submitted validity window
+fn pending_buys() -> kv::Namespace<bool> {
+    kv::Namespace::new("pending_buy")
+}
+
+/// Calculate the price of target_token in whole spend_token per whole target_token.
+///
+/// The pool price is "asset_a per asset_b", so it is already the price we want
+/// when target_token is asset_b, and must be inverted when it is asset_a.
+fn get_target_price(pool_state: &PoolState, config: &StrategyConfig) -> f64 {
+    let (pool_asset_a, _pool_asset_b) = &pool_state.pool_datum.assets;
+    let target_is_asset_a = config.target_token.policy_id == pool_asset_a.0
+        && config.target_token.asset_name == pool_asset_a.1;
+
+    if target_is_asset_a {
+        pool_state
+            .price(config.target_token_decimals, config.spend_token_decimals)
+            .invert()
+            .value()
+    } else {
+        pool_state
+            .price(config.spend_token_decimals, config.target_token_decimals)
+            .value()
+    }
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let pool_price = get_target_price(pool_state, config);
+    let now = pool_state.now_ms(&config.network);
+    tracing::info!(
+        "New pool price for {}: {pool_price}",
+        hex::encode(pool_state.pool_datum.identifier.clone())
+    );
+
+    // An empty pool reports a price of zero, which would drag the trough to zero
+    if !pool_price.is_finite() || pool_price <= 0.0 {
+        tracing::warn!("ignoring unusable pool price {pool_price}");
+        return Ok(Ack);
+    }
+
+    let trough_prices = trough_prices();
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.spend_token, &config.target_token) {
+            continue;
+        }
+        if strategy.is_empty(&[&config.spend_token]) {
+            tracing::info!("strategy skipped: 0 spend_token amount");
+            continue;
+        }
+
+        // Use entry_price from config if provided, otherwise use current pool price
+        let initial_trough = config.entry_price.unwrap_or(pool_price);
+        let stored_trough = trough_prices.get_or_init(&strategy.output, || {
+            info!(
+                "initializing trough price for {}#{} to {} (entry_price: {:?})",
+                hex::encode(&strategy.output.transaction_id.0),
+                strategy.output.output_index,
+                initial_trough,
+                config.entry_price
+            );
+            initial_trough
+        });
+
+        // Compute trough price from stored value, lowering it if the pool price is a new low
+        let trough_price = match stored_trough {
+            Err(e) => {
+                tracing::error!("kv get_or_init failed: {e}");
+                initial_trough
+            }
+            Ok(trough) if pool_price < trough => {
+                // Update trough price (only goes down)
+                info!(
+                    "updating trough price for {}#{} to {}",
+                    hex::encode(&strategy.output.transaction_id.0),
+                    strategy.output.output_index,
+                    pool_price
+                );
+                if let Err(e) = trough_prices.set(&strategy.output, &pool_price) {
+                    tracing::error!(
+                        "failed to update trough price for {}#{}: {}",
+                        hex::encode(&strategy.output.transaction_id.0),
+                        strategy.output.output_index,
+                        e
+                    );
+                }
+                pool_price
+            }
+            Ok(trough) => trough,
+        };
+
+        let trigger_price = trough_price * (1.0 + config.rebound_percent);
+
+        info!(
+            "strategy {}#{}: price={:.8}, trough={:.8}, trigger={:.8}",
+            hex::encode(&strategy.output.transaction_id.0),
+            strategy.output.output_index,
+            pool_price,
+            trough_price,
+            trigger_price
+        );
+
+        if pool_price > trigger_price {
+            if pending_buys().get(&strategy.output)?.is_some() {
+                info!(
+                    "buy already submitted for {:?}, waiting for it to land",
+                    strategy.output
+                );
+                continue;
+            }
+            info!(
+                "buy triggered for {}#{}: price {:.8} > trigger {:.8}",
+                hex::encode(&strategy.output.transaction_id.0),
+                strategy.output.output_index,
+                pool_price,
+                trigger_price
+            );
+            if let Err(e) = trigger_buy(config, now, strategy, trigger_price) {
+                tracing::error!(
+                    "failed to trigger buy for {}#{}: {}",
+                    hex::encode(&strategy.output.transaction_id.0),
+                    strategy.output.output_index,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(Ack)
+}
+
+/// The minimum amount of target_token to accept for `spend_amount` at `trigger_price`
+/// (raw spend_token per raw target_token), never less than 1 unit.
+///
+/// Example: Spending 8000 ADA at trigger_price=8 ADA/SUNDAE with 3% slippage:
+/// - Expected: 8000 / 8 = 1000 SUNDAE
+/// - Minimum:  1000 * (1 - 0.03) = 970 SUNDAE
+fn min_received(spend_amount: u64, trigger_price: f64, slippage_tolerance: f64) -> u64 {
+    let expected_output = spend_amount as f64 / trigger_price;
+    ((expected_output * (1.0 - slippage_tolerance)) as u64).max(1)
+}
+
+/// Entry: Swap all spend_token into target_token when the rebound triggers
+fn trigger_buy(
+    config: &Config<StrategyConfig>,
+    now: u64,
+    strategy: &ManagedStrategy,
+    trigger_price: f64,
+) -> WorkerResult<Ack> {
+    let valid_for_ms = VALIDITY_SECS * 1000;
+    // Validity range extends into the past to handle clock skew and tx propagation delays
+    let validity_range = Interval::inclusive_range(
+        now.saturating_sub(valid_for_ms),
+        now.saturating_add(valid_for_ms),
+    );
+
+    let spend_amount = strategy.balance(&config.spend_token);
+    let min_received = min_received(
+        spend_amount,
+        config.to_raw_price(trigger_price),
+        config.slippage_tolerance,
+    );
+
+    info!(
+        "buy order: spending {} {} for min {} {} (trigger_price={:.8}, slippage={}%)",
+        spend_amount,
+        config.spend_token.name_to_string(),
+        min_received,
+        config.target_token.name_to_string(),
+        trigger_price,
+        config.slippage_tolerance * 100.0
+    );
+
+    let swap = Order::swap(
+        (&config.spend_token, spend_amount),
+        (&config.target_token, min_received),
+    );
+
+    match strategy.submit_execution(&config.network, validity_range, swap) {
+        Ok(Submission::Submitted(_)) => {
+            pending_buys().set_with_ttl(&strategy.output, &true, VALIDITY_SECS)?;
+        }
+        Ok(Submission::Skipped(_)) => return Ok(Ack),
+        Err(e) => {
+            tracing::error!(
+                "failed to submit buy execution for {}#{}: {}",
+                hex::encode(&strategy.output.transaction_id.0),
+                strategy.output.output_index,
+                e
+            );
+            return Ok(Ack);
+        }
+    }
+    info!("buy order submitted successfully");
+    Ok(Ack)
+}
+
+// ============================================================================
+// get-trough-price request handler
+// ============================================================================
+
+/// Request parameters for get-trough-price
+#[derive(Deserialize)]
+struct GetTroughPriceParams {
+    /// Transaction hash of the strategy UTxO (hex-encoded)
+    tx_hash: String,
+    /// Output index of the strategy UTxO
+    output_index: u64,
+}
+
+/// Response for get-trough-price
+#[derive(Serialize)]
+struct GetTroughPriceResponse {
+    /// The current trough price for this strategy, or null if not found
+    trough_price: Option<f64>,
+}
+
+/// Handler for get-trough-price requests
+#[derive(Clone)]
+struct GetTroughPriceHandler;
+
+impl Handler for GetTroughPriceHandler {
+    fn handle(
+        &self,
+        _config: wit::Config,
+        event: wit::Event,
+    ) -> Result<wit::Response, wit::HandleError> {
+        let params: Params<GetTroughPriceParams> =
+            event.try_into().map_err(|_| wit::HandleError {
+                message: "invalid request parameters".to_string(),
+                code: 400,
+            })?;
+
+        let tx_hash_bytes = hex::decode(&params.tx_hash).map_err(|_| wit::HandleError {
+            message: "invalid tx_hash hex encoding".to_string(),
+            code: 400,
+        })?;
+
+        let output_ref = OutputReference {
+            transaction_id: TransactionId(tx_hash_bytes),
+            output_index: params.output_index,
+        };
+
+        let trough_price = trough_prices()
+            .get(&output_ref)
+            .map_err(|e| wit::HandleError {
+                message: e.to_string(),
+                code: 500,
+            })?;
+
+        info!(
+            "get-trough-price for {}#{}: {:?}",
+            params.tx_hash, params.output_index, trough_price
+        );
+
+        let response = GetTroughPriceResponse { trough_price };
+        let json = Json(response);
+
+        json.try_into()
+            .map_err(|e: balius_sdk::Error| wit::HandleError {
+                message: e.to_string(),
+                code: 500,
+            })
+    }
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .with_validity_window(VALIDITY_SECS)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker_with(|w| w.with_request_handler("get-trough-price", GetTroughPriceHandler))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::sim::Simulator;
+
+    fn sundae() -> AssetId {
+        AssetId::from((vec![0x99; 28], b"SUNDAE".to_vec()))
+    }
+
+    fn config() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "spend_token": ".",
+            "spend_token_decimals": 6,
+            "target_token": "9999999999999999999999999999999999999999999999999999999953554e444145",
+            "target_token_decimals": 0,
+            "rebound_percent": 0.05,
+        })
+    }
+
+    #[test]
+    fn prices_are_in_whole_tokens() {
+        let ada = AssetId::from((vec![], vec![]));
+        let config = serde_json::from_value::<StrategyConfig>(config()).unwrap();
+        // 10,000,000 lovelace and 1,000 SUNDAE: 0.01 ADA per SUNDAE
+        let pool = PoolState::mock(10_000_000, 1_000, (&ada, &sundae()));
+        assert!((get_target_price(&pool, &config) - 0.01).abs() < 1e-12);
+        assert!((config.to_raw_price(0.01) - 10_000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn buys_once_while_the_buy_is_in_flight() {
+        let ada = AssetId::from((vec![], vec![]));
+        let mut sim = Simulator::new(strategy(), &config()).unwrap();
+        sim.add_order(ManagedStrategy::mock(&[(&ada, 1_000_000_000)]))
+            .unwrap();
+
+        // ADA per SUNDAE: 10 -> 8 -> 9, above the 8.4 trigger, and it stays there while
+        // the buy lands
+        let pool = |price| PoolState::mock(price * 1_000_000_000_000, 1_000_000, (&ada, &sundae()));
+        let executions = sim
+            .run([(1, pool(10)), (2, pool(8)), (3, pool(9)), (4, pool(9))])
+            .unwrap();
+
+        assert_eq!(executions.len(), 1);
+        let Order::Swap {
+            offer,
+            min_received,
+        } = &executions[0].details
+        else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 1_000_000_000);
+        // 1,000 ADA at the 8.4 ADA trigger is 119 SUNDAE, less 3% slippage
+        assert_eq!(min_received.2, 115);
+    }
+
+    #[test]
+    fn min_received_applies_slippage_to_the_trigger_price() {
+        assert_eq!(min_received(8000, 8.0, 0.03), 970);
+    }
+
+    #[test]
+    fn min_received_is_at_least_one_unit() {
+        assert_eq!(min_received(1, 1000.0, 0.03), 1);
+    }
+
+    #[test]
+    fn rejects_out_of_range_rebound_percent() {
+        let config = serde_json::from_value::<StrategyConfig>(serde_json::json!({
+            "network": "preview",
+            "spend_token": ".",
+            "spend_token_decimals": 6,
+            "target_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.524245525259",
+            "target_token_decimals": 0,
+            "rebound_percent": 1.5,
+        }));
+        assert!(config.is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use sundae_strategies::{Network, types::AssetId};
+
+/// Default slippage tolerance (3%)
+const DEFAULT_SLIPPAGE_TOLERANCE: f64 = 0.03;
+
+#[derive(Deserialize)]
+#[serde(try_from = "ConfigRaw")]
+pub struct Config {
+    pub network: Network,
+    /// The token being liquidated
+    pub sell_token: AssetId,
+    /// The token received for it
+    pub buy_token: AssetId,
+    /// How long, in seconds, to spread the sale over
+    pub total_duration_secs: u64,
+    /// How many equal slices to split the position into
+    pub num_slices: u64,
+    /// The largest price impact (e.g., 0.01 = 1%) a slice may have; slices above it are deferred
+    pub max_price_impact: f64,
+    /// Maximum acceptable slippage on each slice (e.g., 0.03 = 3%)
+    /// Must be in range (0.0, 1.0). Defaults to 3% if not specified.
+    pub slippage_tolerance: f64,
+}
+
+/// Raw config for deserialization before validation
+#[derive(Deserialize)]
+struct ConfigRaw {
+    network: Network,
+    sell_token: AssetId,
+    buy_token: AssetId,
+    total_duration_secs: u64,
+    num_slices: u64,
+    max_price_impact: f64,
+    slippage_tolerance: Option<f64>,
+}
+
+impl TryFrom<ConfigRaw> for Config {
+    type Error = String;
+
+    fn try_from(raw: ConfigRaw) -> Result<Self, Self::Error> {
+        if raw.num_slices == 0 {
+            return Err("num_slices must be >= 1".to_string());
+        }
+        if raw.total_duration_secs < raw.num_slices {
+            return Err(format!(
+                "total_duration_secs must be at least num_slices (got {} < {})",
+                raw.total_duration_secs, raw.num_slices
+            ));
+        }
+        if raw.max_price_impact <= 0.0 || raw.max_price_impact >= 1.0 {
+            return Err(format!(
+                "max_price_impact must be in (0.0, 1.0), got {}",
+                raw.max_price_impact
+            ));
+        }
+
+        let slippage_tolerance = raw.slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE_TOLERANCE);
+        if slippage_tolerance <= 0.0 || slippage_tolerance >= 1.0 {
+            return Err(format!(
+                "slippage_tolerance must be in (0.0, 1.0), got {}",
+                slippage_tolerance
+            ));
+        }
+
+        if raw.sell_token == raw.buy_token {
+            return Err("sell_token and buy_token must be different tokens".to_string());
+        }
+
+        Ok(Config {
+            network: raw.network,
+            sell_token: raw.sell_token,
+            buy_token: raw.buy_token,
+            total_duration_secs: raw.total_duration_secs,
+            num_slices: raw.num_slices,
+            max_price_impact: raw.max_price_impact,
+            slippage_tolerance,
+        })
+    }
+}
@@ -0,0 +1,347 @@
+//! # TWAP Execution Strategy
+//!
+//! This strategy liquidates a large position gradually, splitting it into
+//! `num_slices` equal child orders spread evenly over `total_duration_secs`
+//! to limit price impact.
+//!
+//! ## How It Works
+//!
+//! On the first observation of the pool, the strategy records the start time
+//! and the amount of `sell_token` held by the order. A new slice becomes due
+//! every `total_duration_secs / num_slices` seconds, with the first due
+//! immediately.
+//!
+//! Before selling, the strategy checks the slice's price impact against the
+//! pool. If it exceeds `max_price_impact`, the slice is deferred and carried
+//! into the next observation. When several slices are due at once, they are
+//! sold together if the combined impact allows it, otherwise one at a time.
+//!
+//! A sale only counts once its order UTxO has been consumed (reported through
+//! `on_strategy_spent`) by a transaction that paid the `buy_token` back to the
+//! order. Until then no other slice is submitted; a sale whose validity window
+//! lapses first, or whose order is consumed without it, is retried along with any
+//! slices that fell due meanwhile.
+//!
+//! The strategy stops once the full position is sold, or once the window has
+//! ended (any unsold remainder stays in the order).
+//!
+//! ## Example
+//!
+//! With `num_slices = 4`, `total_duration_secs = 3600` and 1000 `sell_token`:
+//!
+//! 1. t=0: sells 250
+//! 2. t=15m: impact too high, the slice is deferred
+//! 3. t=20m: sells the deferred 250
+//! 4. t=30m: sells 250
+//! 5. t=45m: sells the final 250
+//!
+//! ## Configuration
+//!
+//! - `sell_token`: The token being liquidated
+//! - `buy_token`: The token received for it
+//! - `total_duration_secs`: How long to spread the sale over
+//! - `num_slices`: How many equal slices to sell
+//! - `max_price_impact`: The largest price impact a slice may have (0.01 = 1%)
+//! - `slippage_tolerance`: Maximum acceptable slippage per slice (0.03 = 3%)
+
+mod config;
+
+use balius_sdk::{Ack, Config, Tx, WorkerResult};
+use config::Config as StrategyConfig;
+use serde::{Deserialize, Serialize};
+use sundae_strategies::{EventTime, ManagedStrategy, PoolState, Strategy, kv, types::Order};
+use tracing::info;
+
+/// How long, in seconds, either side of the current slot a sale is valid for
+const VALIDITY_SECS: u64 = 20 * 60;
+
+/// A sale of one or more slices submitted to the market and not yet consumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sale {
+    slices: u64,
+    amount: u64,
+    /// UNIX time (ms) after which the sale can no longer fill
+    expires_ms: u64,
+}
+
+/// Progress of a TWAP strategy, persisted per strategy authorization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TwapState {
+    /// UNIX time (ms) the strategy was first observed
+    start_ms: u64,
+    /// The sell_token balance when the strategy was first observed
+    initial_amount: u64,
+    /// How many slices have been sold
+    slices_completed: u64,
+    /// Total sell_token sold so far
+    sold: u64,
+    #[serde(default)]
+    in_flight: Option<Sale>,
+}
+
+fn twap_states() -> kv::StrategyState<TwapState> {
+    kv::StrategyState::per_authorization("twap_state")
+}
+
+/// How many slices should have been sold by `now_ms`; the first is due immediately.
+fn slices_due(config: &StrategyConfig, state: &TwapState, now_ms: u64) -> u64 {
+    let slice_ms = config.total_duration_secs * 1000 / config.num_slices;
+    let elapsed = now_ms.saturating_sub(state.start_ms);
+    (elapsed / slice_ms + 1).min(config.num_slices)
+}
+
+/// The amount of sell_token covered by the next `count` slices. The final slice
+/// picks up any rounding remainder so the whole position is sold.
+fn slice_amount(config: &StrategyConfig, state: &TwapState, count: u64) -> u64 {
+    if state.slices_completed + count >= config.num_slices {
+        state.initial_amount.saturating_sub(state.sold)
+    } else {
+        state.initial_amount / config.num_slices * count
+    }
+}
+
+#[allow(clippy::ptr_arg)] // Signature must match NewPoolStateCallback type
+fn on_new_pool_state(
+    config: &Config<StrategyConfig>,
+    pool_state: &PoolState,
+    strategies: &Vec<ManagedStrategy>,
+) -> WorkerResult<Ack> {
+    let now_ms = pool_state.now_ms(&config.network);
+    let twap_states = twap_states();
+
+    for strategy in strategies {
+        if !pool_state.is_correct_pool(&strategy.order, &config.sell_token, &config.buy_token) {
+            continue;
+        }
+
+        let balance = strategy.balance(&config.sell_token);
+        let mut state = twap_states.load_or_init(strategy, || TwapState {
+            start_ms: now_ms,
+            initial_amount: balance,
+            slices_completed: 0,
+            sold: 0,
+            in_flight: None,
+        })?;
+
+        if state.slices_completed >= config.num_slices || state.sold >= state.initial_amount {
+            info!("strategy {:?}: position fully sold", strategy.output);
+            continue;
+        }
+        if now_ms.saturating_sub(state.start_ms) > config.total_duration_secs * 1000 {
+            info!(
+                "strategy {:?}: window ended with {} of {} sold",
+                strategy.output, state.sold, state.initial_amount
+            );
+            continue;
+        }
+
+        if let Some(sale) = &state.in_flight
+            && now_ms <= sale.expires_ms
+        {
+            continue;
+        }
+
+        let due = slices_due(config, &state, now_ms).saturating_sub(state.slices_completed);
+        if due == 0 {
+            continue;
+        }
+
+        // Sell every due slice at once if the pool can absorb it, otherwise just one
+        let mut slices = due;
+        let mut amount = slice_amount(config, &state, slices).min(balance);
+        let mut impact = pool_state.price_impact(&config.sell_token, amount);
+        if slices > 1 && impact.is_none_or(|impact| impact > config.max_price_impact) {
+            slices = 1;
+            amount = slice_amount(config, &state, slices).min(balance);
+            impact = pool_state.price_impact(&config.sell_token, amount);
+        }
+        let Some(impact) = impact else {
+            info!("pool has no usable price, skipping this observation");
+            continue;
+        };
+        if impact > config.max_price_impact {
+            info!(
+                "strategy {:?}: deferring slice, price impact {:.4} exceeds {:.4}",
+                strategy.output, impact, config.max_price_impact
+            );
+            continue;
+        }
+        if amount == 0 {
+            continue;
+        }
+
+        let Some(expected) = pool_state.expected_output(&config.sell_token, amount) else {
+            continue;
+        };
+        let min_received =
+            ((expected as f64 * (1.0 - config.slippage_tolerance)).floor() as u64).max(1);
+
+        info!(
+            "strategy {:?}: selling {slices} slice(s), {amount} {} for min {min_received} {} (impact {:.4})",
+            strategy.output,
+            config.sell_token.name_to_string(),
+            config.buy_token.name_to_string(),
+            impact
+        );
+
+        let swap = Order::swap(
+            (&config.sell_token, amount),
+            (&config.buy_token, min_received),
+        );
+        let validity_range = pool_state.get_validity_range(&config.network, VALIDITY_SECS);
+        if strategy
+            .submit_execution(&config.network, validity_range, swap)?
+            .is_skipped()
+        {
+            continue;
+        }
+
+        state.in_flight = Some(Sale {
+            slices,
+            amount,
+            expires_ms: now_ms.saturating_add(VALIDITY_SECS * 1000),
+        });
+        twap_states.store(strategy, &state)?;
+    }
+
+    Ok(Ack)
+}
+
+fn on_strategy_spent(
+    config: &Config<StrategyConfig>,
+    tx: &Tx,
+    strategy: &ManagedStrategy,
+) -> WorkerResult<Ack> {
+    let twap_states = twap_states();
+    let Some(mut state) = twap_states.load(strategy)? else {
+        return Ok(Ack);
+    };
+    let Some(sale) = state.in_flight.take() else {
+        return Ok(Ack);
+    };
+    match strategy.swap_fill(tx, &config.sell_token, &config.buy_token) {
+        Some(fill) => {
+            state.slices_completed += sale.slices;
+            state.sold += sale.amount;
+            info!(
+                "strategy {:?}: sold {} slice(s), {} for {} {} ({} of {} sold)",
+                strategy.output,
+                sale.slices,
+                sale.amount,
+                fill.received,
+                config.buy_token.name_to_string(),
+                state.sold,
+                state.initial_amount
+            );
+        }
+        None => info!(
+            "strategy {:?}: spent without the sale of {}, it'll be retried",
+            strategy.output, sale.amount
+        ),
+    }
+    twap_states.store(strategy, &state)?;
+    Ok(Ack)
+}
+
+fn strategy() -> Strategy<StrategyConfig> {
+    Strategy::<StrategyConfig>::new()
+        .on_new_pool_state(on_new_pool_state)
+        .on_strategy_spent(on_strategy_spent)
+        .with_validity_window(VALIDITY_SECS)
+}
+
+#[balius_sdk::main]
+fn main() -> Worker {
+    balius_sdk::logging::init();
+
+    strategy().worker()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sundae_strategies::{sim::Simulator, types::AssetId};
+
+    fn config_json() -> serde_json::Value {
+        serde_json::json!({
+            "network": "preview",
+            "sell_token": "99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15.534245525259",
+            "buy_token": ".",
+            "total_duration_secs": 3600,
+            "num_slices": 4,
+            "max_price_impact": 0.01,
+        })
+    }
+
+    fn config() -> StrategyConfig {
+        serde_json::from_value(config_json()).unwrap()
+    }
+
+    fn state(slices_completed: u64, sold: u64) -> TwapState {
+        TwapState {
+            start_ms: 0,
+            initial_amount: 1003,
+            slices_completed,
+            sold,
+            in_flight: None,
+        }
+    }
+
+    #[test]
+    fn first_slice_is_due_immediately() {
+        assert_eq!(slices_due(&config(), &state(0, 0), 0), 1);
+    }
+
+    #[test]
+    fn slices_become_due_over_the_window() {
+        let config = config();
+        assert_eq!(slices_due(&config, &state(0, 0), 899_999), 1);
+        assert_eq!(slices_due(&config, &state(0, 0), 900_000), 2);
+        assert_eq!(slices_due(&config, &state(0, 0), 10_000_000), 4);
+    }
+
+    #[test]
+    fn deferred_slices_are_carried() {
+        let config = config();
+        // Two slices due, none sold yet
+        assert_eq!(slice_amount(&config, &state(0, 0), 2), 500);
+    }
+
+    #[test]
+    fn final_slice_sells_the_remainder() {
+        let config = config();
+        assert_eq!(slice_amount(&config, &state(3, 750), 1), 253);
+    }
+
+    #[test]
+    fn only_filled_slices_count_as_sold() {
+        let ada = AssetId::from((vec![], vec![]));
+        let sberry = AssetId::from((
+            hex::decode("99b071ce8580d6a3a11b4902145adb8bfd0d2a03935af8cf66403e15").unwrap(),
+            b"SBERRY".to_vec(),
+        ));
+        let mut sim = Simulator::new(strategy(), &config_json()).unwrap();
+        let order = ManagedStrategy::mock(&[(&sberry, 1_000)]);
+        sim.add_order(order.clone()).unwrap();
+        let pool = || PoolState::mock(1_000_000_000, 1_000_000_000, (&ada, &sberry));
+
+        // Nothing else is sold while the first slice is in flight
+        assert_eq!(sim.run([(1, pool()), (2, pool())]).unwrap().len(), 1);
+
+        // It lapses unfilled, so it's retried along with the slice that fell due meanwhile
+        let executions = sim.run([(1 + 1_201, pool())]).unwrap();
+        assert_eq!(executions.len(), 1);
+        let Order::Swap { offer, .. } = &executions[0].details else {
+            panic!("expected a swap");
+        };
+        assert_eq!(offer.2, 500);
+        let state = twap_states().load(&order).unwrap().unwrap();
+        assert_eq!((state.slices_completed, state.sold), (0, 0));
+
+        sim.observe_tx(order.mock_execution(1_203, &[(&sberry, 500), (&ada, 495)]))
+            .unwrap();
+        let state = twap_states().load(&order).unwrap().unwrap();
+        assert_eq!((state.slices_completed, state.sold), (2, 500));
+        assert!(state.in_flight.is_none());
+    }
+}